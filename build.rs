@@ -0,0 +1,9 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("bundled protoc is available");
+        // SAFETY: build scripts are single-threaded.
+        unsafe { std::env::set_var("PROTOC", protoc) };
+        tonic_build::compile_protos("proto/traffic.proto").expect("failed to compile protos");
+    }
+}
@@ -0,0 +1,25 @@
+-- Example Lua signal controller: an actuated light that stretches its
+-- green while vehicles keep arriving. Select it for light 1 in a config file:
+--
+--     [lua]
+--     1 = controllers/actuated.lua
+--
+-- and run with `cargo run --features lua -- --config my.ini`.
+
+local MIN_GREEN, MAX_GREEN, YELLOW, RED = 4, 12, 2, 4
+
+function decide(light)
+  if light.state == "green" then
+    local busy = light.queue > 0 or light.approaching > 0
+    if light.elapsed >= MAX_GREEN or (light.elapsed >= MIN_GREEN and not busy) then
+      return "yellow"
+    end
+  elseif light.state == "yellow" then
+    if light.elapsed >= YELLOW then
+      return "red"
+    end
+  elseif light.elapsed >= RED then
+    return "green"
+  end
+  return nil
+end
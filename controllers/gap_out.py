@@ -0,0 +1,27 @@
+#!/usr/bin/env python3
+"""Example external signal controller.
+
+Keeps a light green while traffic keeps arriving and ends the green once
+its approach is empty or it has lasted MAX_GREEN seconds. Red lights turn
+green again once someone is waiting.
+
+    cargo run -- --controller "python3 controllers/gap_out.py"
+"""
+import json
+import sys
+
+MIN_GREEN, MAX_GREEN, YELLOW, MIN_RED = 4, 15, 2, 3
+
+for line in sys.stdin:
+    decisions = []
+    for light in json.loads(line)["lights"]:
+        state, elapsed = light["state"], light["elapsed"]
+        waiting = light["queue"] > 0 or light["approaching"] > 0
+        if state == "green":
+            done = elapsed >= MAX_GREEN or (elapsed >= MIN_GREEN and not waiting)
+            decisions.append("yellow" if done else "keep")
+        elif state == "yellow":
+            decisions.append("red" if elapsed >= YELLOW else "keep")
+        else:
+            decisions.append("green" if elapsed >= MIN_RED and waiting else "keep")
+    print(" ".join(decisions), flush=True)
@@ -0,0 +1,969 @@
+use std::time::Instant;
+
+use crossterm::event::KeyCode;
+
+use crate::remote::controller::ExternalController;
+#[cfg(feature = "grpc")]
+use crate::remote::grpc::GrpcServer;
+use crate::remote::http::{self, HttpServer};
+use crate::remote::mqtt::MqttPublisher;
+#[cfg(unix)]
+use crate::remote::socket::ControlSocket;
+use crate::remote::websocket::WebSocketServer;
+
+use crate::systems::career::Progress;
+use crate::systems::challenge::CHALLENGES;
+use crate::systems::config::PerformanceConfig;
+#[cfg(feature = "sqlite")]
+use crate::systems::database::Database;
+use crate::systems::editor::{EditorAction, MapEditor};
+use crate::systems::events::SimEvent;
+use crate::systems::history::History;
+#[cfg(feature = "lua")]
+use crate::systems::lua_control::LuaControllers;
+use crate::systems::optimize;
+use crate::systems::profiler::{Phase, Profiler};
+#[cfg(feature = "scripting")]
+use crate::systems::script::{Script, ScriptAction};
+use crate::systems::simulation::{STEP_SECONDS, Simulation};
+use crate::systems::snapshot;
+use crate::systems::telemetry::Telemetry;
+use crate::systems::vehicle::{Lane, VehicleType};
+use crate::ui::backend::RenderBackend;
+use crate::ui::camera::Camera;
+use crate::ui::console::{Command, Console, ConsoleResult, Setting};
+use crate::ui::help::help_lines;
+use crate::ui::input::{Action, InputHandler};
+use crate::ui::junction::junction_lines;
+use crate::ui::render::{
+    Hud, MIN_SIZE, ScreenLayout, loops_panel, render, render_editor, render_too_small, road_glyphs,
+};
+use crate::ui::sandbox::{Sandbox, SandboxAction};
+use crate::ui::screen::ScreenBuffer;
+use crate::ui::screenshot;
+use crate::ui::selection::{Prompt, PromptKind, PromptResult, Selection};
+use crate::ui::sprites::SpriteSet;
+use crate::ui::theme::Themes;
+use crate::ui::timing_editor::{TimingEditor, TimingEditorAction};
+#[cfg(feature = "tui")]
+use crate::ui::tui::TuiFrontend;
+use crate::ui::tutorial::{Tutorial, TutorialEvent};
+
+/// The running simulation together with everything the UI tracks around it.
+/// Key presses, mouse clicks and console commands all end up here.
+pub struct App {
+    pub sim: Simulation,
+    pub editor: Option<MapEditor>,
+    pub sandbox: Option<Sandbox>,
+    pub timing_editor: Option<TimingEditor>,
+    pub camera: Camera,
+    pub selection: Selection,
+    pub console: Console,
+    pub layout: ScreenLayout,
+    /// Where frames are drawn.
+    pub backend: Box<dyn RenderBackend>,
+    pub quitting: bool,
+    pub input: InputHandler,
+    /// Boxed screen drawn over the simulation, such as the help or a
+    /// challenge's score screen. Any key closes it.
+    overlay: Option<(String, Vec<String>)>,
+    /// Light whose intersection is magnified in the overlay, redrawn every
+    /// frame until a key closes it.
+    junction: Option<usize>,
+    pub tutorial: Option<Tutorial>,
+    /// Index into `CHALLENGES` of the challenge being played.
+    pub challenge: Option<usize>,
+    pub progress: Progress,
+    pub paused: bool,
+    /// Simulated seconds per real second.
+    pub time_scale: f32,
+    /// Frames still to run while paused, queued by the step key.
+    pending_steps: u32,
+    history: History,
+    /// CSV file statistics rows are appended to, from `--stats-out`.
+    pub stats_out: Option<String>,
+    stats_interval: f32,
+    /// Elapsed simulated time at which the next CSV row is due.
+    next_stats_row: f32,
+    /// JSON-lines stream from `--telemetry`.
+    pub telemetry: Option<Telemetry>,
+    /// Outside program deciding the light phases, from `--controller`.
+    pub controller: Option<ExternalController>,
+    /// Rhai scenario script from `--script`.
+    #[cfg(feature = "scripting")]
+    pub script: Option<Script>,
+    /// Lights run by Lua scripts from the `[lua]` config section.
+    #[cfg(feature = "lua")]
+    pub lua: Option<LuaControllers>,
+    /// Widget interface from `--tui`, drawn instead of the text frame.
+    #[cfg(feature = "tui")]
+    pub tui: Option<TuiFrontend>,
+    /// REST API from `--http`.
+    pub http: Option<HttpServer>,
+    /// gRPC service from `--grpc`.
+    #[cfg(feature = "grpc")]
+    pub grpc: Option<GrpcServer>,
+    /// Broker connection from `--mqtt` or the `[mqtt]` config section.
+    pub mqtt: Option<MqttPublisher>,
+    /// Control socket from `--socket`.
+    #[cfg(unix)]
+    pub socket: Option<ControlSocket>,
+    /// Live state feed from `--ws`.
+    pub websocket: Option<WebSocketServer>,
+    /// SQLite recorder from `--db`.
+    #[cfg(feature = "sqlite")]
+    pub database: Option<Database>,
+    /// Where the map editor saves.
+    map_path: String,
+    /// The `--config` file, where the timing editor saves.
+    pub config_path: Option<String>,
+    /// Per-phase frame timings, when `enable_profiling` is on.
+    pub profiler: Option<Profiler>,
+    /// Induction loop states are shown under the road, for debugging.
+    pub show_loops: bool,
+    /// Colour themes, switched with [`Action::CycleTheme`] or `:theme`.
+    pub themes: Themes,
+    /// What the road is drawn with, switched with [`Action::CycleSprites`].
+    sprites: SpriteSet,
+}
+
+impl App {
+    pub fn new(
+        sim: Simulation,
+        input: InputHandler,
+        map_path: String,
+        performance: &PerformanceConfig,
+        themes: Themes,
+        mut backend: Box<dyn RenderBackend>,
+    ) -> Self {
+        let (foreground, background) = themes.current().base_colours();
+        backend.set_base_colours(foreground, background);
+        Self {
+            sim,
+            editor: None,
+            sandbox: None,
+            timing_editor: None,
+            camera: Camera::new(),
+            selection: Selection::default(),
+            console: Console::default(),
+            layout: ScreenLayout::default(),
+            backend,
+            quitting: false,
+            input,
+            overlay: None,
+            junction: None,
+            tutorial: None,
+            challenge: None,
+            progress: Progress::default(),
+            paused: false,
+            time_scale: 1.0,
+            pending_steps: 0,
+            history: History::new(performance),
+            stats_out: None,
+            stats_interval: performance.stats_interval,
+            next_stats_row: 0.0,
+            telemetry: None,
+            controller: None,
+            #[cfg(feature = "scripting")]
+            script: None,
+            #[cfg(feature = "lua")]
+            lua: None,
+            #[cfg(feature = "tui")]
+            tui: None,
+            http: None,
+            websocket: None,
+            mqtt: None,
+            #[cfg(unix)]
+            socket: None,
+            #[cfg(feature = "grpc")]
+            grpc: None,
+            #[cfg(feature = "sqlite")]
+            database: None,
+            map_path,
+            config_path: None,
+            profiler: performance.enable_profiling.then(Profiler::default),
+            show_loops: false,
+            themes,
+            sprites: SpriteSet::default(),
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) {
+        if self.overlay.is_some() {
+            self.overlay = None;
+            self.junction = None;
+        } else if let Some(map_editor) = self.editor.as_mut() {
+            match map_editor.handle_key(code) {
+                EditorAction::None => {}
+                EditorAction::Close => self.editor = None,
+                EditorAction::Apply(road) => {
+                    let _moved = self.sim.replace_road(road);
+                    #[cfg(feature = "lua")]
+                    if let Some(lua) = self.lua.as_mut() {
+                        lua.remap_lights(&_moved);
+                    }
+                    self.editor = None;
+                }
+            }
+        } else if let Some(sandbox) = self.sandbox.as_mut() {
+            match sandbox.handle_key(code, &self.sim) {
+                SandboxAction::None => {}
+                SandboxAction::Close => self.sandbox = None,
+                SandboxAction::Apply(event) => {
+                    self.console.output = Some(match self.sim.apply(event) {
+                        Ok(message) => (message, false),
+                        Err(message) => (message, true),
+                    });
+                }
+            }
+        } else if let Some(timing_editor) = self.timing_editor.as_mut() {
+            match timing_editor.handle_key(code, &self.sim) {
+                TimingEditorAction::None => {}
+                TimingEditorAction::Close => self.timing_editor = None,
+                TimingEditorAction::Apply(event) => {
+                    self.console.output = Some(match self.sim.apply(event) {
+                        Ok(message) => {
+                            timing_editor.changed(&self.sim);
+                            (message, false)
+                        }
+                        Err(message) => (message, true),
+                    });
+                }
+                TimingEditorAction::Save => {
+                    self.console.output = Some(match self.save_timings() {
+                        Ok(message) => (message, false),
+                        Err(message) => (message, true),
+                    });
+                }
+            }
+        } else if self.console.open {
+            if let ConsoleResult::Submitted(line) = self.console.handle_key(code) {
+                self.console.output = Some(match self.run_command(&line) {
+                    Ok(message) => (message, false),
+                    Err(message) => (message, true),
+                });
+            }
+        } else if let Some(prompt) = self.selection.prompt.as_mut() {
+            match prompt.handle_key(code) {
+                PromptResult::Pending => {}
+                PromptResult::Cancelled => self.selection.prompt = None,
+                PromptResult::Submitted(kind, value) => {
+                    self.selection.prompt = None;
+                    match kind {
+                        PromptKind::SelectVehicle => {
+                            self.select(value as u32);
+                        }
+                        PromptKind::TeleportTo => {
+                            if let Some(id) = self.selection.selected {
+                                self.sim.teleport(id, value);
+                            }
+                        }
+                    }
+                }
+            }
+        } else if let Some(action) = self.input.action_for(code) {
+            self.perform(action);
+        }
+    }
+
+    pub fn perform(&mut self, action: Action) {
+        self.notify_tutorial(TutorialEvent::Performed(action));
+        match action {
+            Action::Quit => self.quitting = true,
+            Action::OpenEditor => {
+                self.editor = Some(MapEditor::new(&self.sim.road, &self.map_path))
+            }
+            Action::OpenSandbox => {
+                let cursor = self
+                    .camera
+                    .offset
+                    .min(self.sim.road.length.saturating_sub(1));
+                self.sandbox = Some(Sandbox::new(cursor));
+            }
+            Action::OpenConsole => self.console.open(),
+            Action::ToggleHelp => {
+                self.overlay = match self.overlay {
+                    Some(_) => None,
+                    None => Some(("HELP".to_string(), help_lines(&self.input))),
+                }
+            }
+            Action::TogglePause => self.paused = !self.paused,
+            Action::Step => {
+                self.paused = true;
+                self.pending_steps += 1;
+            }
+            Action::RewindBack => {
+                self.paused = true;
+                if let Some(snapshot) = self.history.back() {
+                    self.sim = snapshot.clone();
+                }
+            }
+            Action::RewindForward => {
+                if let Some(snapshot) = self.history.forward() {
+                    self.sim = snapshot.clone();
+                }
+            }
+            Action::PanLeft => {
+                self.camera.following = None;
+                self.camera.pan(-4, self.sim.road.length, road_glyphs());
+            }
+            Action::PanRight => {
+                self.camera.following = None;
+                self.camera.pan(4, self.sim.road.length, road_glyphs());
+            }
+            Action::FollowNext => {
+                self.camera.following = next_vehicle(&self.sim, self.camera.following);
+                self.selection.selected = self.camera.following;
+            }
+            Action::StopFollowing => self.camera.following = None,
+            Action::SelectVehicle => {
+                self.selection.prompt = Some(Prompt::new(PromptKind::SelectVehicle))
+            }
+            Action::Teleport => {
+                if self.selection.selected.is_some() {
+                    self.selection.prompt = Some(Prompt::new(PromptKind::TeleportTo))
+                }
+            }
+            Action::Despawn => {
+                if let Some(id) = self.selection.selected.take() {
+                    let _ = self.sim.apply(SimEvent::Despawn(id));
+                }
+            }
+            Action::ClearSelection => {
+                self.selection.selected = None;
+                self.selection.selected_light = None;
+                self.camera.following = None;
+            }
+            Action::ToggleZoom => self.camera.toggle_zoom(),
+            Action::ToggleLoops => self.show_loops = !self.show_loops,
+            Action::OpenTimingEditor => match self.selection.selected_light {
+                Some(index) => self.timing_editor = Some(TimingEditor::new(index, &self.sim)),
+                None => {
+                    self.console.output =
+                        Some(("Click a signal first to retime it".to_string(), true))
+                }
+            },
+            Action::ZoomJunction => match self.selection.selected_light {
+                Some(index) => self.junction = Some(index),
+                None => {
+                    self.console.output =
+                        Some(("Click a signal first to zoom into it".to_string(), true))
+                }
+            },
+            Action::Screenshot => {
+                self.console.output = Some(match self.screenshot() {
+                    Ok(message) => (message, false),
+                    Err(message) => (message, true),
+                })
+            }
+            Action::CycleTheme => {
+                self.themes.cycle();
+                self.console.output = Some((self.apply_theme(), false));
+            }
+            Action::CycleSprites => {
+                self.set_sprites(self.sprites.next());
+                self.console.output = Some((format!("Sprites: {}", self.sprites.name()), false));
+            }
+            Action::CycleWeather => {
+                // Moves on from the weather blowing in, if any, so repeated
+                // presses step through every kind.
+                let kind = self
+                    .sim
+                    .weather
+                    .incoming
+                    .map_or(self.sim.weather.current, |(kind, _)| kind);
+                self.console.output =
+                    Some(match self.sim.apply(SimEvent::Weather(kind.next(), 0.5)) {
+                        Ok(message) => (message, false),
+                        Err(message) => (message, true),
+                    });
+            }
+            Action::DispatchEmergency => {
+                let ambulance = SimEvent::Spawn(VehicleType::Ambulance, None);
+                self.console.output = Some(match self.sim.apply(ambulance) {
+                    Ok(message) => (message, false),
+                    Err(message) => (message, true),
+                });
+            }
+            Action::Speed1 | Action::Speed2 | Action::Speed3 | Action::Speed4 | Action::Speed5 => {
+                self.time_scale = action.time_scale().unwrap_or(1.0);
+            }
+        }
+    }
+
+    /// Runs a console command, returning a line to show the user.
+    pub fn run_command(&mut self, line: &str) -> Result<String, String> {
+        match Command::parse(line)? {
+            Command::Action(action) => {
+                self.perform(action);
+                Ok(format!("Done: {}", action.name()))
+            }
+            Command::Spawn(kind, cell) => {
+                let message = self.sim.apply(SimEvent::Spawn(kind, cell))?;
+                self.notify_tutorial(TutorialEvent::Spawned(kind));
+                Ok(message)
+            }
+            Command::Set(setting, value) => {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(format!("{} must be between 0 and 1", setting.name()));
+                }
+                match setting {
+                    Setting::SpawnRate => self.sim.spawn_rate = value,
+                    Setting::BreakdownRate => self.sim.breakdown_rate = value,
+                }
+                Ok(format!("{} = {}", setting.name(), value))
+            }
+            Command::Save(path) => {
+                snapshot::save(&self.sim, &path)?;
+                Ok(format!("Saved state to {}", path))
+            }
+            Command::Select(id) => {
+                if self.select(id) {
+                    Ok(format!("Selected #{}", id))
+                } else {
+                    Err(format!("No vehicle #{}", id))
+                }
+            }
+            Command::TeleportTo(cell) => {
+                let id = self.selection.selected.ok_or("No vehicle selected")?;
+                self.sim.teleport(id, cell);
+                Ok(format!("Teleported #{} to cell {}", id, cell))
+            }
+            Command::Theme(name) => {
+                self.themes.select(&name)?;
+                Ok(self.apply_theme())
+            }
+            Command::Weather(kind, intensity) => self.sim.apply(SimEvent::Weather(kind, intensity)),
+            Command::Time(minute) => self.sim.apply(SimEvent::SetClock(minute)),
+            Command::Debris(cell) => self.sim.apply(SimEvent::Debris(cell)),
+            Command::RoadWorks(from, to, seconds) => {
+                self.sim.apply(SimEvent::RoadWorks(from, to, seconds))
+            }
+            Command::Camera(0) => Err("Lights are numbered from 1".to_string()),
+            Command::Camera(light) => self.sim.apply(SimEvent::ToggleCamera(light - 1)),
+            Command::Malfunction(0) => Err("Lights are numbered from 1".to_string()),
+            Command::Malfunction(light) => self.sim.apply(SimEvent::Malfunction(light - 1)),
+            Command::RightOnRed => self.sim.apply(SimEvent::ToggleRightOnRed),
+        }
+    }
+
+    fn notify_tutorial(&mut self, event: TutorialEvent) {
+        if let Some(tutorial) = self.tutorial.as_mut() {
+            tutorial.notify(event, &self.sim);
+        }
+        self.check_tutorial_finished();
+    }
+
+    fn check_tutorial_finished(&mut self) {
+        if self.tutorial.as_ref().is_some_and(Tutorial::finished) {
+            self.tutorial = None;
+            self.console.output = Some((
+                "🎓 Tutorial complete! Press ? any time for the controls.".to_string(),
+                false,
+            ));
+        }
+    }
+
+    /// Advances the simulation by one fixed step and feeds the outputs that
+    /// sample it (CSV, telemetry, MQTT, database).
+    pub fn step(&mut self) {
+        self.timed(Phase::Control, |app| {
+            app.run_controller();
+            #[cfg(feature = "lua")]
+            app.run_lua();
+        });
+        self.sim
+            .update_profiled(STEP_SECONDS, self.profiler.as_mut());
+        #[cfg(feature = "scripting")]
+        self.timed(Phase::Control, Self::run_script);
+        self.timed(Phase::Stats, |app| {
+            app.export_stats();
+            app.stream_telemetry();
+            app.publish_mqtt();
+            #[cfg(feature = "sqlite")]
+            app.record_database();
+        });
+    }
+
+    /// Runs `work`, charging its time to `phase` when profiling.
+    fn timed(&mut self, phase: Phase, work: impl FnOnce(&mut Self)) {
+        let start = self.profiler.is_some().then(Instant::now);
+        work(self);
+        if let (Some(profiler), Some(start)) = (self.profiler.as_mut(), start) {
+            profiler.add(phase, start.elapsed());
+        }
+    }
+
+    /// Lets the external controller set the lights. If it fails, the lights
+    /// go back to their own timers and the failure is shown.
+    fn run_controller(&mut self) {
+        let Some(controller) = self.controller.as_mut() else {
+            return;
+        };
+        if let Err(message) = controller.update(&mut self.sim) {
+            self.console.output = Some((message, true));
+            if let Some(controller) = self.controller.take() {
+                controller.release(&mut self.sim);
+            }
+        }
+    }
+
+    /// Calls the script's hooks and carries out what they ask for. A script
+    /// error stops the script and is shown; a failed action is only shown.
+    #[cfg(feature = "scripting")]
+    fn run_script(&mut self) {
+        let Some(script) = self.script.as_mut() else {
+            return;
+        };
+        let actions = match script.update(&self.sim) {
+            Ok(actions) => actions,
+            Err(message) => {
+                self.console.output = Some((message, true));
+                self.script = None;
+                return;
+            }
+        };
+        for action in actions {
+            let result = match action {
+                ScriptAction::Event(event) => self.sim.apply(event),
+                ScriptAction::Command(line) => self.run_command(&line),
+            };
+            if let Err(message) = result {
+                self.console.output = Some((format!("Script: {}", message), true));
+            }
+        }
+    }
+
+    /// Lets the Lua scripts set their lights. A script error hands every
+    /// scripted light back to its timer and is shown.
+    #[cfg(feature = "lua")]
+    fn run_lua(&mut self) {
+        let Some(lua) = &self.lua else {
+            return;
+        };
+        if let Err(message) = lua.update(&mut self.sim) {
+            lua.release(&mut self.sim);
+            self.console.output = Some((message, true));
+            self.lua = None;
+        }
+    }
+
+    /// Answers the REST requests that came in since the last frame.
+    fn serve_http(&mut self) {
+        let Some(server) = &self.http else {
+            return;
+        };
+        for request in server.pending() {
+            let (status, body) = http::route(self, &request.method, &request.path, &request.body);
+            request.respond(status, body);
+        }
+    }
+
+    /// Runs the work queued by gRPC calls since the last frame.
+    #[cfg(feature = "grpc")]
+    fn serve_grpc(&mut self) {
+        let Some(server) = &self.grpc else {
+            return;
+        };
+        for job in server.pending() {
+            job(self);
+        }
+    }
+
+    /// Runs the commands that came in on the control socket.
+    #[cfg(unix)]
+    fn serve_socket(&mut self) {
+        let Some(socket) = &self.socket else {
+            return;
+        };
+        for command in socket.pending() {
+            let result = self.run_command(&command.line);
+            command.respond(result);
+        }
+    }
+
+    /// Appends a row to the `--stats-out` file once each sampling interval.
+    /// Writing stops, with a message, if the file can't be written.
+    fn export_stats(&mut self) {
+        let Some(path) = &self.stats_out else {
+            return;
+        };
+        let elapsed = self.sim.stats.elapsed;
+        // After a rewind the clock is behind the last row; carry on from there.
+        if self.next_stats_row > elapsed + self.stats_interval {
+            self.next_stats_row = elapsed;
+        }
+        if elapsed < self.next_stats_row {
+            return;
+        }
+        self.next_stats_row += self.stats_interval.max(STEP_SECONDS);
+        let sim = &self.sim;
+        let active = sim.vehicles.len();
+        let moving = sim.vehicles.iter().filter(|v| !v.stopped).count();
+        let queues: Vec<usize> = (0..sim.lights.len())
+            .map(|index| sim.queue_length(index, Lane::Road))
+            .collect();
+        if let Err(message) = sim.stats.export_csv(path, active, moving, &queues) {
+            self.console.output = Some((message, true));
+            self.stats_out = None;
+        }
+    }
+
+    /// Writes a telemetry line when one is due, stopping the stream with a
+    /// message if the file can't be written (e.g. the reader went away).
+    fn stream_telemetry(&mut self) {
+        let Some(telemetry) = self.telemetry.as_mut() else {
+            return;
+        };
+        if let Err(message) = telemetry.record(&self.sim) {
+            self.console.output = Some((message, true));
+            self.telemetry = None;
+        }
+    }
+
+    /// Publishes light changes and new events, dropping the connection with
+    /// a message once the broker has gone away.
+    fn publish_mqtt(&mut self) {
+        let Some(mqtt) = self.mqtt.as_mut() else {
+            return;
+        };
+        if let Err(message) = mqtt.publish(&self.sim) {
+            self.console.output = Some((message, true));
+            self.mqtt = None;
+        }
+    }
+
+    /// Stores what happened in the last step, dropping the database with a
+    /// message if a write fails.
+    #[cfg(feature = "sqlite")]
+    fn record_database(&mut self) {
+        let Some(database) = self.database.as_mut() else {
+            return;
+        };
+        if let Err(message) = database.record(&self.sim) {
+            self.console.output = Some((message, true));
+            self.database = None;
+        }
+    }
+
+    /// Ends a challenge whose time is up: pauses, records the result in
+    /// the career progress and shows the score screen.
+    fn check_challenge_finished(&mut self) {
+        let Some(index) = self.challenge else {
+            return;
+        };
+        let challenge = &CHALLENGES[index];
+        let stats = &self.sim.stats;
+        if !challenge.finished(stats) {
+            return;
+        }
+        self.challenge = None;
+        self.paused = true;
+
+        let passed = challenge.passed(stats);
+        let unlocked = if passed {
+            let next = self.progress.record_pass(index, challenge.score(stats));
+            if let Err(e) = self.progress.save() {
+                self.console.output = Some((e, true));
+            }
+            next
+        } else {
+            None
+        };
+        let mut lines = challenge.report(stats, self.progress.best(challenge.name));
+        if let Some(next) = unlocked {
+            lines.push(String::new());
+            lines.push(format!("🔓 Unlocked: {}", CHALLENGES[next].name));
+        }
+        let title = if passed {
+            "CHALLENGE PASSED"
+        } else {
+            "CHALLENGE FAILED"
+        };
+        self.overlay = Some((title.to_string(), lines));
+    }
+
+    /// Writes every light's timing into the `[timing]` section of the
+    /// config file.
+    fn save_timings(&self) -> Result<String, String> {
+        let path = self
+            .config_path
+            .as_deref()
+            .ok_or("Start with --config <file> to save timings")?;
+        let plan: Vec<_> = (0..self.sim.lights.len())
+            .map(|index| self.sim.timing(index))
+            .collect();
+        optimize::write_plan(path, &plan)?;
+        Ok(format!("Saved signal timings to {}", path))
+    }
+
+    fn select(&mut self, id: u32) -> bool {
+        let exists = self.sim.vehicles.get(id).is_some();
+        if exists {
+            self.selection.selected = Some(id);
+            self.selection.selected_light = None;
+        }
+        exists
+    }
+
+    /// The terminal is now `columns` by `rows`. The next frame is drawn from
+    /// scratch and laid out for the new size.
+    pub fn resize(&mut self, columns: u16, rows: u16) {
+        self.backend.resize(columns, rows);
+        // The old rows and columns no longer match what is on screen, so
+        // clicks wait for the next frame's layout.
+        self.layout = ScreenLayout::default();
+        self.camera.clamp(self.sim.road.length, road_glyphs());
+    }
+
+    /// Clicking a signal opens its panel, clicking a spawn point toggles it,
+    /// and clicking a vehicle selects it.
+    pub fn handle_click(&mut self, column: u16, row: u16) {
+        if self.editor.is_some() {
+            return;
+        }
+        let Some((lane, cells)) = self.layout.cells_at(column, row) else {
+            return;
+        };
+        let sim = &mut self.sim;
+        if lane == Lane::Road
+            && let Some(index) = sim.lights.iter().position(|l| cells.contains(&l.position))
+        {
+            self.selection.selected_light = Some(index);
+            self.selection.selected = None;
+            return;
+        }
+        if lane == Lane::Road
+            && let Some(&cell) = sim.road.spawn_points.iter().find(|c| cells.contains(c))
+        {
+            let _ = sim.apply(SimEvent::ToggleSpawn(cell));
+            return;
+        }
+        if let Some(vehicle) = sim
+            .vehicles
+            .iter()
+            .find(|v| v.lane == lane && cells.contains(&v.cell()))
+        {
+            self.selection.selected = Some(vehicle.id);
+            self.selection.selected_light = None;
+        }
+    }
+
+    /// Advances the simulation by `dt` real seconds at the current time
+    /// scale and draws the frame. The simulation holds still while the map is
+    /// being edited, and while paused it only moves one frame (at 1x) per
+    /// step key press.
+    pub fn frame(&mut self, dt: f32) {
+        self.serve_http();
+        #[cfg(unix)]
+        self.serve_socket();
+        #[cfg(feature = "grpc")]
+        self.serve_grpc();
+        if self.editor.is_some() && self.draw_too_small() {
+            return;
+        }
+        if let Some(map_editor) = &self.editor {
+            #[cfg(feature = "tui")]
+            if let Some(tui) = self.tui.as_mut() {
+                tui.render_editor(map_editor, self.themes.current());
+                return;
+            }
+            render_editor(self.backend.as_mut(), map_editor, self.themes.current());
+            return;
+        }
+
+        let simulated = if !self.paused {
+            dt * self.time_scale
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            dt
+        } else {
+            0.0
+        };
+        let steps = (simulated / STEP_SECONDS).round() as u32;
+        for _ in 0..steps {
+            self.step();
+        }
+        if steps > 0 {
+            self.history.record(&self.sim, steps as f32 * STEP_SECONDS);
+        }
+        if let Some(tutorial) = self.tutorial.as_mut() {
+            tutorial.update(&self.sim);
+        }
+        self.check_tutorial_finished();
+        self.check_challenge_finished();
+        let glyphs = road_glyphs();
+        if let Some(id) = self.camera.following {
+            match self.sim.vehicles.get(id) {
+                Some(vehicle) => {
+                    self.camera
+                        .center_on(vehicle.cell(), self.sim.road.length, glyphs)
+                }
+                None => self.camera.following = None,
+            }
+        }
+        if let Some(sandbox) = &self.sandbox {
+            let visible = self.camera.visible_cells(glyphs);
+            if !(self.camera.offset..self.camera.offset + visible).contains(&sandbox.cursor) {
+                self.camera
+                    .center_on(sandbox.cursor, self.sim.road.length, glyphs);
+            }
+        }
+        if let Some(id) = self.selection.selected
+            && self.sim.vehicles.get(id).is_none()
+        {
+            self.selection.selected = None;
+        }
+        self.camera.clamp(self.sim.road.length, glyphs);
+        if let Some(index) = self.junction {
+            if index < self.sim.lights.len() {
+                let title = format!("INTERSECTION {}", index + 1);
+                self.overlay = Some((title, junction_lines(&self.sim, index)));
+            } else {
+                self.junction = None;
+                self.overlay = None;
+            }
+        }
+        if let Some(websocket) = &self.websocket {
+            websocket.broadcast(&snapshot::to_json(&self.sim));
+        }
+        if self.draw_too_small() {
+            return;
+        }
+        let started = Instant::now();
+        let hud = Hud {
+            status: self.status(),
+            console: &self.console,
+            banners: self
+                .tutorial
+                .as_ref()
+                .and_then(|tutorial| tutorial.instruction(&self.input))
+                .into_iter()
+                .chain(
+                    self.challenge
+                        .map(|index| CHALLENGES[index].status(&self.sim.stats)),
+                )
+                .chain(
+                    self.sandbox
+                        .as_ref()
+                        .map(|_| format!("🛠 SANDBOX: {}", Sandbox::HELP)),
+                )
+                .chain(
+                    self.timing_editor
+                        .iter()
+                        .flat_map(|editor| editor.lines(&self.sim)),
+                )
+                .collect(),
+            cursor: self.sandbox.as_ref().map(|sandbox| sandbox.cursor),
+            profile: self.profiler.as_ref().map(Profiler::panel),
+            loops: self.show_loops.then(|| loops_panel(&self.sim)),
+            overlay: self
+                .overlay
+                .as_ref()
+                .map(|(title, lines)| (title.as_str(), lines.as_slice())),
+            theme: self.themes.current(),
+            monochrome: self.backend.monochrome(),
+        };
+        #[cfg(feature = "tui")]
+        let layout = match self.tui.as_mut() {
+            Some(tui) => tui.render(&self.sim, &self.camera, &self.selection, &hud),
+            None => render(
+                self.backend.as_mut(),
+                &self.sim,
+                &self.camera,
+                &self.selection,
+                &hud,
+            ),
+        };
+        #[cfg(not(feature = "tui"))]
+        let layout = render(
+            self.backend.as_mut(),
+            &self.sim,
+            &self.camera,
+            &self.selection,
+            &hud,
+        );
+        self.layout = layout;
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.add(Phase::Render, started.elapsed());
+            profiler.end_frame();
+        }
+    }
+
+    /// While the terminal is smaller than [`MIN_SIZE`], draws a note asking
+    /// for more room instead of a frame that would not fit. Returns whether
+    /// it did; the frame comes back by itself once the terminal is big
+    /// enough.
+    fn draw_too_small(&mut self) -> bool {
+        let Some(size) = self
+            .backend
+            .screen()
+            .map(ScreenBuffer::size)
+            .filter(|&(columns, rows)| columns < MIN_SIZE.0 || rows < MIN_SIZE.1)
+        else {
+            return false;
+        };
+        self.layout = ScreenLayout::default();
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui.as_mut() {
+            tui.render_too_small(size);
+            return true;
+        }
+        render_too_small(self.backend.as_mut(), size);
+        true
+    }
+
+    /// Draws signals, vehicles and road markings as `sprites`.
+    pub fn set_sprites(&mut self, sprites: SpriteSet) {
+        self.sprites = sprites;
+        self.backend.set_sprites(sprites);
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui.as_mut() {
+            tui.sprites = sprites;
+        }
+    }
+
+    /// Hands the current theme's screen colours to the backend, returning a
+    /// line naming the theme.
+    fn apply_theme(&mut self) -> String {
+        let theme = self.themes.current();
+        let (foreground, background) = theme.base_colours();
+        self.backend.set_base_colours(foreground, background);
+        format!("Theme: {}", theme.name)
+    }
+
+    /// Saves the last frame drawn to files for [`Action::Screenshot`].
+    fn screenshot(&self) -> Result<String, String> {
+        #[cfg(feature = "tui")]
+        if self.tui.is_some() {
+            return Err("Screenshots need the text interface".to_string());
+        }
+        let screen = self
+            .backend
+            .screen()
+            .ok_or("Nothing is drawn to take a screenshot of")?;
+        screenshot::save(screen)
+    }
+
+    /// Pause or rewind state shown next to the tick counter.
+    fn status(&self) -> String {
+        match self.history.rewound_seconds() {
+            Some(seconds) if self.paused => {
+                format!("⏪ {:.1}s back ([ / ] scrub, p resume from here)", seconds)
+            }
+            _ if self.paused => "⏸ PAUSED (. to step, [ to rewind)".to_string(),
+            _ if self.time_scale != 1.0 => format!("⏩ {}x", self.time_scale),
+            _ => String::new(),
+        }
+    }
+}
+
+/// The vehicle after `current` in ID order, wrapping around to the first.
+fn next_vehicle(sim: &Simulation, current: Option<u32>) -> Option<u32> {
+    let after = current.unwrap_or(0);
+    let ids = sim.vehicles.iter().map(|v| v.id);
+    ids.clone()
+        .filter(|&id| id > after)
+        .min()
+        .or_else(|| ids.min())
+}
@@ -1,124 +1,613 @@
+mod app;
+mod remote;
 mod systems;
+mod ui;
 
-use std::{
-    io::{stdout, Write},
-    thread::sleep,
-    time::Duration,
-};
-use systems::car::Car;
-use systems::traffic_light::TrafficLightState;
+use app::App;
+use remote::controller::ExternalController;
+use remote::http::HttpServer;
+use remote::mqtt::{MqttConfig, MqttPublisher};
+use remote::websocket::WebSocketServer;
+use std::io::{Write, stdout};
+use std::{env, fs, process, thread::sleep, time::Duration};
+use systems::bridge::BridgeConfig;
+use systems::career::Progress;
+use systems::challenge::{CHALLENGES, Challenge};
+use systems::clock::{ClockConfig, SimClock};
+use systems::closure;
+use systems::config::{Config, PerformanceConfig};
+use systems::delivery::DeliveryConfig;
+use systems::demand::DemandConfig;
+use systems::detector::DetectorConfig;
+use systems::driver::DriverConfig;
+use systems::emergency::EmergencyConfig;
+use systems::enforcement::{self, EnforcementCamera};
+use systems::experiment::{self, Scenario};
+use systems::malfunction::MalfunctionConfig;
+use systems::night_flash::NightFlashConfig;
+use systems::optimize;
+use systems::osm;
+use systems::parking::ParkingConfig;
+use systems::rideshare::RideShareConfig;
+use systems::rng::Rng;
+use systems::road::Road;
+use systems::rush_hour::RushHourConfig;
+use systems::school_zone;
+use systems::signal_control::{ControlMode, SignalConfig};
+use systems::simulation::Simulation;
+use systems::special_event::SpecialEvent;
+use systems::telemetry::Telemetry;
+use systems::toll::TollConfig;
+use systems::turns::TurnConfig;
+use systems::weather::{self, WeatherConfig, WeatherSystem, WeatherType};
+use ui::backend::{DisplayConfig, MapStyle, RenderBackend};
+use ui::cast::CastRecorder;
+use ui::input::InputHandler;
+use ui::sprites::SpriteSet;
+use ui::theme::Themes;
+use ui::tutorial::Tutorial;
 
-use crossterm::{
-    event::{poll, read, Event, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode},
-};
+use crossterm::event::{Event, MouseButton, MouseEventKind, poll, read};
+
+const FRAMES_PER_SECOND: u32 = 4;
+/// Where the map editor saves when no `--map` file was given.
+const DEFAULT_MAP_PATH: &str = "map.txt";
 
 fn main() {
-    let mut current = TrafficLightState::Red;
-    let mut cars: Vec<Car> = Vec::new();
-    let mut tick: u32 = 0;
-    let mut next_car_id = 1;
-    let lane_length: usize = 20;
+    let args: Vec<String> = env::args().collect();
+    let loaded = if let Some(path) = flag_value(&args, "--map") {
+        Road::load(path)
+    } else if let Some(path) = flag_value(&args, "--osm") {
+        osm::import(path)
+    } else {
+        Ok(Road::default())
+    };
+    let mut road = loaded.unwrap_or_else(|e| exit_with_error(&e));
+    let config = match flag_value(&args, "--config") {
+        Some(path) => Config::load(path).unwrap_or_else(|e| exit_with_error(&e)),
+        None => Config::default(),
+    };
+    let input = InputHandler::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    let performance =
+        PerformanceConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    let signals = SignalConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    let mut display = DisplayConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    let mut themes = Themes::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some(name) = flag_value(&args, "--theme") {
+        themes.select(name).unwrap_or_else(|e| exit_with_error(&e));
+    }
+    if args.iter().any(|arg| arg == "--bike-lane") {
+        road.bike_lane = true;
+    }
+    match args.get(1).map(String::as_str) {
+        Some("optimize") => return run_optimize(&args, road, signals),
+        Some("bench" | "sweep") => return run_bench(&args, road, signals),
+        _ => {}
+    }
+
+    let map_path = flag_value(&args, "--map")
+        .unwrap_or(DEFAULT_MAP_PATH)
+        .to_string();
+    let rng = match flag_value(&args, "--seed") {
+        Some(seed) => Rng::seeded(seed.parse().unwrap_or_else(|_| {
+            exit_with_error(&format!("--seed expects a whole number, got '{}'", seed))
+        })),
+        None => Rng::from_time(),
+    };
+    let progress = Progress::load();
+    let challenge = if let Some(name) = flag_value(&args, "--challenge") {
+        let index = Challenge::find(name).unwrap_or_else(|| {
+            let names: Vec<&str> = CHALLENGES.iter().map(|c| c.name).collect();
+            exit_with_error(&format!(
+                "Unknown challenge '{}'. Available: {}",
+                name,
+                names.join(", ")
+            ))
+        });
+        if !progress.is_unlocked(index) {
+            exit_with_error(&format!(
+                "Challenge '{}' is locked. Pass '{}' first, or play --career.",
+                CHALLENGES[index].name,
+                CHALLENGES[index - 1].name
+            ));
+        }
+        Some(index)
+    } else if args.iter().any(|arg| arg == "--career") {
+        Some(progress.current())
+    } else {
+        None
+    };
+    let mut sim = match challenge {
+        Some(index) => CHALLENGES[index].simulation(rng),
+        None => Simulation::new(road, rng),
+    };
+    signals
+        .check_lights(sim.lights.len())
+        .unwrap_or_else(|e| exit_with_error(&e));
+    sim.configure_signals(signals);
+    for index in
+        enforcement::camera_lights_from_config(&config).unwrap_or_else(|e| exit_with_error(&e))
+    {
+        let light = sim.lights.get_mut(index).unwrap_or_else(|| {
+            exit_with_error(&format!("[cameras] there is no light {}", index + 1))
+        });
+        light.camera = Some(EnforcementCamera::default());
+    }
+    sim.clock =
+        SimClock::new(ClockConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e)));
+    sim.demand = DemandConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.rush_hour = RushHourConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.emergency = EmergencyConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.drivers = DriverConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.tolls = TollConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.parking = ParkingConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.delivery = DeliveryConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.turns = TurnConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.night_flash =
+        NightFlashConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.malfunctions =
+        MalfunctionConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some(&index) = sim
+        .night_flash
+        .minor
+        .iter()
+        .find(|&&i| i >= sim.lights.len())
+    {
+        exit_with_error(&format!("[night_flash] there is no light {}", index + 1));
+    }
+    sim.loops = DetectorConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some((index, _)) = sim
+        .loops
+        .lights
+        .iter()
+        .find(|(i, _)| *i >= sim.lights.len())
+    {
+        exit_with_error(&format!("[detectors] there is no light {}", index + 1));
+    }
+    sim.rideshare = RideShareConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    sim.special_event = SpecialEvent::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if sim
+        .special_event
+        .venue
+        .is_some_and(|cell| cell >= sim.road.length)
+    {
+        exit_with_error("[special_event] venue is off the road");
+    }
+    sim.configure_bridge(
+        BridgeConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e)),
+    );
+    sim.closures = closure::closures_from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some(off) = sim.closures.iter().find(|c| c.to >= sim.road.length) {
+        exit_with_error(&format!("[closures] {} runs off the road", off.name));
+    }
+    sim.school_zones =
+        school_zone::school_zones_from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some(off) = sim.school_zones.iter().find(|z| z.to >= sim.road.length) {
+        exit_with_error(&format!("[school_zones] {} runs off the road", off.name));
+    }
+    sim.weather = WeatherSystem::new(
+        WeatherConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e)),
+    );
+    if performance.parallel_updates {
+        enable_parallel_updates(&mut sim);
+    }
+    if args.iter().any(|arg| arg == "--graphics") {
+        display.map = MapStyle::Graphics;
+    }
+    if args.iter().any(|arg| arg == "--half-blocks") {
+        display.map = MapStyle::HalfBlocks;
+    }
+    if args.iter().any(|arg| arg == "--tui") {
+        display.tui = true;
+    }
+    if let Some(name) = flag_value(&args, "--sprites") {
+        display.sprites = SpriteSet::from_name(name).unwrap_or_else(|| {
+            exit_with_error(&format!(
+                "--sprites must be emoji, unicode or ascii, not '{}'",
+                name
+            ))
+        });
+    }
+    let mut output: Box<dyn Write> = match flag_value(&args, "--record-cast") {
+        Some(path) => Box::new(CastRecorder::create(path).unwrap_or_else(|e| exit_with_error(&e))),
+        None => Box::new(stdout()),
+    };
+    // The widget interface draws every frame itself; the backend then only
+    // sets up and restores the terminal.
+    let tui_output = (display.tui && !display.headless)
+        .then(|| std::mem::replace(&mut output, Box::new(stdout())));
+    let mut backend = display.backend(output);
+    if let Some(path) = flag_value(&args, "--record-gif") {
+        if display.tui {
+            exit_with_error("--record-gif records the text frame and can't be used with --tui");
+        }
+        backend = record_gif(backend, path);
+    }
+    let mut app = App::new(sim, input, map_path, &performance, themes, backend);
+    if let Some(output) = tui_output {
+        use_tui(&mut app, output);
+    }
+    app.set_sprites(display.sprites);
+    app.challenge = challenge;
+    app.progress = progress;
+    app.config_path = flag_value(&args, "--config").map(str::to_string);
+    if let Some(path) = flag_value(&args, "--stats-out") {
+        // Each run starts a fresh file so the header matches this road.
+        fs::write(path, "")
+            .unwrap_or_else(|e| exit_with_error(&format!("Failed to create {}: {}", path, e)));
+        app.stats_out = Some(path.to_string());
+    }
+    if let Some(path) = flag_value(&args, "--telemetry") {
+        app.telemetry = Some(
+            Telemetry::create(path, performance.telemetry_interval)
+                .unwrap_or_else(|e| exit_with_error(&e)),
+        );
+    }
+    load_lua_controllers(&mut app, &config);
+    if let Some(path) = flag_value(&args, "--script") {
+        load_script(&mut app, path);
+    }
+    if let Some(command) = flag_value(&args, "--controller") {
+        app.controller =
+            Some(ExternalController::spawn(command).unwrap_or_else(|e| exit_with_error(&e)));
+    }
+    if let Some(address) = flag_value(&args, "--http") {
+        app.http = Some(HttpServer::start(address).unwrap_or_else(|e| exit_with_error(&e)));
+    }
+    if let Some(address) = flag_value(&args, "--ws") {
+        app.websocket =
+            Some(WebSocketServer::start(address).unwrap_or_else(|e| exit_with_error(&e)));
+    }
+    let mut mqtt = MqttConfig::from_config(&config).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some(broker) = flag_value(&args, "--mqtt") {
+        mqtt.broker = broker.to_string();
+    }
+    if !mqtt.broker.is_empty() {
+        app.mqtt =
+            Some(MqttPublisher::connect(mqtt, &app.sim).unwrap_or_else(|e| exit_with_error(&e)));
+    }
+    if let Some(path) = flag_value(&args, "--socket") {
+        open_socket(&mut app, path);
+    }
+    if let Some(address) = flag_value(&args, "--grpc") {
+        start_grpc(&mut app, address);
+    }
+    if let Some(path) = flag_value(&args, "--db") {
+        open_database(&mut app, path, &args, performance.stats_interval);
+    }
+    if args.iter().any(|arg| arg == "--tutorial") {
+        app.tutorial = Some(Tutorial::new(&app.sim));
+    }
+    let dt = 1.0 / FRAMES_PER_SECOND as f32;
 
-    enable_raw_mode().expect("Failed to enable raw mode");
+    app.backend
+        .init()
+        .unwrap_or_else(|e| exit_with_error(&format!("Failed to set up the terminal: {}", e)));
 
     loop {
-        let light_duration = match current {
-            TrafficLightState::Red => 5,
-            TrafficLightState::Green => 5,
-            TrafficLightState::Yellow => 2,
-        };
-
-        for remaining in (1..=light_duration).rev() {
-            if poll(Duration::from_millis(100)).unwrap() {
-                if let Event::Key(key_event) = read().unwrap() {
-                    if key_event.code == KeyCode::Char('q') {
-                        println!("\n\n👋 Quitting simulation...\n");
-                        disable_raw_mode().unwrap();
-                        return;
-                    }
-                }
+        // A headless run may have no terminal to read at all; it is driven
+        // over the network instead.
+        if !display.headless {
+            handle_terminal_events(&mut app);
+        }
+        if app.quitting {
+            if let Err(e) = app.backend.restore() {
+                eprintln!("Failed to restore the terminal: {}", e);
+            }
+            println!("\n\n👋 Quitting simulation...\n");
+            if let Some(profiler) = &app.profiler {
+                print!("{}", profiler.summary());
             }
+            return;
+        }
 
-            print!("\x1B[2J\x1B[1;1H");
-            stdout().flush().unwrap();
+        app.frame(dt);
+        sleep(Duration::from_millis(1000 / FRAMES_PER_SECOND as u64));
+    }
+}
 
-            println!();
-            println!("===============================");
-            println!("     TRAFFIC LIGHT SIMULATOR");
-            println!("===============================");
-            println!();
+/// Passes the key presses, clicks and resizes waiting on the terminal to
+/// the app. A terminal that can no longer be read ends the run.
+fn handle_terminal_events(app: &mut App) {
+    loop {
+        let event =
+            match poll(Duration::from_millis(10)).and_then(|ready| ready.then(read).transpose()) {
+                Ok(Some(event)) => event,
+                Ok(None) => return,
+                Err(e) => {
+                    eprintln!("Failed to read the terminal: {}", e);
+                    app.quitting = true;
+                    return;
+                }
+            };
+        match event {
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                app.handle_click(mouse.column, mouse.row)
+            }
+            Event::Key(key_event) => app.handle_key(key_event.code),
+            Event::Resize(columns, rows) => app.resize(columns, rows),
+            _ => {}
+        }
+    }
+}
 
-            println!("Tick: {}", tick);
-            println!();
+/// `optimize`: searches for fixed-time plans that cut the average wait on
+/// the chosen road or challenge, printing progress and the best plan.
+fn run_optimize(args: &[String], road: Road, signals: SignalConfig) {
+    let scenario = scenario_from_args(args, road, signals, "1,2,3,4,5");
+    let generations = flag_value(args, "--generations")
+        .map_or(20, |value| number_flag(value, "--generations") as usize);
+    let population = flag_value(args, "--population")
+        .map_or(16, |value| number_flag(value, "--population") as usize);
+    let rng = match flag_value(args, "--seed") {
+        Some(seed) => Rng::seeded(seed.parse().unwrap_or_else(|_| {
+            exit_with_error(&format!("--seed expects a whole number, got '{}'", seed))
+        })),
+        None => Rng::from_time(),
+    };
+    let start: Vec<_> = (0..scenario.light_count())
+        .map(|index| scenario.signals.timing_for(index))
+        .collect();
+    if start.is_empty() {
+        exit_with_error("The road has no signals to optimize");
+    }
+    let before = scenario.delay(&start);
+    println!(
+        "Starting plan: {:.2}s average wait (95% confidence interval {}) over {} seeds",
+        before.mean,
+        before.interval(),
+        scenario.seeds.len()
+    );
+    let mut optimizer = optimize::Optimizer::new(population, generations.max(1), rng);
+    let (plan, delay) = optimizer.run(&scenario, start, |generation, best| {
+        println!(
+            "Generation {}/{}: best {:.2}s",
+            generation,
+            generations.max(1),
+            best.mean
+        );
+    });
+    println!(
+        "\nBest plan ({:.2}s average wait, 95% confidence interval {}):\n",
+        delay.mean,
+        delay.interval()
+    );
+    print!("{}", optimize::timing_section(&plan));
+    if let Some(path) = flag_value(args, "--write") {
+        optimize::write_plan(path, &plan).unwrap_or_else(|e| exit_with_error(&e));
+        println!("\nWritten to {}", path);
+    }
+}
 
-            let light_symbol = match current {
-                TrafficLightState::Red => "🟥",
-                TrafficLightState::Green => "🟩",
-                TrafficLightState::Yellow => "🟨",
-            };
-            println!("Current light: {} ({}s left)", light_symbol, remaining);
-            println!();
+/// `bench` (or `sweep`): runs every combination of control mode, weather
+/// and spawn rate over the same seeds and prints the wait and throughput of
+/// each.
+fn run_bench(args: &[String], road: Road, signals: SignalConfig) {
+    let runs = flag_value(args, "--runs").map_or(10, |value| number_flag(value, "--runs") as u64);
+    let seeds: Vec<String> = (1..=runs.max(1)).map(|seed| seed.to_string()).collect();
+    let scenario = scenario_from_args(args, road, signals, &seeds.join(","));
+    let modes: Vec<ControlMode> = flag_value(args, "--modes")
+        .unwrap_or("fixed,actuated,adaptive")
+        .split(',')
+        .map(|name| {
+            ControlMode::from_name(name.trim()).unwrap_or_else(|| {
+                exit_with_error(&format!("--modes: unknown control mode '{}'", name))
+            })
+        })
+        .collect();
+    let weathers: Vec<Option<WeatherType>> = match flag_value(args, "--weathers") {
+        Some(names) => names
+            .split(',')
+            .map(|name| {
+                Some(WeatherType::from_name(name.trim()).unwrap_or_else(|| {
+                    exit_with_error(&format!(
+                        "--weathers: unknown weather '{}' (try {})",
+                        name,
+                        weather::names()
+                    ))
+                }))
+            })
+            .collect(),
+        None => vec![None],
+    };
+    let spawn_rates: Vec<f32> = flag_value(args, "--spawn-rates")
+        .unwrap_or("0.2,0.35,0.5,0.8")
+        .split(',')
+        .map(|rate| number_flag(rate.trim(), "--spawn-rates").min(1.0))
+        .collect();
 
-            println!("Vehicle count: {}", next_car_id - 1);
-            println!();
-            println!();
+    println!(
+        "{} runs of {:.0}s per combination\n",
+        scenario.seeds.len(),
+        scenario.duration
+    );
+    println!(
+        "{:<10} {:<9} {:>10}  {:<28} throughput (/min)",
+        "mode", "weather", "spawn rate", "avg wait (s)"
+    );
+    let rows = experiment::sweep(&scenario, &modes, &weathers, &spawn_rates, |row| {
+        let wait = format!("{:.2} {}", row.wait.mean, row.wait.interval());
+        println!(
+            "{:<10} {:<9} {:>10.2}  {:<28} {:.2} {}",
+            row.mode.name(),
+            experiment::weather_name(row.weather),
+            row.spawn_rate,
+            wait,
+            row.throughput.mean,
+            row.throughput.interval()
+        );
+    });
+    println!(
+        "\nBrackets are 95% confidence intervals for the mean. Where two rows' intervals\ndon't overlap, the difference is unlikely to be noise; more --runs narrow them."
+    );
+    if let Some(path) = flag_value(args, "--out") {
+        experiment::write_results(path, &rows).unwrap_or_else(|e| exit_with_error(&e));
+        println!("\nWritten to {}", path);
+    }
+}
 
-            if tick % 3 == 0 && remaining == light_duration {
-                cars.push(Car::new(next_car_id));
-                next_car_id += 1;
-            }
+/// The headless runs picked by `--challenge`, `--seeds` and `--duration`
+/// for the batch subcommands.
+fn scenario_from_args(
+    args: &[String],
+    road: Road,
+    signals: SignalConfig,
+    default_seeds: &str,
+) -> Scenario {
+    let challenge = flag_value(args, "--challenge").map(|name| {
+        Challenge::find(name)
+            .unwrap_or_else(|| exit_with_error(&format!("Unknown challenge '{}'", name)))
+    });
+    let seeds = flag_value(args, "--seeds")
+        .unwrap_or(default_seeds)
+        .split(',')
+        .map(|seed| {
+            seed.trim().parse().unwrap_or_else(|_| {
+                exit_with_error(&format!("--seeds expects whole numbers, got '{}'", seed))
+            })
+        })
+        .collect();
+    let duration = match flag_value(args, "--duration") {
+        Some(value) => number_flag(value, "--duration"),
+        None => challenge.map_or(120.0, |index| CHALLENGES[index].duration),
+    };
+    let scenario = Scenario {
+        road,
+        challenge,
+        signals,
+        spawn_rate: None,
+        weather: None,
+        seeds,
+        duration,
+    };
+    scenario
+        .signals
+        .check_lights(scenario.light_count())
+        .unwrap_or_else(|e| exit_with_error(&e));
+    scenario
+}
 
-            for car in cars.iter_mut() {
-                let can_move = match current {
-                    TrafficLightState::Green => true,
-                    TrafficLightState::Red => false,
-                    TrafficLightState::Yellow => {
-                        !car.should_stop_for_yellow(lane_length)
-                    }
-                };
-                car.update(can_move);
-            }
+/// A non-negative number given to `flag`, or exits explaining what was wrong.
+fn number_flag(value: &str, flag: &str) -> f32 {
+    value
+        .parse()
+        .ok()
+        .filter(|n: &f32| *n >= 0.0)
+        .unwrap_or_else(|| exit_with_error(&format!("{} expects a number, got '{}'", flag, value)))
+}
 
-            let mut lane_top = vec!["  "; lane_length];
-            for car in &cars {
-                if car.position < lane_length {
-                    lane_top[car.position] = car.lane_symbol();
-                }
-            }
+/// Starts recording the run into a SQLite file, labelled with the
+/// challenge or map it was started with.
+#[cfg(feature = "sqlite")]
+fn open_database(app: &mut App, path: &str, args: &[String], interval: f32) {
+    let preset = match app.challenge {
+        Some(index) => CHALLENGES[index].name,
+        None => flag_value(args, "--map")
+            .or_else(|| flag_value(args, "--osm"))
+            .unwrap_or("default"),
+    };
+    let database = systems::database::Database::open(path, &app.sim, preset, interval)
+        .unwrap_or_else(|e| exit_with_error(&e));
+    app.database = Some(database);
+}
 
-            let lane_bottom = vec!["🛣️"; lane_length];
+#[cfg(not(feature = "sqlite"))]
+fn open_database(_: &mut App, _: &str, _: &[String], _: f32) {
+    exit_with_error("--db needs a build with the sqlite feature: cargo run --features sqlite");
+}
 
-            print!("{}   ", light_symbol);
-            for symbol in &lane_top {
-                print!("{}", symbol);
-            }
-            println!();
+#[cfg(feature = "parallel")]
+fn enable_parallel_updates(sim: &mut Simulation) {
+    sim.parallel = true;
+}
 
-            print!("    ");
-            for symbol in &lane_bottom {
-                print!("{}", symbol);
-            }
-            println!();
+#[cfg(not(feature = "parallel"))]
+fn enable_parallel_updates(_: &mut Simulation) {
+    exit_with_error(
+        "parallel_updates needs a build with the parallel feature: cargo run --features parallel",
+    );
+}
 
-            println!();
-            println!();
+#[cfg(feature = "gif")]
+fn record_gif(backend: Box<dyn RenderBackend>, path: &str) -> Box<dyn RenderBackend> {
+    Box::new(ui::clip::ClipBackend::create(path, backend).unwrap_or_else(|e| exit_with_error(&e)))
+}
 
-            sleep(Duration::from_secs(1));
-        }
+#[cfg(not(feature = "gif"))]
+fn record_gif(_: Box<dyn RenderBackend>, _: &str) -> Box<dyn RenderBackend> {
+    exit_with_error("--record-gif needs a build with the gif feature: cargo run --features gif");
+}
 
-        current = current.next();
-        tick += 1;
-    }
+#[cfg(feature = "tui")]
+fn use_tui(app: &mut App, output: Box<dyn Write>) {
+    let tui = ui::tui::TuiFrontend::new(&app.input, output)
+        .unwrap_or_else(|e| exit_with_error(&format!("Failed to set up the terminal: {}", e)));
+    app.tui = Some(tui);
 }
 
+#[cfg(not(feature = "tui"))]
+fn use_tui(_: &mut App, _: Box<dyn Write>) {
+    exit_with_error("--tui needs a build with the tui feature: cargo run --features tui");
+}
 
+#[cfg(feature = "lua")]
+fn load_lua_controllers(app: &mut App, config: &Config) {
+    app.lua = systems::lua_control::LuaControllers::from_config(config, &app.sim)
+        .unwrap_or_else(|e| exit_with_error(&e));
+}
 
+#[cfg(not(feature = "lua"))]
+fn load_lua_controllers(_: &mut App, config: &Config) {
+    if !config.section("lua").is_empty() {
+        exit_with_error(
+            "[lua] controllers need a build with the lua feature: cargo run --features lua",
+        );
+    }
+}
 
+#[cfg(feature = "scripting")]
+fn load_script(app: &mut App, path: &str) {
+    let script =
+        systems::script::Script::load(path, &app.sim).unwrap_or_else(|e| exit_with_error(&e));
+    app.script = Some(script);
+}
 
+#[cfg(not(feature = "scripting"))]
+fn load_script(_: &mut App, _: &str) {
+    exit_with_error(
+        "--script needs a build with the scripting feature: cargo run --features scripting",
+    );
+}
 
+#[cfg(unix)]
+fn open_socket(app: &mut App, path: &str) {
+    let socket = remote::socket::ControlSocket::bind(path).unwrap_or_else(|e| exit_with_error(&e));
+    app.socket = Some(socket);
+}
 
+#[cfg(not(unix))]
+fn open_socket(_: &mut App, _: &str) {
+    exit_with_error("--socket needs Unix domain sockets, which this platform lacks");
+}
 
+#[cfg(feature = "grpc")]
+fn start_grpc(app: &mut App, address: &str) {
+    let server = remote::grpc::GrpcServer::start(address).unwrap_or_else(|e| exit_with_error(&e));
+    app.grpc = Some(server);
+}
 
+#[cfg(not(feature = "grpc"))]
+fn start_grpc(_: &mut App, _: &str) {
+    exit_with_error("--grpc needs a build with the grpc feature: cargo run --features grpc");
+}
 
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
 
+/// Value following `flag` on the command line, e.g. the path in `--map path`.
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    Some(args.get(index + 1).map(String::as_str).unwrap_or_default())
+}
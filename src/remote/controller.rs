@@ -0,0 +1,167 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+
+/// Simulated seconds between decisions.
+const DECISION_SECONDS: f32 = 1.0;
+/// How long the main loop waits for an answer. A controller that takes
+/// longer is treated as failed, so a hung program can't freeze the screen.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Signal control handed to an outside program. Every simulated second it
+/// gets one JSON line on stdin describing each light and its detectors, and
+/// must answer with one line holding a word per light: `red`, `yellow`,
+/// `green`, or `keep`. Lights only change when the program says so.
+pub struct ExternalController {
+    command: String,
+    child: Child,
+    input: ChildStdin,
+    /// Lines the program prints, read on a background thread; an error once
+    /// its output ends.
+    replies: Receiver<Result<String, String>>,
+    /// Elapsed simulated time at which the next decision is due.
+    next: f32,
+}
+
+impl ExternalController {
+    /// Starts `command` through the shell. Its stderr is discarded, since
+    /// the terminal belongs to the simulator; log to a file instead.
+    pub fn spawn(command: &str) -> Result<Self, String> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut child = Command::new(shell)
+            .args([flag, command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start controller '{}': {}", command, e))?;
+        let input = child.stdin.take().expect("stdin is piped");
+        let mut output = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let (sender, replies) = mpsc::channel();
+        thread::spawn(move || {
+            loop {
+                let mut line = String::new();
+                let reply = match output.read_line(&mut line) {
+                    Ok(0) => Err("exited".to_string()),
+                    Ok(_) => Ok(line),
+                    Err(e) => Err(format!("failed: {}", e)),
+                };
+                let done = reply.is_err();
+                if sender.send(reply).is_err() || done {
+                    return;
+                }
+            }
+        });
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            input,
+            replies,
+            next: 0.0,
+        })
+    }
+
+    /// Asks the controller for a decision if one is due and applies it.
+    pub fn update(&mut self, sim: &mut Simulation) -> Result<(), String> {
+        for light in &mut sim.lights {
+            light.held = true;
+        }
+        let elapsed = sim.stats.elapsed;
+        if self.next > elapsed + DECISION_SECONDS {
+            self.next = elapsed;
+        }
+        if elapsed < self.next {
+            return Ok(());
+        }
+        self.next += DECISION_SECONDS;
+
+        let failed = |e: std::io::Error| format!("Controller '{}' failed: {}", self.command, e);
+        let mut request = detector_json(sim);
+        request.push('\n');
+        self.input
+            .write_all(request.as_bytes())
+            .and_then(|_| self.input.flush())
+            .map_err(failed)?;
+        let reply = match self.replies.recv_timeout(REPLY_TIMEOUT) {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(problem)) => return Err(format!("Controller '{}' {}", self.command, problem)),
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(format!(
+                    "Controller '{}' did not answer within {}s",
+                    self.command,
+                    REPLY_TIMEOUT.as_secs()
+                ));
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(format!("Controller '{}' exited", self.command));
+            }
+        };
+
+        let words: Vec<&str> = reply.split_whitespace().collect();
+        if words.len() != sim.lights.len() {
+            return Err(format!(
+                "Controller replied '{}' but there are {} lights",
+                reply.trim(),
+                sim.lights.len()
+            ));
+        }
+        for (light, word) in sim.lights.iter_mut().zip(words) {
+            let state = match word {
+                "keep" => continue,
+                "red" => TrafficLightState::Red,
+                "yellow" => TrafficLightState::Yellow,
+                "green" => TrafficLightState::Green,
+                other => return Err(format!("Controller sent unknown phase '{}'", other)),
+            };
+            if light.state != state {
+                light.force(state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops the program and gives the lights back to their own timers.
+    pub fn release(mut self, sim: &mut Simulation) {
+        for light in &mut sim.lights {
+            light.held = false;
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The line sent for each decision: the time, and per light its state, how
+/// long it has been in it, and what its detectors see.
+fn detector_json(sim: &Simulation) -> String {
+    let mut out = format!("{{\"time\": {:.2}, \"lights\": [", sim.stats.elapsed);
+    for (index, light) in sim.lights.iter().enumerate() {
+        let detectors = sim.detectors(index);
+        if index > 0 {
+            out.push_str(", ");
+        }
+        let _ = write!(
+            out,
+            "{{\"light\": {}, \"position\": {}, \"state\": \"{}\", \"elapsed\": {:.2}, \"queue\": {}, \"bike_queue\": {}, \"approaching\": {}, \"calls\": {}}}",
+            index + 1,
+            light.position,
+            detectors.state.name(),
+            detectors.elapsed,
+            detectors.queue,
+            detectors.bike_queue,
+            detectors.approaching,
+            detectors.calls
+        );
+    }
+    out.push_str("]}");
+    out
+}
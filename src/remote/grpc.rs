@@ -0,0 +1,192 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use tokio::net::TcpListener;
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+use crate::app::App;
+use crate::remote::{listen_address, refused_over_network};
+use crate::systems::events::SimEvent;
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+use crate::systems::vehicle::{Lane, VehicleType};
+
+mod proto {
+    tonic::include_proto!("traffic");
+}
+
+use proto::event::Event as EventKind;
+use proto::traffic_simulator_server::{TrafficSimulator, TrafficSimulatorServer};
+use proto::{Event, EventReply, GetStateRequest, Light, State, StepRequest, Vehicle};
+
+/// Most steps one `Step` call may run: ten simulated minutes.
+const MAX_STEPS: u32 = 16 * 600;
+
+/// Work for the main loop, which owns the app, sent by an RPC handler.
+type Job = Box<dyn FnOnce(&mut App) + Send>;
+
+/// gRPC service defined in `proto/traffic.proto`. RPCs run on a background
+/// Tokio runtime and hand their work to the main loop, which runs it at the
+/// start of the next frame.
+pub struct GrpcServer {
+    jobs: Receiver<Job>,
+}
+
+impl GrpcServer {
+    /// Listens on `address`, e.g. `127.0.0.1:50051`. A bare port listens on
+    /// localhost.
+    pub fn start(address: &str) -> Result<Self, String> {
+        let address = listen_address(address);
+        let runtime = Runtime::new().map_err(|e| format!("Failed to start gRPC: {}", e))?;
+        let listener = runtime
+            .block_on(TcpListener::bind(&address))
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        let (sender, jobs) = mpsc::channel();
+        let service = Service { jobs: sender };
+        thread::spawn(move || {
+            runtime.block_on(async move {
+                let incoming = TcpIncoming::from_listener(listener, true, None)
+                    .expect("listener is already bound");
+                let _ = Server::builder()
+                    .add_service(TrafficSimulatorServer::new(service))
+                    .serve_with_incoming(incoming)
+                    .await;
+            })
+        });
+        Ok(Self { jobs })
+    }
+
+    /// Jobs that arrived since the last call.
+    pub fn pending(&self) -> Vec<Job> {
+        self.jobs.try_iter().collect()
+    }
+}
+
+struct Service {
+    jobs: Sender<Job>,
+}
+
+impl Service {
+    /// Runs `job` on the main loop and waits for its result.
+    async fn call<T: Send + 'static>(
+        &self,
+        job: impl FnOnce(&mut App) -> T + Send + 'static,
+    ) -> Result<T, Status> {
+        let (reply, answer) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |app| {
+                let _ = reply.send(job(app));
+            }))
+            .map_err(|_| Status::unavailable("Simulation has stopped"))?;
+        answer
+            .await
+            .map_err(|_| Status::unavailable("Simulation has stopped"))
+    }
+}
+
+#[tonic::async_trait]
+impl TrafficSimulator for Service {
+    async fn step(&self, request: Request<StepRequest>) -> Result<Response<State>, Status> {
+        let steps = request.into_inner().steps;
+        if steps > MAX_STEPS {
+            return Err(Status::invalid_argument(format!(
+                "At most {} steps per call",
+                MAX_STEPS
+            )));
+        }
+        let state = self
+            .call(move |app| {
+                app.paused = true;
+                for _ in 0..steps {
+                    app.step();
+                }
+                state(&app.sim)
+            })
+            .await?;
+        Ok(Response::new(state))
+    }
+
+    async fn get_state(&self, _: Request<GetStateRequest>) -> Result<Response<State>, Status> {
+        Ok(Response::new(self.call(|app| state(&app.sim)).await?))
+    }
+
+    async fn inject_event(&self, request: Request<Event>) -> Result<Response<EventReply>, Status> {
+        let kind = request
+            .into_inner()
+            .event
+            .ok_or_else(|| Status::invalid_argument("Event is empty"))?;
+        let event = match kind {
+            EventKind::Command(line) => {
+                if let Some(refusal) = refused_over_network(&line) {
+                    return Err(Status::permission_denied(refusal));
+                }
+                let message = self
+                    .call(move |app| app.run_command(&line))
+                    .await?
+                    .map_err(Status::invalid_argument)?;
+                return Ok(Response::new(EventReply { message }));
+            }
+            EventKind::Spawn(spawn) => {
+                let kind = VehicleType::from_key(&spawn.kind).ok_or_else(|| {
+                    Status::invalid_argument(format!("Unknown vehicle type '{}'", spawn.kind))
+                })?;
+                SimEvent::Spawn(kind, spawn.cell.map(|cell| cell as usize))
+            }
+            EventKind::Breakdown(cell) => SimEvent::Breakdown(cell as usize),
+            EventKind::ToggleSpawn(cell) => SimEvent::ToggleSpawn(cell as usize),
+            EventKind::ForceLight(force) => {
+                let state = TrafficLightState::from_name(&force.state).ok_or_else(|| {
+                    Status::invalid_argument(format!("Unknown light state '{}'", force.state))
+                })?;
+                let index = (force.light as usize)
+                    .checked_sub(1)
+                    .ok_or_else(|| Status::invalid_argument("Lights are numbered from 1"))?;
+                SimEvent::ForceLight(index, state)
+            }
+            EventKind::Despawn(id) => SimEvent::Despawn(id),
+        };
+        let message = self
+            .call(move |app| app.sim.apply(event))
+            .await?
+            .map_err(Status::invalid_argument)?;
+        Ok(Response::new(EventReply { message }))
+    }
+}
+
+fn state(sim: &Simulation) -> State {
+    State {
+        time: sim.stats.elapsed,
+        tick: sim.tick,
+        completed_trips: sim.stats.completed_trips,
+        average_wait: sim.stats.average_wait(),
+        throughput: sim.stats.throughput(),
+        lights: sim
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(index, light)| Light {
+                position: light.position as u32,
+                state: light.state.name().to_string(),
+                remaining: light.remaining(),
+                queue: sim.queue_length(index, Lane::Road) as u32,
+            })
+            .collect(),
+        vehicles: sim
+            .vehicles
+            .iter()
+            .map(|vehicle| Vehicle {
+                id: vehicle.id,
+                kind: vehicle.kind.key().to_string(),
+                lane: vehicle.lane.key().to_string(),
+                position: vehicle.position,
+                speed: vehicle.speed,
+                state: vehicle.state_label().to_string(),
+                waited: vehicle.waited_time,
+            })
+            .collect(),
+    }
+}
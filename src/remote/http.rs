@@ -0,0 +1,308 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::app::App;
+use crate::remote::{listen_address, refused_over_network};
+use crate::systems::telemetry::{intersection_json, to_json_line};
+use crate::systems::vehicle::VehicleType;
+
+/// How long a connection waits for the simulation to answer. The main loop
+/// answers once per frame, so this only runs out if it has stopped.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 64 * 1024;
+/// Longest request line or header line accepted, in bytes.
+const MAX_LINE: usize = 8 * 1024;
+/// Most bytes the request line and headers may take together.
+const MAX_HEAD: usize = 32 * 1024;
+/// How long a client may take to send its request or read the answer
+/// before the connection is dropped.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections served at once; more are turned away with a 503.
+const MAX_CONNECTIONS: usize = 32;
+
+/// A parsed request waiting for the main loop to answer it.
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+    reply: Sender<(u16, String)>,
+}
+
+impl HttpRequest {
+    pub fn respond(self, status: u16, body: String) {
+        // The client may have given up already; nothing to do then.
+        let _ = self.reply.send((status, body));
+    }
+}
+
+/// Small embedded HTTP/1.1 server. Connections are read on background
+/// threads and handed to the main loop, which owns the simulation.
+pub struct HttpServer {
+    requests: Receiver<HttpRequest>,
+}
+
+impl HttpServer {
+    /// Listens on `address`, e.g. `127.0.0.1:8080`. A bare port listens on
+    /// localhost.
+    pub fn start(address: &str) -> Result<Self, String> {
+        let address = listen_address(address);
+        let listener = TcpListener::bind(&address)
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        let (sender, requests) = mpsc::channel();
+        let open = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err()
+                    || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err()
+                {
+                    continue;
+                }
+                if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    open.fetch_sub(1, Ordering::SeqCst);
+                    write_response(&mut stream, 503, &error_json("Too many connections"));
+                    continue;
+                }
+                let sender = sender.clone();
+                let open = Arc::clone(&open);
+                thread::spawn(move || {
+                    handle_connection(stream, sender);
+                    open.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Self { requests })
+    }
+
+    /// Requests that arrived since the last call.
+    pub fn pending(&self) -> Vec<HttpRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, sender: Sender<HttpRequest>) {
+    let (status, body) = match read_request(&stream) {
+        Ok((method, path, body)) => {
+            let (reply, answer) = mpsc::channel();
+            let request = HttpRequest {
+                method,
+                path,
+                body,
+                reply,
+            };
+            if sender.send(request).is_err() {
+                return;
+            }
+            answer
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| (503, error_json("Simulation is not responding")))
+        }
+        Err((status, message)) => (status, error_json(&message)),
+    };
+    write_response(&mut stream, status, &body);
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &str) {
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    );
+}
+
+/// Method, path and body of the request on `stream`, or the status and
+/// message to turn it away with.
+fn read_request(stream: impl Read) -> Result<(String, String, String), (u16, String)> {
+    let mut reader = BufReader::new(stream);
+    let mut head_left = MAX_HEAD;
+    let line = read_head_line(&mut reader, &mut head_left, 400)?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(path)) = (words.next(), words.next()) else {
+        return Err((400, "Malformed request line".to_string()));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        let header = read_head_line(&mut reader, &mut head_left, 431)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value
+                .trim()
+                .parse()
+                .map_err(|_| (400, "Bad Content-Length".to_string()))?;
+        }
+    }
+    if length > MAX_BODY {
+        return Err((400, format!("Body larger than {} bytes", MAX_BODY)));
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| (400, format!("Failed to read body: {}", e)))?;
+    let body = String::from_utf8(body).map_err(|_| (400, "Body is not UTF-8".to_string()))?;
+    Ok((method, path, body))
+}
+
+/// One line of the request line and headers, reading no more than
+/// [`MAX_LINE`] bytes or what is left of `head_left`, so a client can't
+/// make the server buffer without end. A line that doesn't fit is answered
+/// with `too_long`, or 431 once the headers as a whole are too large.
+fn read_head_line(
+    reader: &mut impl BufRead,
+    head_left: &mut usize,
+    too_long: u16,
+) -> Result<String, (u16, String)> {
+    let limit = MAX_LINE.min(*head_left);
+    let mut line = String::new();
+    let read = reader
+        .take(limit as u64)
+        .read_line(&mut line)
+        .map_err(|e| (400, format!("Failed to read request: {}", e)))?;
+    if read == limit && !line.ends_with('\n') {
+        return Err(if limit < MAX_LINE {
+            (431, format!("Headers larger than {} bytes", MAX_HEAD))
+        } else {
+            (too_long, format!("Line longer than {} bytes", MAX_LINE))
+        });
+    }
+    *head_left -= read;
+    Ok(line)
+}
+
+/// Answers a request against the running app. Query endpoints return JSON
+/// state; command endpoints go through the console grammar, so they behave
+/// exactly like typing the command after `:`, except that `save` is refused:
+/// a network client must not write files on this machine.
+pub fn route(app: &mut App, method: &str, path: &str, body: &str) -> (u16, String) {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let command = match (method, segments.as_slice()) {
+        ("GET", ["stats"]) => return (200, to_json_line(&app.sim)),
+        ("GET", ["intersections"]) => {
+            let lights: Vec<String> = (0..app.sim.lights.len())
+                .map(|index| intersection_json(&app.sim, index))
+                .collect();
+            return (200, format!("[{}]", lights.join(", ")));
+        }
+        ("GET", ["intersections", id]) => {
+            return match id.parse::<usize>() {
+                Ok(number) if (1..=app.sim.lights.len()).contains(&number) => {
+                    (200, intersection_json(&app.sim, number - 1))
+                }
+                _ => (404, error_json(&format!("No intersection {}", id))),
+            };
+        }
+        ("POST", ["spawn-rate"]) => format!("set spawn_rate {}", body.trim()),
+        ("POST", ["breakdown-rate"]) => format!("set breakdown_rate {}", body.trim()),
+        ("POST", ["spawn"]) => format!("spawn {}", body.trim()),
+        ("POST", ["command"]) => body.trim().to_string(),
+        ("POST", ["weather"]) => format!("weather {}", body.trim()),
+        ("POST", ["emergency"]) => match VehicleType::from_key(body.trim()) {
+            Some(kind) if kind.is_emergency() => format!("spawn {}", kind.key()),
+            _ => {
+                return (
+                    400,
+                    error_json("Body must be ambulance, fire_truck or police"),
+                );
+            }
+        },
+        _ => {
+            return (
+                404,
+                error_json(&format!("No route for {} {}", method, path)),
+            );
+        }
+    };
+    if let Some(refusal) = refused_over_network(&command) {
+        return (403, error_json(&refusal));
+    }
+    match app.run_command(&command) {
+        Ok(message) => (200, format!("{{\"message\": {}}}", json_string(&message))),
+        Err(message) => (400, error_json(&message)),
+    }
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\": {}}}", json_string(message))
+}
+
+/// `text` as a quoted JSON string.
+pub fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        431 => "Request Header Fields Too Large",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("say \"hi\"\\"), "\"say \\\"hi\\\"\\\\\"");
+        assert_eq!(json_string("a\nb\tc\u{1}"), "\"a\\nb\\u0009c\\u0001\"");
+    }
+
+    #[test]
+    fn reads_a_request_with_a_body() {
+        let request = b"POST /spawn HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\ncar 3";
+        let (method, path, body) = read_request(&request[..]).unwrap();
+        assert_eq!(
+            (method.as_str(), path.as_str(), body.as_str()),
+            ("POST", "/spawn", "car 3")
+        );
+    }
+
+    #[test]
+    fn turns_away_oversized_heads() {
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(long_path.as_bytes()).unwrap_err().0, 400);
+
+        let long_header = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(long_header.as_bytes()).unwrap_err().0, 431);
+
+        let header = format!("X: {}\r\n", "a".repeat(1000));
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", header.repeat(40));
+        assert_eq!(read_request(many_headers.as_bytes()).unwrap_err().0, 431);
+
+        // A line without an end still stops at the cap.
+        let endless = "a".repeat(MAX_HEAD * 2);
+        assert_eq!(read_request(endless.as_bytes()).unwrap_err().0, 400);
+    }
+}
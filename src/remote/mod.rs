@@ -0,0 +1,46 @@
+//! Ways to watch and drive a running simulation from outside the terminal.
+
+pub mod controller;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http;
+pub mod mqtt;
+#[cfg(unix)]
+pub mod socket;
+pub mod websocket;
+
+use crate::ui::console::Command;
+
+/// `address` ready to bind; a bare port number means localhost.
+pub fn listen_address(address: &str) -> String {
+    if address.chars().all(|c| c.is_ascii_digit()) {
+        format!("127.0.0.1:{}", address)
+    } else {
+        address.to_string()
+    }
+}
+
+/// Why a console command that came in over the network won't be run, if
+/// it won't. `save` writes wherever it is told on the machine running the
+/// simulation, so only the console and the local control socket may use it.
+/// The WebSocket and MQTT feeds only publish and take no commands.
+pub fn refused_over_network(line: &str) -> Option<String> {
+    match Command::parse(line) {
+        Ok(Command::Save(_)) => Some("save is not available over the network".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_save_from_the_network() {
+        assert!(refused_over_network("save state.json").is_some());
+        assert!(refused_over_network("  save /etc/passwd").is_some());
+        assert!(refused_over_network("spawn car 3").is_none());
+        // Lines that don't parse are left for run_command to explain.
+        assert!(refused_over_network("frobnicate").is_none());
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::remote::http::json_string;
+use crate::systems::config::Config;
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+
+/// Seconds the broker waits without hearing from us before dropping the
+/// connection. A ping goes out at half this.
+const KEEP_ALIVE: u16 = 60;
+/// How long to wait for the broker to take the connection and accept it,
+/// so a wrong address fails at startup instead of hanging.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Broker and topics from the `[mqtt]` config section.
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    /// `host:port` of the broker; publishing is off when empty.
+    pub broker: String,
+    pub client_id: String,
+    /// Topic for each light's state. `{light}` becomes its number.
+    pub light_topic: String,
+    /// Topic for event log entries.
+    pub event_topic: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: String::new(),
+            client_id: "traffic-simulator".to_string(),
+            light_topic: "traffic/intersection/{light}/light".to_string(),
+            event_topic: "traffic/events".to_string(),
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut mqtt = Self::default();
+        for (key, value) in config.section("mqtt") {
+            let field = match key.as_str() {
+                "broker" => &mut mqtt.broker,
+                "client_id" => &mut mqtt.client_id,
+                "light_topic" => &mut mqtt.light_topic,
+                "event_topic" => &mut mqtt.event_topic,
+                other => return Err(format!("Unknown setting '{}' in [mqtt]", other)),
+            };
+            *field = value.clone();
+        }
+        Ok(mqtt)
+    }
+}
+
+/// Publishes light changes and event log entries to an MQTT broker. Light
+/// states are retained, so a dashboard that subscribes later still sees
+/// them. Packets are written on a background thread, which also keeps the
+/// connection alive.
+pub struct MqttPublisher {
+    config: MqttConfig,
+    outbox: Sender<Vec<u8>>,
+    /// Light states last published, to spot changes.
+    published: Vec<TrafficLightState>,
+    /// Event log entries already published.
+    events_seen: usize,
+}
+
+impl MqttPublisher {
+    /// Connects to the broker and waits for it to accept.
+    pub fn connect(config: MqttConfig, sim: &Simulation) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("MQTT broker {}: {}", config.broker, e);
+        let address = config
+            .broker
+            .to_socket_addrs()
+            .map_err(error)?
+            .next()
+            .ok_or_else(|| format!("MQTT broker {}: no address found", config.broker))?;
+        let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).map_err(error)?;
+        stream
+            .set_read_timeout(Some(CONNECT_TIMEOUT))
+            .map_err(error)?;
+        stream
+            .write_all(&connect_packet(&config.client_id))
+            .map_err(error)?;
+        let mut connack = [0; 4];
+        stream
+            .read_exact(&mut connack)
+            .map_err(|e| match e.kind() {
+                ErrorKind::WouldBlock | ErrorKind::TimedOut => format!(
+                    "MQTT broker {} did not accept the connection within {}s",
+                    config.broker,
+                    CONNECT_TIMEOUT.as_secs()
+                ),
+                _ => error(e),
+            })?;
+        stream.set_read_timeout(None).map_err(error)?;
+        if connack[0] != 0x20 || connack[3] != 0 {
+            return Err(format!(
+                "MQTT broker {} refused the connection (code {})",
+                config.broker, connack[3]
+            ));
+        }
+
+        let (outbox, packets) = mpsc::channel::<Vec<u8>>();
+        thread::spawn(move || {
+            let ping_every = Duration::from_secs(KEEP_ALIVE as u64 / 2);
+            loop {
+                let packet = match packets.recv_timeout(ping_every) {
+                    Ok(packet) => packet,
+                    Err(RecvTimeoutError::Timeout) => vec![0xC0, 0x00],
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+                if stream.write_all(&packet).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Self {
+            config,
+            outbox,
+            published: Vec::new(),
+            events_seen: sim.events.pushed(),
+        })
+    }
+
+    /// Publishes the lights that changed and the events logged since the
+    /// last call. Fails once the connection to the broker is gone.
+    pub fn publish(&mut self, sim: &Simulation) -> Result<(), String> {
+        // A road swapped in by the editor can have a different number of lights.
+        self.published.truncate(sim.lights.len());
+        for (index, light) in sim.lights.iter().enumerate() {
+            if self.published.get(index) == Some(&light.state) {
+                continue;
+            }
+            let topic = self
+                .config
+                .light_topic
+                .replace("{light}", &(index + 1).to_string());
+            self.send(publish_packet(&topic, light.state.name(), true))?;
+            match self.published.get_mut(index) {
+                Some(state) => *state = light.state,
+                None => self.published.push(light.state),
+            }
+        }
+
+        self.events_seen = self.events_seen.min(sim.events.pushed());
+        let events: Vec<String> = sim
+            .events
+            .since(self.events_seen)
+            .map(|(time, text)| {
+                format!("{{\"time\": {:.2}, \"text\": {}}}", time, json_string(text))
+            })
+            .collect();
+        for payload in events {
+            self.send(publish_packet(&self.config.event_topic, &payload, false))?;
+        }
+        self.events_seen = sim.events.pushed();
+        Ok(())
+    }
+
+    fn send(&self, packet: Vec<u8>) -> Result<(), String> {
+        self.outbox
+            .send(packet)
+            .map_err(|_| format!("Lost the connection to MQTT broker {}", self.config.broker))
+    }
+}
+
+/// MQTT 3.1.1 CONNECT with a clean session and no credentials.
+fn connect_packet(client_id: &str) -> Vec<u8> {
+    let mut body = string_field("MQTT");
+    body.push(4); // protocol level 3.1.1
+    body.push(0x02); // clean session
+    body.extend_from_slice(&KEEP_ALIVE.to_be_bytes());
+    body.extend(string_field(client_id));
+    packet(0x10, body)
+}
+
+/// QoS 0 PUBLISH.
+fn publish_packet(topic: &str, payload: &str, retain: bool) -> Vec<u8> {
+    let mut body = string_field(topic);
+    body.extend_from_slice(payload.as_bytes());
+    packet(0x30 | retain as u8, body)
+}
+
+/// Fixed header (type byte and variable-length size) followed by `body`.
+fn packet(kind: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![kind];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+fn string_field(text: &str) -> Vec<u8> {
+    let mut field = (text.len() as u16).to_be_bytes().to_vec();
+    field.extend_from_slice(text.as_bytes());
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The remaining-length bytes of a packet with a `length`-byte body.
+    fn remaining_length(length: usize) -> Vec<u8> {
+        let packet = packet(0x30, vec![0; length]);
+        packet[1..packet.len() - length].to_vec()
+    }
+
+    #[test]
+    fn remaining_length_encoding() {
+        assert_eq!(remaining_length(0), vec![0x00]);
+        assert_eq!(remaining_length(127), vec![0x7F]);
+        assert_eq!(remaining_length(128), vec![0x80, 0x01]);
+        assert_eq!(remaining_length(16_383), vec![0xFF, 0x7F]);
+        assert_eq!(remaining_length(16_384), vec![0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn publish_packet_layout() {
+        assert_eq!(
+            publish_packet("a/b", "on", true),
+            vec![0x31, 7, 0, 3, b'a', b'/', b'b', b'o', b'n']
+        );
+    }
+
+    #[test]
+    fn connect_packet_layout() {
+        let packet = connect_packet("sim");
+        assert_eq!(packet[..2], [0x10, 15]);
+        assert_eq!(packet[2..8], [0, 4, b'M', b'Q', b'T', b'T']);
+        assert_eq!(packet[8..12], [4, 0x02, 0, KEEP_ALIVE as u8]);
+        assert_eq!(packet[12..], [0, 3, b's', b'i', b'm']);
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// A command line from a socket client waiting for the main loop to run it.
+pub struct SocketCommand {
+    pub line: String,
+    reply: Sender<Result<String, String>>,
+}
+
+impl SocketCommand {
+    pub fn respond(self, result: Result<String, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Local control socket taking one console command per line, e.g.
+/// `echo "spawn car 3" | nc -U /tmp/traffic.sock`. Each command gets a
+/// line back starting with `ok:` or `error:`.
+pub struct ControlSocket {
+    path: PathBuf,
+    commands: Receiver<SocketCommand>,
+}
+
+impl ControlSocket {
+    /// Listens at `path`, replacing a socket left behind by an earlier run.
+    /// Anything else already at `path` is left alone and is an error.
+    pub fn bind(path: &str) -> Result<Self, String> {
+        let path = PathBuf::from(path);
+        if let Ok(metadata) = fs::symlink_metadata(&path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!("{} exists and is not a socket", path.display()));
+            }
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove old {}: {}", path.display(), e))?;
+        }
+        let listener = UnixListener::bind(&path)
+            .map_err(|e| format!("Failed to listen on {}: {}", path.display(), e))?;
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || handle_client(stream, sender));
+            }
+        });
+        Ok(Self { path, commands })
+    }
+
+    /// Commands that arrived since the last call.
+    pub fn pending(&self) -> Vec<SocketCommand> {
+        self.commands.try_iter().collect()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_client(stream: UnixStream, sender: Sender<SocketCommand>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, answer) = mpsc::channel();
+        if sender.send(SocketCommand { line, reply }).is_err() {
+            return;
+        }
+        let response = match answer.recv() {
+            Ok(Ok(message)) => format!("ok: {}\n", message),
+            Ok(Err(message)) => format!("error: {}\n", message),
+            Err(_) => return,
+        };
+        if writer.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
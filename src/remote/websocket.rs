@@ -0,0 +1,231 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::remote::listen_address;
+
+/// GUID every WebSocket server appends to the client's key (RFC 6455).
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// A client that can't take a frame within this long is dropped rather than
+/// holding up the simulation.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+/// How long a new connection may take to send its upgrade request.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pushes the simulation state to every connected WebSocket client once per
+/// frame. Clients only listen; anything they send is ignored.
+pub struct WebSocketServer {
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+impl WebSocketServer {
+    /// Listens on `address`, e.g. `127.0.0.1:9001`. A bare port listens on
+    /// localhost.
+    pub fn start(address: &str) -> Result<Self, String> {
+        let address = listen_address(address);
+        let listener = TcpListener::bind(&address)
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&clients);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                // Each handshake gets its own thread so a client that
+                // connects and says nothing can't hold up the next one.
+                let accepted = Arc::clone(&accepted);
+                thread::spawn(move || {
+                    if stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_ok()
+                        && handshake(&mut stream).is_ok()
+                    {
+                        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                        accepted.lock().unwrap().push(stream);
+                    }
+                });
+            }
+        });
+        Ok(Self { clients })
+    }
+
+    /// Sends `text` to every client as one text message, dropping clients
+    /// that have gone away.
+    pub fn broadcast(&self, text: &str) {
+        let frame = text_frame(text);
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.write_all(&frame).is_ok());
+    }
+}
+
+/// Answers the HTTP upgrade request that opens a WebSocket connection.
+fn handshake(stream: &mut TcpStream) -> Result<(), String> {
+    let mut reader = BufReader::new(&*stream);
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("sec-websocket-key")
+        {
+            key = Some(value.trim().to_string());
+        }
+    }
+    let Some(key) = key else {
+        let _ = stream.write_all(
+            b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        return Err("Not a WebSocket request".to_string());
+    };
+    let accept = base64(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// An unmasked, unfragmented text frame carrying `text`.
+fn text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// SHA-1 digest, needed only for the handshake's accept key.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (chunk, word) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_6455() {
+        // The handshake example from section 1.3 of RFC 6455.
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let accept = base64(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+        assert_eq!(accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn sha1_known_digests() {
+        let hex =
+            |digest: [u8; 20]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        // Two blocks once padded.
+        assert_eq!(
+            hex(sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn base64_pads_short_input() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn text_frame_lengths() {
+        assert_eq!(text_frame("hi"), vec![0x81, 2, b'h', b'i']);
+        assert_eq!(&text_frame(&"x".repeat(126))[..4], &[0x81, 126, 0, 126]);
+        assert_eq!(
+            &text_frame(&"x".repeat(70_000))[..10],
+            &[0x81, 127, 0, 0, 0, 0, 0, 1, 0x11, 0x70]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::systems::config::Config;
+
+/// Seconds the deck takes to lift or come back down.
+pub const MOVE_SECONDS: f32 = 4.0;
+
+/// The `[drawbridge]` section: how often the bridge lifts for boats and
+/// for how long.
+#[derive(Debug, Clone, Copy)]
+pub struct BridgeConfig {
+    /// Seconds the bridge stays down between lifts.
+    pub interval: f32,
+    /// Seconds the warning lights flash before it lifts.
+    pub warning: f32,
+    /// Seconds it stays up for boats.
+    pub open: f32,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            interval: 90.0,
+            warning: 8.0,
+            open: 20.0,
+        }
+    }
+}
+
+impl BridgeConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut bridge = Self::default();
+        for (key, value) in config.section("drawbridge") {
+            let seconds = value
+                .parse()
+                .ok()
+                .filter(|n: &f32| *n > 0.0)
+                .ok_or_else(|| {
+                    format!("[drawbridge] {} must be a positive number of seconds", key)
+                });
+            match key.as_str() {
+                "interval" => bridge.interval = seconds?,
+                "warning" => bridge.warning = seconds?,
+                "open" => bridge.open = seconds?,
+                other => return Err(format!("Unknown setting '{}' in [drawbridge]", other)),
+            }
+        }
+        Ok(bridge)
+    }
+}
+
+/// Where the drawbridge is in its cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeState {
+    Down,
+    /// Lights flashing: traffic that can still stop does, and the deck
+    /// waits for anything already on it to get off.
+    Warning,
+    Raising,
+    Up,
+    Lowering,
+}
+
+/// A drawbridge on the road that lifts now and then to let boats through.
+/// Traffic stops at its near end from the warning until it is down again.
+#[derive(Debug, Clone)]
+pub struct Drawbridge {
+    /// First and last cell of the deck.
+    pub from: usize,
+    pub to: usize,
+    pub state: BridgeState,
+    /// Seconds until the next change of state.
+    pub time_left: f32,
+    pub config: BridgeConfig,
+}
+
+impl Drawbridge {
+    pub fn new(from: usize, to: usize, config: BridgeConfig) -> Self {
+        Self {
+            from,
+            to,
+            state: BridgeState::Down,
+            time_left: config.interval,
+            config,
+        }
+    }
+
+    /// Moves the cycle on by `dt` seconds. The deck only starts lifting once
+    /// `deck_clear`. Returns the new state when it changes.
+    pub fn update(&mut self, dt: f32, deck_clear: bool) -> Option<BridgeState> {
+        self.time_left -= dt;
+        if self.time_left > 0.0 {
+            return None;
+        }
+        let (next, seconds) = match self.state {
+            BridgeState::Down => (BridgeState::Warning, self.config.warning),
+            BridgeState::Warning if !deck_clear => return None,
+            BridgeState::Warning => (BridgeState::Raising, MOVE_SECONDS),
+            BridgeState::Raising => (BridgeState::Up, self.config.open),
+            BridgeState::Up => (BridgeState::Lowering, MOVE_SECONDS),
+            BridgeState::Lowering => (BridgeState::Down, self.config.interval),
+        };
+        self.state = next;
+        self.time_left = seconds;
+        Some(next)
+    }
+
+    /// Whether traffic has to stop at the near end.
+    pub fn closed(&self) -> bool {
+        self.state != BridgeState::Down
+    }
+
+    /// How far the deck is lifted, from 0 (down) to 1 (fully up).
+    pub fn lift(&self) -> f32 {
+        let moved = (1.0 - self.time_left / MOVE_SECONDS).clamp(0.0, 1.0);
+        match self.state {
+            BridgeState::Down | BridgeState::Warning => 0.0,
+            BridgeState::Raising => moved,
+            BridgeState::Up => 1.0,
+            BridgeState::Lowering => 1.0 - moved,
+        }
+    }
+
+    pub fn status(&self) -> String {
+        let left = self.time_left.max(0.0).ceil();
+        match self.state {
+            BridgeState::Down => format!("bridge down, lifts in {:.0}s", left),
+            BridgeState::Warning if self.time_left <= 0.0 => {
+                "bridge about to lift, waiting for the deck to clear".to_string()
+            }
+            BridgeState::Warning => format!("bridge lifts in {:.0}s, stop!", left),
+            BridgeState::Raising => "bridge lifting".to_string(),
+            BridgeState::Up => format!("bridge up for boats, down in {:.0}s", left),
+            BridgeState::Lowering => "bridge coming down".to_string(),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::systems::challenge::CHALLENGES;
+use crate::systems::config::Config;
+
+/// Folder under the user's config directory where progress is kept.
+const APP_DIR: &str = "terminal-traffic-light-simulator";
+const PROGRESS_FILE: &str = "progress.txt";
+
+/// Which challenges have been unlocked and the best score on each, kept
+/// between runs in `<config dir>/terminal-traffic-light-simulator/progress.txt`.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Number of challenges unlocked, counting from the first.
+    pub unlocked: usize,
+    best: Vec<(String, u32)>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            unlocked: 1,
+            best: Vec::new(),
+        }
+    }
+}
+
+impl Progress {
+    /// `$XDG_CONFIG_HOME`, `%APPDATA%` or `~/.config`, whichever is set.
+    pub fn path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .or_else(|| env::var_os("APPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join(APP_DIR).join(PROGRESS_FILE))
+    }
+
+    /// Saved progress, or a fresh start if there is none yet.
+    pub fn load() -> Self {
+        let Some(config) = Self::path().and_then(|path| Config::load(&path.to_string_lossy()).ok())
+        else {
+            return Self::default();
+        };
+        let unlocked = config
+            .section("career")
+            .iter()
+            .find(|(key, _)| key == "unlocked")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(1);
+        let best = config
+            .section("best")
+            .iter()
+            .filter_map(|(name, score)| Some((name.clone(), score.parse().ok()?)))
+            .collect();
+        Self {
+            unlocked: usize::clamp(unlocked, 1, CHALLENGES.len()),
+            best,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No config directory to save progress in")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let mut text = format!("[career]\nunlocked = {}\n\n[best]\n", self.unlocked);
+        for (name, score) in &self.best {
+            text.push_str(&format!("{} = {}\n", name, score));
+        }
+        fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        index < self.unlocked
+    }
+
+    /// The furthest challenge unlocked so far.
+    pub fn current(&self) -> usize {
+        self.unlocked.min(CHALLENGES.len()) - 1
+    }
+
+    pub fn best(&self, name: &str) -> Option<u32> {
+        self.best
+            .iter()
+            .find(|(challenge, _)| challenge == name)
+            .map(|&(_, score)| score)
+    }
+
+    /// Records a passed run of challenge `index`, keeping the better score
+    /// and unlocking the next challenge. Returns the newly unlocked one.
+    pub fn record_pass(&mut self, index: usize, score: u32) -> Option<usize> {
+        let name = CHALLENGES[index].name;
+        match self
+            .best
+            .iter_mut()
+            .find(|(challenge, _)| challenge == name)
+        {
+            Some((_, best)) => *best = (*best).max(score),
+            None => self.best.push((name.to_string(), score)),
+        }
+        let next = index + 1;
+        if next < CHALLENGES.len() && !self.is_unlocked(next) {
+            self.unlocked = next + 1;
+            Some(next)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::systems::rng::Rng;
+use crate::systems::road::Road;
+use crate::systems::simulation::Simulation;
+use crate::systems::stats::SimulationStats;
+
+/// A goal a challenge run is judged on when its time is up.
+#[derive(Debug, Clone, Copy)]
+pub enum Objective {
+    /// At least this many vehicles reach the end of the road.
+    MinTrips(u32),
+    /// Completed trips wait no more than this many seconds on average.
+    MaxAverageWait(f32),
+}
+
+impl Objective {
+    pub fn met(self, stats: &SimulationStats) -> bool {
+        match self {
+            Objective::MinTrips(trips) => stats.completed_trips >= trips,
+            Objective::MaxAverageWait(seconds) => stats.average_wait() <= seconds,
+        }
+    }
+
+    /// Current standing against the objective, e.g. `trips 12/40`.
+    pub fn progress(self, stats: &SimulationStats) -> String {
+        match self {
+            Objective::MinTrips(trips) => format!("trips {}/{}", stats.completed_trips, trips),
+            Objective::MaxAverageWait(seconds) => {
+                format!(
+                    "avg wait {:.1}s (max {:.1}s)",
+                    stats.average_wait(),
+                    seconds
+                )
+            }
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Objective::MinTrips(trips) => format!("Get {} vehicles through", trips),
+            Objective::MaxAverageWait(seconds) => {
+                format!("Keep the average wait at or below {:.0}s", seconds)
+            }
+        }
+    }
+}
+
+/// A fixed-length run on a given road and traffic level, with objectives to
+/// meet before the clock runs out.
+pub struct Challenge {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub map: &'static str,
+    pub spawn_rate: f32,
+    pub breakdown_rate: f32,
+    /// Simulated seconds the run lasts.
+    pub duration: f32,
+    pub objectives: &'static [Objective],
+}
+
+/// Challenges in career order; passing one unlocks the next.
+pub const CHALLENGES: [Challenge; 4] = [
+    Challenge {
+        name: "warm_up",
+        description: "A quiet street. Keep traffic moving.",
+        map: "S============|======",
+        spawn_rate: 0.35,
+        breakdown_rate: 0.0,
+        duration: 60.0,
+        objectives: &[Objective::MinTrips(8), Objective::MaxAverageWait(6.0)],
+    },
+    Challenge {
+        name: "breakdown_alley",
+        description: "Cars keep breaking down. Clear them before the queue builds.",
+        map: "S============|======",
+        spawn_rate: 0.45,
+        breakdown_rate: 0.12,
+        duration: 90.0,
+        objectives: &[Objective::MinTrips(10), Objective::MaxAverageWait(10.0)],
+    },
+    Challenge {
+        name: "rush_hour",
+        description: "Two signals and heavy demand from both entrances.",
+        map: "S=========|====S========|=====",
+        spawn_rate: 0.9,
+        breakdown_rate: 0.03,
+        duration: 180.0,
+        objectives: &[Objective::MinTrips(30), Objective::MaxAverageWait(15.0)],
+    },
+    Challenge {
+        name: "gridlock",
+        description: "Three signals, three entrances, flat-out demand and frequent breakdowns.",
+        map: "S=======|===S======|===S=====|====",
+        spawn_rate: 1.0,
+        breakdown_rate: 0.06,
+        duration: 240.0,
+        objectives: &[Objective::MinTrips(40), Objective::MaxAverageWait(20.0)],
+    },
+];
+
+impl Challenge {
+    /// Index into [`CHALLENGES`] of a challenge given by name or by its
+    /// 1-based number.
+    pub fn find(name: &str) -> Option<usize> {
+        match name.parse::<usize>() {
+            Ok(number) => number.checked_sub(1).filter(|&i| i < CHALLENGES.len()),
+            Err(_) => CHALLENGES.iter().position(|c| c.name == name),
+        }
+    }
+
+    /// A fresh simulation set up for this challenge.
+    pub fn simulation(&self, rng: Rng) -> Simulation {
+        let road = Road::parse(self.map).expect("challenge maps are valid");
+        let mut sim = Simulation::new(road, rng);
+        sim.spawn_rate = self.spawn_rate;
+        sim.breakdown_rate = self.breakdown_rate;
+        sim
+    }
+
+    pub fn finished(&self, stats: &SimulationStats) -> bool {
+        stats.elapsed >= self.duration
+    }
+
+    pub fn passed(&self, stats: &SimulationStats) -> bool {
+        self.objectives.iter().all(|objective| objective.met(stats))
+    }
+
+    /// Points for a passed run: ten per trip plus a bonus for every second
+    /// of average wait saved under the limit.
+    pub fn score(&self, stats: &SimulationStats) -> u32 {
+        if !self.passed(stats) {
+            return 0;
+        }
+        let wait_bonus: f32 = self
+            .objectives
+            .iter()
+            .map(|objective| match objective {
+                Objective::MaxAverageWait(limit) => (limit - stats.average_wait()).max(0.0) * 50.0,
+                Objective::MinTrips(_) => 0.0,
+            })
+            .sum();
+        stats.completed_trips * 10 + wait_bonus.round() as u32
+    }
+
+    /// One-line status shown while the challenge is running.
+    pub fn status(&self, stats: &SimulationStats) -> String {
+        let left = (self.duration - stats.elapsed).max(0.0).ceil() as u32;
+        let progress: Vec<String> = self
+            .objectives
+            .iter()
+            .map(|objective| objective.progress(stats))
+            .collect();
+        format!(
+            "🏁 {} — {}:{:02} left | {}",
+            self.name,
+            left / 60,
+            left % 60,
+            progress.join(" | ")
+        )
+    }
+
+    /// Lines for the score screen shown when the run ends.
+    pub fn report(&self, stats: &SimulationStats, best: Option<u32>) -> Vec<String> {
+        let mut lines = vec![self.description.to_string(), String::new()];
+        for objective in self.objectives {
+            let mark = if objective.met(stats) { "✔" } else { "✘" };
+            lines.push(format!(
+                "{} {}: {}",
+                mark,
+                objective.describe(),
+                objective.progress(stats)
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "Throughput: {:.1} vehicles/min",
+            stats.throughput()
+        ));
+        lines.push(format!("Score: {}", self.score(stats)));
+        if let Some(best) = best {
+            lines.push(format!("Best:  {}", best));
+        }
+        lines
+    }
+}
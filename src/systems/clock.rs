@@ -0,0 +1,288 @@
+use crate::systems::config::Config;
+
+pub const MINUTES_PER_DAY: f32 = 24.0 * 60.0;
+
+/// Part of the day, for lighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Night,
+    Dawn,
+    Day,
+    Dusk,
+}
+
+impl TimeOfDay {
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeOfDay::Night => "night",
+            TimeOfDay::Dawn => "dawn",
+            TimeOfDay::Day => "day",
+            TimeOfDay::Dusk => "dusk",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Season::Spring => "spring",
+            Season::Summer => "summer",
+            Season::Autumn => "autumn",
+            Season::Winter => "winter",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|season| season.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Weekday::Monday => "monday",
+            Weekday::Tuesday => "tuesday",
+            Weekday::Wednesday => "wednesday",
+            Weekday::Thursday => "thursday",
+            Weekday::Friday => "friday",
+            Weekday::Saturday => "saturday",
+            Weekday::Sunday => "sunday",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|day| day.name() == name)
+    }
+
+    pub fn is_weekend(self) -> bool {
+        matches!(self, Weekday::Saturday | Weekday::Sunday)
+    }
+}
+
+/// The `[clock]` section: what time and season the run starts in and how
+/// fast days and seasons go by.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockConfig {
+    /// Minutes past midnight.
+    pub start: f32,
+    /// Simulated minutes per simulated second.
+    pub minutes_per_second: f32,
+    pub season: Season,
+    pub weekday: Weekday,
+    /// Days before the season turns.
+    pub days_per_season: u32,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            start: 8.0 * 60.0,
+            minutes_per_second: 1.0,
+            season: Season::Spring,
+            weekday: Weekday::Monday,
+            days_per_season: 7,
+        }
+    }
+}
+
+impl ClockConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut clock = Self::default();
+        for (key, value) in config.section("clock") {
+            match key.as_str() {
+                "start" => {
+                    clock.start = parse_time(value)
+                        .ok_or("[clock] start must be a time of day like 07:30")?;
+                }
+                "minutes_per_second" => {
+                    clock.minutes_per_second = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or("[clock] minutes_per_second must be a non-negative number")?;
+                }
+                "season" => {
+                    clock.season = Season::from_name(value)
+                        .ok_or("[clock] season must be spring, summer, autumn or winter")?;
+                }
+                "weekday" => {
+                    clock.weekday = Weekday::from_name(value)
+                        .ok_or("[clock] weekday must be a day of the week, like monday")?;
+                }
+                "days_per_season" => {
+                    clock.days_per_season = value
+                        .parse()
+                        .ok()
+                        .filter(|&days| days > 0)
+                        .ok_or("[clock] days_per_season must be a whole number above 0")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [clock]", other)),
+            }
+        }
+        Ok(clock)
+    }
+}
+
+/// `HH:MM` as minutes past midnight.
+pub fn parse_time(text: &str) -> Option<f32> {
+    let (hours, minutes) = text.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some((hours * 60 + minutes) as f32)
+}
+
+/// Simulated time of day and calendar, running faster than real time so
+/// a whole day passes in a sitting and the seasons turn every
+/// `days_per_season` days.
+#[derive(Debug, Clone)]
+pub struct SimClock {
+    /// Minutes since midnight on the first day.
+    minutes: f32,
+    pub minutes_per_second: f32,
+    /// Season of the first day.
+    first_season: Season,
+    first_weekday: Weekday,
+    pub days_per_season: u32,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new(ClockConfig::default())
+    }
+}
+
+impl SimClock {
+    pub fn new(config: ClockConfig) -> Self {
+        Self {
+            minutes: config.start,
+            minutes_per_second: config.minutes_per_second,
+            first_season: config.season,
+            first_weekday: config.weekday,
+            days_per_season: config.days_per_season,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.minutes += dt * self.minutes_per_second;
+    }
+
+    /// Days since the run started, counting from 0.
+    pub fn day(&self) -> u32 {
+        (self.minutes / MINUTES_PER_DAY) as u32
+    }
+
+    pub fn season(&self) -> Season {
+        let first = Season::ALL
+            .iter()
+            .position(|&season| season == self.first_season)
+            .unwrap_or(0);
+        let turns = (self.day() / self.days_per_season.max(1)) as usize;
+        Season::ALL[(first + turns) % Season::ALL.len()]
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        let first = Weekday::ALL
+            .iter()
+            .position(|&day| day == self.first_weekday)
+            .unwrap_or(0);
+        Weekday::ALL[(first + self.day() as usize) % Weekday::ALL.len()]
+    }
+
+    /// Minutes since midnight on the first day.
+    pub fn total_minutes(&self) -> f32 {
+        self.minutes
+    }
+
+    /// Minutes past midnight today.
+    pub fn minute_of_day(&self) -> f32 {
+        self.minutes % MINUTES_PER_DAY
+    }
+
+    /// Jumps to `minute` past midnight, later today or tomorrow, so the
+    /// clock never runs backwards.
+    pub fn set_time(&mut self, minute: f32) {
+        let mut ahead = minute - self.minute_of_day();
+        if ahead < 0.0 {
+            ahead += MINUTES_PER_DAY;
+        }
+        self.minutes += ahead;
+    }
+
+    pub fn time_of_day(&self) -> TimeOfDay {
+        match self.minute_of_day() / 60.0 {
+            hour if hour < 5.0 => TimeOfDay::Night,
+            hour if hour < 7.0 => TimeOfDay::Dawn,
+            hour if hour < 18.0 => TimeOfDay::Day,
+            hour if hour < 20.0 => TimeOfDay::Dusk,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    /// Cold enough for snow on the road to freeze: all winter, and at
+    /// night outside summer.
+    pub fn cold(&self) -> bool {
+        match self.season() {
+            Season::Winter => true,
+            Season::Summer => false,
+            _ => self.time_of_day() == TimeOfDay::Night,
+        }
+    }
+
+    /// Streetlights and headlights are on outside full daylight.
+    pub fn lights_on(&self) -> bool {
+        self.time_of_day() != TimeOfDay::Day
+    }
+
+    /// Weekday, day and season, like "Wed day 3, winter".
+    pub fn date(&self) -> String {
+        let weekday = self.weekday().name();
+        format!(
+            "{}{} day {}, {}",
+            weekday[..1].to_uppercase(),
+            &weekday[1..3],
+            self.day() + 1,
+            self.season().name()
+        )
+    }
+
+    /// The time as `HH:MM`.
+    pub fn format(&self) -> String {
+        let minute = self.minute_of_day() as u32;
+        format!("{:02}:{:02}", minute / 60, minute % 60)
+    }
+}
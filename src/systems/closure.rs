@@ -0,0 +1,157 @@
+use crate::systems::clock::{SimClock, parse_time};
+use crate::systems::config::Config;
+
+/// A stretch of road shut for a parade, a market or the like at set times
+/// each day. While it is shut, barricades stop traffic at its start and
+/// spawn points whose traffic would run into them stay quiet.
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub name: String,
+    /// First and last cell of the stretch.
+    pub from: usize,
+    pub to: usize,
+    /// Start and end of each closure, in minutes past midnight.
+    pub windows: Vec<(f32, f32)>,
+    /// Whether the barricades are up right now.
+    pub active: bool,
+}
+
+impl Closure {
+    /// Whether the clock is inside one of the closure's windows.
+    pub fn scheduled(&self, clock: &SimClock) -> bool {
+        let minute = clock.minute_of_day();
+        self.windows
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&minute))
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "road closed for {} on cells {}-{}",
+            self.name, self.from, self.to
+        )
+    }
+}
+
+/// The `[closures]` section: one closure per key, named by the key, as the
+/// stretch of cells followed by the times it is shut, e.g.
+/// `parade = 8-12, 10:00-11:30`.
+pub fn closures_from_config(config: &Config) -> Result<Vec<Closure>, String> {
+    config
+        .section("closures")
+        .iter()
+        .map(|(name, value)| {
+            let (from, to, windows) = parse_stretch("closures", name, value)?;
+            Ok(Closure {
+                name: name.clone(),
+                from,
+                to,
+                windows,
+                active: false,
+            })
+        })
+        .collect()
+}
+
+/// Start and end of each daily window, in minutes past midnight.
+pub type Windows = Vec<(f32, f32)>;
+
+/// A stretch of cells and the daily times something happens on it, as
+/// `8-12, 10:00-11:30, 15:00-16:00`: first cell, last cell and windows in
+/// minutes past midnight.
+pub fn parse_stretch(
+    section: &str,
+    name: &str,
+    value: &str,
+) -> Result<(usize, usize, Windows), String> {
+    let usage = || {
+        format!(
+            "[{}] {} must be cells then times, like 8-12, 10:00-11:30",
+            section, name
+        )
+    };
+    let mut parts = value.split(',').map(str::trim);
+    let (from, to) = parts
+        .next()
+        .and_then(|cells| cells.split_once('-'))
+        .and_then(|(from, to)| Some((from.parse().ok()?, to.parse().ok()?)))
+        .filter(|(from, to): &(usize, usize)| from <= to)
+        .ok_or_else(usage)?;
+    let windows = parts
+        .map(|window| {
+            window
+                .split_once('-')
+                .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+                .filter(|(start, end)| start < end)
+                .ok_or_else(usage)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if windows.is_empty() {
+        return Err(usage());
+    }
+    Ok((from, to, windows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::clock::ClockConfig;
+
+    fn at(minute: f32) -> SimClock {
+        SimClock::new(ClockConfig {
+            start: minute,
+            ..ClockConfig::default()
+        })
+    }
+
+    #[test]
+    fn parses_stretches_and_daily_windows() {
+        let config =
+            Config::parse("[closures]\nparade = 8-12, 10:00-11:30, 15:00-16:00\n").unwrap();
+        let closures = closures_from_config(&config).unwrap();
+        assert_eq!(closures.len(), 1);
+        let parade = &closures[0];
+        assert_eq!(
+            (parade.name.as_str(), parade.from, parade.to),
+            ("parade", 8, 12)
+        );
+        assert_eq!(parade.windows, [(600.0, 690.0), (900.0, 960.0)]);
+        assert!(!parade.active);
+        assert_eq!(parade.status(), "road closed for parade on cells 8-12");
+    }
+
+    #[test]
+    fn rejects_bad_stretches() {
+        for value in [
+            "12-8, 10:00-11:00",
+            "8-12",
+            "8-12, 11:00-10:00",
+            "8, 10:00-11:00",
+            "8-12, noon-13:00",
+        ] {
+            assert!(
+                parse_stretch("closures", "parade", value).is_err(),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn scheduled_from_start_until_the_end_each_day() {
+        let (_, _, windows) = parse_stretch("closures", "market", "0-3, 10:00-11:30").unwrap();
+        let market = Closure {
+            name: "market".to_string(),
+            from: 0,
+            to: 3,
+            windows,
+            active: false,
+        };
+        assert!(!market.scheduled(&at(599.0)));
+        assert!(market.scheduled(&at(600.0)));
+        assert!(market.scheduled(&at(689.0)));
+        assert!(!market.scheduled(&at(690.0)));
+        // The same times the next day.
+        assert!(market.scheduled(&at(1440.0 + 630.0)));
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+
+/// Settings loaded from an INI-style file passed with `--config`:
+///
+/// ```text
+/// # comment
+/// [keybindings]
+/// quit = q
+/// pan_left = Left, h
+/// ```
+///
+/// Each subsystem reads the section it owns.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push((name.trim().to_string(), Vec::new()));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("Config line {}: expected key = value", number + 1));
+            };
+            let Some((_, entries)) = sections.last_mut() else {
+                return Err(format!(
+                    "Config line {}: setting outside of a [section]",
+                    number + 1
+                ));
+            };
+            entries.push((key.trim().to_string(), value.trim().to_string()));
+        }
+        Ok(Self { sections })
+    }
+
+    /// All `key = value` entries of a section, in file order.
+    pub fn section(&self, name: &str) -> &[(String, String)] {
+        self.sections
+            .iter()
+            .find(|(section, _)| section == name)
+            .map_or(&[], |(_, entries)| entries.as_slice())
+    }
+}
+
+/// The `[performance]` section: how much history is kept for rewinding,
+/// how often output files are written, and whether frames are profiled.
+#[derive(Debug, Clone, Copy)]
+pub struct PerformanceConfig {
+    /// Seconds of simulation kept in the rewind buffer.
+    pub rewind_seconds: f32,
+    /// Simulated seconds between rewind snapshots.
+    pub rewind_resolution: f32,
+    /// Simulated seconds between rows written to `--stats-out`.
+    pub stats_interval: f32,
+    /// Simulated seconds between `--telemetry` lines; zero writes every step.
+    pub telemetry_interval: f32,
+    /// Time each phase of every frame, shown under the road and summed up
+    /// on exit.
+    pub enable_profiling: bool,
+    /// Move vehicles on all CPU cores (needs the `parallel` feature).
+    pub parallel_updates: bool,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            rewind_seconds: 30.0,
+            rewind_resolution: 0.25,
+            stats_interval: 1.0,
+            telemetry_interval: 1.0,
+            enable_profiling: false,
+            parallel_updates: false,
+        }
+    }
+}
+
+impl PerformanceConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut performance = Self::default();
+        for (key, value) in config.section("performance") {
+            let switch = match key.as_str() {
+                "enable_profiling" => Some(&mut performance.enable_profiling),
+                "parallel_updates" => Some(&mut performance.parallel_updates),
+                _ => None,
+            };
+            if let Some(switch) = switch {
+                *switch = match value.as_str() {
+                    "true" | "yes" | "on" => true,
+                    "false" | "no" | "off" => false,
+                    _ => return Err(format!("[performance] {} must be true or false", key)),
+                };
+                continue;
+            }
+            let number: f32 = value
+                .parse()
+                .ok()
+                .filter(|n: &f32| *n >= 0.0)
+                .ok_or_else(|| format!("[performance] {} must be a non-negative number", key))?;
+            match key.as_str() {
+                "rewind_seconds" => performance.rewind_seconds = number,
+                "rewind_resolution" => performance.rewind_resolution = number,
+                "stats_interval" => performance.stats_interval = number,
+                "telemetry_interval" => performance.telemetry_interval = number,
+                other => return Err(format!("Unknown setting '{}' in [performance]", other)),
+            }
+        }
+        Ok(performance)
+    }
+}
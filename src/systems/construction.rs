@@ -0,0 +1,34 @@
+/// Speed limit through road works, in cells per second: half a car's
+/// cruising speed, so the stretch carries about half the traffic.
+pub const WORKS_SPEED_LIMIT: f32 = 0.75;
+/// How long road works last when no duration is given, in seconds.
+pub const WORKS_SECONDS: f32 = 60.0;
+
+/// Road works on a stretch of road. Cones line it, and traffic through it
+/// keeps to a lower speed limit until the works finish.
+#[derive(Debug, Clone)]
+pub struct RoadWorks {
+    /// First and last cell of the stretch.
+    pub from: usize,
+    pub to: usize,
+    pub time_left: f32,
+}
+
+impl RoadWorks {
+    pub fn new(from: usize, to: usize, seconds: f32) -> Self {
+        Self {
+            from,
+            to,
+            time_left: seconds,
+        }
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "road works on cells {}-{}, {:.0}s left",
+            self.from,
+            self.to,
+            self.time_left.ceil()
+        )
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, params};
+
+use crate::systems::simulation::{STEP_SECONDS, Simulation};
+use crate::systems::stats::efficiency;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    preset TEXT NOT NULL,
+    map TEXT NOT NULL,
+    spawn_rate REAL NOT NULL,
+    breakdown_rate REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS trips (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    vehicle_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    origin INTEGER NOT NULL,
+    waited REAL NOT NULL,
+    finished_at REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    time REAL NOT NULL,
+    description TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    time REAL NOT NULL,
+    vehicles INTEGER NOT NULL,
+    completed_trips INTEGER NOT NULL,
+    avg_wait REAL NOT NULL,
+    throughput REAL NOT NULL,
+    efficiency REAL NOT NULL
+);
+";
+
+/// Keeps trips, events and periodic stats snapshots in a SQLite file, one
+/// `runs` row per launch, so many runs can be compared with plain SQL.
+pub struct Database {
+    connection: Connection,
+    run: i64,
+    /// Simulated seconds between snapshot rows.
+    interval: f32,
+    /// Elapsed simulated time at which the next snapshot is due.
+    next: f32,
+    /// Event log entries already stored.
+    events_seen: usize,
+}
+
+impl Database {
+    /// Opens (creating if needed) the database at `path` and starts a new
+    /// run labelled `preset`.
+    pub fn open(path: &str, sim: &Simulation, preset: &str, interval: f32) -> Result<Self, String> {
+        let error = |e: rusqlite::Error| format!("Database {}: {}", path, e);
+        let connection = Connection::open(path).map_err(error)?;
+        connection.execute_batch(SCHEMA).map_err(error)?;
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        connection
+            .execute(
+                "INSERT INTO runs (started_at, preset, map, spawn_rate, breakdown_rate)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    started_at,
+                    preset,
+                    sim.road.to_map().trim_end(),
+                    sim.spawn_rate,
+                    sim.breakdown_rate
+                ],
+            )
+            .map_err(error)?;
+        let run = connection.last_insert_rowid();
+        Ok(Self {
+            connection,
+            run,
+            interval,
+            next: 0.0,
+            events_seen: sim.events.pushed(),
+        })
+    }
+
+    /// Stores the trips finished and events logged in the last update, and a
+    /// snapshot if one is due.
+    pub fn record(&mut self, sim: &Simulation) -> Result<(), String> {
+        let error = |e: rusqlite::Error| format!("Database: {}", e);
+        for vehicle in &sim.finished {
+            self.connection
+                .execute(
+                    "INSERT INTO trips (run_id, vehicle_id, kind, origin, waited, finished_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        self.run,
+                        vehicle.id,
+                        vehicle.kind.key(),
+                        vehicle.origin,
+                        vehicle.waited_time,
+                        sim.stats.elapsed
+                    ],
+                )
+                .map_err(error)?;
+        }
+
+        // A rewind can put the log behind what was already stored.
+        self.events_seen = self.events_seen.min(sim.events.pushed());
+        for (time, text) in sim.events.since(self.events_seen) {
+            self.connection
+                .execute(
+                    "INSERT INTO events (run_id, time, description) VALUES (?1, ?2, ?3)",
+                    params![self.run, time, text],
+                )
+                .map_err(error)?;
+        }
+        self.events_seen = sim.events.pushed();
+
+        let stats = &sim.stats;
+        if self.next > stats.elapsed + self.interval {
+            self.next = stats.elapsed;
+        }
+        if stats.elapsed < self.next {
+            return Ok(());
+        }
+        self.next += self.interval.max(STEP_SECONDS);
+        let active = sim.vehicles.len();
+        let moving = sim.vehicles.iter().filter(|v| !v.stopped).count();
+        self.connection
+            .execute(
+                "INSERT INTO snapshots
+                 (run_id, time, vehicles, completed_trips, avg_wait, throughput, efficiency)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    self.run,
+                    stats.elapsed,
+                    active,
+                    stats.completed_trips,
+                    stats.average_wait(),
+                    stats.throughput(),
+                    efficiency(active, moving)
+                ],
+            )
+            .map_err(error)?;
+        Ok(())
+    }
+}
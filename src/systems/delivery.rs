@@ -0,0 +1,76 @@
+use crate::systems::config::Config;
+
+/// The `[deliveries]` section: delivery trucks that now and then
+/// double-park in the road lane while they unload.
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryConfig {
+    /// Chance each minute that a truck double-parks somewhere.
+    pub per_minute: f32,
+    /// Shortest stop, in seconds.
+    pub min_seconds: f32,
+    /// Longest stop, in seconds.
+    pub max_seconds: f32,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: 0.5,
+            min_seconds: 30.0,
+            max_seconds: 120.0,
+        }
+    }
+}
+
+impl DeliveryConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut delivery = Self::default();
+        for (key, value) in config.section("deliveries") {
+            match key.as_str() {
+                "per_minute" => {
+                    delivery.per_minute = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| (0.0..=1.0).contains(n))
+                        .ok_or("[deliveries] per_minute must be a fraction from 0 to 1")?;
+                }
+                "min_seconds" => {
+                    delivery.min_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[deliveries] min_seconds must be a positive number")?;
+                }
+                "max_seconds" => {
+                    delivery.max_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[deliveries] max_seconds must be a positive number")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [deliveries]", other)),
+            }
+        }
+        if delivery.min_seconds > delivery.max_seconds {
+            return Err("[deliveries] min_seconds must not be more than max_seconds".to_string());
+        }
+        Ok(delivery)
+    }
+}
+
+/// A delivery truck stopped in the road lane while it unloads. With a
+/// single lane, nothing can get past until it drives off.
+#[derive(Debug, Clone, Copy)]
+pub struct DoubleParked {
+    pub cell: usize,
+    pub time_left: f32,
+}
+
+impl DoubleParked {
+    pub fn status(&self) -> String {
+        format!(
+            "delivery truck double-parked on cell {} ({:.0}s)",
+            self.cell, self.time_left
+        )
+    }
+}
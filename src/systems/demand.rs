@@ -0,0 +1,217 @@
+use crate::systems::clock::SimClock;
+use crate::systems::config::Config;
+use crate::systems::vehicle::VehicleType;
+
+/// Kind of day, for how busy the road is and who is on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayType {
+    Weekday,
+    Weekend,
+    Holiday,
+}
+
+impl DayType {
+    pub const ALL: [DayType; 3] = [DayType::Weekday, DayType::Weekend, DayType::Holiday];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DayType::Weekday => "weekday",
+            DayType::Weekend => "weekend",
+            DayType::Holiday => "holiday",
+        }
+    }
+}
+
+/// How much traffic a kind of day brings, and what it is made of.
+#[derive(Debug, Clone, Copy)]
+pub struct DemandProfile {
+    /// Spawn rate multiplier.
+    pub rate: f32,
+    /// Relative chance of each type in [`VehicleType::ALL`] order.
+    pub mix: [u32; 7],
+}
+
+impl DemandProfile {
+    /// Picks a type from a roll in `0..total()`.
+    pub fn pick(&self, mut roll: u32) -> VehicleType {
+        for (kind, &weight) in VehicleType::ALL.iter().zip(&self.mix) {
+            if roll < weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        VehicleType::Car
+    }
+
+    pub fn total(&self) -> u32 {
+        self.mix.iter().sum()
+    }
+}
+
+/// The `[demand]` section: a profile for each kind of day, and which days
+/// of the run are holidays.
+#[derive(Debug, Clone)]
+pub struct DemandConfig {
+    /// Profiles in [`DayType::ALL`] order.
+    pub profiles: [DemandProfile; 3],
+    /// Holidays, as day numbers counting from 1.
+    pub holidays: Vec<u32>,
+}
+
+impl Default for DemandConfig {
+    fn default() -> Self {
+        Self {
+            profiles: [
+                DemandProfile {
+                    rate: 1.0,
+                    mix: VehicleType::ALL.map(|kind| kind.spawn_weight()),
+                },
+                DemandProfile {
+                    rate: 0.75,
+                    mix: [55, 20, 25, 0, 1, 1, 2],
+                },
+                DemandProfile {
+                    rate: 0.6,
+                    mix: [60, 15, 25, 0, 1, 1, 2],
+                },
+            ],
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl DemandConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut demand = Self::default();
+        for (key, value) in config.section("demand") {
+            if key == "holidays" {
+                demand.holidays = value
+                    .split(',')
+                    .map(|day| day.trim().parse().ok().filter(|&day| day > 0))
+                    .collect::<Option<_>>()
+                    .ok_or("[demand] holidays must be day numbers like 3, 10")?;
+                continue;
+            }
+            let (day, setting) = key
+                .split_once('_')
+                .and_then(|(day, setting)| {
+                    let index = DayType::ALL.iter().position(|kind| kind.name() == day)?;
+                    Some((index, setting))
+                })
+                .ok_or_else(|| format!("Unknown setting '{}' in [demand]", key))?;
+            let profile = &mut demand.profiles[day];
+            match setting {
+                "rate" => {
+                    profile.rate = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or_else(|| format!("[demand] {} must be a non-negative number", key))?;
+                }
+                "mix" => profile.mix = parse_mix(key, value)?,
+                _ => return Err(format!("Unknown setting '{}' in [demand]", key)),
+            }
+        }
+        Ok(demand)
+    }
+
+    pub fn day_type(&self, clock: &SimClock) -> DayType {
+        if self.holidays.contains(&(clock.day() + 1)) {
+            DayType::Holiday
+        } else if clock.weekday().is_weekend() {
+            DayType::Weekend
+        } else {
+            DayType::Weekday
+        }
+    }
+
+    /// The profile for the clock's current day.
+    pub fn profile(&self, clock: &SimClock) -> &DemandProfile {
+        &self.profiles[self.day_type(clock) as usize]
+    }
+}
+
+/// A mix like `car 70, motorcycle 15, bicycle 15, police 2`. Types left
+/// out never spawn, and tow trucks can't be listed since they only come
+/// to incidents.
+fn parse_mix(key: &str, value: &str) -> Result<[u32; 7], String> {
+    let error = || format!("[demand] {} must be weights like car 70, bicycle 30", key);
+    let mut mix = [0; 7];
+    for entry in value.split(',') {
+        let (kind, weight) = entry.trim().split_once(' ').ok_or_else(error)?;
+        let kind = VehicleType::from_key(kind)
+            .filter(|&kind| kind != VehicleType::TowTruck)
+            .ok_or_else(error)?;
+        mix[kind as usize] = weight.trim().parse().map_err(|_| error())?;
+    }
+    if mix.iter().sum::<u32>() == 0 {
+        return Err(error());
+    }
+    Ok(mix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::clock::{ClockConfig, Weekday};
+
+    fn parse(text: &str) -> Result<DemandConfig, String> {
+        DemandConfig::from_config(&Config::parse(text).unwrap())
+    }
+
+    fn clock(weekday: Weekday, day: u32) -> SimClock {
+        let mut clock = SimClock::new(ClockConfig {
+            weekday,
+            ..ClockConfig::default()
+        });
+        // A day is 1440 minutes at a minute a second.
+        clock.update(day as f32 * 1440.0);
+        clock
+    }
+
+    #[test]
+    fn picks_types_by_their_share_of_the_mix() {
+        let profile = DemandProfile {
+            rate: 1.0,
+            mix: [3, 0, 2, 0, 0, 0, 1],
+        };
+        assert_eq!(profile.total(), 6);
+        let picks: Vec<VehicleType> = (0..6).map(|roll| profile.pick(roll)).collect();
+        use VehicleType::*;
+        assert_eq!(picks, [Car, Car, Car, Bicycle, Bicycle, Police]);
+    }
+
+    #[test]
+    fn days_pick_their_profile() {
+        let demand = parse("[demand]\nholidays = 2\nweekend_rate = 0.5\n").unwrap();
+        assert_eq!(
+            demand.day_type(&clock(Weekday::Friday, 0)),
+            DayType::Weekday
+        );
+        // Day 2 is a holiday even though it falls on a Saturday.
+        assert_eq!(
+            demand.day_type(&clock(Weekday::Friday, 1)),
+            DayType::Holiday
+        );
+        assert_eq!(
+            demand.day_type(&clock(Weekday::Friday, 2)),
+            DayType::Weekend
+        );
+        assert_eq!(demand.profile(&clock(Weekday::Friday, 2)).rate, 0.5);
+    }
+
+    #[test]
+    fn mixes_name_their_types() {
+        let demand = parse("[demand]\nweekend_mix = car 55, motorcycle 20, bicycle 25\n").unwrap();
+        assert_eq!(
+            demand.profiles[DayType::Weekend as usize].mix,
+            [55, 20, 25, 0, 0, 0, 0]
+        );
+        assert!(parse("[demand]\nweekday_mix = tow_truck 5, car 1\n").is_err());
+        assert!(parse("[demand]\nweekday_mix = car 0\n").is_err());
+        assert!(parse("[demand]\nweekday_mix = car lots\n").is_err());
+        assert!(parse("[demand]\nholiday_rate = -1\n").is_err());
+        assert!(parse("[demand]\nholidays = 0\n").is_err());
+        assert!(parse("[demand]\nmonday_rate = 2\n").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::systems::config::Config;
+
+/// Induction loops placed at these setbacks when the config names none: one
+/// at the stop line and one a few cells upstream.
+const DEFAULT_SETBACKS: [usize; 2] = [0, 4];
+
+/// The `[detectors]` section: where the induction loops on each approach
+/// sit, as setbacks in cells upstream of the stop line, 0 being the cell
+/// just before it. `setbacks = 0, 4` places them at every light and
+/// `<light number> = 0, 2, 8` at one.
+#[derive(Debug, Clone)]
+pub struct DetectorConfig {
+    pub setbacks: Vec<usize>,
+    /// Light index and its own setbacks.
+    pub lights: Vec<(usize, Vec<usize>)>,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            setbacks: DEFAULT_SETBACKS.to_vec(),
+            lights: Vec::new(),
+        }
+    }
+}
+
+impl DetectorConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut detectors = Self::default();
+        for (key, value) in config.section("detectors") {
+            let setbacks = value
+                .split(',')
+                .map(|part| part.trim().parse())
+                .collect::<Result<Vec<usize>, _>>()
+                .map_err(|_| {
+                    format!(
+                        "[detectors] {} expects cells upstream of the stop line, e.g. '0, 4', got '{}'",
+                        key, value
+                    )
+                })?;
+            if key == "setbacks" {
+                detectors.setbacks = setbacks;
+                continue;
+            }
+            let index = key
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| number.checked_sub(1))
+                .ok_or_else(|| {
+                    format!("[detectors] {}: expected 'setbacks' or a light number", key)
+                })?;
+            detectors.lights.push((index, setbacks));
+        }
+        Ok(detectors)
+    }
+
+    /// Setbacks of the loops at light `index`.
+    pub fn setbacks_for(&self, index: usize) -> &[usize] {
+        self.lights
+            .iter()
+            .find(|(light, _)| *light == index)
+            .map_or(&self.setbacks, |(_, setbacks)| setbacks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<DetectorConfig, String> {
+        DetectorConfig::from_config(&Config::parse(text).unwrap())
+    }
+
+    #[test]
+    fn lights_without_their_own_loops_use_the_shared_setbacks() {
+        let defaults = DetectorConfig::default();
+        assert_eq!(defaults.setbacks_for(0), DEFAULT_SETBACKS);
+
+        let detectors = parse("[detectors]\nsetbacks = 1, 3\n2 = 0, 2, 8\n").unwrap();
+        assert_eq!(detectors.setbacks_for(0), [1, 3]);
+        assert_eq!(detectors.setbacks_for(1), [0, 2, 8]);
+        assert_eq!(detectors.setbacks_for(2), [1, 3]);
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert!(parse("[detectors]\nsetbacks = near\n").is_err());
+        assert!(parse("[detectors]\n0 = 1\n").is_err());
+        assert!(parse("[detectors]\nlights = 1\n").is_err());
+    }
+}
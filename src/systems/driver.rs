@@ -0,0 +1,70 @@
+use crate::systems::config::Config;
+
+/// Seconds after a light turns red that a red-light runner still goes
+/// through it.
+pub const LATE_RED_SECONDS: f32 = 2.0;
+/// How much likelier a crash is for a vehicle crossing on red.
+pub const RED_RUN_CRASH_MULTIPLIER: f32 = 10.0;
+/// How far over speed limits speeders drive.
+pub const SPEEDING_FACTOR: f32 = 1.25;
+
+/// The `[drivers]` section: how drivers behave at the lights.
+#[derive(Debug, Clone)]
+pub struct DriverConfig {
+    /// Fraction of drivers who carry on through a yellow and the first
+    /// moments of a red instead of stopping.
+    pub red_light_runners: f32,
+    /// Fraction of drivers who drive a quarter over speed limits.
+    pub speeders: f32,
+    /// Fraction of red-light runners and speeders who behave while an
+    /// enforcement camera is watching.
+    pub camera_deterrence: f32,
+    /// Traffic keeps to the left, as in the UK, Japan or Australia, so the
+    /// curb and the turns off the road are on the left.
+    pub drive_on_left: bool,
+}
+
+impl Default for DriverConfig {
+    fn default() -> Self {
+        Self {
+            red_light_runners: 0.02,
+            speeders: 0.1,
+            camera_deterrence: 0.7,
+            drive_on_left: false,
+        }
+    }
+}
+
+impl DriverConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut drivers = Self::default();
+        for (key, value) in config.section("drivers") {
+            let fraction = value
+                .parse()
+                .ok()
+                .filter(|n: &f32| (0.0..=1.0).contains(n))
+                .ok_or_else(|| format!("[drivers] {} must be a fraction from 0 to 1", key));
+            match key.as_str() {
+                "drive_on_left" => {
+                    drivers.drive_on_left = match value.as_str() {
+                        "true" => true,
+                        "false" => false,
+                        _ => {
+                            return Err("[drivers] drive_on_left must be true or false".to_string());
+                        }
+                    };
+                }
+                "red_light_runners" => drivers.red_light_runners = fraction?,
+                "speeders" => drivers.speeders = fraction?,
+                "camera_deterrence" => drivers.camera_deterrence = fraction?,
+                other => return Err(format!("Unknown setting '{}' in [drivers]", other)),
+            }
+        }
+        Ok(drivers)
+    }
+
+    /// The side of the road the curb is on, which turns off the road go to.
+    pub fn curb_side(&self) -> &'static str {
+        if self.drive_on_left { "left" } else { "right" }
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs;
+
+use crossterm::event::KeyCode;
+
+use crate::systems::road::Road;
+
+/// What the main loop should do after the editor handled a key.
+pub enum EditorAction {
+    None,
+    Close,
+    Apply(Road),
+}
+
+/// In-app map editor. Edits a copy of the map text; nothing touches the
+/// running simulation until the map is saved and validated.
+pub struct MapEditor {
+    pub cells: Vec<char>,
+    pub bike_lane: bool,
+    /// Speed limit digit of each cell, `-` for none.
+    pub limits: Vec<char>,
+    pub cursor: usize,
+    pub path: String,
+    pub message: String,
+}
+
+impl MapEditor {
+    pub fn new(road: &Road, path: &str) -> Self {
+        let map = road.to_map();
+        Self {
+            cells: map.lines().next().unwrap_or_default().chars().collect(),
+            bike_lane: road.bike_lane,
+            limits: (0..road.length).map(|cell| road.limit_char(cell)).collect(),
+            cursor: 0,
+            path: path.to_string(),
+            message: String::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> EditorAction {
+        self.message.clear();
+        match code {
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => {
+                self.cursor += 1;
+                if self.cursor == self.cells.len() {
+                    self.cells.push('=');
+                    self.limits.push('-');
+                }
+            }
+            KeyCode::Char(ch @ ('=' | '|' | 'S' | 'H' | 'F' | 'D' | 'T' | 'P')) => {
+                self.cells[self.cursor] = ch
+            }
+            KeyCode::Char('s') => self.cells[self.cursor] = 'S',
+            KeyCode::Char('h') => self.cells[self.cursor] = 'H',
+            KeyCode::Char('f') => self.cells[self.cursor] = 'F',
+            KeyCode::Char('d') => self.cells[self.cursor] = 'D',
+            KeyCode::Char('t') => self.cells[self.cursor] = 'T',
+            KeyCode::Char('p') => self.cells[self.cursor] = 'P',
+            KeyCode::Char('b') => self.bike_lane = !self.bike_lane,
+            KeyCode::Char(ch @ ('1'..='9' | '-')) => self.limits[self.cursor] = ch,
+            KeyCode::Backspace | KeyCode::Delete if self.cells.len() > 1 => {
+                self.cells.remove(self.cursor);
+                self.limits.remove(self.cursor);
+                self.cursor = self.cursor.min(self.cells.len() - 1);
+            }
+            KeyCode::Char('w') => return self.save(),
+            KeyCode::Esc | KeyCode::Char('e') => return EditorAction::Close,
+            _ => {}
+        }
+        EditorAction::None
+    }
+
+    pub fn map_text(&self) -> String {
+        let mut text: String = self.cells.iter().collect();
+        text.push('\n');
+        if self.bike_lane {
+            text.push_str(&"b".repeat(self.cells.len()));
+            text.push('\n');
+        }
+        if self.has_limits() {
+            text.extend(&self.limits);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Whether any cell has a speed limit.
+    pub fn has_limits(&self) -> bool {
+        self.limits.iter().any(|&ch| ch != '-')
+    }
+
+    /// Validates the map, writes it to disk, and hands it back to be
+    /// swapped into the simulation.
+    fn save(&mut self) -> EditorAction {
+        let text = self.map_text();
+        match Road::parse(&text) {
+            Ok(road) => match fs::write(&self.path, &text) {
+                Ok(()) => EditorAction::Apply(road),
+                Err(e) => {
+                    self.message = format!("Failed to write {}: {}", self.path, e);
+                    EditorAction::None
+                }
+            },
+            Err(e) => {
+                self.message = format!("Invalid map: {}", e);
+                EditorAction::None
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::systems::config::Config;
+
+/// The `[emergency]` section: how drivers make way for sirens.
+#[derive(Debug, Clone)]
+pub struct EmergencyConfig {
+    /// How far ahead of an ambulance or fire truck, in cells, drivers pull
+    /// over and wait for it to pass. 0 turns pulling over off.
+    pub yield_distance: f32,
+}
+
+impl Default for EmergencyConfig {
+    fn default() -> Self {
+        Self {
+            yield_distance: 6.0,
+        }
+    }
+}
+
+impl EmergencyConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut emergency = Self::default();
+        for (key, value) in config.section("emergency") {
+            match key.as_str() {
+                "yield_distance" => {
+                    emergency.yield_distance = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or("[emergency] yield_distance must be a non-negative number")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [emergency]", other)),
+            }
+        }
+        Ok(emergency)
+    }
+}
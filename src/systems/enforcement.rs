@@ -0,0 +1,48 @@
+use crate::systems::config::Config;
+
+/// How far upstream of its stop line, in cells, a camera watches.
+pub const CAMERA_RANGE: f32 = 4.0;
+/// How far over the limit a vehicle has to cross the stop line for the
+/// camera to flag it.
+pub const SPEEDING_TOLERANCE: f32 = 1.1;
+
+/// A camera at a signal, photographing vehicles that cross its stop line
+/// on red or over the speed limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnforcementCamera {
+    /// Red-light runners it has caught.
+    pub red_light: u32,
+    /// Speeders it has caught.
+    pub speeding: u32,
+}
+
+impl EnforcementCamera {
+    pub fn status(&self) -> String {
+        format!(
+            "camera caught {} on red, {} speeding",
+            self.red_light, self.speeding
+        )
+    }
+}
+
+/// The `[cameras]` section: `lights = 1, 3` puts a camera at each listed
+/// light. Returns light indices, counting from 0.
+pub fn camera_lights_from_config(config: &Config) -> Result<Vec<usize>, String> {
+    let mut lights = Vec::new();
+    for (key, value) in config.section("cameras") {
+        match key.as_str() {
+            "lights" => {
+                for number in value.split(',').map(str::trim) {
+                    let light: usize = number
+                        .parse()
+                        .ok()
+                        .filter(|&n| n >= 1)
+                        .ok_or("[cameras] lights must be light numbers, like 1, 3")?;
+                    lights.push(light - 1);
+                }
+            }
+            other => return Err(format!("Unknown setting '{}' in [cameras]", other)),
+        }
+    }
+    Ok(lights)
+}
@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+use crate::systems::signal_control::Timing;
+use crate::systems::traffic_light::TrafficLightState;
+use crate::systems::vehicle::VehicleType;
+use crate::systems::weather::WeatherType;
+
+/// Entries kept in the log before the oldest are dropped.
+const LOG_CAPACITY: usize = 50;
+
+/// A change made to the simulation from outside its own rules: by the
+/// sandbox, the console or a mouse click. Applying one through
+/// `Simulation::apply` records it in the event log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimEvent {
+    /// Put a vehicle on the road, at the first free spawn point if no cell is given.
+    Spawn(VehicleType, Option<usize>),
+    /// Break down the vehicle at or just behind a cell.
+    Breakdown(usize),
+    /// Switch the spawn point at a cell on or off.
+    ToggleSpawn(usize),
+    /// Set a light (by index) to a state, restarting its timer.
+    ForceLight(usize, TrafficLightState),
+    Despawn(u32),
+    /// Bring in a weather at an intensity from 0 to 1.
+    Weather(WeatherType, f32),
+    /// Move the clock on to a number of minutes past midnight.
+    SetClock(f32),
+    /// Drop debris on a road cell, as a storm would.
+    Debris(usize),
+    /// Start road works from one cell to another for a number of seconds.
+    RoadWorks(usize, usize, f32),
+    /// Put up or take down the enforcement camera at a light (by index).
+    ToggleCamera(usize),
+    /// Take a light (by index) over by hand, or hand it back to its
+    /// controller.
+    Operate(usize),
+    /// Move a light (by index) on to its next phase by hand, taking it over.
+    AdvanceLight(usize),
+    /// Break down a light (by index) to flashing red until repaired.
+    Malfunction(usize),
+    /// Give a light (by index) new timing, as its `[timing]` line would.
+    SetTiming(usize, Timing),
+    /// Allow or forbid right turns on red.
+    ToggleRightOnRed,
+}
+
+/// Timestamped record of notable things that happened: applied events as
+/// well as breakdowns and clearances the simulation produced itself.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    entries: VecDeque<(f32, String)>,
+    /// Entries pushed since the start, including ones since dropped.
+    pushed: usize,
+}
+
+impl EventLog {
+    pub fn push(&mut self, time: f32, text: String) {
+        if self.entries.len() == LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((time, text));
+        self.pushed += 1;
+    }
+
+    /// Running count of entries ever pushed, for readers that keep up with
+    /// the log through [`EventLog::since`].
+    pub fn pushed(&self) -> usize {
+        self.pushed
+    }
+
+    /// Entries pushed after the first `seen`, as far as they are still kept.
+    pub fn since(&self, seen: usize) -> impl Iterator<Item = &(f32, String)> {
+        let first = self.pushed - self.entries.len();
+        self.entries.iter().skip(seen.saturating_sub(first))
+    }
+
+    /// The last `count` entries, oldest first.
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &(f32, String)> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(count))
+    }
+}
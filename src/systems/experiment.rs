@@ -0,0 +1,357 @@
+use std::fs;
+
+use crate::systems::challenge::CHALLENGES;
+use crate::systems::rng::Rng;
+use crate::systems::road::Road;
+use crate::systems::signal_control::{ControlMode, SignalConfig, Timing};
+use crate::systems::simulation::{STEP_SECONDS, Simulation};
+use crate::systems::weather::{WeatherConfig, WeatherSystem, WeatherType};
+
+/// A set of headless runs: one simulation per seed, each `duration`
+/// simulated seconds long, on a road or a challenge.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub road: Road,
+    /// Challenge whose road and traffic to use instead of `road`.
+    pub challenge: Option<usize>,
+    pub signals: SignalConfig,
+    /// Overrides the road's or challenge's spawn rate.
+    pub spawn_rate: Option<f32>,
+    /// Holds the weather at this kind, at full strength, for the whole run
+    /// instead of letting it change with the season.
+    pub weather: Option<WeatherType>,
+    pub seeds: Vec<u64>,
+    pub duration: f32,
+}
+
+impl Scenario {
+    fn simulation(&self, seed: u64) -> Simulation {
+        let rng = Rng::seeded(seed);
+        let mut sim = match self.challenge {
+            Some(index) => CHALLENGES[index].simulation(rng),
+            None => Simulation::new(self.road.clone(), rng),
+        };
+        if let Some(rate) = self.spawn_rate {
+            sim.spawn_rate = rate;
+        }
+        if let Some(kind) = self.weather {
+            sim.weather = WeatherSystem::new(WeatherConfig {
+                start: kind,
+                intensity: 1.0,
+                changes: false,
+                crash_multipliers: sim.weather.crash_multipliers,
+            });
+        }
+        sim
+    }
+
+    pub fn light_count(&self) -> usize {
+        self.simulation(1).lights.len()
+    }
+
+    /// Plays out the run for `seed` with `signals` and returns where it ended.
+    pub fn run(&self, seed: u64, signals: &SignalConfig) -> Simulation {
+        let mut sim = self.simulation(seed);
+        sim.configure_signals(signals.clone());
+        while sim.stats.elapsed < self.duration {
+            sim.update(STEP_SECONDS);
+        }
+        sim
+    }
+
+    /// Average seconds each vehicle spent waiting with `plan` in place,
+    /// across the seeds. Vehicles still on the road count too, so a plan
+    /// that never lets anyone through does not look good.
+    pub fn delay(&self, plan: &[Timing]) -> Summary {
+        let mut signals = self.signals.clone();
+        signals.timings = plan.iter().copied().enumerate().collect();
+        let delays: Vec<f32> = self
+            .seeds
+            .iter()
+            .map(|&seed| {
+                let sim = self.run(seed, &signals);
+                let on_road: Vec<f32> = sim
+                    .vehicles
+                    .iter()
+                    .filter(|v| !v.kind.is_service())
+                    .map(|v| v.waited_time)
+                    .collect();
+                let vehicles = sim.stats.completed_trips as usize + on_road.len();
+                (sim.stats.total_wait + on_road.iter().sum::<f32>()) / vehicles.max(1) as f32
+            })
+            .collect();
+        Summary::of(&delays)
+    }
+}
+
+/// Two-sided 95% critical values of Student's t for 1 to 30 degrees of
+/// freedom; larger samples use the normal value.
+const T_95: [f32; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+const Z_95: f32 = 1.96;
+
+/// Mean and sample standard deviation of one reading across runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Summary {
+    pub mean: f32,
+    pub stddev: f32,
+    /// Number of runs the reading was taken from.
+    pub count: usize,
+}
+
+impl Summary {
+    pub fn of(values: &[f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = if values.len() < 2 {
+            0.0
+        } else {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (values.len() - 1) as f32
+        };
+        Self {
+            mean,
+            stddev: variance.sqrt(),
+            count: values.len(),
+        }
+    }
+
+    /// Half-width of the 95% confidence interval for the mean, or `None`
+    /// with fewer than two runs to estimate the spread from.
+    pub fn margin(&self) -> Option<f32> {
+        let freedom = self.count.checked_sub(1).filter(|&df| df > 0)?;
+        let t = T_95.get(freedom - 1).copied().unwrap_or(Z_95);
+        Some(t * self.stddev / (self.count as f32).sqrt())
+    }
+
+    /// The 95% confidence interval for the mean, e.g. `[4.1, 9.6]`. Every
+    /// reading here is non-negative, so the interval stops at zero.
+    pub fn interval(&self) -> String {
+        match self.margin() {
+            Some(margin) => format!(
+                "[{:.2}, {:.2}]",
+                (self.mean - margin).max(0.0),
+                self.mean + margin
+            ),
+            None => "n/a".to_string(),
+        }
+    }
+}
+
+/// Results for one point of a sweep grid.
+#[derive(Debug, Clone)]
+pub struct BenchRow {
+    pub mode: ControlMode,
+    /// Weather held for the runs, or `None` where it changed by itself.
+    pub weather: Option<WeatherType>,
+    pub spawn_rate: f32,
+    pub runs: usize,
+    /// Average wait of completed trips, in seconds.
+    pub wait: Summary,
+    /// Completed trips per simulated minute.
+    pub throughput: Summary,
+}
+
+/// Runs `scenario` once per seed for every combination of control mode
+/// (applied to all lights), weather and spawn rate, calling `report` as
+/// each combination finishes. A `None` weather leaves it to the season.
+pub fn sweep(
+    scenario: &Scenario,
+    modes: &[ControlMode],
+    weathers: &[Option<WeatherType>],
+    spawn_rates: &[f32],
+    mut report: impl FnMut(&BenchRow),
+) -> Vec<BenchRow> {
+    let mut rows = Vec::new();
+    for &mode in modes {
+        let timings = scenario.signals.timings.iter();
+        let signals = SignalConfig {
+            default: Some(mode),
+            lights: Vec::new(),
+            timings: timings
+                .map(|&(index, timing)| {
+                    (
+                        index,
+                        Timing {
+                            mode: None,
+                            ..timing
+                        },
+                    )
+                })
+                .collect(),
+            ..scenario.signals.clone()
+        };
+        for &weather in weathers {
+            for &spawn_rate in spawn_rates {
+                let point = Scenario {
+                    spawn_rate: Some(spawn_rate),
+                    weather,
+                    ..scenario.clone()
+                };
+                let runs: Vec<Simulation> = point
+                    .seeds
+                    .iter()
+                    .map(|&seed| point.run(seed, &signals))
+                    .collect();
+                let waits: Vec<f32> = runs.iter().map(|sim| sim.stats.average_wait()).collect();
+                let throughputs: Vec<f32> = runs.iter().map(|sim| sim.stats.throughput()).collect();
+                let row = BenchRow {
+                    mode,
+                    weather,
+                    spawn_rate,
+                    runs: runs.len(),
+                    wait: Summary::of(&waits),
+                    throughput: Summary::of(&throughputs),
+                };
+                report(&row);
+                rows.push(row);
+            }
+        }
+    }
+    rows
+}
+
+/// The sweep results as a CSV file, one row per grid point. The `ci95`
+/// columns hold the half-width of each 95% confidence interval.
+pub fn write_results(path: &str, rows: &[BenchRow]) -> Result<(), String> {
+    let mut text = String::from(
+        "mode,weather,spawn_rate,runs,avg_wait_mean,avg_wait_stddev,avg_wait_ci95,throughput_mean,throughput_stddev,throughput_ci95\n",
+    );
+    for row in rows {
+        text.push_str(&format!(
+            "{},{},{:.2},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+            row.mode.name(),
+            weather_name(row.weather),
+            row.spawn_rate,
+            row.runs,
+            row.wait.mean,
+            row.wait.stddev,
+            row.wait.margin().unwrap_or_default(),
+            row.throughput.mean,
+            row.throughput.stddev,
+            row.throughput.margin().unwrap_or_default()
+        ));
+    }
+    fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// How a sweep row's weather is written: its name, or `seasonal` where it
+/// was left to change by itself.
+pub fn weather_name(weather: Option<WeatherType>) -> &'static str {
+    weather.map_or("seasonal", WeatherType::name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario() -> Scenario {
+        Scenario {
+            road: Road::default(),
+            challenge: None,
+            signals: SignalConfig::default(),
+            spawn_rate: None,
+            weather: None,
+            seeds: vec![1, 2],
+            duration: 10.0,
+        }
+    }
+
+    #[test]
+    fn sweep_holds_each_weather_for_its_rows() {
+        let weathers = [None, Some(WeatherType::Snow)];
+        let rows = sweep(
+            &scenario(),
+            &[ControlMode::FixedTime],
+            &weathers,
+            &[0.3, 0.6],
+            |_| {},
+        );
+        let grid: Vec<(Option<WeatherType>, f32)> = rows
+            .iter()
+            .map(|row| (row.weather, row.spawn_rate))
+            .collect();
+        assert_eq!(
+            grid,
+            [
+                (None, 0.3),
+                (None, 0.6),
+                (Some(WeatherType::Snow), 0.3),
+                (Some(WeatherType::Snow), 0.6)
+            ]
+        );
+
+        let snowy = Scenario {
+            weather: Some(WeatherType::Snow),
+            ..scenario()
+        };
+        let sim = snowy.run(1, &snowy.signals);
+        assert_eq!(sim.weather.current, WeatherType::Snow);
+        assert!(!sim.weather.changes);
+    }
+
+    #[test]
+    fn results_have_a_weather_column() {
+        let rows = sweep(
+            &scenario(),
+            &[ControlMode::FixedTime],
+            &[None, Some(WeatherType::Fog)],
+            &[0.3],
+            |_| {},
+        );
+        let path = std::env::temp_dir().join(format!("sweep-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_results(path, &rows).unwrap();
+        let text = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("mode,weather,spawn_rate,runs,"));
+        assert!(lines[1].starts_with("fixed,seasonal,0.30,2,"));
+        assert!(lines[2].starts_with("fixed,fog,0.30,2,"));
+    }
+
+    #[test]
+    fn summary_takes_the_sample_spread() {
+        let summary = Summary::of(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(summary.count, 8);
+        assert!((summary.mean - 5.0).abs() < 1e-5);
+        // Sample (n - 1) variance: 32 / 7.
+        assert!((summary.stddev - (32.0f32 / 7.0).sqrt()).abs() < 1e-5);
+
+        let empty = Summary::of(&[]);
+        assert_eq!((empty.count, empty.mean, empty.stddev), (0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn margin_uses_student_t_then_the_normal_value() {
+        assert_eq!(Summary::of(&[3.0]).margin(), None);
+        assert_eq!(Summary::of(&[3.0]).interval(), "n/a");
+
+        // Two runs: one degree of freedom.
+        let pair = Summary::of(&[1.0, 3.0]);
+        let expected = 12.706 * pair.stddev / 2.0f32.sqrt();
+        assert!((pair.margin().unwrap() - expected).abs() < 1e-4);
+
+        let many: Vec<f32> = (0..40).map(|n| (n % 4) as f32).collect();
+        let summary = Summary::of(&many);
+        let expected = Z_95 * summary.stddev / 40.0f32.sqrt();
+        assert!((summary.margin().unwrap() - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn interval_stops_at_zero() {
+        let summary = Summary {
+            mean: 1.0,
+            stddev: 5.0,
+            count: 4,
+        };
+        let upper = 1.0 + summary.margin().unwrap();
+        assert_eq!(summary.interval(), format!("[0.00, {:.2}]", upper));
+        let steady = Summary::of(&[2.0, 2.0, 2.0]);
+        assert_eq!(steady.interval(), "[2.00, 2.00]");
+    }
+}
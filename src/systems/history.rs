@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+
+use crate::systems::config::PerformanceConfig;
+use crate::systems::simulation::Simulation;
+
+/// Ring buffer of recent simulation snapshots for rewinding.
+///
+/// A snapshot is taken every `rewind_resolution` simulated seconds and the
+/// oldest ones fall off once `rewind_seconds` are stored. While scrubbing,
+/// `position` points at the snapshot on screen; recording again from there
+/// throws away the snapshots after it, so the run resumes from that point.
+pub struct History {
+    snapshots: VecDeque<Simulation>,
+    capacity: usize,
+    resolution: f32,
+    since_snapshot: f32,
+    position: Option<usize>,
+}
+
+impl History {
+    pub fn new(performance: &PerformanceConfig) -> Self {
+        let resolution = performance.rewind_resolution;
+        let capacity = if resolution > 0.0 {
+            (performance.rewind_seconds / resolution).round() as usize
+        } else {
+            0
+        };
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+            resolution,
+            since_snapshot: 0.0,
+            position: None,
+        }
+    }
+
+    /// Notes that `sim` just advanced by `dt` seconds.
+    pub fn record(&mut self, sim: &Simulation, dt: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(position) = self.position.take() {
+            self.snapshots.truncate(position + 1);
+            self.since_snapshot = 0.0;
+        }
+        self.since_snapshot += dt;
+        if self.since_snapshot + f32::EPSILON < self.resolution && !self.snapshots.is_empty() {
+            return;
+        }
+        self.since_snapshot = 0.0;
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(sim.clone());
+    }
+
+    /// The snapshot before the one on screen, if there is one.
+    pub fn back(&mut self) -> Option<&Simulation> {
+        let target = match self.position {
+            Some(position) => position.checked_sub(1)?,
+            None => self.snapshots.len().checked_sub(2)?,
+        };
+        self.position = Some(target);
+        self.snapshots.get(target)
+    }
+
+    /// The snapshot after the one on screen, while scrubbing.
+    pub fn forward(&mut self) -> Option<&Simulation> {
+        let target = self.position? + 1;
+        if target >= self.snapshots.len() {
+            return None;
+        }
+        self.position = Some(target);
+        self.snapshots.get(target)
+    }
+
+    /// How far behind the newest snapshot the one on screen is, in seconds.
+    pub fn rewound_seconds(&self) -> Option<f32> {
+        let position = self.position?;
+        Some((self.snapshots.len() - 1 - position) as f32 * self.resolution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::rng::Rng;
+    use crate::systems::road::Road;
+
+    /// A history of three one-second snapshots, and a simulation to record.
+    fn setup() -> (History, Simulation) {
+        let performance = PerformanceConfig {
+            rewind_seconds: 3.0,
+            rewind_resolution: 1.0,
+            ..PerformanceConfig::default()
+        };
+        let sim = Simulation::new(Road::default(), Rng::seeded(1));
+        (History::new(&performance), sim)
+    }
+
+    /// Records one second of run, marking the snapshot with `tick`.
+    fn advance(history: &mut History, sim: &mut Simulation, tick: u32) {
+        sim.tick = tick;
+        history.record(sim, 1.0);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_snapshots() {
+        let (mut history, mut sim) = setup();
+        for tick in 1..=5 {
+            advance(&mut history, &mut sim, tick);
+        }
+        assert_eq!(history.back().map(|s| s.tick), Some(4));
+        assert_eq!(history.back().map(|s| s.tick), Some(3));
+        assert!(history.back().is_none());
+        assert_eq!(history.rewound_seconds(), Some(2.0));
+    }
+
+    #[test]
+    fn scrubs_forward_up_to_the_newest() {
+        let (mut history, mut sim) = setup();
+        assert!(history.forward().is_none());
+        for tick in 1..=3 {
+            advance(&mut history, &mut sim, tick);
+        }
+        history.back();
+        history.back();
+        assert_eq!(history.forward().map(|s| s.tick), Some(2));
+        assert_eq!(history.forward().map(|s| s.tick), Some(3));
+        assert!(history.forward().is_none());
+        assert_eq!(history.rewound_seconds(), Some(0.0));
+    }
+
+    #[test]
+    fn resuming_drops_the_snapshots_after_the_one_on_screen() {
+        let (mut history, mut sim) = setup();
+        for tick in 1..=3 {
+            advance(&mut history, &mut sim, tick);
+        }
+        history.back();
+        history.back();
+        advance(&mut history, &mut sim, 10);
+        assert_eq!(history.rewound_seconds(), None);
+        assert_eq!(history.back().map(|s| s.tick), Some(1));
+        assert!(history.back().is_none());
+    }
+
+    #[test]
+    fn no_rewind_buffer_records_nothing() {
+        let performance = PerformanceConfig {
+            rewind_seconds: 0.0,
+            ..PerformanceConfig::default()
+        };
+        let mut history = History::new(&performance);
+        let sim = Simulation::new(Road::default(), Rng::seeded(1));
+        history.record(&sim, 1.0);
+        history.record(&sim, 1.0);
+        assert!(history.back().is_none());
+    }
+}
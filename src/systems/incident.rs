@@ -0,0 +1,140 @@
+/// Seconds a tow truck spends hooking up a broken-down vehicle.
+pub const CLEARANCE_SECONDS: f32 = 4.0;
+/// Seconds an ambulance crew spends at a crash before it can be towed.
+pub const TREATMENT_SECONDS: f32 = 6.0;
+/// Seconds a fire crew takes to clear blown-in debris once it arrives.
+pub const DEBRIS_CLEANUP_SECONDS: f32 = 12.0;
+
+/// What put a vehicle out of action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncidentCause {
+    Breakdown,
+    Crash,
+}
+
+impl IncidentCause {
+    pub fn name(self) -> &'static str {
+        match self {
+            IncidentCause::Breakdown => "broken down",
+            IncidentCause::Crash => "crashed",
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            IncidentCause::Breakdown => "breakdown",
+            IncidentCause::Crash => "crash",
+        }
+    }
+}
+
+/// A broken-down or crashed vehicle blocking the lane. It stays put until a
+/// tow truck reaches it and finishes its clearance timer, which after a
+/// crash waits for an ambulance crew to finish first.
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub vehicle_id: u32,
+    pub cause: IncidentCause,
+    pub tow_truck_id: Option<u32>,
+    pub clearance_left: Option<f32>,
+    pub ambulance_id: Option<u32>,
+    /// Seconds of treatment left once the ambulance has arrived.
+    pub treatment_left: Option<f32>,
+    /// Police car sitting with the incident until it is cleared.
+    pub police_id: Option<u32>,
+}
+
+impl Incident {
+    pub fn new(vehicle_id: u32, cause: IncidentCause) -> Self {
+        Self {
+            vehicle_id,
+            cause,
+            tow_truck_id: None,
+            clearance_left: None,
+            ambulance_id: None,
+            treatment_left: None,
+            police_id: None,
+        }
+    }
+
+    /// Whether the tow truck may start clearing: always after a breakdown,
+    /// and after a crash once the ambulance crew is done.
+    pub fn treated(&self) -> bool {
+        self.cause != IncidentCause::Crash || self.treatment_left == Some(0.0)
+    }
+
+    pub fn status(&self) -> String {
+        let cause = self.cause.name();
+        let mut status = match (self.tow_truck_id, self.clearance_left) {
+            (None, _) => format!("#{} {}, waiting for a tow truck", self.vehicle_id, cause),
+            (Some(truck), None) => {
+                format!(
+                    "#{} {}, tow truck #{} en route",
+                    self.vehicle_id, cause, truck
+                )
+            }
+            (Some(truck), Some(left)) => format!(
+                "#{} being cleared by tow truck #{} ({:.0}s)",
+                self.vehicle_id,
+                truck,
+                left.ceil()
+            ),
+        };
+        if self.cause == IncidentCause::Crash {
+            match (self.ambulance_id, self.treatment_left) {
+                (None, _) => status.push_str(", waiting for an ambulance"),
+                (Some(ambulance), None) => {
+                    status.push_str(&format!(", ambulance #{} en route", ambulance))
+                }
+                (Some(ambulance), Some(left)) if left > 0.0 => status.push_str(&format!(
+                    ", ambulance #{} treating ({:.0}s)",
+                    ambulance,
+                    left.ceil()
+                )),
+                _ => {}
+            }
+        }
+        match self.police_id {
+            Some(police) => format!("{}, police #{} attending", status, police),
+            None => status,
+        }
+    }
+}
+
+/// Debris the wind blew onto the road. Road traffic queues behind it until
+/// a fire truck arrives and its crew has cleared it.
+#[derive(Debug, Clone)]
+pub struct Debris {
+    pub cell: usize,
+    pub fire_truck_id: Option<u32>,
+    /// Seconds of cleanup left once the fire truck has arrived.
+    pub cleanup_left: Option<f32>,
+}
+
+impl Debris {
+    pub fn new(cell: usize) -> Self {
+        Self {
+            cell,
+            fire_truck_id: None,
+            cleanup_left: None,
+        }
+    }
+
+    pub fn status(&self) -> String {
+        match (self.fire_truck_id, self.cleanup_left) {
+            (None, _) => format!("debris on cell {}, waiting for a fire truck", self.cell),
+            (Some(truck), None) => {
+                format!(
+                    "debris on cell {}, fire truck #{} en route",
+                    self.cell, truck
+                )
+            }
+            (Some(truck), Some(left)) => format!(
+                "debris on cell {} being cleared by fire truck #{} ({:.0}s)",
+                self.cell,
+                truck,
+                left.ceil()
+            ),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+
+use mlua::{Function, Lua, Value};
+
+use crate::systems::config::Config;
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+
+/// Lights whose phases are decided by Lua scripts, configured in the `[lua]`
+/// section as `<light number> = <script path>`.
+///
+/// Each script runs in its own Lua state and must define a global
+/// `decide(light)`. It is called every simulation step with a table holding
+/// `light`, `position`, `state`, `elapsed` (seconds in the current state),
+/// `queue`, `bike_queue`, `approaching` and `calls` (whether a vehicle is
+/// over one of the approach's induction loops). It returns the phase to show:
+/// `"red"`, `"yellow"`, `"green"`, or `nil` to keep the current one.
+pub struct LuaControllers {
+    /// Light index, script path and the Lua state holding its `decide`.
+    scripts: Vec<(usize, String, Lua)>,
+}
+
+impl LuaControllers {
+    /// Loads the scripts named in `[lua]`, or `None` if there are none.
+    pub fn from_config(config: &Config, sim: &Simulation) -> Result<Option<Self>, String> {
+        let mut scripts = Vec::new();
+        for (light, path) in config.section("lua") {
+            let index = light
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| number.checked_sub(1))
+                .filter(|&index| index < sim.lights.len())
+                .ok_or_else(|| {
+                    format!(
+                        "[lua] {}: expected a light number from 1 to {}",
+                        light,
+                        sim.lights.len()
+                    )
+                })?;
+            let source =
+                fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let lua = Lua::new();
+            lua.load(&source)
+                .set_name(path.as_str())
+                .exec()
+                .map_err(|e| format!("Lua {}: {}", path, e))?;
+            lua.globals()
+                .get::<_, Function>("decide")
+                .map_err(|_| format!("Lua {}: no decide(light) function", path))?;
+            scripts.push((index, path.clone(), lua));
+        }
+        Ok((!scripts.is_empty()).then_some(Self { scripts }))
+    }
+
+    /// Follows the lights to their new indices after the editor changed the
+    /// road; scripts whose light was removed are dropped.
+    pub fn remap_lights(&mut self, moved: &[Option<usize>]) {
+        self.scripts
+            .retain_mut(|(index, _, _)| match moved.get(*index).copied().flatten() {
+                Some(new) => {
+                    *index = new;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// Asks each script for its light's phase and applies it.
+    pub fn update(&self, sim: &mut Simulation) -> Result<(), String> {
+        for (index, path, lua) in &self.scripts {
+            let index = *index;
+            // The editor may have swapped in a road with fewer lights.
+            if index >= sim.lights.len() {
+                continue;
+            }
+            let error = |e: mlua::Error| format!("Lua {}: {}", path, e);
+            let detectors = sim.detectors(index);
+            let light = lua.create_table().map_err(error)?;
+            light.set("light", index + 1).map_err(error)?;
+            light
+                .set("position", sim.lights[index].position)
+                .map_err(error)?;
+            light.set("state", detectors.state.name()).map_err(error)?;
+            light.set("elapsed", detectors.elapsed).map_err(error)?;
+            light.set("queue", detectors.queue).map_err(error)?;
+            light
+                .set("bike_queue", detectors.bike_queue)
+                .map_err(error)?;
+            light
+                .set("approaching", detectors.approaching)
+                .map_err(error)?;
+            light.set("calls", detectors.calls).map_err(error)?;
+
+            let decide: Function = lua.globals().get("decide").map_err(error)?;
+            let state = match decide.call::<_, Value>(light).map_err(error)? {
+                Value::Nil => continue,
+                Value::String(phase) => match phase.to_str().map_err(error)? {
+                    "red" => TrafficLightState::Red,
+                    "yellow" => TrafficLightState::Yellow,
+                    "green" => TrafficLightState::Green,
+                    other => return Err(format!("Lua {}: unknown phase '{}'", path, other)),
+                },
+                other => {
+                    return Err(format!(
+                        "Lua {}: decide returned a {}, expected a phase name or nil",
+                        path,
+                        other.type_name()
+                    ));
+                }
+            };
+            let light = &mut sim.lights[index];
+            light.held = true;
+            if light.state != state {
+                light.force(state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives the scripted lights back to their own timers.
+    pub fn release(&self, sim: &mut Simulation) {
+        for &(index, _, _) in &self.scripts {
+            if let Some(light) = sim.lights.get_mut(index) {
+                light.held = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::systems::config::Config;
+
+/// The `[malfunctions]` section: signals that now and then fail and fall
+/// back to flashing red, as an all-way stop, until a crew repairs them.
+#[derive(Debug, Clone, Copy)]
+pub struct MalfunctionConfig {
+    /// Chance each minute that one of the working signals fails.
+    pub per_minute: f32,
+    /// Seconds a repair takes.
+    pub repair_seconds: f32,
+}
+
+impl Default for MalfunctionConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: 0.05,
+            repair_seconds: 60.0,
+        }
+    }
+}
+
+impl MalfunctionConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut malfunctions = Self::default();
+        for (key, value) in config.section("malfunctions") {
+            match key.as_str() {
+                "per_minute" => {
+                    malfunctions.per_minute = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| (0.0..=1.0).contains(n))
+                        .ok_or("[malfunctions] per_minute must be a fraction from 0 to 1")?;
+                }
+                "repair_seconds" => {
+                    malfunctions.repair_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[malfunctions] repair_seconds must be a positive number")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [malfunctions]", other)),
+            }
+        }
+        Ok(malfunctions)
+    }
+}
@@ -1,2 +1,49 @@
+pub mod bridge;
+pub mod career;
+pub mod challenge;
+pub mod clock;
+pub mod closure;
+pub mod config;
+pub mod construction;
+#[cfg(feature = "sqlite")]
+pub mod database;
+pub mod delivery;
+pub mod demand;
+pub mod detector;
+pub mod driver;
+pub mod editor;
+pub mod emergency;
+pub mod enforcement;
+pub mod events;
+pub mod experiment;
+pub mod history;
+pub mod incident;
+#[cfg(feature = "lua")]
+pub mod lua_control;
+pub mod malfunction;
+pub mod nema;
+pub mod night_flash;
+pub mod optimize;
+pub mod osm;
+pub mod parking;
+pub mod pool;
+pub mod profiler;
+pub mod rideshare;
+pub mod rng;
+pub mod road;
+pub mod rush_hour;
+pub mod school_zone;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod signal_control;
+pub mod simulation;
+pub mod snapshot;
+pub mod spatial;
+pub mod special_event;
+pub mod stats;
+pub mod telemetry;
+pub mod toll;
 pub mod traffic_light;
-pub mod car;
+pub mod turns;
+pub mod vehicle;
+pub mod weather;
@@ -0,0 +1,344 @@
+use crate::systems::config::Config;
+use crate::systems::signal_control::{ControlMode, SignalController};
+use crate::systems::simulation::Detectors;
+use crate::systems::traffic_light::{LEADING_BIKE_INTERVAL, TrafficLightState};
+
+/// The `[nema]` section: phase times for lights run as an eight-phase
+/// dual-ring controller. The road is the through movement of phases 2
+/// and 6; phases 1 and 5 are its protected lefts, 4 and 8 the cross
+/// street's throughs and 3 and 7 their lefts. Ring 1 runs 1, 2, 3, 4 and
+/// ring 2 runs 5, 6, 7, 8, crossing the barrier between the two streets
+/// together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NemaConfig {
+    /// Green the road always gets, and the most it can be stretched to.
+    pub min_green: u32,
+    pub max_green: u32,
+    /// Seconds with the road's induction loops empty before its green gaps
+    /// out.
+    pub gap: u32,
+    /// Protected left phases (1, 5, 3 and 7), or 0 for permissive lefts
+    /// that go in gaps during the throughs and skip their phases.
+    pub left_green: u32,
+    /// The cross street's through phases (4 and 8).
+    pub cross_green: u32,
+}
+
+impl Default for NemaConfig {
+    fn default() -> Self {
+        Self {
+            min_green: 4,
+            max_green: 15,
+            gap: 2,
+            left_green: 3,
+            cross_green: 6,
+        }
+    }
+}
+
+impl NemaConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut nema = Self::default();
+        for (key, value) in config.section("nema") {
+            let field = match key.as_str() {
+                "min_green" => &mut nema.min_green,
+                "max_green" => &mut nema.max_green,
+                "gap" => &mut nema.gap,
+                "left_green" => &mut nema.left_green,
+                "cross_green" => &mut nema.cross_green,
+                other => return Err(format!("Unknown setting '{}' in [nema]", other)),
+            };
+            *field = value
+                .parse()
+                .map_err(|_| format!("[nema] {} expects whole seconds, got '{}'", key, value))?;
+        }
+        if nema.min_green == 0 || nema.cross_green == 0 {
+            return Err("[nema] min_green and cross_green must be at least a second".to_string());
+        }
+        if nema.max_green < nema.min_green {
+            return Err("[nema] max_green can't be below min_green".to_string());
+        }
+        Ok(nema)
+    }
+}
+
+/// Where a phase pair is in its interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interval {
+    Green,
+    Yellow,
+    RedClearance,
+    /// The bicycles' leading green, after the last clearance.
+    BikeLead,
+}
+
+impl Interval {
+    fn name(self) -> &'static str {
+        match self {
+            Interval::Green => "green",
+            Interval::Yellow => "yellow",
+            Interval::RedClearance => "red clearance",
+            Interval::BikeLead => "bike lead",
+        }
+    }
+}
+
+/// One step of the road's red: the phase pair timing, which interval it
+/// is in and for how long.
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    phases: (u8, u8),
+    interval: Interval,
+    seconds: u32,
+}
+
+/// Eight-phase ring-and-barrier controller. Only the road's phases see
+/// vehicles, so its green is actuated (a minimum, then stretched until the
+/// approach gaps out or the maximum is reached) and the other phases run
+/// their set times during its red.
+#[derive(Debug, Clone)]
+pub struct Nema {
+    times: NemaConfig,
+    yellow: u32,
+    red_clearance: u32,
+    /// When into the green a loop last reported a vehicle.
+    last_call: f32,
+}
+
+impl Nema {
+    pub fn new(times: NemaConfig, yellow: u32, red_clearance: u32) -> Self {
+        Self {
+            times,
+            yellow,
+            red_clearance,
+            last_call: 0.0,
+        }
+    }
+
+    /// The road's red, step by step: the clearance after phases 2 and 6,
+    /// then across the barrier the cross street's lefts and throughs, and
+    /// back across it the road's own lefts. With clearances, the bicycles'
+    /// leading green comes after the last one rather than during it.
+    fn red_steps(&self) -> Vec<Step> {
+        let mut steps = vec![Step {
+            phases: (2, 6),
+            interval: Interval::RedClearance,
+            seconds: self.red_clearance,
+        }];
+        let mut serve = |phases, green| {
+            if green == 0 {
+                return;
+            }
+            for (interval, seconds) in [
+                (Interval::Green, green),
+                (Interval::Yellow, self.yellow),
+                (Interval::RedClearance, self.red_clearance),
+            ] {
+                steps.push(Step {
+                    phases,
+                    interval,
+                    seconds,
+                });
+            }
+        };
+        serve((3, 7), self.times.left_green);
+        serve((4, 8), self.times.cross_green);
+        serve((1, 5), self.times.left_green);
+        if self.red_clearance > 0 {
+            steps.push(Step {
+                phases: (2, 6),
+                interval: Interval::BikeLead,
+                seconds: LEADING_BIKE_INTERVAL,
+            });
+        }
+        steps.retain(|step| step.seconds > 0);
+        steps
+    }
+
+    /// The step `elapsed` seconds into the road's red, and seconds left in it.
+    fn red_step(&self, elapsed: f32) -> Option<(Step, f32)> {
+        let mut start = 0.0;
+        for step in self.red_steps() {
+            let end = start + step.seconds as f32;
+            if elapsed < end {
+                return Some((step, end - elapsed));
+            }
+            start = end;
+        }
+        None
+    }
+}
+
+impl SignalController for Nema {
+    fn mode(&self) -> ControlMode {
+        ControlMode::Nema
+    }
+
+    fn duration(&self, state: TrafficLightState) -> Option<u32> {
+        Some(match state {
+            TrafficLightState::Green => self.times.max_green,
+            TrafficLightState::Yellow => self.yellow,
+            TrafficLightState::Red => self.red_steps().iter().map(|step| step.seconds).sum(),
+        })
+    }
+
+    fn decide(&mut self, detectors: &Detectors) -> Option<TrafficLightState> {
+        let elapsed = detectors.elapsed;
+        let done = match detectors.state {
+            TrafficLightState::Green => {
+                if elapsed < self.last_call || detectors.calls {
+                    self.last_call = elapsed;
+                }
+                let gapped = elapsed - self.last_call + f32::EPSILON >= self.times.gap as f32;
+                let served = |seconds: u32| elapsed + f32::EPSILON >= seconds as f32;
+                served(self.times.max_green) || (served(self.times.min_green) && gapped)
+            }
+            state => elapsed + f32::EPSILON >= self.duration(state).unwrap_or(0) as f32,
+        };
+        if !done {
+            return None;
+        }
+        self.last_call = 0.0;
+        Some(detectors.state.next())
+    }
+
+    fn all_red(&self) -> u32 {
+        self.red_clearance
+    }
+
+    fn clearing(&self, elapsed: f32) -> bool {
+        self.red_step(elapsed)
+            .is_some_and(|(step, _)| step.interval == Interval::RedClearance)
+    }
+
+    fn phase_note(&self, state: TrafficLightState, elapsed: f32) -> Option<String> {
+        let (phases, interval) = match state {
+            TrafficLightState::Green => ((2, 6), Interval::Green),
+            TrafficLightState::Yellow => ((2, 6), Interval::Yellow),
+            TrafficLightState::Red => {
+                let (step, _) = self.red_step(elapsed)?;
+                (step.phases, step.interval)
+            }
+        };
+        Some(format!(
+            "phases {}+{} {}",
+            phases.0,
+            phases.1,
+            interval.name()
+        ))
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 0.25;
+
+    /// Runs `nema` for `seconds` from the start of the road's red, returning
+    /// the time of each phase change and the phase it went to. `calls`
+    /// says whether a loop is occupied at a time into the run.
+    fn run(
+        nema: &mut Nema,
+        seconds: f32,
+        calls: impl Fn(f32) -> bool,
+    ) -> Vec<(f32, TrafficLightState)> {
+        let (mut state, mut elapsed, mut time) = (TrafficLightState::Red, 0.0, 0.0);
+        let mut changes = Vec::new();
+        while time < seconds {
+            time += STEP;
+            elapsed += STEP;
+            let detectors = Detectors {
+                state,
+                elapsed,
+                queue: 0,
+                bike_queue: 0,
+                approaching: 0,
+                calls: calls(time),
+            };
+            if let Some(next) = nema.decide(&detectors) {
+                changes.push((time, next));
+                (state, elapsed) = (next, 0.0);
+            }
+        }
+        changes
+    }
+
+    fn note(nema: &Nema, elapsed: f32) -> String {
+        nema.phase_note(TrafficLightState::Red, elapsed).unwrap()
+    }
+
+    #[test]
+    fn red_serves_the_other_phases_around_the_barrier() {
+        let nema = Nema::new(NemaConfig::default(), 2, 1);
+        // Clearance, 3+7, 4+8 and 1+5 each with yellow and clearance, then
+        // the bike lead.
+        assert_eq!(
+            nema.duration(TrafficLightState::Red),
+            Some(1 + 6 + 9 + 6 + 1)
+        );
+        assert_eq!(note(&nema, 0.5), "phases 2+6 red clearance");
+        assert_eq!(note(&nema, 1.5), "phases 3+7 green");
+        assert_eq!(note(&nema, 6.5), "phases 3+7 red clearance");
+        assert_eq!(note(&nema, 7.5), "phases 4+8 green");
+        assert_eq!(note(&nema, 16.5), "phases 1+5 green");
+        assert_eq!(note(&nema, 22.5), "phases 2+6 bike lead");
+        assert!(nema.clearing(0.5) && nema.clearing(6.5));
+        assert!(!nema.clearing(1.5) && !nema.clearing(22.5));
+    }
+
+    #[test]
+    fn permissive_lefts_and_no_clearance_shorten_the_red() {
+        let permissive = NemaConfig {
+            left_green: 0,
+            ..NemaConfig::default()
+        };
+        let nema = Nema::new(permissive, 2, 1);
+        assert_eq!(nema.duration(TrafficLightState::Red), Some(1 + 9 + 1));
+        assert_eq!(note(&nema, 1.5), "phases 4+8 green");
+
+        // Without clearances there is no bike lead to follow them either.
+        let nema = Nema::new(NemaConfig::default(), 2, 0);
+        assert_eq!(nema.duration(TrafficLightState::Red), Some(5 + 8 + 5));
+        assert_eq!(nema.all_red(), 0);
+        assert!(!nema.clearing(0.5));
+    }
+
+    #[test]
+    fn road_green_gaps_out_or_runs_to_its_maximum() {
+        let red = 23.0;
+        let mut quiet = Nema::new(NemaConfig::default(), 2, 1);
+        let changes = run(&mut quiet, red + 10.0, |_| false);
+        assert_eq!(changes[0], (red, TrafficLightState::Green));
+        // An empty approach still gets the minimum green.
+        assert_eq!(changes[1], (red + 4.0, TrafficLightState::Yellow));
+
+        let mut busy = Nema::new(NemaConfig::default(), 2, 1);
+        let changes = run(&mut busy, red + 20.0, |_| true);
+        assert_eq!(changes[1], (red + 15.0, TrafficLightState::Yellow));
+        assert_eq!(changes[2], (red + 17.0, TrafficLightState::Red));
+
+        // Arrivals until 6s into the green keep it for a gap after them.
+        let mut late = Nema::new(NemaConfig::default(), 2, 1);
+        let changes = run(&mut late, red + 20.0, |time| time <= red + 6.0);
+        assert_eq!(changes[1], (red + 8.0, TrafficLightState::Yellow));
+    }
+
+    #[test]
+    fn config_checks_its_times() {
+        let parse = |text: &str| NemaConfig::from_config(&Config::parse(text).unwrap());
+        let nema = parse("[nema]\nmin_green = 5\nmax_green = 20\nleft_green = 0\n").unwrap();
+        assert_eq!(
+            (nema.min_green, nema.max_green, nema.left_green),
+            (5, 20, 0)
+        );
+        assert!(parse("[nema]\nmin_green = 10\nmax_green = 5\n").is_err());
+        assert!(parse("[nema]\ncross_green = 0\n").is_err());
+        assert!(parse("[nema]\ngap = two\n").is_err());
+        assert!(parse("[nema]\nwalk = 5\n").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::systems::clock::{SimClock, parse_time};
+use crate::systems::config::Config;
+use crate::systems::traffic_light::Flashing;
+
+/// Speed limit through a light flashing yellow, in cells per second.
+pub const CAUTION_SPEED_LIMIT: f32 = 0.75;
+
+/// The `[night_flash]` section: the hours the signals stop cycling and
+/// flash instead, yellow where the road is the major street and red at the
+/// lights listed as minor, where it crosses a busier one.
+#[derive(Debug, Clone, Default)]
+pub struct NightFlashConfig {
+    /// Start and end, in minutes past midnight. The end may come before
+    /// the start, for hours that run past midnight.
+    pub hours: Option<(f32, f32)>,
+    /// Lights (by index) where the road is the minor street.
+    pub minor: Vec<usize>,
+}
+
+impl NightFlashConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut night = Self::default();
+        for (key, value) in config.section("night_flash") {
+            match key.as_str() {
+                "hours" => {
+                    let usage = "[night_flash] hours must look like 23:00-05:00";
+                    let (start, end) = value.split_once('-').ok_or(usage)?;
+                    let start = parse_time(start.trim()).ok_or(usage)?;
+                    let end = parse_time(end.trim()).ok_or(usage)?;
+                    night.hours = Some((start, end));
+                }
+                "minor" => {
+                    for number in value.split(',').map(str::trim) {
+                        let light: usize = number
+                            .parse()
+                            .ok()
+                            .filter(|&n| n >= 1)
+                            .ok_or("[night_flash] minor must be light numbers, like 1, 3")?;
+                        night.minor.push(light - 1);
+                    }
+                }
+                other => return Err(format!("Unknown setting '{}' in [night_flash]", other)),
+            }
+        }
+        Ok(night)
+    }
+
+    /// Whether the clock is in the flashing hours.
+    pub fn active(&self, clock: &SimClock) -> bool {
+        let minute = clock.minute_of_day();
+        match self.hours {
+            Some((start, end)) if start <= end => (start..end).contains(&minute),
+            Some((start, end)) => minute >= start || minute < end,
+            None => false,
+        }
+    }
+
+    /// How light `index` flashes during the hours.
+    pub fn flashing_for(&self, index: usize) -> Flashing {
+        if self.minor.contains(&index) {
+            Flashing::Red
+        } else {
+            Flashing::Yellow
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs;
+
+use crate::systems::experiment::{Scenario, Summary};
+use crate::systems::rng::Rng;
+use crate::systems::signal_control::Timing;
+
+/// Shortest and longest green or red a plan may use, in seconds.
+const MIN_PHASE: u32 = 2;
+const MAX_PHASE: u32 = 20;
+/// Best plans carried over unchanged into the next generation.
+const ELITE: usize = 2;
+/// Chance that each green, red or offset of a child is nudged.
+const MUTATION_CHANCE: f32 = 0.25;
+
+/// Evolves fixed-time plans for every light of a scenario: each generation
+/// keeps the best plans, breeds the rest from tournament winners, and
+/// nudges a few of their times at random.
+pub struct Optimizer {
+    pub population: usize,
+    pub generations: usize,
+    rng: Rng,
+}
+
+impl Optimizer {
+    pub fn new(population: usize, generations: usize, rng: Rng) -> Self {
+        Self {
+            population: population.max(ELITE + 1),
+            generations,
+            rng,
+        }
+    }
+
+    /// Runs the search starting from `start`, calling `report` with the
+    /// generation number and best delay after each one. Returns the best
+    /// plan found and its delay.
+    pub fn run(
+        &mut self,
+        scenario: &Scenario,
+        start: Vec<Timing>,
+        mut report: impl FnMut(usize, &Summary),
+    ) -> (Vec<Timing>, Summary) {
+        let mut population: Vec<(Vec<Timing>, Option<Summary>)> = vec![(start.clone(), None)];
+        while population.len() < self.population {
+            let plan = start.iter().map(|&base| self.random_timing(base)).collect();
+            population.push((plan, None));
+        }
+
+        for generation in 1..=self.generations {
+            for (plan, delay) in population.iter_mut() {
+                if delay.is_none() {
+                    *delay = Some(scenario.delay(plan));
+                }
+            }
+            population.sort_by(|a, b| mean_delay(a).total_cmp(&mean_delay(b)));
+            report(generation, &population[0].1.unwrap_or_default());
+            if generation == self.generations {
+                break;
+            }
+
+            let mut next: Vec<(Vec<Timing>, Option<Summary>)> = population[..ELITE].to_vec();
+            while next.len() < self.population {
+                let mother = self.tournament(&population);
+                let father = self.tournament(&population);
+                let child = mother
+                    .iter()
+                    .zip(father)
+                    .map(|(&a, &b)| {
+                        let timing = if self.rng.chance(0.5) { a } else { b };
+                        self.mutate(timing)
+                    })
+                    .collect();
+                next.push((child, None));
+            }
+            population = next;
+        }
+        let (plan, delay) = population.swap_remove(0);
+        (plan, delay.unwrap_or_default())
+    }
+
+    /// The better of two plans picked at random.
+    fn tournament<'a>(&mut self, population: &'a [(Vec<Timing>, Option<Summary>)]) -> &'a [Timing] {
+        let a = self.rng.below(population.len() as u32) as usize;
+        let b = self.rng.below(population.len() as u32) as usize;
+        &population[a.min(b)].0
+    }
+
+    /// A random green, red and offset, keeping the rest of `base`.
+    fn random_timing(&mut self, base: Timing) -> Timing {
+        let span = MAX_PHASE - MIN_PHASE + 1;
+        let mut timing = Timing {
+            green: MIN_PHASE + self.rng.below(span),
+            red: MIN_PHASE + self.rng.below(span),
+            offset: 0,
+            ..base
+        };
+        timing.offset = self.rng.below(timing.cycle());
+        timing
+    }
+
+    fn mutate(&mut self, mut timing: Timing) -> Timing {
+        if self.rng.chance(MUTATION_CHANCE) {
+            timing.green = self.nudge(timing.green).clamp(MIN_PHASE, MAX_PHASE);
+        }
+        if self.rng.chance(MUTATION_CHANCE) {
+            timing.red = self.nudge(timing.red).clamp(MIN_PHASE, MAX_PHASE);
+        }
+        if self.rng.chance(MUTATION_CHANCE) {
+            timing.offset = self.nudge(timing.offset);
+        }
+        timing.offset %= timing.cycle();
+        timing
+    }
+
+    /// Moves `seconds` up or down by one to three.
+    fn nudge(&mut self, seconds: u32) -> u32 {
+        let step = 1 + self.rng.below(3);
+        if self.rng.chance(0.5) {
+            seconds + step
+        } else {
+            seconds.saturating_sub(step)
+        }
+    }
+}
+
+fn mean_delay((_, delay): &(Vec<Timing>, Option<Summary>)) -> f32 {
+    delay.map_or(f32::MAX, |delay| delay.mean)
+}
+
+/// A plan as the `[timing]` config section that loads it.
+pub fn timing_section(plan: &[Timing]) -> String {
+    let mut text = String::from("[timing]\n");
+    for (index, timing) in plan.iter().enumerate() {
+        text.push_str(&format!("{} = {}\n", index + 1, timing));
+    }
+    text
+}
+
+/// Writes `plan` into the config file at `path`, replacing its `[timing]`
+/// section and keeping everything else. Creates the file if needed.
+pub fn write_plan(path: &str, plan: &[Timing]) -> Result<(), String> {
+    let old = fs::read_to_string(path).unwrap_or_default();
+    let section = timing_section(plan);
+    let mut text = String::new();
+    let mut replaced = false;
+    let mut skipping = false;
+    for line in old.lines() {
+        let header = line.trim().starts_with('[');
+        if header && skipping {
+            skipping = false;
+            text.push('\n');
+        }
+        if line.trim() == "[timing]" {
+            text.push_str(&section);
+            replaced = true;
+            skipping = true;
+        } else if !skipping {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    if !replaced {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&section);
+    }
+    fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::systems::road::Road;
+
+/// Real-world distance squeezed into one road cell.
+const METERS_PER_CELL: f64 = 25.0;
+const MIN_CELLS: usize = 10;
+const MAX_CELLS: usize = 80;
+
+struct Node {
+    lat: f64,
+    lon: f64,
+    signal: bool,
+}
+
+struct Way {
+    nodes: Vec<u64>,
+}
+
+/// Imports a small OpenStreetMap `.osm` extract as a (caricature) road.
+///
+/// The longest highway in the extract becomes the simulated road, scaled
+/// down to the terminal. Nodes tagged `highway=traffic_signals` along it
+/// become signals, and junctions with other highways become spawn points
+/// where side-street traffic joins.
+pub fn import(path: &str) -> Result<Road, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    road_from(&text)
+}
+
+/// The road for the extract in `text`. Ways can name nodes the extract
+/// leaves out, at its edges; those are skipped, both when measuring a way
+/// and when walking along it.
+fn road_from(text: &str) -> Result<Road, String> {
+    let (nodes, ways) = parse(text)?;
+
+    let way_length = |way: &Way| -> f64 {
+        let present: Vec<&Node> = way.nodes.iter().filter_map(|id| nodes.get(id)).collect();
+        present
+            .windows(2)
+            .map(|pair| distance(pair[0], pair[1]))
+            .sum()
+    };
+    let main = ways
+        .iter()
+        .max_by(|a, b| way_length(a).total_cmp(&way_length(b)))
+        .ok_or("Extract contains no highways")?;
+    let total = way_length(main);
+    if total <= 0.0 {
+        return Err("Longest highway has no length".to_string());
+    }
+
+    let length = ((total / METERS_PER_CELL).round() as usize).clamp(MIN_CELLS, MAX_CELLS);
+    let mut cells = vec!['='; length];
+    cells[0] = 'S';
+
+    let mut travelled = 0.0;
+    let mut previous: Option<&Node> = None;
+    for id in &main.nodes {
+        let Some(node) = nodes.get(id) else {
+            continue;
+        };
+        if let Some(previous) = previous {
+            travelled += distance(previous, node);
+        }
+        previous = Some(node);
+
+        let cell = (((travelled / total) * (length - 1) as f64).round() as usize).min(length - 1);
+        let junction = ways
+            .iter()
+            .any(|other| !std::ptr::eq(other, main) && other.nodes.contains(id));
+        if node.signal && cell > 0 {
+            cells[cell] = '|';
+        } else if junction && cell + 1 < length && cells[cell + 1] == '=' {
+            // Side-street traffic joins just past the junction.
+            cells[cell + 1] = 'S';
+        }
+    }
+
+    Road::parse(&cells.iter().collect::<String>())
+}
+
+fn parse(text: &str) -> Result<(HashMap<u64, Node>, Vec<Way>), String> {
+    let mut nodes = HashMap::new();
+    let mut ways = Vec::new();
+    let mut current_node: Option<(u64, Node)> = None;
+    let mut current_way: Option<(Way, bool)> = None;
+
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            return Err("Unterminated XML tag".to_string());
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+        match name {
+            "node" => {
+                let id = number(attrs, "id")?;
+                let node = Node {
+                    lat: number(attrs, "lat")?,
+                    lon: number(attrs, "lon")?,
+                    signal: false,
+                };
+                if self_closing {
+                    nodes.insert(id, node);
+                } else {
+                    current_node = Some((id, node));
+                }
+            }
+            "/node" => {
+                if let Some((id, node)) = current_node.take() {
+                    nodes.insert(id, node);
+                }
+            }
+            "way" => current_way = Some((Way { nodes: Vec::new() }, false)),
+            "/way" => {
+                if let Some((way, true)) = current_way.take() {
+                    ways.push(way);
+                }
+            }
+            "nd" => {
+                if let Some((way, _)) = current_way.as_mut() {
+                    way.nodes.push(number(attrs, "ref")?);
+                }
+            }
+            "tag" => {
+                let key = attribute(attrs, "k").unwrap_or_default();
+                let value = attribute(attrs, "v").unwrap_or_default();
+                if let Some((_, node)) = current_node.as_mut() {
+                    node.signal |= key == "highway" && value == "traffic_signals";
+                }
+                if let Some((_, highway)) = current_way.as_mut() {
+                    *highway |= key == "highway";
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((nodes, ways))
+}
+
+fn attribute<'a>(attrs: &'a str, key: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        let pattern = format!("{}={}", key, quote);
+        let mut search = attrs;
+        while let Some(found) = search.find(&pattern) {
+            // Make sure we matched the whole attribute name, not a suffix.
+            let boundary = found == 0 || search[..found].ends_with(char::is_whitespace);
+            let value = &search[found + pattern.len()..];
+            if boundary {
+                return value.find(quote).map(|end| &value[..end]);
+            }
+            search = value;
+        }
+    }
+    None
+}
+
+fn number<T: std::str::FromStr>(attrs: &str, key: &str) -> Result<T, String> {
+    attribute(attrs, key)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("Missing or invalid '{}' attribute", key))
+}
+
+/// Great-circle distance between two nodes, in meters.
+fn distance(a: &Node, b: &Node) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_000.0;
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.lon - a.lon).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A main street of three nodes, the middle one signalised, crossed at
+    /// its first node by a side street. The building isn't a highway.
+    const EXTRACT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="51.5000" lon="-0.1000"/>
+  <node id="2" lat="51.5000" lon="-0.0950">
+    <tag k="highway" v="traffic_signals"/>
+  </node>
+  <node id="3" lat='51.5000' lon='-0.0900'/>
+  <node id="4" lat="51.5010" lon="-0.1000"/>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <nd ref="3"/>
+    <tag k="highway" v="primary"/>
+  </way>
+  <way id="11">
+    <nd ref="4"/>
+    <nd ref="1"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="12">
+    <nd ref="1"/>
+    <nd ref="4"/>
+    <tag k="building" v="yes"/>
+  </way>
+</osm>
+"#;
+
+    #[test]
+    fn parses_nodes_and_highways() {
+        let (nodes, ways) = parse(EXTRACT).unwrap();
+        assert_eq!(nodes.len(), 4);
+        assert!(nodes[&2].signal);
+        assert!(!nodes[&1].signal);
+        assert_eq!(nodes[&3].lon, -0.09);
+        assert_eq!(ways.len(), 2);
+        assert_eq!(ways[0].nodes, vec![1, 2, 3]);
+        assert_eq!(ways[1].nodes, vec![4, 1]);
+    }
+
+    #[test]
+    fn distance_is_in_meters() {
+        let (nodes, _) = parse(EXTRACT).unwrap();
+        // A thousandth of a degree of latitude is about 111m.
+        let north = distance(&nodes[&1], &nodes[&4]);
+        assert!((north - 111.0).abs() < 1.0, "{}", north);
+    }
+
+    #[test]
+    fn imports_the_longest_highway() {
+        let road = road_from(EXTRACT).unwrap();
+        // About 700m of main street at 25m a cell.
+        assert_eq!(road.length, 28);
+        assert_eq!(road.signals, vec![14]);
+        // Side-street traffic joins just past the junction at the start.
+        assert_eq!(road.spawn_points, vec![0, 1]);
+    }
+
+    #[test]
+    fn skips_nodes_missing_from_the_extract() {
+        // The main street names node 99, which was cut off the extract.
+        let extract = EXTRACT.replace(
+            "<nd ref=\"1\"/>\n    <nd ref=\"2\"/>",
+            "<nd ref=\"1\"/>\n    <nd ref=\"99\"/>\n    <nd ref=\"2\"/>",
+        );
+        assert!(extract.contains("\"99\""));
+        let road = road_from(&extract).unwrap();
+        assert_eq!(road.length, 28);
+        assert_eq!(road.signals, vec![14]);
+    }
+
+    #[test]
+    fn rejects_extracts_without_highways() {
+        let extract = r#"<osm><node id="1" lat="1" lon="1"/></osm>"#;
+        assert_eq!(
+            road_from(extract).err(),
+            Some("Extract contains no highways".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_broken_xml() {
+        assert_eq!(
+            parse("<osm><node id=\"1\" lat=\"5\"").err(),
+            Some("Unterminated XML tag".to_string())
+        );
+        assert!(parse(r#"<node id="x" lat="1" lon="2"/>"#).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::systems::config::Config;
+use crate::systems::vehicle::VehicleType;
+
+/// Times a driver goes round the block to try a full car park again before
+/// giving up on it.
+pub const MAX_CIRCLES: u32 = 2;
+
+/// The `[parking]` section: how big the car parks are and how many drivers
+/// are looking for one.
+#[derive(Debug, Clone, Copy)]
+pub struct ParkingConfig {
+    /// Spaces in each car park.
+    pub capacity: usize,
+    /// Fraction of drivers heading for a car park further along the road.
+    pub share: f32,
+    /// Average seconds a parked vehicle stays before driving off again.
+    pub stay_seconds: f32,
+}
+
+impl Default for ParkingConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 12,
+            share: 0.15,
+            stay_seconds: 90.0,
+        }
+    }
+}
+
+impl ParkingConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut parking = Self::default();
+        for (key, value) in config.section("parking") {
+            match key.as_str() {
+                "capacity" => {
+                    parking.capacity = value
+                        .parse()
+                        .map_err(|_| "[parking] capacity must be a whole number of spaces")?;
+                }
+                "share" => {
+                    parking.share = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| (0.0..=1.0).contains(n))
+                        .ok_or("[parking] share must be a fraction from 0 to 1")?;
+                }
+                "stay_seconds" => {
+                    parking.stay_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[parking] stay_seconds must be a positive number")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [parking]", other)),
+            }
+        }
+        Ok(parking)
+    }
+}
+
+/// A vehicle left in a car park, and the seconds until its driver is back.
+#[derive(Debug, Clone, Copy)]
+pub struct ParkedVehicle {
+    pub kind: VehicleType,
+    pub time_left: f32,
+}
+
+/// A car park beside the road.
+#[derive(Debug, Clone)]
+pub struct ParkingLot {
+    pub cell: usize,
+    pub parked: Vec<ParkedVehicle>,
+}
+
+impl ParkingLot {
+    pub fn new(cell: usize) -> Self {
+        Self {
+            cell,
+            parked: Vec::new(),
+        }
+    }
+
+    pub fn status(&self, capacity: usize) -> String {
+        format!(
+            "car park at {}: {}/{} spaces taken",
+            self.cell,
+            self.parked.len(),
+            capacity
+        )
+    }
+}
+
+/// A driver who found a car park full and is going round the block to
+/// come back past it.
+#[derive(Debug, Clone, Copy)]
+pub struct Circling {
+    pub kind: VehicleType,
+    /// Cell of the car park.
+    pub lot: usize,
+    /// Times round the block so far, this one included.
+    pub circles: u32,
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use crate::systems::spatial::SpatialIndex;
+use crate::systems::vehicle::{Lane, Vehicle};
+
+/// The vehicles on the road, each kept in a slot that stays put for as long
+/// as the vehicle lives. Slots of vehicles that leave are reused by later
+/// ones, so steady spawning and finishing doesn't shuffle the rest around.
+///
+/// Iteration follows the road order, front first, as of the last step.
+#[derive(Debug, Clone, Default)]
+pub struct VehiclePool {
+    slots: Vec<Option<Vehicle>>,
+    free: Vec<usize>,
+    /// Occupied slots, front of the road first. Vehicles added since the
+    /// last sort are at the end.
+    order: Vec<usize>,
+    by_id: HashMap<u32, usize>,
+    spatial: SpatialIndex,
+}
+
+impl VehiclePool {
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Vehicle> + Clone + '_ {
+        self.order.iter().map(|&slot| self.occupied(slot))
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Vehicle> {
+        self.by_id.get(&id).map(|&slot| self.occupied(slot))
+    }
+
+    /// The vehicle with `id`, to change anything but its position or lane;
+    /// [`VehiclePool::place`] moves it.
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Vehicle> {
+        let slot = *self.by_id.get(&id)?;
+        self.slots[slot].as_mut()
+    }
+
+    pub fn insert(&mut self, vehicle: Vehicle) {
+        let slot = self.free.pop().unwrap_or_else(|| {
+            self.slots.push(None);
+            self.slots.len() - 1
+        });
+        self.spatial.insert(slot, &vehicle);
+        self.by_id.insert(vehicle.id, slot);
+        self.order.push(slot);
+        self.slots[slot] = Some(vehicle);
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Vehicle> {
+        let vehicle = self.take(id)?;
+        self.order.retain(|&slot| self.slots[slot].is_some());
+        Some(vehicle)
+    }
+
+    /// Empties the vehicle's slot, leaving `order` for the caller to tidy.
+    fn take(&mut self, id: u32) -> Option<Vehicle> {
+        let slot = self.by_id.remove(&id)?;
+        let vehicle = self.slots[slot].take()?;
+        self.spatial.remove(slot, &vehicle);
+        self.free.push(slot);
+        Some(vehicle)
+    }
+
+    /// Moves the vehicle with `id` to `position`.
+    pub fn place(&mut self, id: u32, position: f32) -> Option<&mut Vehicle> {
+        let slot = *self.by_id.get(&id)?;
+        let vehicle = self.slots[slot].as_mut()?;
+        self.spatial.remove(slot, vehicle);
+        vehicle.position = position;
+        self.spatial.insert(slot, vehicle);
+        Some(vehicle)
+    }
+
+    /// Removes every vehicle `keep` says no to.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Vehicle) -> bool) {
+        let mut removed = Vec::new();
+        self.remove_into(|vehicle| !keep(vehicle), &mut removed);
+    }
+
+    /// Moves the vehicles `leave` picks out of the pool and into `out`.
+    pub fn remove_into(&mut self, mut leave: impl FnMut(&Vehicle) -> bool, out: &mut Vec<Vehicle>) {
+        let leaving: Vec<u32> = self.iter().filter(|v| leave(v)).map(|v| v.id).collect();
+        if leaving.is_empty() {
+            return;
+        }
+        out.extend(leaving.into_iter().filter_map(|id| self.take(id)));
+        self.order.retain(|&slot| self.slots[slot].is_some());
+    }
+
+    /// Vehicles in `lane` whose front is somewhere from `from` to `to`,
+    /// give or take a few cells. Callers check the exact condition.
+    pub fn near(&self, lane: Lane, from: f32, to: f32) -> impl Iterator<Item = &Vehicle> {
+        self.spatial
+            .near(lane, from, to)
+            .map(|slot| self.occupied(slot))
+    }
+
+    /// Puts the vehicles back in road order, front first.
+    pub fn sort_by_position(&mut self) {
+        let slots = &self.slots;
+        self.order.sort_by(|&a, &b| {
+            let (a, b) = (&slots[a], &slots[b]);
+            let position = |vehicle: &Option<Vehicle>| vehicle.as_ref().map_or(0.0, |v| v.position);
+            position(b).total_cmp(&position(a))
+        });
+    }
+
+    /// Lets `step` move each vehicle in road order, front first, showing it
+    /// the vehicles ahead as they are after their own move.
+    pub fn move_in_order(&mut self, mut step: impl FnMut(Ahead, &mut Vehicle)) {
+        for position in 0..self.order.len() {
+            let slot = self.order[position];
+            let Some(mut vehicle) = self.slots[slot].take() else {
+                continue;
+            };
+            let ahead = Ahead {
+                slots: &self.slots,
+                order: &self.order[..position],
+            };
+            step(ahead, &mut vehicle);
+            self.slots[slot] = Some(vehicle);
+        }
+        self.reindex();
+    }
+
+    /// Works out a move for every vehicle from where the traffic is now,
+    /// on all cores, then applies all of them.
+    #[cfg(feature = "parallel")]
+    pub fn move_in_parallel<T: Send>(
+        &mut self,
+        plan: impl Fn(Ahead, &Vehicle) -> T + Sync,
+        apply: impl Fn(&mut Vehicle, T) + Sync,
+    ) {
+        use rayon::prelude::*;
+
+        let (slots, order) = (&self.slots, &self.order);
+        let mut plans: Vec<Option<T>> = Vec::new();
+        plans.resize_with(slots.len(), || None);
+        let planned: Vec<(usize, T)> = (0..order.len())
+            .into_par_iter()
+            .map(|position| {
+                let slot = order[position];
+                let ahead = Ahead {
+                    slots,
+                    order: &order[..position],
+                };
+                (slot, plan(ahead, self.occupied(slot)))
+            })
+            .collect();
+        for (slot, planned) in planned {
+            plans[slot] = Some(planned);
+        }
+        self.slots
+            .par_iter_mut()
+            .zip(plans)
+            .for_each(|(vehicle, plan)| {
+                if let (Some(vehicle), Some(plan)) = (vehicle, plan) {
+                    apply(vehicle, plan);
+                }
+            });
+        self.reindex();
+    }
+
+    fn occupied(&self, slot: usize) -> &Vehicle {
+        self.slots[slot]
+            .as_ref()
+            .expect("ordered slots are occupied")
+    }
+
+    fn reindex(&mut self) {
+        let occupied = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, vehicle)| Some((slot, vehicle.as_ref()?)));
+        self.spatial.rebuild(occupied);
+    }
+}
+
+/// The vehicles in front of the one being moved, furthest first.
+#[derive(Clone, Copy)]
+pub struct Ahead<'a> {
+    slots: &'a [Option<Vehicle>],
+    order: &'a [usize],
+}
+
+impl<'a> Ahead<'a> {
+    pub fn iter(self) -> impl DoubleEndedIterator<Item = &'a Vehicle> + Clone {
+        self.order
+            .iter()
+            .filter_map(move |&slot| self.slots[slot].as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::vehicle::VehicleType;
+
+    fn car(id: u32, position: f32) -> Vehicle {
+        let mut vehicle = Vehicle::new(id, VehicleType::Car, Lane::Road);
+        vehicle.position = position;
+        vehicle
+    }
+
+    fn ids(pool: &VehiclePool) -> Vec<u32> {
+        pool.iter().map(|v| v.id).collect()
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let mut pool = VehiclePool::default();
+        for id in 1..=3 {
+            pool.insert(car(id, id as f32));
+        }
+        assert_eq!(pool.remove(2).map(|v| v.id), Some(2));
+        assert!(pool.get(2).is_none());
+        assert!(pool.remove(2).is_none());
+
+        pool.insert(car(4, 8.0));
+        assert_eq!(pool.slots.len(), 3);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.get(4).map(|v| v.position), Some(8.0));
+    }
+
+    #[test]
+    fn iterates_front_first_once_sorted() {
+        let mut pool = VehiclePool::default();
+        pool.insert(car(1, 2.0));
+        pool.insert(car(2, 9.0));
+        pool.insert(car(3, 5.0));
+        // Newcomers wait at the back until the next sort.
+        assert_eq!(ids(&pool), [1, 2, 3]);
+        pool.sort_by_position();
+        assert_eq!(ids(&pool), [2, 3, 1]);
+        assert_eq!(
+            pool.iter().rev().map(|v| v.id).collect::<Vec<_>>(),
+            [1, 3, 2]
+        );
+    }
+
+    #[test]
+    fn retain_and_remove_into_keep_the_order() {
+        let mut pool = VehiclePool::default();
+        for id in 1..=5 {
+            pool.insert(car(id, 10.0 - id as f32));
+        }
+        pool.sort_by_position();
+        let mut finished = Vec::new();
+        pool.remove_into(|v| v.id % 2 == 0, &mut finished);
+        assert_eq!(finished.iter().map(|v| v.id).collect::<Vec<_>>(), [2, 4]);
+        pool.retain(|v| v.id != 3);
+        assert_eq!(ids(&pool), [1, 5]);
+        assert_eq!(pool.free.len(), 3);
+    }
+
+    #[test]
+    fn moved_vehicles_are_found_where_they_went() {
+        let mut pool = VehiclePool::default();
+        pool.insert(car(1, 1.0));
+        pool.insert(car(2, 20.0));
+        pool.place(1, 18.0);
+        let near: Vec<u32> = pool.near(Lane::Road, 17.0, 21.0).map(|v| v.id).collect();
+        assert!(near.contains(&1) && near.contains(&2));
+        assert_eq!(pool.near(Lane::Road, 0.0, 2.0).count(), 0);
+
+        pool.sort_by_position();
+        pool.move_in_order(|ahead, vehicle| {
+            // The vehicle in front has already moved on by the time the one
+            // behind it is stepped.
+            if vehicle.id == 1 {
+                assert_eq!(ahead.iter().map(|v| v.position).collect::<Vec<_>>(), [30.0]);
+            }
+            vehicle.position += 10.0;
+        });
+        assert_eq!(pool.near(Lane::Road, 16.0, 21.0).count(), 0);
+        assert_eq!(pool.near(Lane::Road, 28.0, 30.0).count(), 2);
+        assert_eq!(pool.near(Lane::Bike, 0.0, 40.0).count(), 0);
+    }
+}
@@ -0,0 +1,138 @@
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+/// Parts of a frame the profiler tells apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// New vehicles, breakdowns and tow truck dispatch.
+    Spawning,
+    /// Car following, trips finishing and incident clearance.
+    Vehicles,
+    /// Detector readings and signal controllers.
+    Intersections,
+    /// External, Lua and script controllers.
+    Control,
+    /// CSV, telemetry, MQTT and database output.
+    Stats,
+    Render,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 6] = [
+        Phase::Spawning,
+        Phase::Vehicles,
+        Phase::Intersections,
+        Phase::Control,
+        Phase::Stats,
+        Phase::Render,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Spawning => "spawning",
+            Phase::Vehicles => "vehicles",
+            Phase::Intersections => "intersections",
+            Phase::Control => "control",
+            Phase::Stats => "stats",
+            Phase::Render => "render",
+        }
+    }
+}
+
+/// Wall-clock time spent in each phase, for the last frame and the whole
+/// session. Turned on with `enable_profiling` in `[performance]`.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    current: [Duration; Phase::ALL.len()],
+    last: [Duration; Phase::ALL.len()],
+    total: [Duration; Phase::ALL.len()],
+    frames: u32,
+    /// Longest frame so far, all phases together.
+    slowest: Duration,
+}
+
+impl Profiler {
+    pub fn add(&mut self, phase: Phase, time: Duration) {
+        self.current[phase as usize] += time;
+    }
+
+    /// Closes the frame being timed and starts the next one.
+    pub fn end_frame(&mut self) {
+        for (total, time) in self.total.iter_mut().zip(self.current) {
+            *total += time;
+        }
+        self.slowest = self.slowest.max(self.current.iter().sum());
+        self.last = std::mem::take(&mut self.current);
+        self.frames += 1;
+    }
+
+    /// One line with the last frame's breakdown, e.g.
+    /// `⏱ 1.42 ms: spawning 0.02  vehicles 0.10 ...`.
+    pub fn panel(&self) -> String {
+        let frame: Duration = self.last.iter().sum();
+        let phases: Vec<String> = Phase::ALL
+            .iter()
+            .zip(self.last)
+            .map(|(phase, time)| format!("{} {:.2}", phase.name(), millis(time)))
+            .collect();
+        format!("⏱ {:.2} ms: {}", millis(frame), phases.join("  "))
+    }
+
+    /// Per-phase totals for the whole session with a bar for each phase's
+    /// share of the time, widest first.
+    pub fn summary(&self) -> String {
+        let all: Duration = self.total.iter().sum();
+        let mut phases: Vec<(Phase, Duration)> = Phase::ALL.into_iter().zip(self.total).collect();
+        phases.sort_by_key(|&(_, time)| Reverse(time));
+        let mut text = format!(
+            "Profile over {} frames: {:.2} ms per frame on average, slowest {:.2} ms\n",
+            self.frames,
+            millis(all) / self.frames.max(1) as f32,
+            millis(self.slowest)
+        );
+        for (phase, time) in phases {
+            let share = if all.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f32() / all.as_secs_f32()
+            };
+            let line = format!(
+                "  {:<14} {:>8.3} ms/frame {:>5.1}%  {}",
+                phase.name(),
+                millis(time) / self.frames.max(1) as f32,
+                share * 100.0,
+                "█".repeat((share * 40.0).round() as usize)
+            );
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn millis(time: Duration) -> f32 {
+    time.as_secs_f32() * 1000.0
+}
+
+/// Times consecutive stretches of work, charging each to a phase. Without a
+/// profiler it does nothing and never reads the clock.
+pub struct Stopwatch<'a> {
+    profiler: Option<&'a mut Profiler>,
+    since: Option<Instant>,
+}
+
+impl<'a> Stopwatch<'a> {
+    pub fn start(profiler: Option<&'a mut Profiler>) -> Self {
+        let since = profiler.as_ref().map(|_| Instant::now());
+        Self { profiler, since }
+    }
+
+    /// Charges the time since the last lap (or the start) to `phase`.
+    pub fn lap(&mut self, phase: Phase) {
+        if let (Some(profiler), Some(since)) = (self.profiler.as_mut(), self.since) {
+            let now = Instant::now();
+            profiler.add(phase, now - since);
+            self.since = Some(now);
+        }
+    }
+}
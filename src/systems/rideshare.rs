@@ -0,0 +1,50 @@
+use crate::systems::config::Config;
+
+/// How close to its stop, in cells, a ride-share car has to have pulled up
+/// to count as at the curb.
+pub const CURB_REACH: f32 = 1.0;
+
+/// The `[rideshare]` section: cars that pull up at the curb on the way to
+/// pick up or drop off a passenger, holding up the lane behind them.
+#[derive(Debug, Clone, Copy)]
+pub struct RideShareConfig {
+    /// Fraction of cars that make a stop.
+    pub share: f32,
+    /// Seconds each stop takes.
+    pub stop_seconds: f32,
+}
+
+impl Default for RideShareConfig {
+    fn default() -> Self {
+        Self {
+            share: 0.08,
+            stop_seconds: 6.0,
+        }
+    }
+}
+
+impl RideShareConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut rideshare = Self::default();
+        for (key, value) in config.section("rideshare") {
+            match key.as_str() {
+                "share" => {
+                    rideshare.share = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| (0.0..=1.0).contains(n))
+                        .ok_or("[rideshare] share must be a fraction from 0 to 1")?;
+                }
+                "stop_seconds" => {
+                    rideshare.stop_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[rideshare] stop_seconds must be a positive number")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [rideshare]", other)),
+            }
+        }
+        Ok(rideshare)
+    }
+}
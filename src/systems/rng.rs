@@ -0,0 +1,40 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Small xorshift generator so the simulation stays dependency-free.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x2545_F491_4F6C_DD1D);
+        Self::seeded(nanos)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        (x >> 32) as u32
+    }
+
+    /// Returns a value in `0..bound`.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        self.next_u32() % bound.max(1)
+    }
+
+    /// Returns true with the given probability (0.0 to 1.0).
+    pub fn chance(&mut self, probability: f32) -> bool {
+        (self.next_u32() as f32 / u32::MAX as f32) < probability
+    }
+}
@@ -0,0 +1,338 @@
+use std::fs;
+
+/// Layout used when no `--map` file is given: one signal two thirds of the
+/// way along a 20-cell road.
+pub const DEFAULT_MAP: &str = "S============|======";
+
+/// The road the simulation runs on, built from a map definition.
+///
+/// Maps are plain text. The first non-comment line is the road itself:
+///
+/// - `S` a spawn point where vehicles enter
+/// - `=` plain road
+/// - `|` a signalised stop line
+/// - `H` a hospital, where ambulances set out from and return to
+/// - `F` a fire station, the same for fire trucks
+/// - `D` a drawbridge deck, one unbroken stretch that lifts for boats
+/// - `T` a toll booth, where drivers stop to pay
+/// - `P` a car park, which some drivers are heading for
+///
+/// An optional line of `b` characters, as long as the road, adds a bicycle
+/// lane alongside it. Another optional line, also as long as the road, sets
+/// speed limits on the road lane: a digit `n` limits its cell to `n`
+/// quarters of a cell per second (so `4` is 1 cell/s and `6` a car's
+/// cruising speed) and `-` leaves it unlimited. Lines starting with `#` are
+/// ignored.
+#[derive(Debug, Clone)]
+pub struct Road {
+    pub length: usize,
+    pub spawn_points: Vec<usize>,
+    pub signals: Vec<usize>,
+    pub facilities: Vec<(usize, Facility)>,
+    /// First and last cell of the drawbridge, if there is one.
+    pub bridge: Option<(usize, usize)>,
+    pub toll_booths: Vec<usize>,
+    pub parking_lots: Vec<usize>,
+    pub bike_lane: bool,
+    /// Stretches of road with a speed limit, as first cell, last cell and
+    /// limit in cells per second.
+    pub speed_limits: Vec<(usize, usize, f32)>,
+}
+
+/// Speed limit, in cells per second, of one step of a map's limit digits.
+pub const LIMIT_STEP: f32 = 0.25;
+
+/// A roadside building emergency vehicles are based at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facility {
+    Hospital,
+    FireStation,
+}
+
+impl Facility {
+    pub fn name(self) -> &'static str {
+        match self {
+            Facility::Hospital => "hospital",
+            Facility::FireStation => "fire station",
+        }
+    }
+
+    /// Character marking it in a map file.
+    pub fn map_char(self) -> char {
+        match self {
+            Facility::Hospital => 'H',
+            Facility::FireStation => 'F',
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Facility::Hospital => "🏥",
+            Facility::FireStation => "🧯",
+        }
+    }
+}
+
+impl Road {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read map {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'));
+
+        let road_line = lines.next().ok_or("Map is empty")?;
+        let mut spawn_points = Vec::new();
+        let mut signals = Vec::new();
+        let mut facilities = Vec::new();
+        let mut bridge: Vec<usize> = Vec::new();
+        let mut toll_booths = Vec::new();
+        let mut parking_lots = Vec::new();
+        for (cell, ch) in road_line.chars().enumerate() {
+            match ch {
+                'S' => spawn_points.push(cell),
+                '=' => {}
+                '|' => signals.push(cell),
+                'H' => facilities.push((cell, Facility::Hospital)),
+                'F' => facilities.push((cell, Facility::FireStation)),
+                'D' => bridge.push(cell),
+                'T' => toll_booths.push(cell),
+                'P' => parking_lots.push(cell),
+                ' ' => return Err(format!("Road is disconnected at column {}", cell + 1)),
+                other => {
+                    return Err(format!(
+                        "Unknown map character '{}' at column {}",
+                        other,
+                        cell + 1
+                    ));
+                }
+            }
+        }
+        let length = road_line.chars().count();
+        let bridge = match (bridge.first(), bridge.last()) {
+            (Some(&from), Some(&to)) if to - from + 1 == bridge.len() => Some((from, to)),
+            (Some(_), _) => {
+                return Err("The drawbridge (D) must be one unbroken stretch".to_string());
+            }
+            _ => None,
+        };
+
+        if spawn_points.is_empty() {
+            return Err("Map needs at least one spawn point (S)".to_string());
+        }
+        if signals.iter().any(|&signal| signal < spawn_points[0]) {
+            return Err("Every signal must be downstream of a spawn point".to_string());
+        }
+
+        let mut bike_lane = false;
+        let mut speed_limits = None;
+        for line in lines {
+            if line.chars().count() != length {
+                return Err(
+                    "Bike lane and speed limit lines must be as long as the road".to_string(),
+                );
+            } else if !bike_lane && line.chars().all(|ch| ch == 'b') {
+                bike_lane = true;
+            } else if speed_limits.is_none() {
+                speed_limits = Some(parse_speed_limits(line)?);
+            } else {
+                return Err("Map has unexpected extra lines".to_string());
+            }
+        }
+
+        Ok(Self {
+            length,
+            spawn_points,
+            signals,
+            facilities,
+            bridge,
+            toll_booths,
+            parking_lots,
+            bike_lane,
+            speed_limits: speed_limits.unwrap_or_default(),
+        })
+    }
+
+    /// Renders the road back into the map file format.
+    pub fn to_map(&self) -> String {
+        let mut map: String = (0..self.length)
+            .map(|cell| {
+                if self.signals.contains(&cell) {
+                    '|'
+                } else if self.spawn_points.contains(&cell) {
+                    'S'
+                } else if let Some(&(_, facility)) = self.facilities.iter().find(|f| f.0 == cell) {
+                    facility.map_char()
+                } else if self
+                    .bridge
+                    .is_some_and(|(from, to)| (from..=to).contains(&cell))
+                {
+                    'D'
+                } else if self.toll_booths.contains(&cell) {
+                    'T'
+                } else if self.parking_lots.contains(&cell) {
+                    'P'
+                } else {
+                    '='
+                }
+            })
+            .collect();
+        map.push('\n');
+        if self.bike_lane {
+            map.push_str(&"b".repeat(self.length));
+            map.push('\n');
+        }
+        if !self.speed_limits.is_empty() {
+            map.extend((0..self.length).map(|cell| self.limit_char(cell)));
+            map.push('\n');
+        }
+        map
+    }
+
+    /// Speed limit on the road lane at `cell`, in cells per second.
+    pub fn speed_limit_at(&self, cell: usize) -> Option<f32> {
+        self.speed_limits
+            .iter()
+            .find(|&&(from, to, _)| (from..=to).contains(&cell))
+            .map(|&(_, _, limit)| limit)
+    }
+
+    /// Character for `cell` in a map's speed limit line.
+    pub fn limit_char(&self, cell: usize) -> char {
+        self.speed_limit_at(cell)
+            .and_then(|limit| char::from_digit((limit / LIMIT_STEP).round() as u32, 10))
+            .unwrap_or('-')
+    }
+
+    /// Cells of every `facility` of the given kind, upstream first.
+    pub fn facilities_of(&self, facility: Facility) -> impl Iterator<Item = usize> + '_ {
+        self.facilities
+            .iter()
+            .filter(move |f| f.1 == facility)
+            .map(|f| f.0)
+    }
+
+    /// Nearest signal at or beyond `position`, if any.
+    pub fn next_signal(&self, position: f32) -> Option<usize> {
+        self.signals
+            .iter()
+            .position(|&signal| position <= signal as f32)
+    }
+}
+
+/// Reads a speed limit line into stretches of equal limit.
+fn parse_speed_limits(line: &str) -> Result<Vec<(usize, usize, f32)>, String> {
+    let mut limits: Vec<(usize, usize, f32)> = Vec::new();
+    for (cell, ch) in line.chars().enumerate() {
+        let limit = match ch {
+            '-' => continue,
+            '1'..='9' => ch.to_digit(10).unwrap_or_default() as f32 * LIMIT_STEP,
+            other => {
+                return Err(format!(
+                    "Speed limit line has '{}' at column {}: use 1-9 or -",
+                    other,
+                    cell + 1
+                ));
+            }
+        };
+        match limits.last_mut() {
+            Some((_, to, last)) if *to + 1 == cell && *last == limit => *to = cell,
+            _ => limits.push((cell, cell, limit)),
+        }
+    }
+    Ok(limits)
+}
+
+impl Default for Road {
+    fn default() -> Self {
+        Self::parse(DEFAULT_MAP).expect("default map is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_road_line() {
+        let road = Road::parse("# a comment\nS===|===S=|\n").unwrap();
+        assert_eq!(road.length, 11);
+        assert_eq!(road.spawn_points, vec![0, 8]);
+        assert_eq!(road.signals, vec![4, 10]);
+        assert!(!road.bike_lane);
+    }
+
+    #[test]
+    fn parses_a_bike_lane() {
+        let road = Road::parse("S====|====\nbbbbbbbbbb\n").unwrap();
+        assert!(road.bike_lane);
+    }
+
+    #[test]
+    fn rejects_bad_maps() {
+        let error = |text| Road::parse(text).unwrap_err();
+        assert_eq!(error("====|==="), "Map needs at least one spawn point (S)");
+        assert_eq!(error(""), "Map is empty");
+        assert_eq!(error("S== =|"), "Road is disconnected at column 4");
+        assert_eq!(error("S==X|"), "Unknown map character 'X' at column 4");
+        assert_eq!(
+            error("=|=S=="),
+            "Every signal must be downstream of a spawn point"
+        );
+        assert_eq!(
+            error("S===|\nbbb"),
+            "Bike lane and speed limit lines must be as long as the road"
+        );
+    }
+
+    #[test]
+    fn parses_speed_limits() {
+        let road = Road::parse("S====|====\nbbbbbbbbbb\n--44--2---\n").unwrap();
+        assert_eq!(road.speed_limits, vec![(2, 3, 1.0), (6, 6, 0.5)]);
+        assert_eq!(road.speed_limit_at(3), Some(1.0));
+        assert_eq!(road.speed_limit_at(4), None);
+        assert_eq!(road.limit_char(6), '2');
+    }
+
+    #[test]
+    fn speed_limits_without_a_bike_lane() {
+        let road = Road::parse("S====|\n666---\n").unwrap();
+        assert!(!road.bike_lane);
+        assert_eq!(road.speed_limits, vec![(0, 2, 1.5)]);
+        assert_eq!(road.to_map(), "S====|\n666---\n");
+    }
+
+    #[test]
+    fn rejects_bad_speed_limits() {
+        assert_eq!(
+            Road::parse("S===|\n--x--").unwrap_err(),
+            "Speed limit line has 'x' at column 3: use 1-9 or -"
+        );
+        assert_eq!(
+            Road::parse("S===|\n-----\n-----").unwrap_err(),
+            "Map has unexpected extra lines"
+        );
+    }
+
+    #[test]
+    fn parses_the_drawbridge() {
+        let road = Road::parse("S==DDD=|").unwrap();
+        assert_eq!(road.bridge, Some((3, 5)));
+        assert_eq!(road.to_map(), "S==DDD=|\n");
+        assert_eq!(
+            Road::parse("SDD=D|").unwrap_err(),
+            "The drawbridge (D) must be one unbroken stretch"
+        );
+    }
+
+    #[test]
+    fn default_map_round_trips() {
+        let road = Road::default();
+        assert_eq!(road.to_map().lines().next(), Some(DEFAULT_MAP));
+    }
+}
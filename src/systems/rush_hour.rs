@@ -0,0 +1,101 @@
+use crate::systems::clock::{SimClock, Weekday, parse_time};
+use crate::systems::config::Config;
+
+/// Rush hour on weekdays unless `[rush_hour]` says otherwise, as minutes
+/// past midnight.
+const WEEKDAY_WINDOWS: [(f32, f32); 2] = [(7.0 * 60.0, 9.0 * 60.0), (16.0 * 60.0, 18.0 * 60.0)];
+
+/// The `[rush_hour]` section: when each day's rush hours are and how much
+/// busier the road gets during them.
+#[derive(Debug, Clone)]
+pub struct RushHourConfig {
+    /// Spawn rate multiplier while a window is open.
+    pub multiplier: f32,
+    /// Start and end of each window, in minutes past midnight, for each
+    /// day from Monday.
+    pub windows: [Vec<(f32, f32)>; 7],
+}
+
+impl Default for RushHourConfig {
+    fn default() -> Self {
+        Self {
+            multiplier: 2.5,
+            windows: std::array::from_fn(|day| {
+                if Weekday::ALL[day].is_weekend() {
+                    Vec::new()
+                } else {
+                    WEEKDAY_WINDOWS.to_vec()
+                }
+            }),
+        }
+    }
+}
+
+impl RushHourConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut rush_hour = Self::default();
+        for (key, value) in config.section("rush_hour") {
+            match key.as_str() {
+                "multiplier" => {
+                    rush_hour.multiplier = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or("[rush_hour] multiplier must be a non-negative number")?;
+                }
+                "weekdays" => {
+                    let windows = parse_windows(key, value)?;
+                    for (day, list) in rush_hour.windows.iter_mut().enumerate() {
+                        if !Weekday::ALL[day].is_weekend() {
+                            *list = windows.clone();
+                        }
+                    }
+                }
+                "weekends" => {
+                    let windows = parse_windows(key, value)?;
+                    for (day, list) in rush_hour.windows.iter_mut().enumerate() {
+                        if Weekday::ALL[day].is_weekend() {
+                            *list = windows.clone();
+                        }
+                    }
+                }
+                other => match Weekday::from_name(other) {
+                    Some(day) => rush_hour.windows[day as usize] = parse_windows(key, value)?,
+                    None => return Err(format!("Unknown setting '{}' in [rush_hour]", other)),
+                },
+            }
+        }
+        Ok(rush_hour)
+    }
+
+    /// Whether a rush hour window is open at the clock's current time.
+    pub fn active(&self, clock: &SimClock) -> bool {
+        let minute = clock.minute_of_day();
+        self.windows[clock.weekday() as usize]
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&minute))
+    }
+}
+
+/// A comma-separated list of `HH:MM-HH:MM` windows, or `none`.
+fn parse_windows(key: &str, value: &str) -> Result<Vec<(f32, f32)>, String> {
+    if value == "none" {
+        return Ok(Vec::new());
+    }
+    value
+        .split(',')
+        .map(|window| {
+            window
+                .trim()
+                .split_once('-')
+                .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+                .filter(|(start, end)| start < end)
+                .ok_or_else(|| {
+                    format!(
+                        "[rush_hour] {} must be windows like 07:00-09:00, 16:30-18:00, or none",
+                        key
+                    )
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,57 @@
+use crate::systems::clock::SimClock;
+use crate::systems::closure::parse_stretch;
+use crate::systems::config::Config;
+
+/// Speed limit in an active school zone, in cells per second.
+pub const SCHOOL_SPEED_LIMIT: f32 = 0.6;
+
+/// A stretch of road outside a school where, at the start and end of the
+/// school day, beacons flash and traffic keeps to a low speed limit.
+#[derive(Debug, Clone)]
+pub struct SchoolZone {
+    pub name: String,
+    /// First and last cell of the stretch.
+    pub from: usize,
+    pub to: usize,
+    /// Start and end of each active period, in minutes past midnight.
+    pub windows: Vec<(f32, f32)>,
+    /// Whether the beacons are flashing right now.
+    pub active: bool,
+}
+
+impl SchoolZone {
+    /// Whether the clock is inside one of the zone's active periods.
+    pub fn scheduled(&self, clock: &SimClock) -> bool {
+        let minute = clock.minute_of_day();
+        self.windows
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&minute))
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "school zone {} on cells {}-{}, {} cells/s",
+            self.name, self.from, self.to, SCHOOL_SPEED_LIMIT
+        )
+    }
+}
+
+/// The `[school_zones]` section: one zone per key, named by the key, as
+/// the stretch of cells followed by the times it is active, e.g.
+/// `elm_primary = 4-9, 07:30-08:30, 14:45-15:30`.
+pub fn school_zones_from_config(config: &Config) -> Result<Vec<SchoolZone>, String> {
+    config
+        .section("school_zones")
+        .iter()
+        .map(|(name, value)| {
+            let (from, to, windows) = parse_stretch("school_zones", name, value)?;
+            Ok(SchoolZone {
+                name: name.clone(),
+                from,
+                to,
+                windows,
+                active: false,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use rhai::{AST, CallFnOptions, Dynamic, Engine, INT, Map, Scope};
+
+use crate::systems::events::SimEvent;
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+use crate::systems::vehicle::Lane;
+
+/// Operations a single hook call may run before it is cut off, so a runaway
+/// loop in a script can't freeze the simulator.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Something a script asked for, applied once the hook returns.
+pub enum ScriptAction {
+    Event(SimEvent),
+    /// A console command line, for everything the console can already do.
+    Command(String),
+}
+
+/// What scripts can read about the simulation, refreshed before each hook.
+#[derive(Default)]
+struct Readings {
+    time: f32,
+    vehicles: usize,
+    queues: Vec<usize>,
+}
+
+/// A Rhai scenario script. It may define any of these hooks:
+///
+/// - `init()` once at the start
+/// - `on_tick(time)` once per simulated second
+/// - `on_vehicle_spawned(id, kind, cell)` for every new vehicle
+/// - `on_light_change(light, state)` when a light (numbered from 1) changes
+///
+/// and call `spawn(kind)`, `spawn(kind, cell)`, `breakdown(cell)`,
+/// `toggle_spawn(cell)`, `force_light(light, state)`, `set_spawn_rate(x)`,
+/// `set_breakdown_rate(x)` or `command(line)` from them, plus the readings
+/// `time()`, `vehicle_count()` and `queue(light)`. Rhai functions can't see
+/// top-level variables, so hooks keep their state in `this`, a map that
+/// persists between calls.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    /// The `this` map shared by all hooks.
+    state: Dynamic,
+    actions: Rc<RefCell<Vec<ScriptAction>>>,
+    readings: Rc<RefCell<Readings>>,
+    /// Highest vehicle ID already reported to `on_vehicle_spawned`.
+    last_vehicle: u32,
+    lights: Vec<TrafficLightState>,
+    next_tick: f32,
+}
+
+impl Script {
+    pub fn load(path: &str, sim: &Simulation) -> Result<Self, String> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let actions = Rc::new(RefCell::new(Vec::new()));
+        let readings = Rc::new(RefCell::new(Readings::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine, &actions, &readings);
+        let ast = engine
+            .compile(&source)
+            .map_err(|e| format!("Script {}: {}", path, e))?;
+        let mut script = Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            state: Map::new().into(),
+            actions,
+            readings,
+            last_vehicle: sim.vehicles.iter().map(|v| v.id).max().unwrap_or(0),
+            lights: sim.lights.iter().map(|light| light.state).collect(),
+            next_tick: sim.stats.elapsed,
+        };
+        script.call("init", ())?;
+        Ok(script)
+    }
+
+    /// Calls the hooks for whatever happened since the last call and
+    /// returns the actions they asked for.
+    pub fn update(&mut self, sim: &Simulation) -> Result<Vec<ScriptAction>, String> {
+        *self.readings.borrow_mut() = Readings {
+            time: sim.stats.elapsed,
+            vehicles: sim.vehicles.len(),
+            queues: (0..sim.lights.len())
+                .map(|index| sim.queue_length(index, Lane::Road))
+                .collect(),
+        };
+
+        let mut spawned: Vec<_> = sim
+            .vehicles
+            .iter()
+            .filter(|v| v.id > self.last_vehicle)
+            .collect();
+        spawned.sort_by_key(|v| v.id);
+        for vehicle in spawned {
+            self.last_vehicle = vehicle.id;
+            let args = (
+                vehicle.id as INT,
+                vehicle.kind.key().to_string(),
+                vehicle.cell() as INT,
+            );
+            self.call("on_vehicle_spawned", args)?;
+        }
+
+        self.lights.truncate(sim.lights.len());
+        for (index, light) in sim.lights.iter().enumerate() {
+            if self.lights.get(index) == Some(&light.state) {
+                continue;
+            }
+            if index < self.lights.len() {
+                self.lights[index] = light.state;
+                let args = ((index + 1) as INT, light.state.name().to_string());
+                self.call("on_light_change", args)?;
+            } else {
+                self.lights.push(light.state);
+            }
+        }
+
+        // After a rewind, carry on ticking from the earlier time.
+        if self.next_tick > sim.stats.elapsed + 1.0 {
+            self.next_tick = sim.stats.elapsed;
+        }
+        if sim.stats.elapsed >= self.next_tick {
+            self.next_tick += 1.0;
+            self.call("on_tick", (sim.stats.elapsed as rhai::FLOAT,))?;
+        }
+        Ok(self.actions.borrow_mut().drain(..).collect())
+    }
+
+    /// Calls `name` if the script defines it with a matching arity.
+    fn call(&mut self, name: &str, args: impl rhai::FuncArgs) -> Result<(), String> {
+        let mut values = Vec::new();
+        args.parse(&mut values);
+        let defined = self
+            .ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == values.len());
+        if !defined {
+            return Ok(());
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, values)
+            .map(|_| ())
+            .map_err(|e| format!("Script {}: {}", name, e))
+    }
+}
+
+fn register_api(
+    engine: &mut Engine,
+    actions: &Rc<RefCell<Vec<ScriptAction>>>,
+    readings: &Rc<RefCell<Readings>>,
+) {
+    let queue = |actions: &Rc<RefCell<Vec<ScriptAction>>>| {
+        let actions = Rc::clone(actions);
+        move |action: ScriptAction| actions.borrow_mut().push(action)
+    };
+
+    let push = queue(actions);
+    engine.register_fn("spawn", move |kind: &str| {
+        push(ScriptAction::Command(format!("spawn {}", kind)))
+    });
+    let push = queue(actions);
+    engine.register_fn("spawn", move |kind: &str, cell: INT| {
+        push(ScriptAction::Command(format!("spawn {} {}", kind, cell)))
+    });
+    let push = queue(actions);
+    engine.register_fn("breakdown", move |cell: INT| {
+        push(ScriptAction::Event(SimEvent::Breakdown(
+            cell.max(0) as usize
+        )))
+    });
+    let push = queue(actions);
+    engine.register_fn("toggle_spawn", move |cell: INT| {
+        push(ScriptAction::Event(SimEvent::ToggleSpawn(
+            cell.max(0) as usize
+        )))
+    });
+    let push = queue(actions);
+    engine.register_fn("force_light", move |light: INT, state: &str| {
+        let state = match state {
+            "red" => TrafficLightState::Red,
+            "yellow" => TrafficLightState::Yellow,
+            "green" => TrafficLightState::Green,
+            _ => return,
+        };
+        if light >= 1 {
+            push(ScriptAction::Event(SimEvent::ForceLight(
+                light as usize - 1,
+                state,
+            )));
+        }
+    });
+    let push = queue(actions);
+    engine.register_fn("set_spawn_rate", move |rate: rhai::FLOAT| {
+        push(ScriptAction::Command(format!("set spawn_rate {}", rate)))
+    });
+    let push = queue(actions);
+    engine.register_fn("set_breakdown_rate", move |rate: rhai::FLOAT| {
+        push(ScriptAction::Command(format!(
+            "set breakdown_rate {}",
+            rate
+        )))
+    });
+    let push = queue(actions);
+    engine.register_fn("command", move |line: &str| {
+        push(ScriptAction::Command(line.to_string()))
+    });
+
+    let read = Rc::clone(readings);
+    engine.register_fn("time", move || read.borrow().time as rhai::FLOAT);
+    let read = Rc::clone(readings);
+    engine.register_fn("vehicle_count", move || read.borrow().vehicles as INT);
+    let read = Rc::clone(readings);
+    engine.register_fn("queue", move |light: INT| {
+        let readings = read.borrow();
+        (light as usize)
+            .checked_sub(1)
+            .and_then(|index| readings.queues.get(index))
+            .map_or(0, |&queue| queue as INT)
+    });
+}
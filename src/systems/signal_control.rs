@@ -0,0 +1,902 @@
+use std::fmt;
+
+use crate::systems::config::Config;
+use crate::systems::nema::{Nema, NemaConfig};
+use crate::systems::simulation::Detectors;
+use crate::systems::traffic_light::{Flashing, LEADING_BIKE_INTERVAL, TrafficLightState};
+
+/// Speed limit through a roundabout, in cells per second.
+pub const ROUNDABOUT_SPEED_LIMIT: f32 = 0.5;
+
+/// Built-in ways of running an intersection, chosen per light in
+/// `[signals]`. The last three put up signs instead of a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlMode {
+    /// The same green, yellow and red times every cycle.
+    FixedTime,
+    /// Green is stretched while vehicles keep arriving, up to a maximum.
+    Actuated,
+    /// Green length follows the queue waiting when it starts, and a long
+    /// queue cuts the red short.
+    Adaptive,
+    /// Never changes on its own; switch it from the sandbox.
+    Manual,
+    /// Eight-phase ring-and-barrier cycle, timed from `[nema]`.
+    Nema,
+    /// Everyone stops at the line, then goes when clear.
+    AllWayStop,
+    /// Drivers slow down and go on if the way is clear.
+    Yield,
+    /// Drivers yield on entry and go round at walking pace.
+    Roundabout,
+}
+
+impl ControlMode {
+    pub const ALL: [ControlMode; 8] = [
+        ControlMode::FixedTime,
+        ControlMode::Actuated,
+        ControlMode::Adaptive,
+        ControlMode::Manual,
+        ControlMode::Nema,
+        ControlMode::AllWayStop,
+        ControlMode::Yield,
+        ControlMode::Roundabout,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControlMode::FixedTime => "fixed",
+            ControlMode::Actuated => "actuated",
+            ControlMode::Adaptive => "adaptive",
+            ControlMode::Manual => "manual",
+            ControlMode::Nema => "nema",
+            ControlMode::AllWayStop => "stop",
+            ControlMode::Yield => "yield",
+            ControlMode::Roundabout => "roundabout",
+        }
+    }
+
+    /// Whether the intersection has a signal, rather than signs.
+    pub fn is_signal(self) -> bool {
+        self.sign().is_none()
+    }
+
+    /// How a light stands in for the signs of an unsignalled intersection:
+    /// flashing red for a stop, flashing yellow for a yield or roundabout.
+    pub fn sign(self) -> Option<Flashing> {
+        match self {
+            ControlMode::AllWayStop => Some(Flashing::Red),
+            ControlMode::Yield | ControlMode::Roundabout => Some(Flashing::Yellow),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn controller(self) -> Box<dyn SignalController> {
+        self.with_timing(Timing::default())
+    }
+
+    /// A controller in this mode using the times in `timing` that apply to
+    /// it: all of them for fixed time, just the yellow for the others.
+    pub fn with_timing(self, timing: Timing) -> Box<dyn SignalController> {
+        match self {
+            ControlMode::FixedTime => Box::new(FixedTime::new(timing)),
+            ControlMode::Actuated => Box::new(Actuated {
+                yellow: timing.yellow,
+                last_call: 0.0,
+            }),
+            ControlMode::Adaptive => Box::new(Adaptive {
+                green: ADAPTIVE_MIN_GREEN,
+                yellow: timing.yellow,
+            }),
+            ControlMode::Manual => Box::new(Manual),
+            ControlMode::Nema => Box::new(Nema::new(
+                NemaConfig::default(),
+                timing.yellow,
+                timing.all_red.unwrap_or(0),
+            )),
+            ControlMode::AllWayStop | ControlMode::Yield | ControlMode::Roundabout => {
+                Box::new(Signs { mode: self })
+            }
+        }
+    }
+}
+
+/// Decides when a light moves to its next phase. Every light owns one.
+/// Controllers are shared read-only with vehicle update threads.
+pub trait SignalController: fmt::Debug + Send + Sync {
+    fn mode(&self) -> ControlMode;
+
+    /// Whole seconds `state` is planned to last, or `None` if it lasts
+    /// until something else happens.
+    fn duration(&self, state: TrafficLightState) -> Option<u32>;
+
+    /// The phase to switch to now, or `None` to stay in the current one.
+    fn decide(&mut self, detectors: &Detectors) -> Option<TrafficLightState>;
+
+    /// The timing plan it runs, for fixed-time controllers.
+    fn timing(&self) -> Option<Timing> {
+        None
+    }
+
+    /// Seconds of all-red at each end of the red, while conflicting traffic
+    /// clears the intersection.
+    fn all_red(&self) -> u32 {
+        0
+    }
+
+    /// Whether a red that has lasted `elapsed` seconds is in one of its
+    /// all-red intervals.
+    fn clearing(&self, _elapsed: f32) -> bool {
+        false
+    }
+
+    /// Which of its phases the controller is timing, `elapsed` seconds into
+    /// `state`, for controllers with more phases than the light shows.
+    fn phase_note(&self, _state: TrafficLightState, _elapsed: f32) -> Option<String> {
+        None
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController>;
+}
+
+impl Clone for Box<dyn SignalController> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// True once `detectors` have been in their state for `seconds`.
+fn served(detectors: &Detectors, seconds: u32) -> bool {
+    detectors.elapsed + f32::EPSILON >= seconds as f32
+}
+
+/// How one intersection runs, from its line in the `[timing]` section.
+/// Green, red and offset only apply to fixed-time lights; the yellow,
+/// all-red and mode override the `[signals]` settings for this light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    pub green: u32,
+    pub yellow: u32,
+    pub red: u32,
+    /// Seconds into its cycle the light starts, counted from the start of
+    /// red. Staggering offsets lets consecutive lights form a green wave.
+    pub offset: u32,
+    /// All-red clearance for this light, instead of the `[signals]` one.
+    pub all_red: Option<u32>,
+    /// Control mode for this light, instead of the `[signals]` one.
+    pub mode: Option<ControlMode>,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            green: TrafficLightState::Green.duration(),
+            yellow: TrafficLightState::Yellow.duration(),
+            red: TrafficLightState::Red.duration(),
+            offset: 0,
+            all_red: None,
+            mode: None,
+        }
+    }
+}
+
+/// Longest green, yellow, red or all-red a `[timing]` line may ask for.
+pub const MAX_TIMING_SECONDS: u32 = 300;
+
+impl Timing {
+    /// Seconds from one start of red to the next.
+    pub fn cycle(&self) -> u32 {
+        self.green + self.yellow + self.red
+    }
+
+    /// Parses `green 6, yellow 3, red 4, all_red 1, offset 2, mode fixed`.
+    /// Missing parts keep their defaults.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut timing = Self::default();
+        for part in text.split(',') {
+            let mut words = part.split_whitespace();
+            let (Some(name), Some(value), None) = (words.next(), words.next(), words.next()) else {
+                return Err(format!("expected e.g. 'green 6', got '{}'", part.trim()));
+            };
+            if name == "mode" {
+                timing.mode = Some(ControlMode::from_name(value).ok_or_else(|| {
+                    let names: Vec<&str> = ControlMode::ALL.iter().map(|m| m.name()).collect();
+                    format!("unknown mode '{}' (expected {})", value, names.join(", "))
+                })?);
+                continue;
+            }
+            let seconds: u32 = value
+                .parse()
+                .map_err(|_| format!("{} expects whole seconds, got '{}'", name, value))?;
+            match name {
+                "green" => timing.green = seconds,
+                "yellow" => timing.yellow = seconds,
+                "red" => timing.red = seconds,
+                "all_red" => timing.all_red = Some(seconds),
+                "offset" => timing.offset = seconds,
+                other => {
+                    return Err(format!(
+                        "unknown part '{}' (expected green, yellow, red, all_red, offset or mode)",
+                        other
+                    ));
+                }
+            }
+        }
+        if timing.green == 0 || timing.yellow == 0 || timing.red == 0 {
+            return Err("green, yellow and red must last at least a second".to_string());
+        }
+        let longest = [
+            timing.green,
+            timing.yellow,
+            timing.red,
+            timing.all_red.unwrap_or(0),
+        ];
+        if longest
+            .into_iter()
+            .any(|seconds| seconds > MAX_TIMING_SECONDS)
+        {
+            return Err(format!(
+                "phases can last at most {} seconds",
+                MAX_TIMING_SECONDS
+            ));
+        }
+        if timing.offset >= timing.cycle() {
+            return Err(format!(
+                "offset {} must be less than the {}s cycle",
+                timing.offset,
+                timing.cycle()
+            ));
+        }
+        Ok(timing)
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "green {}", self.green)?;
+        if self.yellow != TrafficLightState::Yellow.duration() {
+            write!(f, ", yellow {}", self.yellow)?;
+        }
+        write!(f, ", red {}", self.red)?;
+        if let Some(seconds) = self.all_red {
+            write!(f, ", all_red {}", seconds)?;
+        }
+        write!(f, ", offset {}", self.offset)?;
+        if let Some(mode) = self.mode {
+            write!(f, ", mode {}", mode.name())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FixedTime {
+    timing: Timing,
+    /// Length of the first red, which absorbs the offset, until it ends.
+    first_red: Option<u32>,
+}
+
+impl FixedTime {
+    pub fn new(timing: Timing) -> Self {
+        let cycle = timing.cycle();
+        let first_red = (timing.red + cycle - timing.offset % cycle) % cycle;
+        Self {
+            timing,
+            first_red: (!timing.offset.is_multiple_of(cycle)).then_some(first_red),
+        }
+    }
+}
+
+impl SignalController for FixedTime {
+    fn mode(&self) -> ControlMode {
+        ControlMode::FixedTime
+    }
+
+    fn duration(&self, state: TrafficLightState) -> Option<u32> {
+        Some(match state {
+            TrafficLightState::Green => self.timing.green,
+            TrafficLightState::Red => self.first_red.unwrap_or(self.timing.red),
+            TrafficLightState::Yellow => self.timing.yellow,
+        })
+    }
+
+    fn decide(&mut self, detectors: &Detectors) -> Option<TrafficLightState> {
+        let duration = self.duration(detectors.state)?;
+        if !served(detectors, duration) {
+            return None;
+        }
+        if detectors.state == TrafficLightState::Red {
+            self.first_red = None;
+        }
+        Some(detectors.state.next())
+    }
+
+    fn timing(&self) -> Option<Timing> {
+        Some(self.timing)
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+const ACTUATED_MIN_GREEN: u32 = 3;
+const ACTUATED_MAX_GREEN: u32 = 12;
+/// Seconds with no loop occupied before the green gaps out.
+const ACTUATED_GAP: u32 = 2;
+
+/// Extends the green while the induction loops keep reporting vehicles, up
+/// to a maximum, and gaps out once they have been empty for a while.
+#[derive(Debug, Clone)]
+pub struct Actuated {
+    yellow: u32,
+    /// When into the green a loop last reported a vehicle.
+    last_call: f32,
+}
+
+impl SignalController for Actuated {
+    fn mode(&self) -> ControlMode {
+        ControlMode::Actuated
+    }
+
+    fn duration(&self, state: TrafficLightState) -> Option<u32> {
+        match state {
+            TrafficLightState::Green => Some(ACTUATED_MAX_GREEN),
+            TrafficLightState::Yellow => Some(self.yellow),
+            TrafficLightState::Red => Some(state.duration()),
+        }
+    }
+
+    fn decide(&mut self, detectors: &Detectors) -> Option<TrafficLightState> {
+        let done = match detectors.state {
+            TrafficLightState::Green => {
+                // A fresh green, or a loop reporting a vehicle, restarts the gap.
+                if detectors.elapsed < self.last_call || detectors.calls {
+                    self.last_call = detectors.elapsed;
+                }
+                let gap = detectors.elapsed - self.last_call + f32::EPSILON >= ACTUATED_GAP as f32;
+                served(detectors, ACTUATED_MAX_GREEN)
+                    || (served(detectors, ACTUATED_MIN_GREEN) && gap)
+            }
+            TrafficLightState::Yellow => served(detectors, self.yellow),
+            TrafficLightState::Red => served(detectors, TrafficLightState::Red.duration()),
+        };
+        if !done {
+            return None;
+        }
+        self.last_call = 0.0;
+        Some(detectors.state.next())
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+const ADAPTIVE_MIN_GREEN: u32 = 3;
+const ADAPTIVE_MAX_GREEN: u32 = 15;
+/// Extra green per vehicle queued when the green starts.
+const ADAPTIVE_SECONDS_PER_VEHICLE: u32 = 2;
+/// A queue this long ends the red early, once it has lasted `ADAPTIVE_MIN_RED`.
+const ADAPTIVE_QUEUE_TRIGGER: usize = 4;
+const ADAPTIVE_MIN_RED: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct Adaptive {
+    /// Length of the current or next green, set as it starts.
+    green: u32,
+    yellow: u32,
+}
+
+impl SignalController for Adaptive {
+    fn mode(&self) -> ControlMode {
+        ControlMode::Adaptive
+    }
+
+    fn duration(&self, state: TrafficLightState) -> Option<u32> {
+        match state {
+            TrafficLightState::Green => Some(self.green),
+            TrafficLightState::Yellow => Some(self.yellow),
+            TrafficLightState::Red => Some(state.duration()),
+        }
+    }
+
+    fn decide(&mut self, detectors: &Detectors) -> Option<TrafficLightState> {
+        let done = match detectors.state {
+            TrafficLightState::Green => served(detectors, self.green),
+            TrafficLightState::Red => {
+                served(detectors, TrafficLightState::Red.duration())
+                    || (served(detectors, ADAPTIVE_MIN_RED)
+                        && detectors.queue >= ADAPTIVE_QUEUE_TRIGGER)
+            }
+            TrafficLightState::Yellow => served(detectors, self.yellow),
+        };
+        if !done {
+            return None;
+        }
+        let next = detectors.state.next();
+        if next == TrafficLightState::Green {
+            self.green = (ADAPTIVE_MIN_GREEN
+                + ADAPTIVE_SECONDS_PER_VEHICLE * detectors.queue as u32)
+                .min(ADAPTIVE_MAX_GREEN);
+        }
+        Some(next)
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+/// Wraps another controller to hold the light red for `seconds` at both
+/// ends of its red: once after its own yellow, before the cross street
+/// gets green, and again after the cross street's yellow, before its own
+/// green. The bicycles' leading green follows the closing all-red. The
+/// wrapped controller runs the red in between.
+#[derive(Debug, Clone)]
+pub struct AllRed {
+    inner: Box<dyn SignalController>,
+    seconds: u32,
+    /// When into the red the wrapped controller ended it, and the phase it
+    /// picked, while the closing all-red and the leading bike green run.
+    closing: Option<(f32, TrafficLightState)>,
+}
+
+impl AllRed {
+    pub fn new(inner: Box<dyn SignalController>, seconds: u32) -> Self {
+        Self {
+            inner,
+            seconds,
+            closing: None,
+        }
+    }
+}
+
+impl SignalController for AllRed {
+    fn mode(&self) -> ControlMode {
+        self.inner.mode()
+    }
+
+    fn duration(&self, state: TrafficLightState) -> Option<u32> {
+        let duration = self.inner.duration(state)?;
+        Some(match state {
+            TrafficLightState::Red => duration + 2 * self.seconds + LEADING_BIKE_INTERVAL,
+            _ => duration,
+        })
+    }
+
+    fn decide(&mut self, detectors: &Detectors) -> Option<TrafficLightState> {
+        if detectors.state != TrafficLightState::Red {
+            self.closing = None;
+            return self.inner.decide(detectors);
+        }
+        if let Some((from, next)) = self.closing {
+            if detectors.elapsed >= from {
+                let ends = from + (self.seconds + LEADING_BIKE_INTERVAL) as f32;
+                if detectors.elapsed + f32::EPSILON < ends {
+                    return None;
+                }
+                self.closing = None;
+                return Some(next);
+            }
+            // A red forced on afresh starts over.
+            self.closing = None;
+        }
+        if !served(detectors, self.seconds) {
+            return None;
+        }
+        let inner = Detectors {
+            elapsed: detectors.elapsed - self.seconds as f32,
+            ..*detectors
+        };
+        if let Some(next) = self.inner.decide(&inner) {
+            self.closing = Some((detectors.elapsed, next));
+        }
+        None
+    }
+
+    fn timing(&self) -> Option<Timing> {
+        self.inner.timing()
+    }
+
+    fn all_red(&self) -> u32 {
+        self.seconds
+    }
+
+    /// The closing all-red is followed by the bicycles' leading green, so
+    /// they only set off once the intersection has cleared.
+    fn clearing(&self, elapsed: f32) -> bool {
+        elapsed < self.seconds as f32
+            || self
+                .closing
+                .is_some_and(|(from, _)| elapsed < from + self.seconds as f32)
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Manual;
+
+impl SignalController for Manual {
+    fn mode(&self) -> ControlMode {
+        ControlMode::Manual
+    }
+
+    fn duration(&self, _: TrafficLightState) -> Option<u32> {
+        None
+    }
+
+    fn decide(&mut self, _: &Detectors) -> Option<TrafficLightState> {
+        None
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+/// An intersection without a signal. The light flashes for its signs and
+/// never changes.
+#[derive(Debug, Clone)]
+pub struct Signs {
+    mode: ControlMode,
+}
+
+impl SignalController for Signs {
+    fn mode(&self) -> ControlMode {
+        self.mode
+    }
+
+    fn duration(&self, _: TrafficLightState) -> Option<u32> {
+        None
+    }
+
+    fn decide(&mut self, _: &Detectors) -> Option<TrafficLightState> {
+        None
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
+
+/// The `[signals]` section: `default = <mode>` for every light, and
+/// `<light number> = <mode>` to override single lights, plus `all_red =
+/// <seconds>` of clearance at each end of every red. Each intersection
+/// can be tuned further with a `<light number> = green 6, yellow 3, red 4,
+/// all_red 1, offset 2, mode fixed` line in `[timing]`.
+#[derive(Debug, Clone, Default)]
+pub struct SignalConfig {
+    pub default: Option<ControlMode>,
+    /// Seconds of all-red at each end of the red, for every light that
+    /// isn't manual.
+    pub all_red: u32,
+    /// Light index and its mode.
+    pub lights: Vec<(usize, ControlMode)>,
+    /// Light index and its fixed-time plan.
+    pub timings: Vec<(usize, Timing)>,
+    /// Phase times for ring-and-barrier lights.
+    pub nema: NemaConfig,
+}
+
+impl SignalConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut signals = Self::default();
+        for (key, value) in config.section("signals") {
+            if key == "all_red" {
+                signals.all_red = value.parse().map_err(|_| {
+                    format!("[signals] all_red expects whole seconds, got '{}'", value)
+                })?;
+                if signals.all_red > MAX_TIMING_SECONDS {
+                    return Err(format!(
+                        "[signals] all_red can last at most {} seconds",
+                        MAX_TIMING_SECONDS
+                    ));
+                }
+                continue;
+            }
+            let mode = ControlMode::from_name(value).ok_or_else(|| {
+                let names: Vec<&str> = ControlMode::ALL.iter().map(|m| m.name()).collect();
+                format!(
+                    "[signals] {}: unknown mode '{}' (expected {})",
+                    key,
+                    value,
+                    names.join(", ")
+                )
+            })?;
+            if key == "default" {
+                signals.default = Some(mode);
+                continue;
+            }
+            let index = light_index(key).ok_or_else(|| {
+                format!("[signals] {}: expected 'default' or a light number", key)
+            })?;
+            if signals.lights.iter().any(|&(light, _)| light == index) {
+                return Err(format!("[signals] light {} is set twice", key));
+            }
+            signals.lights.push((index, mode));
+        }
+        for (key, value) in config.section("timing") {
+            let index = light_index(key)
+                .ok_or_else(|| format!("[timing] {}: expected a light number", key))?;
+            let timing = Timing::parse(value).map_err(|e| format!("[timing] {}: {}", key, e))?;
+            if signals.timings.iter().any(|&(light, _)| light == index) {
+                return Err(format!("[timing] light {} is set twice", key));
+            }
+            let picked = signals.lights.iter().find(|&&(light, _)| light == index);
+            if let (Some(mode), Some(&(_, other))) = (timing.mode, picked)
+                && mode != other
+            {
+                return Err(format!(
+                    "[timing] {}: mode {} clashes with {} in [signals]",
+                    key,
+                    mode.name(),
+                    other.name()
+                ));
+            }
+            signals.timings.push((index, timing));
+        }
+        signals.nema = NemaConfig::from_config(config)?;
+        Ok(signals)
+    }
+
+    /// Errors if a `[signals]` or `[timing]` line names a light past the
+    /// last of the `count` on the road.
+    pub fn check_lights(&self, count: usize) -> Result<(), String> {
+        let named = self.lights.iter().map(|&(index, _)| ("signals", index));
+        let timed = self.timings.iter().map(|&(index, _)| ("timing", index));
+        match named.chain(timed).find(|&(_, index)| index >= count) {
+            Some((section, index)) => Err(format!(
+                "[{}] there is no light {} (the road has {})",
+                section,
+                index + 1,
+                count
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Mode for light `index`, if the config picks one.
+    pub fn mode_for(&self, index: usize) -> Option<ControlMode> {
+        self.lights
+            .iter()
+            .find(|&&(light, _)| light == index)
+            .map(|&(_, mode)| mode)
+            .or_else(|| self.timing_for(index).mode)
+            .or(self.default)
+    }
+
+    /// Timing for light `index`, the default one if none is set.
+    pub fn timing_for(&self, index: usize) -> Timing {
+        self.timings
+            .iter()
+            .find(|&&(light, _)| light == index)
+            .map_or_else(Timing::default, |&(_, timing)| timing)
+    }
+
+    /// Controller for light `index` running in `mode`.
+    pub fn controller(&self, index: usize, mode: ControlMode) -> Box<dyn SignalController> {
+        let timing = self.timing_for(index);
+        let all_red = timing.all_red.unwrap_or(self.all_red);
+        if mode == ControlMode::Nema {
+            // The all-red is its red clearance, between every phase.
+            return Box::new(Nema::new(self.nema, timing.yellow, all_red));
+        }
+        let controller = mode.with_timing(timing);
+        if all_red == 0 || mode == ControlMode::Manual || !mode.is_signal() {
+            controller
+        } else {
+            Box::new(AllRed::new(controller, all_red))
+        }
+    }
+}
+
+/// Light index from its number as written in the config, counting from 1.
+fn light_index(key: &str) -> Option<usize> {
+    key.parse::<usize>().ok()?.checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 0.25;
+
+    /// Runs `controller` for `seconds` from the start of a red, returning
+    /// each phase change as the time it happened and the phase it went to.
+    /// `calls` says whether a loop is occupied at a time into the run.
+    fn run(
+        controller: &mut dyn SignalController,
+        seconds: f32,
+        calls: impl Fn(f32) -> bool,
+    ) -> Vec<(f32, TrafficLightState)> {
+        let (mut state, mut elapsed, mut time) = (TrafficLightState::Red, 0.0, 0.0);
+        let mut changes = Vec::new();
+        while time < seconds {
+            time += STEP;
+            elapsed += STEP;
+            let detectors = Detectors {
+                state,
+                elapsed,
+                queue: 0,
+                bike_queue: 0,
+                approaching: 0,
+                calls: calls(time),
+            };
+            if let Some(next) = controller.decide(&detectors) {
+                changes.push((time, next));
+                (state, elapsed) = (next, 0.0);
+            }
+        }
+        changes
+    }
+
+    fn timing(green: u32, yellow: u32, red: u32) -> Timing {
+        Timing {
+            green,
+            yellow,
+            red,
+            ..Timing::default()
+        }
+    }
+
+    #[test]
+    fn fixed_time_cycles_through_its_phases() {
+        let mut controller = FixedTime::new(timing(4, 2, 3));
+        assert_eq!(
+            run(&mut controller, 13.0, |_| false),
+            vec![
+                (3.0, TrafficLightState::Green),
+                (7.0, TrafficLightState::Yellow),
+                (9.0, TrafficLightState::Red),
+                (12.0, TrafficLightState::Green),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_time_offset_shortens_the_first_red() {
+        let mut controller = FixedTime::new(Timing {
+            offset: 2,
+            ..timing(4, 2, 3)
+        });
+        let changes = run(&mut controller, 12.0, |_| false);
+        assert_eq!(changes[0], (1.0, TrafficLightState::Green));
+        assert_eq!(changes[3], (10.0, TrafficLightState::Green));
+    }
+
+    #[test]
+    fn parses_timing_lines() {
+        let timing = Timing::parse("green 8, yellow 3, red 4, all_red 1, offset 3, mode fixed");
+        assert_eq!(
+            timing,
+            Ok(Timing {
+                green: 8,
+                yellow: 3,
+                red: 4,
+                offset: 3,
+                all_red: Some(1),
+                mode: Some(ControlMode::FixedTime),
+            })
+        );
+        assert_eq!(
+            Timing::parse("green 9").map(|t| (t.green, t.red)),
+            Ok((9, Timing::default().red))
+        );
+    }
+
+    #[test]
+    fn timing_must_stay_in_bounds() {
+        let error = |text| Timing::parse(text).unwrap_err();
+        assert_eq!(
+            error("green 0"),
+            "green, yellow and red must last at least a second"
+        );
+        assert_eq!(error("red 301"), "phases can last at most 300 seconds");
+        assert_eq!(
+            error("green 6, all_red 301"),
+            "phases can last at most 300 seconds"
+        );
+        assert!(Timing::parse("green 300, yellow 300, red 300").is_ok());
+        assert!(error("green six").contains("expects whole seconds"));
+        assert!(error("blue 3").starts_with("unknown part 'blue'"));
+        assert!(error("mode sometimes").starts_with("unknown mode 'sometimes'"));
+    }
+
+    #[test]
+    fn offset_must_be_within_the_cycle() {
+        assert!(Timing::parse("green 6, yellow 2, red 4, offset 11").is_ok());
+        assert_eq!(
+            Timing::parse("green 6, yellow 2, red 4, offset 12").unwrap_err(),
+            "offset 12 must be less than the 12s cycle"
+        );
+    }
+
+    #[test]
+    fn timing_lines_round_trip() {
+        let text = "green 8, yellow 4, red 4, all_red 2, offset 3, mode actuated";
+        let timing = Timing::parse(text).unwrap();
+        assert_eq!(Timing::parse(&timing.to_string()), Ok(timing));
+    }
+
+    #[test]
+    fn actuated_gaps_out_without_calls() {
+        let mut controller = ControlMode::Actuated.with_timing(timing(6, 2, 5));
+        // Red for 5s, then the minimum green with the loops empty.
+        assert_eq!(
+            run(controller.as_mut(), 10.0, |_| false),
+            vec![
+                (5.0, TrafficLightState::Green),
+                (8.0, TrafficLightState::Yellow),
+                (10.0, TrafficLightState::Red),
+            ]
+        );
+    }
+
+    #[test]
+    fn actuated_extends_green_while_loops_call() {
+        let mut controller = ControlMode::Actuated.with_timing(timing(6, 2, 5));
+        // Calls until 11s: the green gaps out 2s after the last one.
+        let changes = run(controller.as_mut(), 14.0, |time| time < 11.0);
+        assert_eq!(changes[1], (12.75, TrafficLightState::Yellow));
+    }
+
+    #[test]
+    fn actuated_green_stops_at_its_maximum() {
+        let mut controller = ControlMode::Actuated.with_timing(timing(6, 2, 5));
+        let changes = run(controller.as_mut(), 20.0, |_| true);
+        assert_eq!(changes[0], (5.0, TrafficLightState::Green));
+        assert_eq!(changes[1], (17.0, TrafficLightState::Yellow));
+    }
+
+    #[test]
+    fn all_red_clears_both_ends_of_the_red() {
+        let mut controller = AllRed::new(Box::new(FixedTime::new(timing(4, 2, 3))), 1);
+        assert_eq!(controller.duration(TrafficLightState::Red), Some(6));
+        // 1s clearance, the 3s red, 1s clearance, then the bikes' 1s lead.
+        assert_eq!(
+            run(&mut controller, 19.0, |_| false),
+            vec![
+                (6.0, TrafficLightState::Green),
+                (10.0, TrafficLightState::Yellow),
+                (12.0, TrafficLightState::Red),
+                (18.0, TrafficLightState::Green),
+            ]
+        );
+    }
+
+    #[test]
+    fn all_red_clearing_leaves_the_bike_lead_out() {
+        let mut controller = AllRed::new(Box::new(FixedTime::new(timing(4, 2, 3))), 1);
+        let mut clearing = Vec::new();
+        let mut elapsed = 0.0;
+        while elapsed < 5.75 {
+            elapsed += STEP;
+            controller.decide(&Detectors {
+                state: TrafficLightState::Red,
+                elapsed,
+                queue: 0,
+                bike_queue: 0,
+                approaching: 0,
+                calls: false,
+            });
+            clearing.push((elapsed, controller.clearing(elapsed)));
+        }
+        let at = |time: f32| clearing.iter().find(|(t, _)| *t == time).unwrap().1;
+        assert!(at(0.5));
+        assert!(!at(2.0));
+        assert!(at(4.5));
+        assert!(!at(5.5));
+        assert_eq!(
+            TrafficLightState::Red.bike_phase(1),
+            TrafficLightState::Green
+        );
+    }
+}
@@ -0,0 +1,2327 @@
+use crate::systems::bridge::{BridgeConfig, BridgeState, Drawbridge};
+use crate::systems::clock::SimClock;
+use crate::systems::closure::Closure;
+use crate::systems::construction::{RoadWorks, WORKS_SPEED_LIMIT};
+use crate::systems::delivery::{DeliveryConfig, DoubleParked};
+use crate::systems::demand::{DayType, DemandConfig};
+use crate::systems::detector::DetectorConfig;
+use crate::systems::driver::{
+    DriverConfig, LATE_RED_SECONDS, RED_RUN_CRASH_MULTIPLIER, SPEEDING_FACTOR,
+};
+use crate::systems::emergency::EmergencyConfig;
+use crate::systems::enforcement::{CAMERA_RANGE, EnforcementCamera, SPEEDING_TOLERANCE};
+use crate::systems::events::{EventLog, SimEvent};
+use crate::systems::incident::{
+    CLEARANCE_SECONDS, DEBRIS_CLEANUP_SECONDS, Debris, Incident, IncidentCause, TREATMENT_SECONDS,
+};
+use crate::systems::malfunction::MalfunctionConfig;
+use crate::systems::night_flash::{CAUTION_SPEED_LIMIT, NightFlashConfig};
+use crate::systems::parking::{Circling, MAX_CIRCLES, ParkedVehicle, ParkingConfig, ParkingLot};
+use crate::systems::pool::{Ahead, VehiclePool};
+use crate::systems::profiler::{Phase, Profiler, Stopwatch};
+use crate::systems::rideshare::RideShareConfig;
+use crate::systems::rng::Rng;
+use crate::systems::road::Road;
+use crate::systems::rush_hour::RushHourConfig;
+use crate::systems::school_zone::{SCHOOL_SPEED_LIMIT, SchoolZone};
+use crate::systems::signal_control::{ControlMode, ROUNDABOUT_SPEED_LIMIT, SignalConfig, Timing};
+use crate::systems::special_event::{EventPhase, SpecialEvent};
+use crate::systems::stats::SimulationStats;
+use crate::systems::toll::{BOOTH_REACH, EXPRESS_SPEED_LIMIT, TollBooth, TollConfig};
+use crate::systems::traffic_light::{Flashing, STOP_LINE_REACH, TrafficLight, TrafficLightState};
+use crate::systems::turns::{CROSSING_REACH, TurnConfig};
+use crate::systems::vehicle::{
+    Lane, MIN_GAP, Obstacle, RoadConditions, SKID_BRAKING, Vehicle, VehicleType,
+};
+use crate::systems::weather::WeatherSystem;
+
+pub const SPAWN_CHANCE_PER_SECOND: f32 = 0.35;
+pub const BREAKDOWN_CHANCE_PER_SECOND: f32 = 0.03;
+/// Chance per second that the wind blows debris onto the road, at full
+/// strength.
+const DEBRIS_CHANCE_PER_SECOND: f32 = 0.04;
+/// Chance that a vehicle crossing a stop line crashes in clear weather.
+const CRASH_CHANCE_PER_CROSSING: f32 = 0.004;
+/// Chance per second that a vehicle braking for a stop line on full ice
+/// skids past it.
+const SKID_CHANCE_PER_SECOND: f32 = 0.6;
+/// Fixed slice of simulated time per update. Faster or slower playback runs
+/// more or fewer of these per frame, so a seeded run plays out the same at
+/// every speed.
+pub const STEP_SECONDS: f32 = 1.0 / 16.0;
+
+/// Readings at one light, as a signal controller sees them.
+#[derive(Debug, Clone, Copy)]
+pub struct Detectors {
+    pub state: TrafficLightState,
+    /// Seconds the light has been in `state`.
+    pub elapsed: f32,
+    /// Road vehicles stopped on the approach.
+    pub queue: usize,
+    pub bike_queue: usize,
+    /// Road vehicles still moving towards the light.
+    pub approaching: usize,
+    /// A road vehicle is over one of the approach's induction loops.
+    pub calls: bool,
+}
+
+/// All state of a running simulation: the road, its signals, the
+/// vehicles and incidents on it, the time of day and the weather.
+#[derive(Clone)]
+pub struct Simulation {
+    pub road: Road,
+    pub lights: Vec<TrafficLight>,
+    pub vehicles: VehiclePool,
+    pub incidents: Vec<Incident>,
+    /// Debris blocking the road lane.
+    pub debris: Vec<Debris>,
+    /// Delivery trucks double-parked in the road lane.
+    pub deliveries: Vec<DoubleParked>,
+    /// How often delivery trucks double-park, and for how long.
+    pub delivery: DeliveryConfig,
+    /// Stretches of road under works, slowing the road lane.
+    pub road_works: Vec<RoadWorks>,
+    /// Scheduled closures, from the `[closures]` config section.
+    pub closures: Vec<Closure>,
+    /// School zones, from the `[school_zones]` config section.
+    pub school_zones: Vec<SchoolZone>,
+    /// The drawbridge on the road, if the map has one.
+    pub bridge: Option<Drawbridge>,
+    bridge_config: BridgeConfig,
+    /// The toll booths on the road, and what they charge.
+    pub toll_booths: Vec<TollBooth>,
+    pub tolls: TollConfig,
+    /// The car parks on the road, how big they are and who uses them.
+    pub parking_lots: Vec<ParkingLot>,
+    pub parking: ParkingConfig,
+    /// Drivers going round the block after finding a car park full.
+    pub circling: Vec<Circling>,
+    /// How many cars stop at the curb for ride-share passengers.
+    pub rideshare: RideShareConfig,
+    /// How many drivers turn right at the signals, and whether they may on red.
+    pub turns: TurnConfig,
+    /// The hours the signals flash instead of cycling.
+    pub night_flash: NightFlashConfig,
+    night_flashing: bool,
+    /// How often signals fail, and how long they take to repair.
+    pub malfunctions: MalfunctionConfig,
+    /// Where the induction loops sit on each approach.
+    pub loops: DetectorConfig,
+    pub tick: u32,
+    pub spawned: u32,
+    /// Spawn points switched off by the user.
+    pub disabled_spawns: Vec<usize>,
+    /// Chance per second that a vehicle enters at a spawn point.
+    pub spawn_rate: f32,
+    /// Chance per second that a moving vehicle breaks down.
+    pub breakdown_rate: f32,
+    pub stats: SimulationStats,
+    pub events: EventLog,
+    pub clock: SimClock,
+    /// Traffic level and mix for each kind of day.
+    pub demand: DemandConfig,
+    /// When rush hour is and how much it adds to `spawn_rate`.
+    pub rush_hour: RushHourConfig,
+    /// How drivers make way for sirens.
+    pub emergency: EmergencyConfig,
+    /// How drivers behave at the lights.
+    pub drivers: DriverConfig,
+    /// Venue drawing a crowd, from the `[special_event]` config section.
+    pub special_event: SpecialEvent,
+    pub weather: WeatherSystem,
+    /// How each light is controlled, from the `[signals]` config section.
+    signals: SignalConfig,
+    /// Vehicles that reached the end of the road during the last update.
+    pub finished: Vec<Vehicle>,
+    next_vehicle_id: u32,
+    rng: Rng,
+    second_timer: f32,
+    /// Move vehicles on worker threads, from `parallel_updates` in
+    /// `[performance]`.
+    #[cfg(feature = "parallel")]
+    pub parallel: bool,
+}
+
+impl Simulation {
+    pub fn new(road: Road, rng: Rng) -> Self {
+        let lights = road
+            .signals
+            .iter()
+            .map(|&cell| TrafficLight::new(cell))
+            .collect();
+        let bridge_config = BridgeConfig::default();
+        Self {
+            bridge: road
+                .bridge
+                .map(|(from, to)| Drawbridge::new(from, to, bridge_config)),
+            bridge_config,
+            toll_booths: road
+                .toll_booths
+                .iter()
+                .map(|&cell| TollBooth::new(cell))
+                .collect(),
+            tolls: TollConfig::default(),
+            parking_lots: road
+                .parking_lots
+                .iter()
+                .map(|&cell| ParkingLot::new(cell))
+                .collect(),
+            parking: ParkingConfig::default(),
+            circling: Vec::new(),
+            rideshare: RideShareConfig::default(),
+            turns: TurnConfig::default(),
+            night_flash: NightFlashConfig::default(),
+            night_flashing: false,
+            malfunctions: MalfunctionConfig::default(),
+            loops: DetectorConfig::default(),
+            road,
+            lights,
+            vehicles: VehiclePool::default(),
+            incidents: Vec::new(),
+            debris: Vec::new(),
+            deliveries: Vec::new(),
+            delivery: DeliveryConfig::default(),
+            road_works: Vec::new(),
+            closures: Vec::new(),
+            school_zones: Vec::new(),
+            tick: 0,
+            spawned: 0,
+            disabled_spawns: Vec::new(),
+            spawn_rate: SPAWN_CHANCE_PER_SECOND,
+            breakdown_rate: BREAKDOWN_CHANCE_PER_SECOND,
+            stats: SimulationStats::default(),
+            events: EventLog::default(),
+            clock: SimClock::default(),
+            demand: DemandConfig::default(),
+            rush_hour: RushHourConfig::default(),
+            emergency: EmergencyConfig::default(),
+            drivers: DriverConfig::default(),
+            special_event: SpecialEvent::default(),
+            weather: WeatherSystem::default(),
+            signals: SignalConfig::default(),
+            finished: Vec::new(),
+            next_vehicle_id: 1,
+            rng,
+            second_timer: 0.0,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+        }
+    }
+
+    /// Advances everything by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        self.update_profiled(dt, None);
+    }
+
+    /// [`Simulation::update`], charging each part of the work to `profiler`.
+    pub fn update_profiled(&mut self, dt: f32, profiler: Option<&mut Profiler>) {
+        let mut stopwatch = Stopwatch::start(profiler);
+        self.stats.elapsed += dt;
+        self.second_timer += dt;
+        if self.second_timer + f32::EPSILON >= 1.0 {
+            self.second_timer = 0.0;
+            self.spawn_vehicle();
+            self.spawn_event_traffic();
+            self.roll_breakdown();
+            self.roll_debris();
+            self.roll_delivery();
+            self.roll_malfunction();
+            self.spawn_circling();
+        }
+
+        self.clock.update(dt);
+        self.update_closures();
+        self.update_night_flash();
+        self.update_repairs(dt);
+        self.update_school_zones();
+        self.update_bridge(dt);
+        let cold = self.clock.cold();
+        self.weather
+            .update(dt, self.clock.season(), cold, &mut self.rng);
+        self.dispatch_responders();
+        self.roll_skids(dt);
+        self.update_yielding();
+        self.update_turns_on_red();
+        self.update_flashing_stops();
+        stopwatch.lap(Phase::Spawning);
+        self.update_vehicles(dt);
+        self.update_incidents(dt);
+        self.update_debris(dt);
+        self.update_deliveries(dt);
+        self.update_road_works(dt);
+        self.update_tolls(dt);
+        self.update_parking(dt);
+        self.update_curb_stops(dt);
+        stopwatch.lap(Phase::Vehicles);
+
+        let detectors: Vec<Detectors> = (0..self.lights.len())
+            .map(|index| self.detectors(index))
+            .collect();
+        for (index, (light, detectors)) in self.lights.iter_mut().zip(&detectors).enumerate() {
+            if light.update(dt, detectors) && index == 0 {
+                self.tick += 1;
+            }
+        }
+        stopwatch.lap(Phase::Intersections);
+    }
+
+    /// Swaps in a new road layout, keeping whatever traffic still fits on it.
+    /// Signals that stay in place keep their current state, and settings
+    /// kept by light number follow them to their new number; those of
+    /// removed signals are dropped. Returns the new index of each old light.
+    pub fn replace_road(&mut self, road: Road) -> Vec<Option<usize>> {
+        let old_lights = std::mem::take(&mut self.lights);
+        let moved: Vec<Option<usize>> = old_lights
+            .iter()
+            .map(|light| road.signals.iter().position(|&cell| cell == light.position))
+            .collect();
+        self.lights = road
+            .signals
+            .iter()
+            .map(|&cell| {
+                old_lights
+                    .iter()
+                    .find(|light| light.position == cell)
+                    .cloned()
+                    .unwrap_or_else(|| TrafficLight::new(cell))
+            })
+            .collect();
+        let mut signals = self.signals.clone();
+        remap_lights(&mut signals.lights, &moved);
+        remap_lights(&mut signals.timings, &moved);
+        self.configure_signals(signals);
+        remap_lights(&mut self.loops.lights, &moved);
+        self.night_flash.minor = self
+            .night_flash
+            .minor
+            .iter()
+            .filter_map(|&index| moved.get(index).copied().flatten())
+            .collect();
+
+        let length = road.length as f32;
+        let bike_lane = road.bike_lane;
+        self.vehicles
+            .retain(|v| v.position < length && (bike_lane || v.lane == Lane::Road));
+
+        let vehicles = &self.vehicles;
+        self.incidents
+            .retain(|i| vehicles.get(i.vehicle_id).is_some());
+        self.debris.retain(|d| d.cell < road.length);
+        self.deliveries.retain(|d| d.cell < road.length);
+        self.road_works.retain(|w| w.to < road.length);
+        self.closures.retain(|c| c.to < road.length);
+        self.school_zones.retain(|z| z.to < road.length);
+        if self.bridge.as_ref().map(|b| (b.from, b.to)) != road.bridge {
+            self.bridge = road
+                .bridge
+                .map(|(from, to)| Drawbridge::new(from, to, self.bridge_config));
+        }
+        let old_booths = std::mem::take(&mut self.toll_booths);
+        self.toll_booths = road
+            .toll_booths
+            .iter()
+            .map(|&cell| {
+                old_booths
+                    .iter()
+                    .find(|booth| booth.cell == cell)
+                    .cloned()
+                    .unwrap_or_else(|| TollBooth::new(cell))
+            })
+            .collect();
+        let old_lots = std::mem::take(&mut self.parking_lots);
+        self.parking_lots = road
+            .parking_lots
+            .iter()
+            .map(|&cell| {
+                old_lots
+                    .iter()
+                    .find(|lot| lot.cell == cell)
+                    .cloned()
+                    .unwrap_or_else(|| ParkingLot::new(cell))
+            })
+            .collect();
+        self.circling.retain(|c| road.parking_lots.contains(&c.lot));
+        let lost: Vec<u32> = self
+            .vehicles
+            .iter()
+            .filter(|v| {
+                v.parking
+                    .is_some_and(|cell| !road.parking_lots.contains(&cell))
+            })
+            .map(|v| v.id)
+            .collect();
+        for id in lost {
+            if let Some(vehicle) = self.vehicles.get_mut(id) {
+                vehicle.parking = None;
+                vehicle.destination = None;
+            }
+        }
+        self.forget_missing_responders();
+        self.disabled_spawns
+            .retain(|cell| road.spawn_points.contains(cell));
+        if self
+            .special_event
+            .venue
+            .is_some_and(|cell| cell >= road.length)
+        {
+            self.special_event.venue = None;
+        }
+        self.road = road;
+        moved
+    }
+
+    /// Whether a spawn point is sending traffic: switched on, and not behind
+    /// a closure its traffic would run into.
+    pub fn spawn_open(&self, cell: usize) -> bool {
+        self.spawn_enabled(cell) && !self.closures.iter().any(|c| c.active && cell <= c.to)
+    }
+
+    pub fn spawn_enabled(&self, cell: usize) -> bool {
+        !self.disabled_spawns.contains(&cell)
+    }
+
+    /// Vehicles in `lane` stopped on the approach to light `index`.
+    pub fn queue_length(&self, index: usize, lane: Lane) -> usize {
+        self.on_approach(index, lane).filter(|v| v.stopped).count()
+    }
+
+    /// Vehicles in `lane` whose next signal is light `index`.
+    fn on_approach(&self, index: usize, lane: Lane) -> impl Iterator<Item = &Vehicle> {
+        let cell = |index: usize| self.road.signals.get(index).map_or(0.0, |&c| c as f32);
+        let previous = index.checked_sub(1).map_or(0.0, cell);
+        self.vehicles
+            .near(lane, previous, cell(index))
+            .filter(move |v| self.road.next_signal(v.position) == Some(index))
+    }
+
+    /// Gives each light the controller `signals` picks for it, keeping the
+    /// config for lights added later in the editor.
+    pub fn configure_signals(&mut self, signals: SignalConfig) {
+        for (index, light) in self.lights.iter_mut().enumerate() {
+            let mode = signals.mode_for(index).unwrap_or(light.controller.mode());
+            let controller = signals.controller(index, mode);
+            if light.controller.mode() != mode
+                || light.controller.timing() != controller.timing()
+                || light.controller.all_red() != controller.all_red()
+                || light.controller.duration(TrafficLightState::Yellow)
+                    != controller.duration(TrafficLightState::Yellow)
+            {
+                let had_signs = !light.controller.mode().is_signal();
+                light.controller = controller;
+                if had_signs || !mode.is_signal() {
+                    light.set_flashing(mode.sign());
+                }
+            }
+        }
+        self.signals = signals;
+    }
+
+    /// The timing light `index` runs with, as its `[timing]` line has it.
+    pub fn timing(&self, index: usize) -> Timing {
+        self.signals.timing_for(index)
+    }
+
+    /// Each induction loop at light `index`, by setback, and whether a road
+    /// vehicle is over it. Loops past the start of the road are left out.
+    pub fn loop_states(&self, index: usize) -> Vec<(usize, bool)> {
+        let position = self.lights[index].position;
+        self.loops
+            .setbacks_for(index)
+            .iter()
+            .filter_map(|&setback| {
+                let cell = position.checked_sub(setback + 1)?;
+                let occupied = self
+                    .vehicles
+                    .near(Lane::Road, cell as f32, cell as f32 + 1.0)
+                    .any(|v| v.cell() == cell);
+                Some((setback, occupied))
+            })
+            .collect()
+    }
+
+    /// What the detectors at light `index` see, for signal controllers.
+    pub fn detectors(&self, index: usize) -> Detectors {
+        let light = &self.lights[index];
+        Detectors {
+            state: light.state,
+            elapsed: light.elapsed(),
+            queue: self.queue_length(index, Lane::Road),
+            bike_queue: self.queue_length(index, Lane::Bike),
+            approaching: self
+                .on_approach(index, Lane::Road)
+                .filter(|v| !v.stopped)
+                .count(),
+            calls: self
+                .loop_states(index)
+                .iter()
+                .any(|&(_, occupied)| occupied),
+        }
+    }
+
+    /// Switches a spawn point on or off. Returns false if `cell` is not one.
+    pub fn toggle_spawn(&mut self, cell: usize) -> bool {
+        if !self.road.spawn_points.contains(&cell) {
+            return false;
+        }
+        match self.disabled_spawns.iter().position(|&c| c == cell) {
+            Some(index) => {
+                self.disabled_spawns.remove(index);
+            }
+            None => self.disabled_spawns.push(cell),
+        }
+        true
+    }
+
+    /// Applies an outside change to the simulation and records it in the
+    /// event log. Returns a description of what happened.
+    pub fn apply(&mut self, event: SimEvent) -> Result<String, String> {
+        let text = match event {
+            SimEvent::Spawn(kind, cell) => {
+                let id = self.spawn(kind, cell)?;
+                let vehicle = self.vehicles.get(id);
+                let at = vehicle.map_or(0, |v| v.cell());
+                format!("Spawned {} #{} at cell {}", kind.name(), id, at)
+            }
+            SimEvent::Breakdown(cell) => {
+                let id = self
+                    .vehicles
+                    .iter()
+                    .filter(|v| {
+                        v.lane == Lane::Road
+                            && !v.kind.is_service()
+                            && !v.broken_down
+                            && v.cell() <= cell
+                    })
+                    .max_by(|a, b| a.position.total_cmp(&b.position))
+                    .map(|v| v.id)
+                    .ok_or_else(|| {
+                        format!("No vehicle at or behind cell {} to break down", cell)
+                    })?;
+                self.break_down(id, IncidentCause::Breakdown);
+                format!("Forced a breakdown of #{}", id)
+            }
+            SimEvent::ToggleSpawn(cell) => {
+                if !self.toggle_spawn(cell) {
+                    return Err(format!("Cell {} is not a spawn point", cell));
+                }
+                let state = if self.spawn_enabled(cell) {
+                    "on"
+                } else {
+                    "off"
+                };
+                format!("Spawn point at cell {} switched {}", cell, state)
+            }
+            SimEvent::ForceLight(index, state) => {
+                let light = self.signal_mut(index)?;
+                light.force(state);
+                format!("Light {} forced to {}", index + 1, state.name())
+            }
+            SimEvent::Operate(index) => {
+                let now = self.stats.elapsed;
+                let flashing = self.flashing_for(index);
+                let light = self.signal_mut(index)?;
+                match light.manual_since.take() {
+                    Some(since) => {
+                        light.held = false;
+                        if flashing.is_some() {
+                            light.set_flashing(flashing);
+                        }
+                        self.stats.record_manual_control(now - since);
+                        format!(
+                            "Operator released light {} after {:.0}s",
+                            index + 1,
+                            now - since
+                        )
+                    }
+                    None => {
+                        light.take_manual_control(now);
+                        format!("Operator took over light {}", index + 1)
+                    }
+                }
+            }
+            SimEvent::AdvanceLight(index) => {
+                let now = self.stats.elapsed;
+                let light = self.signal_mut(index)?;
+                if light.manual_since.is_none() {
+                    light.take_manual_control(now);
+                }
+                let state = light.state.next();
+                light.force(state);
+                format!("Operator turned light {} {}", index + 1, state.name())
+            }
+            SimEvent::Despawn(id) => {
+                if !self.despawn(id) {
+                    return Err(format!("No vehicle #{}", id));
+                }
+                format!("Removed #{}", id)
+            }
+            SimEvent::Weather(kind, intensity) => {
+                self.weather.change_to(kind, intensity);
+                format!("Weather turning to {}", kind.name())
+            }
+            SimEvent::Debris(cell) => {
+                self.drop_debris(cell)?;
+                format!("Debris blown onto cell {}", cell)
+            }
+            SimEvent::RoadWorks(from, to, seconds) => {
+                if from > to || to >= self.road.length {
+                    return Err(format!(
+                        "Cells {}-{} are not a stretch of the road",
+                        from, to
+                    ));
+                }
+                if seconds <= 0.0 {
+                    return Err("Road works need a duration above 0".to_string());
+                }
+                self.road_works.push(RoadWorks::new(from, to, seconds));
+                format!("Road works on cells {}-{} for {:.0}s", from, to, seconds)
+            }
+            SimEvent::ToggleCamera(index) => {
+                let light = self
+                    .lights
+                    .get_mut(index)
+                    .ok_or_else(|| format!("There is no light {}", index + 1))?;
+                light.camera = match light.camera {
+                    Some(_) => None,
+                    None => Some(EnforcementCamera::default()),
+                };
+                let verb = if light.camera.is_some() { "up" } else { "down" };
+                format!("Camera at light {} {}", index + 1, verb)
+            }
+            SimEvent::Malfunction(index) => {
+                let repair_seconds = self.malfunctions.repair_seconds;
+                let light = self.signal_mut(index)?;
+                if light.repair_left.is_some() {
+                    return Err(format!("Light {} is already out of order", index + 1));
+                }
+                light.malfunction(repair_seconds);
+                format!("Light {} failed, flashing red until repaired", index + 1)
+            }
+            SimEvent::SetTiming(index, timing) => {
+                let mode = self.signal_mut(index)?.controller.mode();
+                self.signals.timings.retain(|&(light, _)| light != index);
+                self.signals.timings.push((index, timing));
+                self.lights[index].controller = self.signals.controller(index, mode);
+                format!("Light {} retimed: {}", index + 1, timing)
+            }
+            SimEvent::ToggleRightOnRed => {
+                self.turns.right_on_red = !self.turns.right_on_red;
+                let side = self.drivers.curb_side();
+                if self.turns.right_on_red {
+                    format!("Turning {} on red allowed", side)
+                } else {
+                    format!("No turning {} on red", side)
+                }
+            }
+            SimEvent::SetClock(minute) => {
+                self.clock.set_time(minute);
+                format!("Clock set to {}", self.clock.format())
+            }
+        };
+        self.events.push(self.stats.elapsed, text.clone());
+        Ok(text)
+    }
+
+    /// Puts a vehicle on the road at `cell`, or at the first enabled spawn
+    /// point when no cell is given. Bicycles use the bike lane if there is one.
+    pub fn spawn(&mut self, kind: VehicleType, cell: Option<usize>) -> Result<u32, String> {
+        let cell = match cell {
+            Some(cell) if cell < self.road.length => cell,
+            Some(cell) => return Err(format!("Cell {} is off the road", cell)),
+            None => self
+                .road
+                .spawn_points
+                .iter()
+                .copied()
+                .find(|&cell| self.spawn_enabled(cell))
+                .ok_or("Every spawn point is switched off")?,
+        };
+        let lane = self.lane_for(kind);
+        if !self.entry_clear(lane, cell, kind.length(), false) {
+            return Err(format!("Cell {} is occupied", cell));
+        }
+        Ok(self.add_vehicle(kind, lane, cell))
+    }
+
+    /// Removes a vehicle on the spot, cleaning up any incident it was part of.
+    pub fn despawn(&mut self, id: u32) -> bool {
+        if self.vehicles.remove(id).is_none() {
+            return false;
+        }
+
+        if let Some(incident) = self.incidents.iter().find(|i| i.vehicle_id == id) {
+            let responders = [
+                incident.tow_truck_id,
+                incident.ambulance_id,
+                incident.police_id,
+            ];
+            for responder in responders.into_iter().flatten() {
+                self.release(responder);
+            }
+        }
+        self.incidents.retain(|i| i.vehicle_id != id);
+        self.forget_missing_responders();
+        true
+    }
+
+    /// Stands a responder down: it is free to drive on and no longer
+    /// waved through traffic.
+    fn release(&mut self, id: u32) {
+        if let Some(vehicle) = self.vehicles.get_mut(id) {
+            vehicle.held = false;
+            vehicle.responding = false;
+            vehicle.destination = home_cell(&self.road, vehicle);
+        }
+    }
+
+    /// Drops responders that are no longer on the road from the incidents
+    /// and debris they were on, so new ones get sent.
+    fn forget_missing_responders(&mut self) {
+        let vehicles = &self.vehicles;
+        let gone = |id: Option<u32>| id.is_some_and(|id| vehicles.get(id).is_none());
+        for incident in self.incidents.iter_mut() {
+            if gone(incident.tow_truck_id) {
+                incident.tow_truck_id = None;
+                incident.clearance_left = None;
+            }
+            // An ambulance that has finished may have driven off already.
+            if gone(incident.ambulance_id) && !incident.treated() {
+                incident.ambulance_id = None;
+                incident.treatment_left = None;
+            }
+            if gone(incident.police_id) {
+                incident.police_id = None;
+            }
+        }
+        for debris in self.debris.iter_mut() {
+            if gone(debris.fire_truck_id) {
+                debris.fire_truck_id = None;
+                debris.cleanup_left = None;
+            }
+        }
+    }
+
+    /// Moves a vehicle to `cell`, bringing it to a standstill there.
+    pub fn teleport(&mut self, id: u32, cell: usize) -> bool {
+        let last = self.road.length.saturating_sub(1);
+        let Some(vehicle) = self.vehicles.place(id, cell.min(last) as f32) else {
+            return false;
+        };
+        vehicle.speed = 0.0;
+        true
+    }
+
+    /// Whether a vehicle of `length` can enter `lane` at `cell` without
+    /// landing on top of someone. With `ignore_stopped`, queued vehicles are
+    /// assumed to make way.
+    fn entry_clear(&self, lane: Lane, cell: usize, length: f32, ignore_stopped: bool) -> bool {
+        let at = cell as f32;
+        let reach = at + length + MIN_GAP + VehicleType::longest();
+        self.vehicles
+            .near(lane, at - MIN_GAP, reach)
+            .filter(|v| !(ignore_stopped && v.stopped))
+            .all(|v| v.rear() >= at + length + MIN_GAP || v.position <= at - MIN_GAP)
+    }
+
+    fn lane_for(&self, kind: VehicleType) -> Lane {
+        if self.road.bike_lane && kind == VehicleType::Bicycle {
+            Lane::Bike
+        } else {
+            Lane::Road
+        }
+    }
+
+    fn add_vehicle(&mut self, kind: VehicleType, lane: Lane, cell: usize) -> u32 {
+        let id = self.next_vehicle_id;
+        let mut vehicle = Vehicle::new(id, kind, lane);
+        vehicle.position = cell as f32;
+        vehicle.origin = cell;
+        if lane == Lane::Road && !kind.is_service() {
+            let drivers = &self.drivers;
+            let rng = &mut self.rng;
+            let mut habit = |fraction: f32| fraction > 0.0 && rng.chance(fraction);
+            vehicle.runs_lights = habit(drivers.red_light_runners);
+            vehicle.speeds = habit(drivers.speeders);
+            vehicle.camera_shy =
+                (vehicle.runs_lights || vehicle.speeds) && habit(drivers.camera_deterrence);
+            vehicle.express = !self.road.toll_booths.is_empty() && habit(self.tolls.express);
+        }
+        self.vehicles.insert(vehicle);
+        self.next_vehicle_id += 1;
+        self.spawned += 1;
+        id
+    }
+
+    /// Whether the road is in one of today's rush hour windows. Holidays
+    /// have none.
+    pub fn rush_hour_active(&self) -> bool {
+        self.demand.day_type(&self.clock) != DayType::Holiday && self.rush_hour.active(&self.clock)
+    }
+
+    fn spawn_vehicle(&mut self) {
+        let profile = *self.demand.profile(&self.clock);
+        let mut rate = self.spawn_rate * profile.rate;
+        if self.rush_hour_active() {
+            rate *= self.rush_hour.multiplier;
+        }
+        if !self.rng.chance(rate.min(1.0)) {
+            return;
+        }
+        let kind = profile.pick(self.rng.below(profile.total()));
+        let lane = self.lane_for(kind);
+        let spawn_points: Vec<usize> = self
+            .road
+            .spawn_points
+            .iter()
+            .copied()
+            .filter(|&cell| self.spawn_open(cell))
+            .collect();
+        if spawn_points.is_empty() {
+            return;
+        }
+        let cell = spawn_points[self.rng.below(spawn_points.len() as u32) as usize];
+        if self.entry_clear(lane, cell, kind.length(), false) {
+            let id = self.add_vehicle(kind, lane, cell);
+            self.seek_parking(id);
+            self.plan_curb_stop(id);
+            self.plan_turn(id);
+        }
+    }
+
+    /// Sends the `right_share` of new cars and motorcycles with nowhere
+    /// else to be off the road to the right at one of the signals ahead.
+    fn plan_turn(&mut self, id: u32) {
+        let Some(vehicle) = self.vehicles.get(id) else {
+            return;
+        };
+        let ahead: Vec<usize> = (0..self.lights.len())
+            .filter(|&index| self.lights[index].position as f32 > vehicle.position + 1.0)
+            .collect();
+        if !matches!(vehicle.kind, VehicleType::Car | VehicleType::Motorcycle)
+            || vehicle.lane != Lane::Road
+            || vehicle.destination.is_some()
+            || vehicle.curb_stop.is_some()
+            || ahead.is_empty()
+            || !self.rng.chance(self.turns.right_share)
+        {
+            return;
+        }
+        let index = ahead[self.rng.below(ahead.len() as u32) as usize];
+        let cell = self.lights[index].position;
+        if let Some(vehicle) = self.vehicles.get_mut(id) {
+            vehicle.turns_at = Some(index);
+            vehicle.destination = Some(cell);
+        }
+    }
+
+    /// With right turn on red allowed, clears each driver turning right who
+    /// has stopped at a red line to go, once no cyclist is coming up the
+    /// bike lane it turns across. Counts the red it was spared.
+    fn update_turns_on_red(&mut self) {
+        if !self.turns.right_on_red {
+            return;
+        }
+        let cyclists: Vec<f32> = self
+            .vehicles
+            .iter()
+            .filter(|v| v.lane == Lane::Bike)
+            .map(|v| v.position)
+            .collect();
+        let waiting: Vec<(u32, usize)> = self
+            .vehicles
+            .iter()
+            .filter(|v| v.stopped && !v.turning_on_red && !v.broken_down)
+            .filter_map(|v| v.turns_at.map(|index| (v.id, index)))
+            .collect();
+        for (id, index) in waiting {
+            let Some(light) = self.lights.get(index) else {
+                continue;
+            };
+            let stop_line = light.position as f32;
+            let crossing_clear = !cyclists
+                .iter()
+                .any(|&p| p > stop_line - CROSSING_REACH && p <= stop_line + 1.0);
+            let Some(vehicle) = self.vehicles.get_mut(id) else {
+                continue;
+            };
+            if light.state == TrafficLightState::Red
+                && stop_line - vehicle.position <= STOP_LINE_REACH
+                && crossing_clear
+            {
+                vehicle.turning_on_red = true;
+                self.stats.record_turn_on_red(light.remaining());
+            }
+        }
+    }
+
+    /// Gives the `share` of new cars with nowhere else to be a ride-share
+    /// stop somewhere further along the road.
+    fn plan_curb_stop(&mut self, id: u32) {
+        let Some(vehicle) = self.vehicles.get(id) else {
+            return;
+        };
+        let first = vehicle.cell() + 2;
+        if vehicle.kind != VehicleType::Car
+            || vehicle.lane != Lane::Road
+            || vehicle.destination.is_some()
+            || first >= self.road.length
+            || !self.rng.chance(self.rideshare.share)
+        {
+            return;
+        }
+        let cell = first + self.rng.below((self.road.length - first) as u32) as usize;
+        if let Some(vehicle) = self.vehicles.get_mut(id) {
+            vehicle.curb_stop = Some((cell, self.rideshare.stop_seconds));
+        }
+    }
+
+    /// Counts down the stop of each ride-share car standing at the curb, and
+    /// sends it on once done.
+    fn update_curb_stops(&mut self, dt: f32) {
+        let stopping: Vec<u32> = self
+            .vehicles
+            .iter()
+            .filter(|v| v.curb_stop.is_some())
+            .map(|v| v.id)
+            .collect();
+        for id in stopping {
+            let Some(vehicle) = self.vehicles.get_mut(id) else {
+                continue;
+            };
+            let Some((cell, left)) = vehicle.curb_stop else {
+                continue;
+            };
+            if vehicle.at_curb() {
+                vehicle.curb_stop = (left > dt).then_some((cell, left - dt));
+            } else if vehicle.position > cell as f32 {
+                vehicle.curb_stop = None;
+            }
+        }
+    }
+
+    /// Sends the `share` of new drivers who want to park to one of the car
+    /// parks further along the road.
+    fn seek_parking(&mut self, id: u32) {
+        let Some(vehicle) = self.vehicles.get(id) else {
+            return;
+        };
+        let lots: Vec<usize> = self
+            .parking_lots
+            .iter()
+            .map(|lot| lot.cell)
+            .filter(|&cell| cell as f32 > vehicle.position)
+            .collect();
+        if lots.is_empty()
+            || vehicle.lane != Lane::Road
+            || vehicle.kind.is_service()
+            || vehicle.kind == VehicleType::Bicycle
+            || !self.rng.chance(self.parking.share)
+        {
+            return;
+        }
+        let lot = lots[self.rng.below(lots.len() as u32) as usize];
+        if let Some(vehicle) = self.vehicles.get_mut(id) {
+            vehicle.parking = Some(lot);
+            vehicle.destination = Some(lot);
+        }
+    }
+
+    /// Brings drivers who went round the block back onto the road at the
+    /// nearest open spawn point before their car park, once there is room.
+    /// Without one they have no way back and give up.
+    fn spawn_circling(&mut self) {
+        let mut circling = std::mem::take(&mut self.circling);
+        circling.retain(|c| {
+            let Some(cell) = self
+                .road
+                .spawn_points
+                .iter()
+                .copied()
+                .filter(|&cell| cell < c.lot && self.spawn_open(cell))
+                .max()
+            else {
+                return false;
+            };
+            if !self.entry_clear(Lane::Road, cell, c.kind.length(), false) {
+                return true;
+            }
+            let id = self.add_vehicle(c.kind, Lane::Road, cell);
+            if let Some(vehicle) = self.vehicles.get_mut(id) {
+                vehicle.parking = Some(c.lot);
+                vehicle.destination = Some(c.lot);
+                vehicle.circles = c.circles;
+            }
+            false
+        });
+        self.circling = circling;
+    }
+
+    /// Counts down each parked vehicle's stay and lets it back out onto the
+    /// road once its time is up and there is room.
+    fn update_parking(&mut self, dt: f32) {
+        for index in 0..self.parking_lots.len() {
+            let lot = &mut self.parking_lots[index];
+            for parked in lot.parked.iter_mut() {
+                parked.time_left -= dt;
+            }
+            let cell = lot.cell;
+            let Some(leaving) = lot.parked.iter().position(|p| p.time_left <= 0.0) else {
+                continue;
+            };
+            let kind = lot.parked[leaving].kind;
+            if self.entry_clear(Lane::Road, cell, kind.length(), false) {
+                self.parking_lots[index].parked.remove(leaving);
+                self.add_vehicle(kind, Lane::Road, cell);
+            }
+        }
+    }
+
+    /// Adds the special event's crowd: before it starts, drivers from the
+    /// spawn points feeding the venue who leave the road there; after it
+    /// ends, drivers pulling out of the venue.
+    fn spawn_event_traffic(&mut self) {
+        let event = &self.special_event;
+        let Some(venue) = event.venue.filter(|&cell| cell < self.road.length) else {
+            return;
+        };
+        let (cells, destination): (Vec<usize>, _) = match event.phase(&self.clock) {
+            Some(EventPhase::Arriving) => (
+                self.road
+                    .spawn_points
+                    .iter()
+                    .copied()
+                    .filter(|&cell| self.spawn_open(cell) && event.feeds(cell))
+                    .collect(),
+                Some(venue),
+            ),
+            Some(EventPhase::Leaving) => (vec![venue], None),
+            _ => return,
+        };
+        if cells.is_empty() || !self.rng.chance((self.spawn_rate * event.impact).min(1.0)) {
+            return;
+        }
+        let profile = *self.demand.profile(&self.clock);
+        let kind = match profile.pick(self.rng.below(profile.total())) {
+            kind if kind.is_service() => VehicleType::Car,
+            kind => kind,
+        };
+        let lane = self.lane_for(kind);
+        let cell = cells[self.rng.below(cells.len() as u32) as usize];
+        if self.entry_clear(lane, cell, kind.length(), false) {
+            let id = self.add_vehicle(kind, lane, cell);
+            if let Some(vehicle) = self.vehicles.get_mut(id) {
+                vehicle.destination = destination;
+            }
+        }
+    }
+
+    fn roll_breakdown(&mut self) {
+        if !self.rng.chance(self.breakdown_rate) {
+            return;
+        }
+        let candidates: Vec<u32> = self
+            .vehicles
+            .iter()
+            .filter(|v| {
+                v.lane == Lane::Road && !v.kind.is_service() && !v.broken_down && !v.stopped
+            })
+            .map(|v| v.id)
+            .collect();
+        if !candidates.is_empty() {
+            let id = candidates[self.rng.below(candidates.len() as u32) as usize];
+            self.break_down(id, IncidentCause::Breakdown);
+            self.events
+                .push(self.stats.elapsed, format!("#{} broke down", id));
+        }
+    }
+
+    /// In a strong wind, now and then blows debris onto a free stretch of
+    /// the road lane.
+    fn roll_debris(&mut self) {
+        if !self
+            .rng
+            .chance(DEBRIS_CHANCE_PER_SECOND * self.weather.wind())
+        {
+            return;
+        }
+        let cell = 1 + self.rng.below(self.road.length.saturating_sub(2) as u32) as usize;
+        if self.drop_debris(cell).is_ok() {
+            self.events.push(
+                self.stats.elapsed,
+                format!("Wind blew debris onto cell {}", cell),
+            );
+        }
+    }
+
+    /// Blocks the road lane at `cell`, unless a signal, spawn point,
+    /// vehicle or other debris is already there.
+    fn drop_debris(&mut self, cell: usize) -> Result<(), String> {
+        if cell >= self.road.length {
+            return Err(format!("Cell {} is off the road", cell));
+        }
+        if self.road.signals.contains(&cell) || self.road.spawn_points.contains(&cell) {
+            return Err(format!("Cell {} has a signal or spawn point", cell));
+        }
+        let occupied = self.debris.iter().any(|d| d.cell == cell)
+            || self.vehicles.iter().any(|v| {
+                v.lane == Lane::Road && v.rear() < (cell + 1) as f32 && v.position >= cell as f32
+            });
+        if occupied {
+            return Err(format!("Cell {} is not clear", cell));
+        }
+        self.debris.push(Debris::new(cell));
+        Ok(())
+    }
+
+    /// Now and then has a delivery truck double-park on a free cell of the
+    /// road lane for a while.
+    fn roll_delivery(&mut self) {
+        if !self.rng.chance(self.delivery.per_minute / 60.0) {
+            return;
+        }
+        let cell = 1 + self.rng.below(self.road.length.saturating_sub(2) as u32) as usize;
+        let taken = self.road.signals.contains(&cell)
+            || self.road.spawn_points.contains(&cell)
+            || self.road.toll_booths.contains(&cell)
+            || self.debris.iter().any(|d| d.cell == cell)
+            || self.deliveries.iter().any(|d| d.cell == cell)
+            || self.vehicles.iter().any(|v| {
+                v.lane == Lane::Road && v.rear() < (cell + 1) as f32 && v.position >= cell as f32
+            });
+        if taken {
+            return;
+        }
+        let (min, max) = (self.delivery.min_seconds, self.delivery.max_seconds);
+        let time_left = min + (max - min) * self.rng.below(101) as f32 / 100.0;
+        self.deliveries.push(DoubleParked { cell, time_left });
+        self.events.push(
+            self.stats.elapsed,
+            format!(
+                "Delivery truck double-parked on cell {} for {:.0}s",
+                cell, time_left
+            ),
+        );
+    }
+
+    /// Counts down each double-parked delivery and moves the truck on when
+    /// it is unloaded.
+    fn update_deliveries(&mut self, dt: f32) {
+        for delivery in self.deliveries.iter_mut() {
+            delivery.time_left -= dt;
+            if delivery.time_left <= 0.0 {
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("Delivery truck on cell {} drove off", delivery.cell),
+                );
+            }
+        }
+        self.deliveries.retain(|d| d.time_left > 0.0);
+    }
+
+    /// Sets how often the drawbridge lifts, starting its cycle over.
+    pub fn configure_bridge(&mut self, config: BridgeConfig) {
+        self.bridge_config = config;
+        self.bridge = self
+            .road
+            .bridge
+            .map(|(from, to)| Drawbridge::new(from, to, config));
+    }
+
+    /// Runs the drawbridge's cycle. It only lifts once nothing is left on
+    /// the deck.
+    fn update_bridge(&mut self, dt: f32) {
+        let Some(bridge) = self.bridge.as_mut() else {
+            return;
+        };
+        let (from, to) = (bridge.from as f32, (bridge.to + 1) as f32);
+        let deck_clear = !self
+            .vehicles
+            .iter()
+            .any(|v| v.position >= from && v.rear() < to);
+        let text = match bridge.update(dt, deck_clear) {
+            Some(BridgeState::Warning) => "Bridge warning lights on",
+            Some(BridgeState::Raising) => "Bridge lifting for boats",
+            Some(BridgeState::Down) => "Bridge down, traffic moving again",
+            _ => return,
+        };
+        self.events.push(self.stats.elapsed, text.to_string());
+    }
+
+    /// Lines across the road traffic has to stop at right now.
+    fn barriers(&self) -> Vec<Barrier> {
+        let closures = self.closures.iter().filter(|c| c.active).map(|c| Barrier {
+            cell: c.from as f32,
+            soft: false,
+            toll: false,
+        });
+        let bridge = self.bridge.iter().filter(|b| b.closed()).map(|b| Barrier {
+            cell: b.from as f32,
+            soft: b.state == BridgeState::Warning,
+            toll: false,
+        });
+        let booths = self.toll_booths.iter().map(|b| Barrier {
+            cell: b.cell as f32,
+            soft: false,
+            toll: true,
+        });
+        let deliveries = self.deliveries.iter().map(|d| Barrier {
+            cell: d.cell as f32,
+            soft: false,
+            toll: false,
+        });
+        closures
+            .chain(bridge)
+            .chain(booths)
+            .chain(deliveries)
+            .collect()
+    }
+
+    /// Puts up or takes down the barricades of each closure as its times
+    /// come round.
+    fn update_closures(&mut self) {
+        for closure in self.closures.iter_mut() {
+            let scheduled = closure.scheduled(&self.clock);
+            if scheduled == closure.active {
+                continue;
+            }
+            closure.active = scheduled;
+            let text = if scheduled {
+                format!(
+                    "Road closed for {} on cells {}-{}",
+                    closure.name, closure.from, closure.to
+                )
+            } else {
+                format!(
+                    "Road reopened after {} on cells {}-{}",
+                    closure.name, closure.from, closure.to
+                )
+            };
+            self.events.push(self.stats.elapsed, text);
+        }
+    }
+
+    /// Sets the signals flashing when the night hours start, and back to
+    /// their cycles when they end.
+    fn update_night_flash(&mut self) {
+        let active = self.night_flash.active(&self.clock);
+        if active == self.night_flashing {
+            return;
+        }
+        self.night_flashing = active;
+        for (index, light) in self.lights.iter_mut().enumerate() {
+            let signal = light.controller.mode().is_signal();
+            if !signal || light.repair_left.is_some() || light.manual_since.is_some() {
+                continue;
+            }
+            light.set_flashing(active.then(|| self.night_flash.flashing_for(index)));
+        }
+        let text = if active {
+            "Signals flashing for the night"
+        } else {
+            "Signals back on their cycles"
+        };
+        self.events.push(self.stats.elapsed, text.to_string());
+    }
+
+    /// Light `index`, or an error if there is none or it stands for the
+    /// signs of an unsignalled intersection.
+    fn signal_mut(&mut self, index: usize) -> Result<&mut TrafficLight, String> {
+        let light = self
+            .lights
+            .get_mut(index)
+            .ok_or_else(|| format!("There is no light {}", index + 1))?;
+        let mode = light.controller.mode();
+        if !mode.is_signal() {
+            return Err(format!(
+                "Intersection {} has no signal ({})",
+                index + 1,
+                light.countdown()
+            ));
+        }
+        Ok(light)
+    }
+
+    /// How light `index` would be flashing if nobody were directing it: as
+    /// its signs, red while broken, or as the night hours have it.
+    fn flashing_for(&self, index: usize) -> Option<Flashing> {
+        let light = self.lights.get(index);
+        if let Some(sign) = light.and_then(|l| l.controller.mode().sign()) {
+            return Some(sign);
+        }
+        if light.is_some_and(|l| l.repair_left.is_some()) {
+            Some(Flashing::Red)
+        } else {
+            self.night_flashing
+                .then(|| self.night_flash.flashing_for(index))
+        }
+    }
+
+    /// Now and then breaks down one of the working signals to flashing red.
+    fn roll_malfunction(&mut self) {
+        if !self.rng.chance(self.malfunctions.per_minute / 60.0) {
+            return;
+        }
+        let working: Vec<usize> = (0..self.lights.len())
+            .filter(|&index| {
+                let light = &self.lights[index];
+                light.repair_left.is_none() && light.controller.mode().is_signal()
+            })
+            .collect();
+        if working.is_empty() {
+            return;
+        }
+        let index = working[self.rng.below(working.len() as u32) as usize];
+        self.lights[index].malfunction(self.malfunctions.repair_seconds);
+        self.events.push(
+            self.stats.elapsed,
+            format!("Light {} failed, flashing red until repaired", index + 1),
+        );
+    }
+
+    /// Counts down the repair of each broken signal and puts it back on its
+    /// cycle, or flashing for the night, once done.
+    fn update_repairs(&mut self, dt: f32) {
+        for (index, light) in self.lights.iter_mut().enumerate() {
+            let Some(left) = light.repair_left else {
+                continue;
+            };
+            if left > dt {
+                light.repair_left = Some(left - dt);
+                continue;
+            }
+            light.repair_left = None;
+            // An operator directing traffic there carries on until released.
+            if light.manual_since.is_none() {
+                let night = self.night_flashing;
+                light.set_flashing(night.then(|| self.night_flash.flashing_for(index)));
+            }
+            self.events
+                .push(self.stats.elapsed, format!("Light {} repaired", index + 1));
+        }
+    }
+
+    /// Lets each vehicle that has come to a stop at a light flashing red go
+    /// on through it, as at a stop sign, and forgets the light once passed.
+    fn update_flashing_stops(&mut self) {
+        let stops: Vec<(u32, Option<usize>)> = self
+            .vehicles
+            .iter()
+            .map(|v| {
+                let next = self.road.next_signal(v.position);
+                let stopped_at = next.filter(|&index| {
+                    let light = &self.lights[index];
+                    v.stopped
+                        && light.flashing == Some(Flashing::Red)
+                        && light.position as f32 - v.position <= STOP_LINE_REACH
+                });
+                (
+                    v.id,
+                    stopped_at.or(v.cleared_flash.filter(|&c| Some(c) == next)),
+                )
+            })
+            .collect();
+        for (id, cleared) in stops {
+            if let Some(vehicle) = self.vehicles.get_mut(id) {
+                vehicle.cleared_flash = cleared;
+            }
+        }
+    }
+
+    /// Switches each school zone's beacons on and off as its times come
+    /// round.
+    fn update_school_zones(&mut self) {
+        for zone in self.school_zones.iter_mut() {
+            let scheduled = zone.scheduled(&self.clock);
+            if scheduled == zone.active {
+                continue;
+            }
+            zone.active = scheduled;
+            let text = if scheduled {
+                format!(
+                    "School zone {} on cells {}-{} active",
+                    zone.name, zone.from, zone.to
+                )
+            } else {
+                format!(
+                    "School zone {} on cells {}-{} over",
+                    zone.name, zone.from, zone.to
+                )
+            };
+            self.events.push(self.stats.elapsed, text);
+        }
+    }
+
+    /// Stretches of the road lane with a lower speed limit right now: the
+    /// map's own limits, road works, active school zones and toll booths.
+    fn speed_zones(&self) -> Vec<SpeedZone> {
+        let limits = self
+            .road
+            .speed_limits
+            .iter()
+            .map(|&(from, to, limit)| SpeedZone { from, to, limit });
+        let works = self.road_works.iter().map(|w| SpeedZone {
+            from: w.from,
+            to: w.to,
+            limit: WORKS_SPEED_LIMIT,
+        });
+        let schools = self
+            .school_zones
+            .iter()
+            .filter(|z| z.active)
+            .map(|z| SpeedZone {
+                from: z.from,
+                to: z.to,
+                limit: SCHOOL_SPEED_LIMIT,
+            });
+        let booths = self.toll_booths.iter().map(|b| SpeedZone {
+            from: b.cell,
+            to: b.cell,
+            limit: EXPRESS_SPEED_LIMIT,
+        });
+        let flashing = self
+            .lights
+            .iter()
+            .filter(|l| l.flashing == Some(Flashing::Yellow))
+            .map(|l| SpeedZone {
+                from: l.position,
+                to: l.position,
+                limit: if l.controller.mode() == ControlMode::Roundabout {
+                    ROUNDABOUT_SPEED_LIMIT
+                } else {
+                    CAUTION_SPEED_LIMIT
+                },
+            });
+        limits
+            .chain(works)
+            .chain(schools)
+            .chain(booths)
+            .chain(flashing)
+            .collect()
+    }
+
+    /// Takes the toll from the driver at each booth once they have stood
+    /// there for the service time, then calls up the next one, and adds up
+    /// the time drivers spend waiting in line for the booths.
+    fn update_tolls(&mut self, dt: f32) {
+        let (toll, service) = (self.tolls.toll, self.tolls.service_seconds);
+        for booth in self.toll_booths.iter_mut() {
+            let cell = booth.cell;
+            booth.serving = match booth.serving {
+                Some((id, left)) if left > dt && self.vehicles.get(id).is_some() => {
+                    Some((id, left - dt))
+                }
+                Some((id, _)) => {
+                    if let Some(vehicle) = self.vehicles.get_mut(id) {
+                        vehicle.paid_toll_at = Some(cell);
+                        self.stats.record_toll(toll);
+                    }
+                    None
+                }
+                None => self
+                    .vehicles
+                    .iter()
+                    .find(|v| {
+                        v.stopped
+                            && v.owes_toll(cell)
+                            && (cell as f32 - BOOTH_REACH..=cell as f32).contains(&v.position)
+                    })
+                    .map(|v| (v.id, service)),
+            };
+        }
+        let waiting = self
+            .vehicles
+            .iter()
+            .filter(|v| v.stopped && self.queued_for_toll(v))
+            .count();
+        self.stats.toll_delay += waiting as f32 * dt;
+    }
+
+    /// Whether the next thing `vehicle` has to stop at is a toll booth it
+    /// has yet to pay at, rather than a signal.
+    fn queued_for_toll(&self, vehicle: &Vehicle) -> bool {
+        let Some(booth) = self
+            .road
+            .toll_booths
+            .iter()
+            .copied()
+            .find(|&cell| vehicle.position <= cell as f32 && vehicle.owes_toll(cell))
+        else {
+            return false;
+        };
+        self.road
+            .next_signal(vehicle.position)
+            .is_none_or(|index| self.lights[index].position > booth)
+    }
+
+    /// Counts down each stretch of road works and reopens it when done.
+    fn update_road_works(&mut self, dt: f32) {
+        for works in self.road_works.iter_mut() {
+            works.time_left -= dt;
+            if works.time_left <= 0.0 {
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("Road works on cells {}-{} finished", works.from, works.to),
+                );
+            }
+        }
+        self.road_works.retain(|w| w.time_left > 0.0);
+    }
+
+    /// Starts the cleanup once a fire truck has pulled up behind the debris,
+    /// and takes the debris off the road when the crew is done.
+    fn update_debris(&mut self, dt: f32) {
+        let mut released = Vec::new();
+        for debris in self.debris.iter_mut() {
+            let Some(truck) = debris
+                .fire_truck_id
+                .and_then(|id| self.vehicles.get_mut(id))
+            else {
+                continue;
+            };
+            match debris.cleanup_left.as_mut() {
+                None => {
+                    let gap = debris.cell as f32 - truck.position;
+                    if truck.stopped && gap < 2.0 * truck.kind.clearance() {
+                        truck.held = true;
+                        debris.cleanup_left = Some(DEBRIS_CLEANUP_SECONDS);
+                    }
+                }
+                Some(left) => {
+                    *left -= dt;
+                    if *left <= 0.0 {
+                        released.push(truck.id);
+                        self.events.push(
+                            self.stats.elapsed,
+                            format!("Debris on cell {} cleared", debris.cell),
+                        );
+                    }
+                }
+            }
+        }
+        self.debris
+            .retain(|d| d.cleanup_left.is_none_or(|left| left > 0.0));
+        for id in released {
+            self.release(id);
+        }
+    }
+
+    /// On ice, vehicles braking for a stop line they couldn't stop short of
+    /// with locked wheels may lose grip and slide on into the intersection.
+    fn roll_skids(&mut self, dt: f32) {
+        let ice = self.weather.ice;
+        if ice <= 0.0 {
+            return;
+        }
+        let grip = self.weather.grip();
+        let braking: Vec<(u32, usize)> = self
+            .vehicles
+            .iter()
+            .filter(|v| {
+                v.lane == Lane::Road
+                    && !v.kind.has_siren()
+                    && !v.skidding
+                    && !v.stopped
+                    && !v.broken_down
+            })
+            .filter_map(|v| {
+                let signal = self.road.next_signal(v.position)?;
+                let light = &self.lights[signal];
+                let distance = light.position as f32 - v.position;
+                let must_stop = match light.state {
+                    TrafficLightState::Green => false,
+                    TrafficLightState::Red => true,
+                    TrafficLightState::Yellow => {
+                        v.should_stop_for_yellow(light.position as f32, grip)
+                    }
+                };
+                (must_stop && distance < v.braking_distance(grip * SKID_BRAKING))
+                    .then_some((v.id, signal))
+            })
+            .collect();
+        for (id, signal) in braking {
+            if !self.rng.chance(SKID_CHANCE_PER_SECOND * ice * dt) {
+                continue;
+            }
+            if let Some(vehicle) = self.vehicles.get_mut(id) {
+                vehicle.skidding = true;
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("#{} skidded past light {} on the ice", id, signal + 1),
+                );
+            }
+        }
+    }
+
+    fn break_down(&mut self, id: u32, cause: IncidentCause) {
+        if let Some(vehicle) = self.vehicles.get_mut(id) {
+            vehicle.broken_down = true;
+            self.incidents.push(Incident::new(id, cause));
+        }
+    }
+
+    /// Sends what each scene still needs: a tow truck to every incident, an
+    /// ambulance to every crash and a fire truck to every piece of debris.
+    fn dispatch_responders(&mut self) {
+        for index in 0..self.incidents.len() {
+            let incident = &self.incidents[index];
+            let Some(scene) = self.vehicles.get(incident.vehicle_id).map(|v| v.position) else {
+                continue;
+            };
+            let vehicle_id = incident.vehicle_id;
+            if incident.tow_truck_id.is_none() {
+                self.incidents[index].tow_truck_id = self.dispatch(VehicleType::TowTruck, scene);
+            }
+            let incident = &self.incidents[index];
+            if incident.cause == IncidentCause::Crash && incident.ambulance_id.is_none() {
+                let ambulance = self.dispatch(VehicleType::Ambulance, scene);
+                if let Some(id) = ambulance {
+                    self.events.push(
+                        self.stats.elapsed,
+                        format!("Ambulance #{} sent to #{}", id, vehicle_id),
+                    );
+                }
+                self.incidents[index].ambulance_id = ambulance;
+            }
+        }
+        for index in 0..self.debris.len() {
+            if self.debris[index].fire_truck_id.is_some() {
+                continue;
+            }
+            let cell = self.debris[index].cell;
+            let truck = self.dispatch(VehicleType::FireTruck, cell as f32);
+            if let Some(id) = truck {
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("Fire truck #{} sent to debris on cell {}", id, cell),
+                );
+            }
+            self.debris[index].fire_truck_id = truck;
+        }
+    }
+
+    /// Brings `kind` in from the closest spawn point behind `scene`, waved
+    /// through traffic on its way. Returns its id, or `None` while the
+    /// entry is blocked.
+    fn dispatch(&mut self, kind: VehicleType, scene: f32) -> Option<u32> {
+        let upstream = |cells: &mut dyn Iterator<Item = usize>| {
+            cells.filter(|&cell| cell as f32 <= scene).max()
+        };
+        let base = kind
+            .base()
+            .and_then(|base| upstream(&mut self.road.facilities_of(base)));
+        let cell = base
+            .or_else(|| upstream(&mut self.road.spawn_points.iter().copied()))
+            .unwrap_or(self.road.spawn_points[0]);
+
+        // Queued vehicles make way, so only moving traffic holds a responder back.
+        if !self.entry_clear(Lane::Road, cell, kind.length(), true) {
+            return None;
+        }
+        let id = self.add_vehicle(kind, Lane::Road, cell);
+        if let Some(vehicle) = self.vehicles.get_mut(id) {
+            vehicle.responding = true;
+        }
+        Some(id)
+    }
+
+    /// Moves every vehicle along its lane and takes off the ones that
+    /// reached the end of the road.
+    fn update_vehicles(&mut self, dt: f32) {
+        self.vehicles.sort_by_position();
+        let before: Vec<(u32, f32)> = self
+            .vehicles
+            .iter()
+            .filter(|v| v.lane == Lane::Road && !v.kind.is_service())
+            .map(|v| (v.id, v.position))
+            .collect();
+
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            self.update_vehicles_parallel(dt);
+        } else {
+            self.update_vehicles_in_order(dt);
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.update_vehicles_in_order(dt);
+
+        let length = self.road.length as f32;
+        self.finished.clear();
+        self.vehicles.remove_into(
+            |v| v.position >= length || v.destination.is_some_and(|cell| v.position >= cell as f32),
+            &mut self.finished,
+        );
+        for vehicle in &self.finished {
+            if let Some(cell) = vehicle.parking.filter(|_| vehicle.position < length) {
+                let capacity = self.parking.capacity;
+                match self.parking_lots.iter_mut().find(|lot| lot.cell == cell) {
+                    Some(lot) if lot.parked.len() < capacity => {
+                        let stay =
+                            self.parking.stay_seconds * (0.5 + self.rng.below(101) as f32 / 100.0);
+                        lot.parked.push(ParkedVehicle {
+                            kind: vehicle.kind,
+                            time_left: stay,
+                        });
+                        self.stats.parked += 1;
+                    }
+                    _ if vehicle.circles < MAX_CIRCLES => {
+                        self.stats.parking_full += 1;
+                        self.circling.push(Circling {
+                            kind: vehicle.kind,
+                            lot: cell,
+                            circles: vehicle.circles + 1,
+                        });
+                        self.events.push(
+                            self.stats.elapsed,
+                            format!(
+                                "#{} found the car park at {} full, circling",
+                                vehicle.id, cell
+                            ),
+                        );
+                        continue;
+                    }
+                    _ => {
+                        self.stats.parking_full += 1;
+                        self.events.push(
+                            self.stats.elapsed,
+                            format!("#{} gave up on the car park at {}", vehicle.id, cell),
+                        );
+                    }
+                }
+            }
+            self.stats.record_trip(vehicle);
+            if let Some(base) = vehicle.kind.base().filter(|_| vehicle.position < length) {
+                self.events.push(
+                    self.stats.elapsed,
+                    format!(
+                        "{} #{} back at the {}",
+                        vehicle.kind.name(),
+                        vehicle.id,
+                        base.name()
+                    ),
+                );
+            }
+        }
+        self.roll_crashes(&before);
+    }
+
+    /// Pulls over every civilian vehicle with an ambulance or fire truck
+    /// coming up behind it within the yield distance, and lets it go again
+    /// once the siren has passed.
+    fn update_yielding(&mut self) {
+        let distance = self.emergency.yield_distance;
+        let sirens: Vec<f32> = self
+            .vehicles
+            .iter()
+            .filter(|v| v.lane == Lane::Road && v.kind.has_siren() && !v.held)
+            .map(|v| v.position)
+            .collect();
+        let yielding: Vec<(u32, bool)> = self
+            .vehicles
+            .iter()
+            .map(|v| {
+                let behind = |&siren: &f32| siren < v.position && v.position - siren <= distance;
+                let pulls_over = v.lane == Lane::Road
+                    && !v.kind.is_service()
+                    && !v.broken_down
+                    && sirens.iter().any(behind);
+                (v.id, pulls_over)
+            })
+            .collect();
+        for (id, pulls_over) in yielding {
+            if let Some(vehicle) = self.vehicles.get_mut(id) {
+                vehicle.yielding = pulls_over;
+            }
+        }
+    }
+
+    /// Gives each road vehicle that crossed a stop line since `before` a
+    /// chance of crashing there, higher in bad weather and far higher for
+    /// running the red, which also counts as a violation at that light. A
+    /// crashed vehicle blocks the road like a breakdown until it is towed.
+    fn roll_crashes(&mut self, before: &[(u32, f32)]) {
+        let chance = CRASH_CHANCE_PER_CROSSING * self.weather.crash_multiplier();
+        let zones = self.speed_zones();
+        for &(id, from) in before {
+            let Some((to, speed, skidding)) = self
+                .vehicles
+                .get(id)
+                .map(|v| (v.position, v.speed, v.skidding))
+            else {
+                continue;
+            };
+            let Some(light) = self
+                .lights
+                .iter()
+                .position(|l| from < l.position as f32 && l.position as f32 <= to)
+            else {
+                continue;
+            };
+            let ran_red = self.lights[light].state == TrafficLightState::Red
+                && self.lights[light].flashing.is_none()
+                && !skidding;
+            if ran_red {
+                self.lights[light].violations += 1;
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("#{} ran the red at light {}", id, light + 1),
+                );
+            }
+            let stop_line = self.lights[light].position as f32;
+            let speeding = speed > limit_at(&zones, stop_line, f32::INFINITY) * SPEEDING_TOLERANCE;
+            if let Some(camera) = self.lights[light].camera.as_mut()
+                && (ran_red || speeding)
+            {
+                camera.red_light += ran_red as u32;
+                camera.speeding += speeding as u32;
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("Camera at light {} caught #{}", light + 1, id),
+                );
+            }
+            let multiplier = if ran_red {
+                RED_RUN_CRASH_MULTIPLIER
+            } else {
+                1.0
+            };
+            if !self.rng.chance(chance * multiplier) {
+                continue;
+            }
+            let weather = self.weather.dominant();
+            self.break_down(id, IncidentCause::Crash);
+            self.stats.record_crash(weather);
+            self.events.push(
+                self.stats.elapsed,
+                format!(
+                    "#{} crashed at light {} in {}",
+                    id,
+                    light + 1,
+                    weather.name()
+                ),
+            );
+        }
+    }
+
+    /// Runs the car-following model front to back so every vehicle reacts to the
+    /// already-updated position of the one ahead of it.
+    fn update_vehicles_in_order(&mut self, dt: f32) {
+        let (road, lights, debris) = (&self.road, &self.lights, &self.debris);
+        let barriers = &self.barriers();
+        let (weather, zones) = (self.weather.conditions(), &self.speed_zones());
+        self.vehicles.move_in_order(|ahead, vehicle| {
+            let conditions = conditions_at(weather, zones, lights, vehicle);
+            let obstacle = obstacle_for(road, lights, debris, barriers, conditions, ahead, vehicle);
+            vehicle.update(dt, obstacle, conditions);
+        });
+    }
+
+    /// Works out every vehicle's obstacle from where the traffic ahead was
+    /// at the start of the step, then moves them all at once. Vehicles only
+    /// move forwards, so the gaps they see are never wider than the real ones.
+    #[cfg(feature = "parallel")]
+    fn update_vehicles_parallel(&mut self, dt: f32) {
+        let (road, lights, debris) = (&self.road, &self.lights, &self.debris);
+        let barriers = &self.barriers();
+        let (weather, zones) = (self.weather.conditions(), &self.speed_zones());
+        self.vehicles.move_in_parallel(
+            |ahead, vehicle| {
+                let conditions = conditions_at(weather, zones, lights, vehicle);
+                obstacle_for(road, lights, debris, barriers, conditions, ahead, vehicle)
+            },
+            |vehicle, obstacle| {
+                vehicle.update(dt, obstacle, conditions_at(weather, zones, lights, vehicle))
+            },
+        );
+    }
+
+    /// Starts the clearance timer once a tow truck has pulled up behind its
+    /// incident, and removes the broken-down vehicle when the timer runs out.
+    /// A police car that stops right behind an incident sits with it until
+    /// then.
+    fn update_incidents(&mut self, dt: f32) {
+        let mut cleared = Vec::new();
+
+        for incident in self.incidents.iter_mut() {
+            if incident.police_id.is_some() {
+                continue;
+            }
+            let Some(broken_rear) = self.vehicles.get(incident.vehicle_id).map(|v| v.rear()) else {
+                continue;
+            };
+            let police = self.vehicles.iter().find(|v| {
+                v.kind.attends_incidents()
+                    && v.lane == Lane::Road
+                    && v.stopped
+                    && !v.held
+                    && (0.0..2.0 * MIN_GAP).contains(&(broken_rear - v.position))
+            });
+            if let Some(id) = police.map(|v| v.id) {
+                incident.police_id = Some(id);
+                if let Some(police) = self.vehicles.get_mut(id) {
+                    police.held = true;
+                }
+                self.events.push(
+                    self.stats.elapsed,
+                    format!("Police #{} attending #{}", id, incident.vehicle_id),
+                );
+            }
+        }
+
+        for incident in self.incidents.iter_mut() {
+            let Some(broken_rear) = self.vehicles.get(incident.vehicle_id).map(|v| v.rear()) else {
+                continue;
+            };
+            // Crews already at work extend the scene back towards later arrivals.
+            let scene_rear = [incident.tow_truck_id, incident.ambulance_id]
+                .into_iter()
+                .flatten()
+                .filter_map(|id| self.vehicles.get(id))
+                .filter(|v| v.held)
+                .map(|v| v.rear())
+                .fold(broken_rear, f32::min);
+
+            if let Some(ambulance) = incident
+                .ambulance_id
+                .and_then(|id| self.vehicles.get_mut(id))
+            {
+                match incident.treatment_left.as_mut() {
+                    None => {
+                        if ambulance.stopped && scene_rear - ambulance.position < 2.0 * MIN_GAP {
+                            ambulance.held = true;
+                            incident.treatment_left = Some(TREATMENT_SECONDS);
+                        }
+                    }
+                    Some(left) if *left > 0.0 => {
+                        *left = (*left - dt).max(0.0);
+                        if *left == 0.0 {
+                            ambulance.held = false;
+                            ambulance.responding = false;
+                            ambulance.destination = home_cell(&self.road, ambulance);
+                            self.events.push(
+                                self.stats.elapsed,
+                                format!(
+                                    "Ambulance #{} finished treating #{}",
+                                    ambulance.id, incident.vehicle_id
+                                ),
+                            );
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            let Some(truck) = incident
+                .tow_truck_id
+                .and_then(|id| self.vehicles.get_mut(id))
+            else {
+                continue;
+            };
+            // The truck waits at the scene until any casualties are treated.
+            if !truck.held && truck.stopped && scene_rear - truck.position < 2.0 * MIN_GAP {
+                truck.held = true;
+            }
+            match incident.clearance_left.as_mut() {
+                None => {
+                    if truck.held && incident.treated() {
+                        incident.clearance_left = Some(CLEARANCE_SECONDS);
+                    }
+                }
+                Some(left) => {
+                    *left -= dt;
+                    if *left <= 0.0 {
+                        truck.held = false;
+                        truck.responding = false;
+                        cleared.push(incident.vehicle_id);
+                    }
+                }
+            }
+        }
+
+        for &id in &cleared {
+            self.events
+                .push(self.stats.elapsed, format!("#{} towed away", id));
+        }
+        let released: Vec<u32> = self
+            .incidents
+            .iter()
+            .filter(|i| cleared.contains(&i.vehicle_id))
+            .filter_map(|i| i.police_id)
+            .collect();
+        for id in released {
+            if let Some(police) = self.vehicles.get_mut(id) {
+                police.held = false;
+            }
+        }
+        self.vehicles.retain(|v| !cleared.contains(&v.id));
+        self.incidents.retain(|i| !cleared.contains(&i.vehicle_id));
+    }
+}
+
+/// The weather's road conditions for `vehicle`, with the lowest speed limit
+/// of the zones it is driving through. Bad weather holds bicycles to a share
+/// of their top speed on top of what it does to everyone. Speed zones only
+/// slow the road lane, and speeders keep a quarter over them unless a camera
+/// puts them off.
+fn conditions_at(
+    weather: RoadConditions,
+    zones: &[SpeedZone],
+    lights: &[TrafficLight],
+    vehicle: &Vehicle,
+) -> RoadConditions {
+    let weather = if vehicle.kind == VehicleType::Bicycle {
+        RoadConditions {
+            speed_limit: weather
+                .speed_limit
+                .min(VehicleType::Bicycle.max_speed() * weather.cycling),
+            ..weather
+        }
+    } else {
+        weather
+    };
+    if vehicle.lane != Lane::Road {
+        return weather;
+    }
+    let mut speed_limit = limit_at(zones, vehicle.position, weather.speed_limit);
+    let watching = lights.iter().any(|light| {
+        light.camera.is_some()
+            && (0.0..CAMERA_RANGE).contains(&(light.position as f32 - vehicle.position))
+    });
+    if vehicle.speeds && vehicle.misbehaves(watching) {
+        speed_limit *= SPEEDING_FACTOR;
+    }
+    RoadConditions {
+        speed_limit,
+        ..weather
+    }
+}
+
+/// The lowest speed limit at `position` of `zones` and `limit`.
+fn limit_at(zones: &[SpeedZone], position: f32, limit: f32) -> f32 {
+    zones
+        .iter()
+        .filter(|z| z.covers(position))
+        .map(|z| z.limit)
+        .fold(limit, f32::min)
+}
+
+/// The first facility of `vehicle`'s base kind still ahead of it, where it
+/// heads once stood down. Without one it drives on off the road.
+fn home_cell(road: &Road, vehicle: &Vehicle) -> Option<usize> {
+    let base = vehicle.kind.base()?;
+    road.facilities_of(base)
+        .find(|&cell| cell as f32 >= vehicle.position)
+}
+
+/// A line across the road that traffic has to stop at: the barricades of a
+/// road closure, a drawbridge about to lift, or a toll booth.
+#[derive(Debug, Clone, Copy)]
+struct Barrier {
+    cell: f32,
+    /// Drivers too close to stop in comfort carry on, as for a yellow.
+    soft: bool,
+    /// A toll booth, which only stops drivers yet to pay there.
+    toll: bool,
+}
+
+/// A stretch of the road lane with a lower speed limit.
+#[derive(Debug, Clone, Copy)]
+struct SpeedZone {
+    /// First and last cell of the stretch.
+    from: usize,
+    to: usize,
+    limit: f32,
+}
+
+impl SpeedZone {
+    /// Whether a vehicle with its front at `position` is in the zone, or
+    /// close enough to be slowing for it.
+    fn covers(&self, position: f32) -> bool {
+        (self.from as f32 - 1.0..self.to as f32 + 1.0).contains(&position)
+    }
+}
+
+/// The nearest thing `vehicle` has to stop or slow down for: a red (or
+/// unsafe yellow) light, debris in the road lane, a barrier across the
+/// road, or the vehicle it is following. `ahead` holds the vehicles in
+/// front of it, furthest first. A vehicle skidding on ice can't stop for
+/// lights, ambulances and fire trucks drive through them, and nothing
+/// further away than the visibility is seen at all.
+fn obstacle_for(
+    road: &Road,
+    lights: &[TrafficLight],
+    debris: &[Debris],
+    barriers: &[Barrier],
+    conditions: RoadConditions,
+    ahead: Ahead,
+    vehicle: &Vehicle,
+) -> Option<Obstacle> {
+    let mut obstacle = None;
+    if !vehicle.skidding
+        && !vehicle.kind.has_siren()
+        && let Some(signal) = road.next_signal(vehicle.position)
+    {
+        let light = &lights[signal];
+        let stop_line = light.position as f32;
+        let state = match vehicle.lane {
+            Lane::Road => light.state,
+            Lane::Bike => light.bike_phase(),
+        };
+        // Red-light runners carry on through a yellow, and treat a red that
+        // has only just come on like a yellow, unless a camera puts them off.
+        let runs_lights = vehicle.runs_lights && vehicle.misbehaves(light.camera.is_some());
+        let must_stop = match (light.flashing, state) {
+            (Some(Flashing::Yellow), _) => false,
+            (Some(Flashing::Red), _) => vehicle.cleared_flash != Some(signal),
+            (None, TrafficLightState::Green) => false,
+            (None, TrafficLightState::Red) if vehicle.turning_on_red => false,
+            (None, TrafficLightState::Red) if runs_lights && light.elapsed() < LATE_RED_SECONDS => {
+                vehicle.should_stop_for_yellow(stop_line, conditions.grip)
+            }
+            (None, TrafficLightState::Red) => true,
+            (None, TrafficLightState::Yellow) => {
+                !runs_lights && vehicle.should_stop_for_yellow(stop_line, conditions.grip)
+            }
+        };
+        if must_stop {
+            obstacle = Some(Obstacle {
+                gap: stop_line - vehicle.position,
+                speed: 0.0,
+            });
+        }
+    }
+
+    if vehicle.lane == Lane::Road
+        && let Some(cell) = debris
+            .iter()
+            .map(|d| d.cell as f32)
+            .filter(|&cell| cell >= vehicle.position)
+            .min_by(f32::total_cmp)
+    {
+        let gap = cell - vehicle.position;
+        if obstacle.is_none_or(|o: Obstacle| gap < o.gap) {
+            obstacle = Some(Obstacle { gap, speed: 0.0 });
+        }
+    }
+
+    if let Some(barricade) = barriers
+        .iter()
+        .filter(|b| !b.soft || vehicle.should_stop_for_yellow(b.cell, conditions.grip))
+        .filter(|b| !b.toll || vehicle.owes_toll(b.cell as usize))
+        .map(|b| b.cell)
+        .filter(|&cell| cell >= vehicle.position)
+        .min_by(f32::total_cmp)
+    {
+        let gap = barricade - vehicle.position;
+        if obstacle.is_none_or(|o: Obstacle| gap < o.gap) {
+            obstacle = Some(Obstacle { gap, speed: 0.0 });
+        }
+    }
+
+    if let Some((cell, _)) = vehicle.curb_stop
+        && cell as f32 >= vehicle.position
+    {
+        let gap = cell as f32 - vehicle.position;
+        if obstacle.is_none_or(|o: Obstacle| gap < o.gap) {
+            obstacle = Some(Obstacle { gap, speed: 0.0 });
+        }
+    }
+
+    // Motorcycles filter past queued cars rather than following them, sirens
+    // pass drivers pulled over for them, and responders are waved through
+    // to the scene, pulling up behind the incident and any crews already at
+    // work there.
+    let leader = ahead.iter().rev().find(|other| {
+        other.lane == vehicle.lane
+            && !(vehicle.kind.can_filter() && other.kind == VehicleType::Car && other.stopped)
+            && !(vehicle.kind.has_siren() && other.yielding)
+            && (!vehicle.responding || other.broken_down || other.held)
+    });
+    if let Some(leader) = leader {
+        let gap = leader.rear() - vehicle.position;
+        if obstacle.is_none_or(|o: Obstacle| gap < o.gap) {
+            obstacle = Some(Obstacle {
+                gap,
+                speed: leader.speed,
+            });
+        }
+    }
+    obstacle.filter(|o| o.gap <= conditions.visibility)
+}
+
+/// Moves settings kept by light index to where `moved` says each light went,
+/// dropping those whose light is gone.
+fn remap_lights<T>(entries: &mut Vec<(usize, T)>, moved: &[Option<usize>]) {
+    *entries = std::mem::take(entries)
+        .into_iter()
+        .filter_map(|(index, entry)| Some((moved.get(index).copied().flatten()?, entry)))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.1;
+
+    /// A simulation of `map` with nothing happening on its own: no random
+    /// arrivals, breakdowns, failures, weather changes or red-light runners.
+    fn quiet(map: &str) -> Simulation {
+        let mut sim = Simulation::new(Road::parse(map).unwrap(), Rng::seeded(7));
+        sim.spawn_rate = 0.0;
+        sim.breakdown_rate = 0.0;
+        sim.malfunctions.per_minute = 0.0;
+        sim.weather.changes = false;
+        sim.drivers.red_light_runners = 0.0;
+        sim
+    }
+
+    /// Road-lane vehicles front first, as (front, rear).
+    fn road_traffic(sim: &Simulation) -> Vec<(f32, f32)> {
+        let mut traffic: Vec<(f32, f32)> = sim
+            .vehicles
+            .iter()
+            .filter(|v| v.lane == Lane::Road)
+            .map(|v| (v.position, v.rear()))
+            .collect();
+        traffic.sort_by(|a, b| b.0.total_cmp(&a.0));
+        traffic
+    }
+
+    #[test]
+    fn queue_stops_at_a_red_without_overlapping() {
+        let mut sim = quiet("S===========|===");
+        sim.apply(SimEvent::ForceLight(0, TrafficLightState::Red))
+            .unwrap();
+        sim.apply(SimEvent::Operate(0)).unwrap();
+        for step in 0..600 {
+            if step % 20 == 0 && step < 200 {
+                // The spawn point may still be occupied; that's fine.
+                let _ = sim.apply(SimEvent::Spawn(VehicleType::Car, Some(0)));
+            }
+            sim.update(DT);
+            let traffic = road_traffic(&sim);
+            for pair in traffic.windows(2) {
+                let ((_, ahead_rear), (behind_front, _)) = (pair[0], pair[1]);
+                assert!(
+                    behind_front <= ahead_rear + 1e-4,
+                    "overlap at step {}: {:?}",
+                    step,
+                    traffic
+                );
+            }
+            assert!(traffic.iter().all(|&(front, _)| front <= 12.0 + 1e-4));
+        }
+        let queued = sim.vehicles.iter().filter(|v| v.stopped).count();
+        assert!(queued >= 4, "only {} queued", queued);
+        assert_eq!(sim.stats.completed_trips, 0);
+    }
+
+    #[test]
+    fn queue_drains_on_green() {
+        let mut sim = quiet("S===========|===");
+        sim.apply(SimEvent::ForceLight(0, TrafficLightState::Red))
+            .unwrap();
+        sim.apply(SimEvent::Operate(0)).unwrap();
+        for step in 0..300 {
+            if step % 20 == 0 && step < 100 {
+                let _ = sim.apply(SimEvent::Spawn(VehicleType::Car, Some(0)));
+            }
+            sim.update(DT);
+        }
+        let queued = sim.vehicles.len();
+        sim.apply(SimEvent::ForceLight(0, TrafficLightState::Green))
+            .unwrap();
+        for _ in 0..300 {
+            sim.update(DT);
+        }
+        assert_eq!(sim.stats.completed_trips as usize, queued);
+    }
+
+    #[test]
+    fn removing_a_signal_moves_settings_to_the_lights_that_stay() {
+        let mut sim = quiet("S====|====|====|===");
+        let mut signals = sim.signals.clone();
+        signals.lights = vec![(0, ControlMode::Manual), (1, ControlMode::Actuated)];
+        sim.configure_signals(signals);
+        sim.loops.lights = vec![(0, vec![1]), (1, vec![3])];
+        sim.night_flash.minor = vec![0, 1, 2];
+        sim.lights[1].camera = Some(EnforcementCamera::default());
+        sim.special_event.venue = Some(17);
+
+        // The editor takes out the first and last signals and shortens the
+        // road, leaving the middle one as light 1.
+        let moved = sim.replace_road(Road::parse("S=========|====").unwrap());
+        assert_eq!(moved, [None, Some(0), None]);
+        assert_eq!(sim.lights.len(), 1);
+        assert_eq!(sim.signals.lights, [(0, ControlMode::Actuated)]);
+        assert_eq!(sim.lights[0].controller.mode(), ControlMode::Actuated);
+        assert_eq!(sim.loops.lights, [(0, vec![3])]);
+        assert_eq!(sim.night_flash.minor, [0]);
+        assert!(sim.lights[0].camera.is_some());
+        assert_eq!(sim.special_event.venue, None);
+    }
+
+    #[test]
+    fn loops_call_when_a_vehicle_is_over_them() {
+        let mut sim = quiet("S=========|===");
+        sim.loops = DetectorConfig {
+            setbacks: vec![0, 4],
+            lights: Vec::new(),
+        };
+        assert_eq!(sim.loop_states(0), [(0, false), (4, false)]);
+        assert!(!sim.detectors(0).calls);
+
+        // Signal at cell 10: the stop-line loop covers cell 9, the other 5.
+        let mut car = Vehicle::new(1, VehicleType::Car, Lane::Road);
+        car.position = 5.5;
+        sim.vehicles.insert(car);
+        assert_eq!(sim.loop_states(0), [(0, false), (4, true)]);
+        assert!(sim.detectors(0).calls);
+
+        // A bicycle doesn't set off the road's loops.
+        sim.vehicles.remove(1);
+        let mut bike = Vehicle::new(2, VehicleType::Bicycle, Lane::Bike);
+        bike.position = 9.5;
+        sim.vehicles.insert(bike);
+        assert!(!sim.detectors(0).calls);
+    }
+
+    #[test]
+    fn loops_beyond_the_start_of_the_road_are_left_out() {
+        let mut sim = quiet("S==|=====");
+        sim.loops.lights = vec![(0, vec![0, 2, 8])];
+        let setbacks: Vec<usize> = sim.loop_states(0).iter().map(|&(s, _)| s).collect();
+        assert_eq!(setbacks, [0, 2]);
+    }
+
+    #[test]
+    fn closures_hold_traffic_and_spawns_until_they_reopen() {
+        let mut sim = quiet("S===============");
+        // The clock starts at 08:00 and runs a minute a second.
+        let (from, to, windows) =
+            crate::systems::closure::parse_stretch("closures", "parade", "8-12, 08:00-08:20")
+                .unwrap();
+        sim.closures = vec![Closure {
+            name: "parade".to_string(),
+            from,
+            to,
+            windows,
+            active: false,
+        }];
+        sim.apply(SimEvent::Spawn(VehicleType::Car, Some(0)))
+            .unwrap();
+        for _ in 0..150 {
+            sim.update(DT);
+        }
+        assert!(sim.closures[0].active);
+        assert!(!sim.spawn_open(0));
+        let (front, _) = road_traffic(&sim)[0];
+        assert!(front <= 8.0, "car at {} ran into the barricades", front);
+
+        for _ in 0..200 {
+            sim.update(DT);
+        }
+        assert!(!sim.closures[0].active);
+        assert!(sim.spawn_open(0));
+        assert_eq!(sim.stats.completed_trips, 1);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt::{self, Write as _};
+use std::fs;
+
+use crate::systems::simulation::Simulation;
+
+/// Writes the current simulation state to `path` as JSON.
+pub fn save(sim: &Simulation, path: &str) -> Result<(), String> {
+    fs::write(path, to_json(sim)).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// The road, signals, vehicles, incidents and debris as a JSON document, for
+/// inspecting a run outside the simulator.
+pub fn to_json(sim: &Simulation) -> String {
+    let mut out = String::new();
+    write_json(&mut out, sim).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_json(out: &mut String, sim: &Simulation) -> fmt::Result {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"time\": {:.2},", sim.stats.elapsed)?;
+    writeln!(out, "  \"tick\": {},", sim.tick)?;
+    writeln!(out, "  \"spawned\": {},", sim.spawned)?;
+    writeln!(
+        out,
+        "  \"map\": \"{}\",",
+        sim.road.to_map().trim_end().replace('\n', "\\n")
+    )?;
+
+    writeln!(out, "  \"lights\": [")?;
+    for (index, light) in sim.lights.iter().enumerate() {
+        writeln!(
+            out,
+            "    {{\"position\": {}, \"state\": \"{}\", \"remaining\": {}, \"violations\": {}, \"camera\": {}}}{}",
+            light.position,
+            light.state.name(),
+            light.remaining(),
+            light.violations,
+            light.camera.map_or("null".to_string(), |camera| format!(
+                "{{\"red_light\": {}, \"speeding\": {}}}",
+                camera.red_light, camera.speeding
+            )),
+            separator(index, sim.lights.len())
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"vehicles\": [")?;
+    for (index, vehicle) in sim.vehicles.iter().enumerate() {
+        writeln!(
+            out,
+            "    {{\"id\": {}, \"kind\": \"{}\", \"lane\": \"{}\", \"position\": {:.2}, \"speed\": {:.2}, \"state\": \"{}\", \"waited\": {:.2}, \"origin\": {}}}{}",
+            vehicle.id,
+            vehicle.kind.key(),
+            vehicle.lane.key(),
+            vehicle.position,
+            vehicle.speed,
+            vehicle.state_label(),
+            vehicle.waited_time,
+            vehicle.origin,
+            separator(index, sim.vehicles.len())
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"incidents\": [")?;
+    for (index, incident) in sim.incidents.iter().enumerate() {
+        let truck = incident
+            .tow_truck_id
+            .map_or("null".to_string(), |id| id.to_string());
+        let ambulance = incident
+            .ambulance_id
+            .map_or("null".to_string(), |id| id.to_string());
+        writeln!(
+            out,
+            "    {{\"vehicle_id\": {}, \"cause\": \"{}\", \"tow_truck_id\": {}, \"ambulance_id\": {}}}{}",
+            incident.vehicle_id,
+            incident.cause.key(),
+            truck,
+            ambulance,
+            separator(index, sim.incidents.len())
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"debris\": [")?;
+    for (index, debris) in sim.debris.iter().enumerate() {
+        let truck = debris
+            .fire_truck_id
+            .map_or("null".to_string(), |id| id.to_string());
+        let cleanup = debris
+            .cleanup_left
+            .map_or("null".to_string(), |left| format!("{:.1}", left));
+        writeln!(
+            out,
+            "    {{\"cell\": {}, \"fire_truck_id\": {}, \"cleanup_left\": {}}}{}",
+            debris.cell,
+            truck,
+            cleanup,
+            separator(index, sim.debris.len())
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"road_works\": [")?;
+    for (index, works) in sim.road_works.iter().enumerate() {
+        writeln!(
+            out,
+            "    {{\"from\": {}, \"to\": {}, \"time_left\": {:.1}}}{}",
+            works.from,
+            works.to,
+            works.time_left,
+            separator(index, sim.road_works.len())
+        )?;
+    }
+    writeln!(out, "  ]")?;
+    writeln!(out, "}}")
+}
+
+fn separator(index: usize, len: usize) -> &'static str {
+    if index + 1 < len { "," } else { "" }
+}
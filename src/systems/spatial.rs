@@ -0,0 +1,122 @@
+use crate::systems::vehicle::{Lane, Vehicle};
+
+/// Road cells covered by one bucket.
+const BUCKET_CELLS: usize = 4;
+
+/// Vehicles bucketed by lane and stretch of road, so a check at one spot of
+/// a long, busy road only looks at the vehicles near it.
+///
+/// Buckets hold `VehiclePool` slots. Vehicles are added and removed one at
+/// a time as they come and go; after traffic has moved, the pool rebuilds
+/// the whole index.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    road: Vec<Vec<usize>>,
+    bike: Vec<Vec<usize>>,
+}
+
+impl SpatialIndex {
+    pub fn rebuild<'a>(&mut self, vehicles: impl Iterator<Item = (usize, &'a Vehicle)>) {
+        // Emptied rather than dropped, so the buckets keep their allocations.
+        for bucket in self.road.iter_mut().chain(self.bike.iter_mut()) {
+            bucket.clear();
+        }
+        for (slot, vehicle) in vehicles {
+            self.insert(slot, vehicle);
+        }
+    }
+
+    pub fn insert(&mut self, slot: usize, vehicle: &Vehicle) {
+        let buckets = self.lane_mut(vehicle.lane);
+        let bucket = bucket_of(vehicle.position);
+        if buckets.len() <= bucket {
+            buckets.resize_with(bucket + 1, Vec::new);
+        }
+        buckets[bucket].push(slot);
+    }
+
+    /// Takes out a vehicle added with [`SpatialIndex::insert`], which must
+    /// not have moved since.
+    pub fn remove(&mut self, slot: usize, vehicle: &Vehicle) {
+        let bucket = bucket_of(vehicle.position);
+        if let Some(bucket) = self.lane_mut(vehicle.lane).get_mut(bucket)
+            && let Some(index) = bucket.iter().position(|&s| s == slot)
+        {
+            bucket.swap_remove(index);
+        }
+    }
+
+    /// Slots of the vehicles in `lane` whose front is somewhere from `from`
+    /// to `to`, give or take a bucket. Callers check the exact condition
+    /// themselves.
+    pub fn near(&self, lane: Lane, from: f32, to: f32) -> impl Iterator<Item = usize> + '_ {
+        let buckets = match lane {
+            Lane::Road => &self.road,
+            Lane::Bike => &self.bike,
+        };
+        let first = bucket_of(from).min(buckets.len());
+        let last = (bucket_of(to) + 1).clamp(first, buckets.len());
+        buckets[first..last].iter().flatten().copied()
+    }
+
+    fn lane_mut(&mut self, lane: Lane) -> &mut Vec<Vec<usize>> {
+        match lane {
+            Lane::Road => &mut self.road,
+            Lane::Bike => &mut self.bike,
+        }
+    }
+}
+
+fn bucket_of(position: f32) -> usize {
+    position.max(0.0) as usize / BUCKET_CELLS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::vehicle::VehicleType;
+
+    fn vehicle(lane: Lane, position: f32) -> Vehicle {
+        let mut vehicle = Vehicle::new(0, VehicleType::Car, lane);
+        vehicle.position = position;
+        vehicle
+    }
+
+    fn near(index: &SpatialIndex, lane: Lane, from: f32, to: f32) -> Vec<usize> {
+        let mut slots: Vec<usize> = index.near(lane, from, to).collect();
+        slots.sort();
+        slots
+    }
+
+    #[test]
+    fn finds_vehicles_by_lane_and_stretch() {
+        let mut index = SpatialIndex::default();
+        index.insert(0, &vehicle(Lane::Road, 1.0));
+        index.insert(1, &vehicle(Lane::Road, 9.5));
+        index.insert(2, &vehicle(Lane::Bike, 9.0));
+        index.insert(3, &vehicle(Lane::Road, 30.0));
+
+        assert_eq!(near(&index, Lane::Road, 8.0, 11.0), [1]);
+        assert_eq!(near(&index, Lane::Bike, 8.0, 11.0), [2]);
+        assert_eq!(near(&index, Lane::Road, 0.0, 40.0), [0, 1, 3]);
+        // Past the end of the buckets, or behind the road's start.
+        assert!(near(&index, Lane::Road, 50.0, 60.0).is_empty());
+        assert_eq!(near(&index, Lane::Road, -5.0, 0.5), [0]);
+    }
+
+    #[test]
+    fn remove_and_rebuild_follow_the_traffic() {
+        let mut index = SpatialIndex::default();
+        let (a, b) = (vehicle(Lane::Road, 2.0), vehicle(Lane::Road, 14.0));
+        index.insert(0, &a);
+        index.insert(1, &b);
+        index.remove(0, &a);
+        assert_eq!(near(&index, Lane::Road, 0.0, 20.0), [1]);
+
+        let moved = [vehicle(Lane::Road, 25.0), vehicle(Lane::Bike, 3.0)];
+        index.rebuild(moved.iter().enumerate());
+        assert!(near(&index, Lane::Road, 12.0, 16.0).is_empty());
+        assert_eq!(near(&index, Lane::Road, 24.0, 26.0), [0]);
+        assert_eq!(near(&index, Lane::Bike, 0.0, 4.0), [1]);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::systems::clock::{MINUTES_PER_DAY, SimClock, parse_time};
+use crate::systems::config::Config;
+
+/// Minutes before the start that the crowd arrives in.
+const ARRIVAL_MINUTES: f32 = 60.0;
+/// Minutes after the end that the crowd leaves in.
+const DEPARTURE_MINUTES: f32 = 45.0;
+
+/// Where a special event is in its day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    /// The crowd is driving in from the spawn points near the venue.
+    Arriving,
+    Underway,
+    /// The crowd is driving away from the venue.
+    Leaving,
+}
+
+/// The `[special_event]` section: a stadium or concert venue on the road
+/// that draws a surge of traffic towards it before the event and sends it
+/// off afterwards.
+#[derive(Debug, Clone)]
+pub struct SpecialEvent {
+    /// Cell the venue is at. Without one there is no event.
+    pub venue: Option<usize>,
+    /// Day of the run it happens on, counting from 1.
+    pub day: u32,
+    /// Start time in minutes past midnight, and how long it lasts.
+    pub start: f32,
+    pub minutes: f32,
+    /// How far upstream of the venue, in cells, spawn points feed the
+    /// arriving crowd.
+    pub radius: usize,
+    /// Spawn rate multiplier for the crowd, arriving and leaving.
+    pub impact: f32,
+}
+
+impl Default for SpecialEvent {
+    fn default() -> Self {
+        Self {
+            venue: None,
+            day: 1,
+            start: 19.0 * 60.0,
+            minutes: 120.0,
+            radius: 10,
+            impact: 3.0,
+        }
+    }
+}
+
+impl SpecialEvent {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut event = Self::default();
+        for (key, value) in config.section("special_event") {
+            match key.as_str() {
+                "venue" => {
+                    event.venue = Some(
+                        value
+                            .parse()
+                            .map_err(|_| "[special_event] venue must be a cell number")?,
+                    );
+                }
+                "day" => {
+                    event.day = value
+                        .parse()
+                        .ok()
+                        .filter(|&day| day >= 1)
+                        .ok_or("[special_event] day must be a day number from 1")?;
+                }
+                "start" => {
+                    event.start = parse_time(value)
+                        .ok_or("[special_event] start must be a time like 19:30")?;
+                }
+                "minutes" => {
+                    event.minutes = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[special_event] minutes must be a positive number")?;
+                }
+                "radius" => {
+                    event.radius = value
+                        .parse()
+                        .map_err(|_| "[special_event] radius must be a whole number of cells")?;
+                }
+                "impact" => {
+                    event.impact = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or("[special_event] impact must be a non-negative number")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [special_event]", other)),
+            }
+        }
+        Ok(event)
+    }
+
+    /// Where the event is at the clock's current time, if it is on at all.
+    pub fn phase(&self, clock: &SimClock) -> Option<EventPhase> {
+        self.venue?;
+        let start = (self.day - 1) as f32 * MINUTES_PER_DAY + self.start;
+        let end = start + self.minutes;
+        let now = clock.total_minutes();
+        if (start - ARRIVAL_MINUTES..start).contains(&now) {
+            Some(EventPhase::Arriving)
+        } else if (start..end).contains(&now) {
+            Some(EventPhase::Underway)
+        } else if (end..end + DEPARTURE_MINUTES).contains(&now) {
+            Some(EventPhase::Leaving)
+        } else {
+            None
+        }
+    }
+
+    /// Whether spawn point `cell` is close enough upstream of the venue to
+    /// feed the arriving crowd.
+    pub fn feeds(&self, cell: usize) -> bool {
+        self.venue
+            .is_some_and(|venue| cell <= venue && venue - cell <= self.radius)
+    }
+}
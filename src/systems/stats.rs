@@ -0,0 +1,162 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use crate::systems::vehicle::Vehicle;
+use crate::systems::weather::WeatherType;
+
+/// Running totals for a simulation, counted from the moment it started.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationStats {
+    /// Simulated seconds so far.
+    pub elapsed: f32,
+    /// Vehicles that reached the end of the road. Tow trucks and emergency
+    /// vehicles don't count.
+    pub completed_trips: u32,
+    /// Seconds the completed trips spent standing still, in total.
+    pub total_wait: f32,
+    /// Crashes at the lights, by the weather at the time, in
+    /// [`WeatherType::ALL`] order.
+    pub crashes: [u32; 5],
+    /// Tolls taken at the booths, how many drivers paid them, and the
+    /// seconds drivers spent standing in line for the booths.
+    pub toll_revenue: f32,
+    pub tolls_paid: u32,
+    pub toll_delay: f32,
+    /// Vehicles that found a space in a car park, and the times a driver
+    /// arrived to find one full.
+    pub parked: u32,
+    pub parking_full: u32,
+    /// Right turns made on red, and the seconds of red the drivers would
+    /// otherwise have waited out at the line.
+    pub turns_on_red: u32,
+    pub red_saved: f32,
+    /// Times an operator took a light over by hand and handed it back, and
+    /// the seconds those periods lasted.
+    pub manual_periods: u32,
+    pub manual_seconds: f32,
+}
+
+impl SimulationStats {
+    pub fn record_trip(&mut self, vehicle: &Vehicle) {
+        if vehicle.kind.is_service() {
+            return;
+        }
+        self.completed_trips += 1;
+        self.total_wait += vehicle.waited_time;
+    }
+
+    pub fn record_crash(&mut self, weather: WeatherType) {
+        self.crashes[weather as usize] += 1;
+    }
+
+    pub fn record_toll(&mut self, toll: f32) {
+        self.toll_revenue += toll;
+        self.tolls_paid += 1;
+    }
+
+    pub fn record_turn_on_red(&mut self, red_left: u32) {
+        self.turns_on_red += 1;
+        self.red_saved += red_left as f32;
+    }
+
+    pub fn record_manual_control(&mut self, seconds: f32) {
+        self.manual_periods += 1;
+        self.manual_seconds += seconds;
+    }
+
+    /// Average seconds each paid toll cost in waiting, paying included.
+    pub fn average_toll_delay(&self) -> f32 {
+        if self.tolls_paid == 0 {
+            0.0
+        } else {
+            self.toll_delay / self.tolls_paid as f32
+        }
+    }
+
+    pub fn total_crashes(&self) -> u32 {
+        self.crashes.iter().sum()
+    }
+
+    /// Crashes so far and the weather they happened in, like
+    /// "3 (rain 2, snow 1)".
+    pub fn crash_summary(&self) -> String {
+        let by_weather: Vec<String> = WeatherType::ALL
+            .iter()
+            .zip(self.crashes)
+            .filter(|&(_, count)| count > 0)
+            .map(|(kind, count)| format!("{} {}", kind.name(), count))
+            .collect();
+        if by_weather.is_empty() {
+            return "0".to_string();
+        }
+        format!("{} ({})", self.total_crashes(), by_weather.join(", "))
+    }
+
+    /// Average seconds a completed trip spent waiting.
+    pub fn average_wait(&self) -> f32 {
+        if self.completed_trips == 0 {
+            0.0
+        } else {
+            self.total_wait / self.completed_trips as f32
+        }
+    }
+
+    /// Completed trips per simulated minute.
+    pub fn throughput(&self) -> f32 {
+        if self.elapsed <= 0.0 {
+            0.0
+        } else {
+            self.completed_trips as f32 * 60.0 / self.elapsed
+        }
+    }
+
+    /// Appends one row of readings to a CSV file, writing the header first
+    /// if the file is new or empty. `active` and `moving` count the vehicles
+    /// on the road; `queues` holds the road queue at each light.
+    pub fn export_csv(
+        &self,
+        path: &str,
+        active: usize,
+        moving: usize,
+        queues: &[usize],
+    ) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let empty = file.metadata().map_or(true, |meta| meta.len() == 0);
+        let mut text = String::new();
+        if empty {
+            text.push_str("time,active_vehicles,avg_wait,throughput,efficiency");
+            for index in 0..queues.len() {
+                text.push_str(&format!(",queue_light_{}", index + 1));
+            }
+            text.push('\n');
+        }
+        text.push_str(&format!(
+            "{:.2},{},{:.2},{:.2},{:.3}",
+            self.elapsed,
+            active,
+            self.average_wait(),
+            self.throughput(),
+            efficiency(active, moving)
+        ));
+        for queue in queues {
+            text.push_str(&format!(",{}", queue));
+        }
+        text.push('\n');
+        file.write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+/// Share of the vehicles on the road that are moving rather than queued,
+/// from 0 to 1. An empty road counts as fully efficient.
+pub fn efficiency(active: usize, moving: usize) -> f32 {
+    if active == 0 {
+        1.0
+    } else {
+        moving as f32 / active as f32
+    }
+}
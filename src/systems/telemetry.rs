@@ -0,0 +1,105 @@
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::Write;
+
+use crate::systems::simulation::{STEP_SECONDS, Simulation};
+use crate::systems::stats::efficiency;
+use crate::systems::vehicle::Lane;
+use crate::systems::weather::WeatherType;
+
+/// Streams one JSON object per line to a file as the simulation runs, so a
+/// dashboard can follow along with `tail -f` or by reading from a FIFO.
+pub struct Telemetry {
+    file: File,
+    path: String,
+    /// Simulated seconds between lines; zero writes one every step.
+    interval: f32,
+    /// Elapsed simulated time at which the next line is due.
+    next: f32,
+}
+
+impl Telemetry {
+    /// Creates (or truncates) `path`. Opening a FIFO waits for a reader.
+    pub fn create(path: &str, interval: f32) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        Ok(Self {
+            file,
+            path: path.to_string(),
+            interval,
+            next: 0.0,
+        })
+    }
+
+    /// Writes a line if one is due at the simulation's current time.
+    pub fn record(&mut self, sim: &Simulation) -> Result<(), String> {
+        let elapsed = sim.stats.elapsed;
+        // After a rewind the clock is behind the last line; carry on from there.
+        if self.next > elapsed + self.interval {
+            self.next = elapsed;
+        }
+        if elapsed < self.next {
+            return Ok(());
+        }
+        self.next += self.interval.max(STEP_SECONDS);
+        let mut line = to_json_line(sim);
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", self.path, e))
+    }
+}
+
+/// Summary statistics and the state of each intersection as a single-line
+/// JSON object.
+pub fn to_json_line(sim: &Simulation) -> String {
+    let mut out = String::new();
+    write_json_line(&mut out, sim).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_json_line(out: &mut String, sim: &Simulation) -> fmt::Result {
+    let stats = &sim.stats;
+    let active = sim.vehicles.len();
+    let moving = sim.vehicles.iter().filter(|v| !v.stopped).count();
+    write!(
+        out,
+        "{{\"time\": {:.2}, \"tick\": {}, \"vehicles\": {}, \"incidents\": {}, \"completed_trips\": {}, \"avg_wait\": {:.2}, \"throughput\": {:.2}, \"efficiency\": {:.3}, \"crashes\": {{",
+        stats.elapsed,
+        sim.tick,
+        active,
+        sim.incidents.len(),
+        stats.completed_trips,
+        stats.average_wait(),
+        stats.throughput(),
+        efficiency(active, moving)
+    )?;
+    for (index, kind) in WeatherType::ALL.iter().enumerate() {
+        if index > 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "\"{}\": {}", kind.name(), stats.crashes[index])?;
+    }
+    write!(out, "}}, \"intersections\": [")?;
+    for index in 0..sim.lights.len() {
+        if index > 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "{}", intersection_json(sim, index))?;
+    }
+    write!(out, "]}}")
+}
+
+/// State and queue of light `index` as a JSON object. Lights are numbered
+/// from 1 in the output, as on screen.
+pub fn intersection_json(sim: &Simulation, index: usize) -> String {
+    let light = &sim.lights[index];
+    format!(
+        "{{\"light\": {}, \"position\": {}, \"state\": \"{}\", \"remaining\": {}, \"control\": \"{}\", \"queue\": {}}}",
+        index + 1,
+        light.position,
+        light.state.name(),
+        light.remaining(),
+        light.controller.mode().name(),
+        sim.queue_length(index, Lane::Road)
+    )
+}
@@ -0,0 +1,80 @@
+use crate::systems::config::Config;
+
+/// Speed limit past a toll booth, in cells per second, for drivers with a
+/// transponder who don't stop.
+pub const EXPRESS_SPEED_LIMIT: f32 = 0.75;
+/// How close to a booth, in cells, a stopped driver has to be to pay.
+pub const BOOTH_REACH: f32 = 1.0;
+
+/// The `[tolls]` section: what drivers pay at toll booths and how long it
+/// takes.
+#[derive(Debug, Clone, Copy)]
+pub struct TollConfig {
+    /// Toll per vehicle at each booth.
+    pub toll: f32,
+    /// Seconds a driver stands at the booth paying.
+    pub service_seconds: f32,
+    /// Fraction of drivers with a transponder, who slow down past the booth
+    /// instead of stopping.
+    pub express: f32,
+}
+
+impl Default for TollConfig {
+    fn default() -> Self {
+        Self {
+            toll: 2.5,
+            service_seconds: 4.0,
+            express: 0.25,
+        }
+    }
+}
+
+impl TollConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut tolls = Self::default();
+        for (key, value) in config.section("tolls") {
+            match key.as_str() {
+                "toll" => {
+                    tolls.toll = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or("[tolls] toll must be a non-negative amount")?;
+                }
+                "service_seconds" => {
+                    tolls.service_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or("[tolls] service_seconds must be a positive number")?;
+                }
+                "express" => {
+                    tolls.express = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| (0.0..=1.0).contains(n))
+                        .ok_or("[tolls] express must be a fraction from 0 to 1")?;
+                }
+                other => return Err(format!("Unknown setting '{}' in [tolls]", other)),
+            }
+        }
+        Ok(tolls)
+    }
+}
+
+/// A toll booth on the road, serving one driver at a time.
+#[derive(Debug, Clone)]
+pub struct TollBooth {
+    pub cell: usize,
+    /// Vehicle paying right now, and the seconds it has left.
+    pub serving: Option<(u32, f32)>,
+}
+
+impl TollBooth {
+    pub fn new(cell: usize) -> Self {
+        Self {
+            cell,
+            serving: None,
+        }
+    }
+}
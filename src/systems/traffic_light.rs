@@ -1,10 +1,20 @@
-#[derive(Debug, Clone, Copy)]
+use crate::systems::enforcement::EnforcementCamera;
+use crate::systems::signal_control::{ControlMode, SignalController};
+use crate::systems::simulation::Detectors;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrafficLightState {
     Red,
     Yellow,
     Green,
 }
 
+/// Seconds of green the bicycle signal gets before the main light turns green.
+pub const LEADING_BIKE_INTERVAL: u32 = 1;
+/// How close to the stop line, in cells, a vehicle has to have stopped to
+/// count as stopped at it.
+pub const STOP_LINE_REACH: f32 = 1.0;
+
 impl TrafficLightState {
     pub fn next(self) -> Self {
         match self {
@@ -13,5 +23,234 @@ impl TrafficLightState {
             TrafficLightState::Yellow => TrafficLightState::Red,
         }
     }
+
+    /// How long the light stays in this state, in seconds.
+    pub fn duration(self) -> u32 {
+        match self {
+            TrafficLightState::Red => 5,
+            TrafficLightState::Green => 5,
+            TrafficLightState::Yellow => 2,
+        }
+    }
+
+    /// State of the bicycle signal while the main light is `self` with
+    /// `remaining` seconds left. Bikes get a leading green at the end of red.
+    pub fn bike_phase(self, remaining: u32) -> Self {
+        match self {
+            TrafficLightState::Red if remaining <= LEADING_BIKE_INTERVAL => {
+                TrafficLightState::Green
+            }
+            other => other,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TrafficLightState::Red => "red",
+            TrafficLightState::Yellow => "yellow",
+            TrafficLightState::Green => "green",
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            TrafficLightState::Red,
+            TrafficLightState::Yellow,
+            TrafficLightState::Green,
+        ]
+        .into_iter()
+        .find(|state| state.name() == name)
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TrafficLightState::Red => "🟥",
+            TrafficLightState::Green => "🟩",
+            TrafficLightState::Yellow => "🟨",
+        }
+    }
+}
+
+/// How a light shows while it flashes instead of cycling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flashing {
+    /// Go on with care.
+    Yellow,
+    /// Stop at the line, then go when clear, as at a stop sign.
+    Red,
+}
+
+impl Flashing {
+    /// The colour it flashes in.
+    pub fn state(self) -> TrafficLightState {
+        match self {
+            Flashing::Yellow => TrafficLightState::Yellow,
+            Flashing::Red => TrafficLightState::Red,
+        }
+    }
 }
 
+/// A signal at one stop line. Its controller decides when it changes.
+#[derive(Debug, Clone)]
+pub struct TrafficLight {
+    pub position: usize,
+    pub state: TrafficLightState,
+    pub controller: Box<dyn SignalController>,
+    /// Under outside control: the timer keeps running, but the state only
+    /// changes when forced.
+    pub held: bool,
+    /// Road vehicles that have crossed the stop line on red.
+    pub violations: u32,
+    /// Enforcement camera watching the stop line, if there is one.
+    pub camera: Option<EnforcementCamera>,
+    /// Flashing instead of cycling, and in which colour.
+    pub flashing: Option<Flashing>,
+    /// Out of order and flashing red, with the seconds left on its repair.
+    pub repair_left: Option<f32>,
+    /// When an operator took over the light by hand, in simulated seconds.
+    pub manual_since: Option<f32>,
+    elapsed: f32,
+}
+
+impl TrafficLight {
+    pub fn new(position: usize) -> Self {
+        Self {
+            position,
+            state: TrafficLightState::Red,
+            controller: ControlMode::FixedTime.controller(),
+            held: false,
+            violations: 0,
+            camera: None,
+            flashing: None,
+            repair_left: None,
+            manual_since: None,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the light by `dt` seconds and lets its controller decide on
+    /// the phase. Returns true when it changed state.
+    pub fn update(&mut self, dt: f32, detectors: &Detectors) -> bool {
+        self.elapsed += dt;
+        if self.held || self.flashing.is_some() {
+            return false;
+        }
+        let detectors = Detectors {
+            state: self.state,
+            elapsed: self.elapsed,
+            ..*detectors
+        };
+        match self.controller.decide(&detectors) {
+            Some(state) => {
+                self.force(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switches straight to `state` and restarts its timer.
+    pub fn force(&mut self, state: TrafficLightState) {
+        self.state = state;
+        self.elapsed = 0.0;
+    }
+
+    /// Starts the light flashing, or, given `None`, sends it back to its
+    /// cycle from the start of red.
+    pub fn set_flashing(&mut self, flashing: Option<Flashing>) {
+        self.flashing = flashing;
+        self.force(flashing.map_or(TrafficLightState::Red, Flashing::state));
+    }
+
+    /// Breaks the light down to flashing red, an all-way stop, until a
+    /// repair taking `seconds` is done.
+    pub fn malfunction(&mut self, seconds: f32) {
+        self.repair_left = Some(seconds);
+        // An operator directing traffic carries on regardless.
+        if self.manual_since.is_none() {
+            self.set_flashing(Some(Flashing::Red));
+        }
+    }
+
+    /// Hands the light to an operator at `now`: it stops flashing and keeps
+    /// its colour until they change it.
+    pub fn take_manual_control(&mut self, now: f32) {
+        self.manual_since = Some(now);
+        self.held = true;
+        self.flashing = None;
+    }
+
+    /// Seconds spent in the current state.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Whole seconds the current state is planned to last, or `None` while
+    /// it lasts until something else happens (a manual, held or flashing
+    /// light).
+    fn planned(&self) -> Option<u32> {
+        if self.held || self.flashing.is_some() {
+            None
+        } else {
+            self.controller.duration(self.state)
+        }
+    }
+
+    /// Whole seconds left in the current state, counting down to 1, or 0
+    /// when the state has no planned end.
+    pub fn remaining(&self) -> u32 {
+        self.planned()
+            .map_or(0, |duration| duration.saturating_sub(self.elapsed as u32))
+    }
+
+    /// Whether the light is in an all-red interval, held red while
+    /// conflicting traffic clears the intersection.
+    pub fn clearing(&self) -> bool {
+        self.state == TrafficLightState::Red
+            && !self.held
+            && self.flashing.is_none()
+            && self.controller.clearing(self.elapsed)
+    }
+
+    /// `3s left` (followed by the ring phases for ring-and-barrier
+    /// controllers), `all-red, 3s left`, `flashing`, `out of order` while
+    /// awaiting repair, `manual` under an operator, `holding` when the
+    /// state has no planned end, or the signs at an unsignalled
+    /// intersection.
+    pub fn countdown(&self) -> String {
+        match self.controller.mode() {
+            ControlMode::AllWayStop => return "all-way stop".to_string(),
+            ControlMode::Yield => return "yield".to_string(),
+            ControlMode::Roundabout => return "roundabout".to_string(),
+            _ => {}
+        }
+        if self.manual_since.is_some() {
+            return format!("manual, {:.0}s", self.elapsed);
+        }
+        if let Some(left) = self.repair_left {
+            return format!("out of order, flashing red, repaired in {:.0}s", left);
+        }
+        if self.flashing.is_some() {
+            return "flashing".to_string();
+        }
+        let countdown = match self.planned() {
+            Some(_) if self.clearing() => format!("all-red, {}s left", self.remaining()),
+            Some(_) => format!("{}s left", self.remaining()),
+            None => return "holding".to_string(),
+        };
+        match self.controller.phase_note(self.state, self.elapsed) {
+            Some(note) => format!("{}, {}", countdown, note),
+            None => countdown,
+        }
+    }
+
+    /// The bicycle signal gets its leading green only when the end of the
+    /// red is known in advance, and never during an all-red interval.
+    pub fn bike_phase(&self) -> TrafficLightState {
+        match self.planned() {
+            Some(_) if !self.clearing() => self.state.bike_phase(self.remaining()),
+            _ => self.state,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::systems::config::Config;
+
+/// How far back from the stop line, in cells, the bike lane has to be
+/// clear of cyclists for a driver to turn across it on red.
+pub const CROSSING_REACH: f32 = 2.0;
+
+/// The `[turns]` section: drivers who turn off the road at a signal, to the
+/// right or, driving on the left, to the left, and whether they may do so
+/// on red.
+#[derive(Debug, Clone, Copy)]
+pub struct TurnConfig {
+    /// Fraction of cars and motorcycles that turn right at one of the
+    /// signals ahead instead of driving to the end of the road.
+    pub right_share: f32,
+    /// Lets them turn on red once they have stopped at the line and the
+    /// bike lane they cross is clear.
+    pub right_on_red: bool,
+}
+
+impl Default for TurnConfig {
+    fn default() -> Self {
+        Self {
+            right_share: 0.15,
+            right_on_red: false,
+        }
+    }
+}
+
+impl TurnConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut turns = Self::default();
+        for (key, value) in config.section("turns") {
+            match key.as_str() {
+                "right_share" => {
+                    turns.right_share = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| (0.0..=1.0).contains(n))
+                        .ok_or("[turns] right_share must be a fraction from 0 to 1")?;
+                }
+                "right_on_red" => {
+                    turns.right_on_red = match value.as_str() {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err("[turns] right_on_red must be true or false".to_string()),
+                    };
+                }
+                other => return Err(format!("Unknown setting '{}' in [turns]", other)),
+            }
+        }
+        Ok(turns)
+    }
+}
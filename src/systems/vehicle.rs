@@ -0,0 +1,577 @@
+use crate::systems::rideshare::CURB_REACH;
+use crate::systems::road::Facility;
+
+/// Minimum bumper-to-bumper gap kept when stopped, in cells.
+pub const MIN_GAP: f32 = 0.3;
+/// Time headway drivers try to keep to the vehicle ahead, in seconds.
+pub const TIME_HEADWAY: f32 = 0.8;
+/// Comfortable braking deceleration, in cells per second squared.
+pub const COMFORT_DECEL: f32 = 1.5;
+/// Speeds below this count as standing still.
+const STOPPED_SPEED: f32 = 0.05;
+/// Drivers keep to a speed that covers what they can see in this many
+/// seconds.
+const SIGHT_SECONDS: f32 = 3.0;
+/// Share of the usual braking a vehicle manages while its wheels slide.
+pub const SKID_BRAKING: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VehicleType {
+    Car,
+    Motorcycle,
+    Bicycle,
+    TowTruck,
+    Ambulance,
+    FireTruck,
+    Police,
+}
+
+impl VehicleType {
+    pub const ALL: [VehicleType; 7] = [
+        VehicleType::Car,
+        VehicleType::Motorcycle,
+        VehicleType::Bicycle,
+        VehicleType::TowTruck,
+        VehicleType::Ambulance,
+        VehicleType::FireTruck,
+        VehicleType::Police,
+    ];
+
+    /// Relative chance of this type being picked when a vehicle spawns on
+    /// a weekday. Tow trucks are only ever dispatched to incidents.
+    pub fn spawn_weight(self) -> u32 {
+        match self {
+            VehicleType::Car => 70,
+            VehicleType::Motorcycle => 15,
+            VehicleType::Bicycle => 15,
+            VehicleType::TowTruck => 0,
+            VehicleType::Ambulance | VehicleType::FireTruck => 1,
+            VehicleType::Police => 2,
+        }
+    }
+
+    /// Desired cruising speed, in cells per second.
+    pub fn max_speed(self) -> f32 {
+        match self {
+            VehicleType::Car => 1.5,
+            VehicleType::Motorcycle => 2.0,
+            VehicleType::Bicycle => 0.7,
+            VehicleType::TowTruck => 1.8,
+            VehicleType::Ambulance => 2.0,
+            VehicleType::FireTruck => 1.2,
+            VehicleType::Police => 1.8,
+        }
+    }
+
+    /// Maximum acceleration, in cells per second squared.
+    pub fn acceleration(self) -> f32 {
+        match self {
+            VehicleType::Car => 1.0,
+            VehicleType::Motorcycle => 1.5,
+            VehicleType::Bicycle => 0.6,
+            VehicleType::TowTruck => 1.2,
+            VehicleType::Ambulance => 1.4,
+            VehicleType::FireTruck => 0.7,
+            VehicleType::Police => 1.3,
+        }
+    }
+
+    /// How many cells this vehicle occupies. Motorcycles and bicycles are
+    /// short enough that two of them fit in one cell.
+    pub fn length(self) -> f32 {
+        match self {
+            VehicleType::Motorcycle | VehicleType::Bicycle => 0.5,
+            _ => 1.0,
+        }
+    }
+
+    /// Length of the longest kind of vehicle.
+    pub fn longest() -> f32 {
+        Self::ALL
+            .iter()
+            .map(|kind| kind.length())
+            .fold(0.0, f32::max)
+    }
+
+    /// Space kept to whatever is ahead when stopped. Fire trucks need
+    /// twice the usual room to manoeuvre.
+    pub fn clearance(self) -> f32 {
+        match self {
+            VehicleType::FireTruck => 2.0 * MIN_GAP,
+            _ => MIN_GAP,
+        }
+    }
+
+    /// Ambulances, fire trucks and police cars.
+    pub fn is_emergency(self) -> bool {
+        matches!(
+            self,
+            VehicleType::Ambulance | VehicleType::FireTruck | VehicleType::Police
+        )
+    }
+
+    /// Where this kind is based, if anywhere: it is dispatched from there
+    /// and drives back there once its job is done.
+    pub fn base(self) -> Option<Facility> {
+        match self {
+            VehicleType::Ambulance => Some(Facility::Hospital),
+            VehicleType::FireTruck => Some(Facility::FireStation),
+            _ => None,
+        }
+    }
+
+    /// Tow trucks and emergency vehicles, which are on duty rather than
+    /// making trips of their own.
+    pub fn is_service(self) -> bool {
+        self == VehicleType::TowTruck || self.is_emergency()
+    }
+
+    /// Ambulances and fire trucks drive through red lights under their
+    /// sirens.
+    pub fn has_siren(self) -> bool {
+        matches!(self, VehicleType::Ambulance | VehicleType::FireTruck)
+    }
+
+    /// Police cars that come up behind an incident stop and sit with it
+    /// until it is cleared.
+    pub fn attends_incidents(self) -> bool {
+        self == VehicleType::Police
+    }
+
+    /// Motorcycles can filter between queued cars instead of queueing
+    /// behind them.
+    pub fn can_filter(self) -> bool {
+        self == VehicleType::Motorcycle
+    }
+
+    pub fn sprite(self) -> &'static str {
+        match self {
+            VehicleType::Car => "🚗",
+            VehicleType::Motorcycle => "🛵",
+            VehicleType::Bicycle => "🚲",
+            VehicleType::TowTruck => "🛻",
+            VehicleType::Ambulance => "🚑",
+            VehicleType::FireTruck => "🚒",
+            VehicleType::Police => "🚓",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VehicleType::Car => "Car",
+            VehicleType::Motorcycle => "Motorcycle",
+            VehicleType::Bicycle => "Bicycle",
+            VehicleType::TowTruck => "Tow truck",
+            VehicleType::Ambulance => "Ambulance",
+            VehicleType::FireTruck => "Fire truck",
+            VehicleType::Police => "Police car",
+        }
+    }
+
+    /// Lowercase identifier used in console commands and saved state.
+    pub fn key(self) -> &'static str {
+        match self {
+            VehicleType::Car => "car",
+            VehicleType::Motorcycle => "motorcycle",
+            VehicleType::Bicycle => "bicycle",
+            VehicleType::TowTruck => "tow_truck",
+            VehicleType::Ambulance => "ambulance",
+            VehicleType::FireTruck => "fire_truck",
+            VehicleType::Police => "police",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+}
+
+/// Which lane a vehicle travels in. The bike lane is optional and only
+/// bicycles ever use it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    Road,
+    Bike,
+}
+
+impl Lane {
+    pub fn key(self) -> &'static str {
+        match self {
+            Lane::Road => "road",
+            Lane::Bike => "bike",
+        }
+    }
+}
+
+/// What the weather and road works leave drivers to work with.
+#[derive(Debug, Clone, Copy)]
+pub struct RoadConditions {
+    /// How well tyres hold the road, from 0 to 1.
+    pub grip: f32,
+    /// How far ahead drivers can see, in cells.
+    pub visibility: f32,
+    /// Top speed allowed where the vehicle is, in cells per second.
+    pub speed_limit: f32,
+    /// Share of their usual top speed cyclists can keep up, from 0 to 1.
+    /// Rain, wind and snow slow them far more than drivers.
+    pub cycling: f32,
+}
+
+impl Default for RoadConditions {
+    fn default() -> Self {
+        Self {
+            grip: 1.0,
+            visibility: f32::INFINITY,
+            speed_limit: f32::INFINITY,
+            cycling: 1.0,
+        }
+    }
+}
+
+/// Something ahead a vehicle has to keep its distance from: either the
+/// vehicle in front or a stop line it must not cross.
+#[derive(Debug, Clone, Copy)]
+pub struct Obstacle {
+    /// Free space between this vehicle's front and the obstacle, in cells.
+    pub gap: f32,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Vehicle {
+    pub id: u32,
+    pub kind: VehicleType,
+    pub lane: Lane,
+    /// Position of the vehicle's front bumper, in cells from the lane start.
+    pub position: f32,
+    pub speed: f32,
+    pub stopped: bool,
+    /// Broken down and blocking the lane until a tow truck clears it.
+    pub broken_down: bool,
+    /// Kept in place regardless of the road ahead, e.g. a tow truck at work.
+    pub held: bool,
+    /// Sliding on ice past a stop line, braking as hard as the grip allows.
+    pub skidding: bool,
+    /// Dispatched to an incident or debris and waved through the queue.
+    pub responding: bool,
+    /// Cell it leaves the road at before the end, such as the facility a
+    /// responder returns to.
+    pub destination: Option<usize>,
+    /// Pulled over for a siren coming up behind it.
+    pub yielding: bool,
+    /// Drives on through yellows, and through a fresh red it can't stop
+    /// for in comfort.
+    pub runs_lights: bool,
+    /// Drives over speed limits.
+    pub speeds: bool,
+    /// Drops its bad habits while an enforcement camera is watching.
+    pub camera_shy: bool,
+    /// Pays tolls by transponder, slowing past booths instead of stopping.
+    pub express: bool,
+    /// Cell of the last toll booth it paid at.
+    pub paid_toll_at: Option<usize>,
+    /// Cell of the car park it is looking for a space in.
+    pub parking: Option<usize>,
+    /// Times it has gone round the block after finding the car park full.
+    pub circles: u32,
+    /// Cell a ride-share car pulls up at the curb to pick up or drop off,
+    /// and the seconds it still has to wait there.
+    pub curb_stop: Option<(usize, f32)>,
+    /// Signal (by index) it turns right off the road at.
+    pub turns_at: Option<usize>,
+    /// Stopped at the line for a red and cleared to turn right on it.
+    pub turning_on_red: bool,
+    /// Light (by index) flashing red that it has stopped at and may now go
+    /// through.
+    pub cleared_flash: Option<usize>,
+    /// Cell the vehicle entered the road at.
+    pub origin: usize,
+    /// Total seconds spent standing still.
+    pub waited_time: f32,
+}
+
+impl Vehicle {
+    pub fn new(id: u32, kind: VehicleType, lane: Lane) -> Self {
+        Self {
+            id,
+            kind,
+            lane,
+            position: 0.0,
+            speed: 0.0,
+            stopped: false,
+            broken_down: false,
+            held: false,
+            skidding: false,
+            responding: false,
+            destination: None,
+            yielding: false,
+            runs_lights: false,
+            speeds: false,
+            camera_shy: false,
+            express: false,
+            paid_toll_at: None,
+            parking: None,
+            circles: 0,
+            curb_stop: None,
+            turns_at: None,
+            turning_on_red: false,
+            cleared_flash: None,
+            origin: 0,
+            waited_time: 0.0,
+        }
+    }
+
+    /// Whether the driver's bad habits show, given whether a camera is
+    /// `watching`.
+    pub fn misbehaves(&self, watching: bool) -> bool {
+        !(watching && self.camera_shy)
+    }
+
+    /// Whether the driver still has to stop and pay at the toll booth at
+    /// `cell`. Service vehicles go through free.
+    pub fn owes_toll(&self, cell: usize) -> bool {
+        self.lane == Lane::Road
+            && !self.kind.is_service()
+            && !self.express
+            && self.paid_toll_at.is_none_or(|paid| paid < cell)
+    }
+
+    /// Whether it has pulled up at its ride-share stop.
+    pub fn at_curb(&self) -> bool {
+        self.curb_stop.is_some_and(|(cell, _)| {
+            self.stopped && (cell as f32 - CURB_REACH..=cell as f32).contains(&self.position)
+        })
+    }
+
+    /// Lane cell the vehicle is drawn in.
+    pub fn cell(&self) -> usize {
+        self.position.max(0.0) as usize
+    }
+
+    /// Position of the rear bumper.
+    pub fn rear(&self) -> f32 {
+        self.position - self.kind.length()
+    }
+
+    /// IDM-lite car following: accelerate toward the cruising speed, brake
+    /// to keep a safe gap to `obstacle`, and never run into it. With less
+    /// than full grip drivers brake earlier and gentler, and in poor
+    /// visibility they slow to what they can see. Drivers pulling over for
+    /// a siren brake to a halt.
+    pub fn update(&mut self, dt: f32, obstacle: Option<Obstacle>, conditions: RoadConditions) {
+        let grip = conditions.grip;
+        if self.broken_down || self.held {
+            self.speed = 0.0;
+            self.stopped = true;
+            self.waited_time += dt;
+            return;
+        }
+
+        let max_speed = self
+            .kind
+            .max_speed()
+            .min(conditions.visibility / SIGHT_SECONDS)
+            .min(conditions.speed_limit);
+        let accel = self.kind.acceleration();
+        let mut target = 1.0 - (self.speed / max_speed).powi(4);
+
+        if let Some(obstacle) = obstacle {
+            let closing = self.speed - obstacle.speed;
+            let desired_gap = self.kind.clearance()
+                + self.speed * TIME_HEADWAY
+                + self.speed * closing / (2.0 * (accel * COMFORT_DECEL * grip).sqrt());
+            let gap = obstacle.gap.max(0.01);
+            target -= (desired_gap.max(0.0) / gap).powi(2);
+        }
+
+        self.speed = if self.skidding {
+            (self.speed - COMFORT_DECEL * grip * SKID_BRAKING * dt).max(0.0)
+        } else if self.yielding {
+            (self.speed - COMFORT_DECEL * grip * dt).max(0.0)
+        } else {
+            (self.speed + accel * target * dt).clamp(0.0, max_speed)
+        };
+        let mut step = self.speed * dt;
+        if let Some(obstacle) = obstacle {
+            let room = (obstacle.gap - self.kind.clearance() * 0.5).max(0.0);
+            if step > room {
+                step = room;
+                self.speed = obstacle.speed.min(self.speed);
+            }
+        }
+        self.position += step;
+        self.stopped = self.speed < STOPPED_SPEED;
+        if self.stopped {
+            self.skidding = false;
+        }
+        if self.stopped {
+            self.waited_time += dt;
+        }
+    }
+
+    pub fn state_label(&self) -> &'static str {
+        if self.broken_down {
+            "broken down"
+        } else if self.held && self.kind.attends_incidents() {
+            "attending"
+        } else if self.held {
+            "working"
+        } else if self.skidding {
+            "skidding"
+        } else if self.yielding {
+            "yielding"
+        } else if self.at_curb() {
+            "at the curb"
+        } else if self.stopped {
+            "stopped"
+        } else {
+            "moving"
+        }
+    }
+
+    pub fn render(&self) -> String {
+        if self.stopped {
+            format!(
+                "{} #{} (stopped) at {:.1}",
+                self.kind.sprite(),
+                self.id,
+                self.position
+            )
+        } else {
+            format!(
+                "{} #{} moving at {:.1} cells/s, at {:.1}",
+                self.kind.sprite(),
+                self.id,
+                self.speed,
+                self.position
+            )
+        }
+    }
+
+    pub fn lane_symbol(&self) -> &str {
+        if self.broken_down {
+            "💥"
+        } else if self.stopped && self.kind == VehicleType::Car {
+            "🅿️"
+        } else {
+            self.kind.sprite()
+        }
+    }
+
+    /// Optional logic for smart stopping: stop for a yellow only if the stop
+    /// line can still be reached with comfortable braking.
+    pub fn should_stop_for_yellow(&self, stop_line: f32, grip: f32) -> bool {
+        let distance = stop_line - self.position;
+        distance >= 0.0 && self.braking_distance(grip) <= distance
+    }
+
+    /// Distance needed to stop with comfortable braking at `grip`.
+    pub fn braking_distance(&self, grip: f32) -> f32 {
+        self.speed * self.speed / (2.0 * COMFORT_DECEL * grip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.1;
+
+    fn car_at(id: u32, position: f32) -> Vehicle {
+        Vehicle {
+            position,
+            ..Vehicle::new(id, VehicleType::Car, Lane::Road)
+        }
+    }
+
+    #[test]
+    fn accelerates_to_cruising_speed() {
+        let mut car = car_at(1, 0.0);
+        for _ in 0..200 {
+            car.update(DT, None, RoadConditions::default());
+        }
+        let top = VehicleType::Car.max_speed();
+        assert!((car.speed - top).abs() < 0.01, "{}", car.speed);
+    }
+
+    #[test]
+    fn stops_before_a_stop_line() {
+        let stop_line = 10.0;
+        let mut car = car_at(1, 0.0);
+        car.speed = VehicleType::Car.max_speed();
+        for _ in 0..300 {
+            let obstacle = Obstacle {
+                gap: stop_line - car.position,
+                speed: 0.0,
+            };
+            car.update(DT, Some(obstacle), RoadConditions::default());
+            assert!(
+                car.position <= stop_line,
+                "ran the line at {}",
+                car.position
+            );
+        }
+        assert!(car.stopped);
+        assert!(
+            stop_line - car.position < 1.0,
+            "stopped at {}",
+            car.position
+        );
+    }
+
+    #[test]
+    fn followers_keep_behind_the_vehicle_ahead() {
+        // A leader crawling along and a follower closing on it at full speed.
+        let mut leader = car_at(1, 6.0);
+        leader.speed = 0.3;
+        let mut follower = car_at(2, 0.0);
+        follower.speed = VehicleType::Car.max_speed();
+        for step in 0..400 {
+            // The leader stops dead halfway through.
+            let leader_obstacle = (step > 200).then_some(Obstacle {
+                gap: 0.0,
+                speed: 0.0,
+            });
+            leader.update(DT, leader_obstacle, RoadConditions::default());
+            let obstacle = Obstacle {
+                gap: leader.rear() - follower.position,
+                speed: leader.speed,
+            };
+            follower.update(DT, Some(obstacle), RoadConditions::default());
+            assert!(
+                follower.position <= leader.rear(),
+                "overlap at step {}: {} past {}",
+                step,
+                follower.position,
+                leader.rear()
+            );
+        }
+        assert!(follower.stopped);
+    }
+
+    #[test]
+    fn poor_grip_brakes_earlier() {
+        let line = |grip: f32| {
+            let mut car = car_at(1, 0.0);
+            car.speed = VehicleType::Car.max_speed();
+            let conditions = RoadConditions {
+                grip,
+                ..RoadConditions::default()
+            };
+            let mut braking_from = None;
+            for _ in 0..300 {
+                let before = car.speed;
+                let obstacle = Obstacle {
+                    gap: 20.0 - car.position,
+                    speed: 0.0,
+                };
+                car.update(DT, Some(obstacle), conditions);
+                if car.speed < before && braking_from.is_none() {
+                    braking_from = Some(car.position);
+                }
+            }
+            braking_from.unwrap()
+        };
+        assert!(line(0.3) < line(1.0));
+    }
+}
@@ -0,0 +1,364 @@
+use crate::systems::clock::Season;
+use crate::systems::config::Config;
+use crate::systems::rng::Rng;
+use crate::systems::vehicle::RoadConditions;
+
+/// Seconds a change of weather takes to blow in.
+const TRANSITION_SECONDS: f32 = 20.0;
+/// Shortest and longest spell of one weather before it may change, in
+/// seconds.
+const SPELL_SECONDS: (u32, u32) = (60, 180);
+/// Seconds of full-strength snow to cover the road.
+const SNOW_COVER_SECONDS: f32 = 60.0;
+/// Seconds for a full snow cover to freeze to ice under clear, cold skies.
+const FREEZE_SECONDS: f32 = 40.0;
+/// Seconds for snow and ice to melt away once it's warm.
+const THAW_SECONDS: f32 = 120.0;
+/// How far drivers can see, in cells, through the thinnest and the
+/// thickest fog.
+const FOG_VISIBILITY: (f32, f32) = (20.0, 2.0);
+/// Least share of their usual top speed cyclists keep in the worst weather.
+const MIN_CYCLING: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherType {
+    Clear,
+    Rain,
+    Storm,
+    Snow,
+    Fog,
+}
+
+impl WeatherType {
+    pub const ALL: [WeatherType; 5] = [
+        WeatherType::Clear,
+        WeatherType::Rain,
+        WeatherType::Storm,
+        WeatherType::Snow,
+        WeatherType::Fog,
+    ];
+
+    /// Lowercase identifier used in config, console commands and the APIs.
+    pub fn name(self) -> &'static str {
+        match self {
+            WeatherType::Clear => "clear",
+            WeatherType::Rain => "rain",
+            WeatherType::Storm => "storm",
+            WeatherType::Snow => "snow",
+            WeatherType::Fog => "fog",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// The kind after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Chance of this weather coming next when a spell ends in `season`.
+    /// Each season's chances add up to 1.
+    pub fn seasonal_probability(self, season: Season) -> f32 {
+        let [clear, rain, storm, snow, fog] = match season {
+            Season::Spring => [0.45, 0.30, 0.05, 0.02, 0.18],
+            Season::Summer => [0.60, 0.15, 0.15, 0.0, 0.10],
+            Season::Autumn => [0.35, 0.30, 0.08, 0.02, 0.25],
+            Season::Winter => [0.30, 0.10, 0.02, 0.38, 0.20],
+        };
+        match self {
+            WeatherType::Clear => clear,
+            WeatherType::Rain => rain,
+            WeatherType::Storm => storm,
+            WeatherType::Snow => snow,
+            WeatherType::Fog => fog,
+        }
+    }
+}
+
+/// The `[weather]` section: what the run starts in, whether it changes
+/// by itself, and how much more often vehicles crash in each weather.
+#[derive(Debug, Clone, Copy)]
+pub struct WeatherConfig {
+    pub start: WeatherType,
+    /// Strength of the starting weather, from 0 to 1.
+    pub intensity: f32,
+    pub changes: bool,
+    /// Crash chance multiplier at full strength, in [`WeatherType::ALL`]
+    /// order.
+    pub crash_multipliers: [f32; 5],
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            start: WeatherType::Clear,
+            intensity: 0.5,
+            changes: true,
+            crash_multipliers: [1.0, 2.0, 3.0, 4.0, 1.5],
+        }
+    }
+}
+
+impl WeatherConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut weather = Self::default();
+        for (key, value) in config.section("weather") {
+            match key.as_str() {
+                "start" => {
+                    weather.start = WeatherType::from_name(value)
+                        .ok_or_else(|| format!("[weather] start must be one of {}", names()))?;
+                }
+                "intensity" => {
+                    weather.intensity = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (0.0..=1.0).contains(n))
+                        .ok_or("[weather] intensity must be a number from 0 to 1")?;
+                }
+                "changes" => {
+                    weather.changes = match value.as_str() {
+                        "true" | "yes" | "on" => true,
+                        "false" | "no" | "off" => false,
+                        _ => return Err("[weather] changes must be true or false".to_string()),
+                    };
+                }
+                other => {
+                    let kind = other
+                        .strip_prefix("crash_multiplier_")
+                        .and_then(WeatherType::from_name)
+                        .ok_or_else(|| format!("Unknown setting '{}' in [weather]", other))?;
+                    weather.crash_multipliers[kind as usize] = value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n >= 0.0)
+                        .ok_or_else(|| {
+                            format!("[weather] {} must be a non-negative number", other)
+                        })?;
+                }
+            }
+        }
+        Ok(weather)
+    }
+}
+
+/// Every weather's name, comma-separated.
+pub fn names() -> String {
+    let names: Vec<&str> = WeatherType::ALL.iter().map(|kind| kind.name()).collect();
+    names.join(", ")
+}
+
+/// The simulated weather: a spell of one kind at some intensity, now and
+/// then giving way to another over [`TRANSITION_SECONDS`].
+#[derive(Debug, Clone)]
+pub struct WeatherSystem {
+    pub current: WeatherType,
+    /// How strong the current weather is, from 0 to 1.
+    pub intensity: f32,
+    /// Weather blowing in, and how strong it will be once it has.
+    pub incoming: Option<(WeatherType, f32)>,
+    /// How far the change to `incoming` has got, from 0 to 1.
+    pub progress: f32,
+    /// Pick new weather when a spell ends.
+    pub changes: bool,
+    /// Snow lying on the road, from 0 to 1.
+    pub snow_cover: f32,
+    /// Ice on the road, from 0 to 1.
+    pub ice: f32,
+    /// Crash chance multiplier for each weather at full strength.
+    pub crash_multipliers: [f32; 5],
+    /// Seconds until the current spell ends.
+    spell_left: f32,
+}
+
+impl Default for WeatherSystem {
+    fn default() -> Self {
+        Self::new(WeatherConfig::default())
+    }
+}
+
+impl WeatherSystem {
+    pub fn new(config: WeatherConfig) -> Self {
+        Self {
+            current: config.start,
+            intensity: config.intensity,
+            incoming: None,
+            progress: 0.0,
+            changes: config.changes,
+            snow_cover: 0.0,
+            ice: 0.0,
+            crash_multipliers: config.crash_multipliers,
+            spell_left: SPELL_SECONDS.0 as f32,
+        }
+    }
+
+    /// Moves any change of weather along, and rolls for a new spell when
+    /// the current one ends, with the odds of `season`. Snow settles on the
+    /// road, and freezes to ice if the sky then clears while it's `cold`.
+    pub fn update(&mut self, dt: f32, season: Season, cold: bool, rng: &mut Rng) {
+        self.update_road(dt, cold);
+        if let Some((kind, intensity)) = self.incoming {
+            self.progress += dt / TRANSITION_SECONDS;
+            if self.progress >= 1.0 {
+                (self.current, self.intensity) = (kind, intensity);
+                (self.incoming, self.progress) = (None, 0.0);
+            }
+            return;
+        }
+        if !self.changes {
+            return;
+        }
+        self.spell_left -= dt;
+        if self.spell_left > 0.0 {
+            return;
+        }
+        let (shortest, longest) = SPELL_SECONDS;
+        self.spell_left = (shortest + rng.below(longest - shortest + 1)) as f32;
+        let mut roll = rng.below(1000) as f32 / 1000.0;
+        let kind = WeatherType::ALL
+            .into_iter()
+            .find(|kind| {
+                roll -= kind.seasonal_probability(season);
+                roll < 0.0
+            })
+            .unwrap_or(WeatherType::Clear);
+        let intensity = 0.2 + 0.8 * rng.below(101) as f32 / 100.0;
+        self.change_to(kind, intensity);
+    }
+
+    fn update_road(&mut self, dt: f32, cold: bool) {
+        let snow = self.strength(WeatherType::Snow);
+        self.snow_cover = (self.snow_cover + snow * dt / SNOW_COVER_SECONDS).min(1.0);
+        if cold {
+            let frozen =
+                (self.strength(WeatherType::Clear) * dt / FREEZE_SECONDS).min(self.snow_cover);
+            self.snow_cover -= frozen;
+            self.ice = (self.ice + frozen).min(1.0);
+        } else if snow == 0.0 {
+            let thaw = dt / THAW_SECONDS;
+            self.snow_cover = (self.snow_cover - thaw).max(0.0);
+            self.ice = (self.ice - thaw).max(0.0);
+        }
+    }
+
+    /// How well tyres hold the road, from 1 when dry down to 0.2 on
+    /// sheet ice under fresh snow.
+    pub fn grip(&self) -> f32 {
+        1.0 - 0.2 * self.snow_cover - 0.6 * self.ice
+    }
+
+    /// The weather there is most of right now: the current one, or the
+    /// incoming one once it is more than halfway in.
+    pub fn dominant(&self) -> WeatherType {
+        match self.incoming {
+            Some((kind, _)) if self.progress >= 0.5 => kind,
+            _ => self.current,
+        }
+    }
+
+    /// How many times more likely a crash is than on a clear day, scaling
+    /// each weather's multiplier by how strong it is.
+    pub fn crash_multiplier(&self) -> f32 {
+        WeatherType::ALL.iter().fold(1.0, |total, &kind| {
+            total + (self.crash_multipliers[kind as usize] - 1.0) * self.strength(kind)
+        })
+    }
+
+    /// How far drivers can see, in cells. Fog closes in the most, snow and
+    /// rain less so.
+    pub fn visibility(&self) -> f32 {
+        let murk = (self.strength(WeatherType::Fog)
+            + 0.5 * self.strength(WeatherType::Snow)
+            + 0.2 * self.strength(WeatherType::Rain))
+        .min(1.0);
+        if murk == 0.0 {
+            return f32::INFINITY;
+        }
+        let (thinnest, thickest) = FOG_VISIBILITY;
+        thinnest - (thinnest - thickest) * murk
+    }
+
+    /// Share of their usual top speed cyclists manage: down to 60% in heavy
+    /// rain, 40% in a storm and on fresh snow, and less again on ice.
+    pub fn cycling(&self) -> f32 {
+        let drag = 0.4 * self.strength(WeatherType::Rain)
+            + 0.6 * self.strength(WeatherType::Storm)
+            + 0.4 * self.strength(WeatherType::Snow)
+            + 0.2 * self.snow_cover
+            + 0.4 * self.ice;
+        (1.0 - drag).max(MIN_CYCLING)
+    }
+
+    pub fn conditions(&self) -> RoadConditions {
+        RoadConditions {
+            grip: self.grip(),
+            visibility: self.visibility(),
+            cycling: self.cycling(),
+            ..RoadConditions::default()
+        }
+    }
+
+    /// Starts blowing in `kind` at `intensity`. The same weather just
+    /// changes strength.
+    pub fn change_to(&mut self, kind: WeatherType, intensity: f32) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        // A change more than halfway through counts as done.
+        if let Some((incoming, strength)) = self.incoming
+            && self.progress >= 0.5
+        {
+            (self.current, self.intensity) = (incoming, strength);
+        }
+        if kind == self.current {
+            (self.intensity, self.incoming, self.progress) = (intensity, None, 0.0);
+        } else {
+            (self.incoming, self.progress) = (Some((kind, intensity)), 0.0);
+        }
+    }
+
+    /// How much of `kind` there is right now, from 0 to 1: the current
+    /// weather fading out and the incoming one fading in.
+    pub fn strength(&self, kind: WeatherType) -> f32 {
+        let mut strength = 0.0;
+        if self.current == kind {
+            strength += self.intensity * (1.0 - self.progress);
+        }
+        if let Some((incoming, intensity)) = self.incoming
+            && incoming == kind
+        {
+            strength += intensity * self.progress;
+        }
+        strength
+    }
+
+    /// How hard the wind blows, from 0 to 1. Storms bring gales, and rain
+    /// a breeze.
+    pub fn wind(&self) -> f32 {
+        (self.strength(WeatherType::Storm) + 0.3 * self.strength(WeatherType::Rain)).min(1.0)
+    }
+
+    /// A short description, like "heavy rain, turning to fog".
+    pub fn describe(&self) -> String {
+        let mut text = describe(self.current, self.intensity);
+        if let Some((kind, _)) = self.incoming {
+            text.push_str(&format!(", turning to {}", kind.name()));
+        }
+        if self.ice >= 0.3 {
+            text.push_str(", icy roads");
+        }
+        text
+    }
+}
+
+fn describe(kind: WeatherType, intensity: f32) -> String {
+    if kind == WeatherType::Clear {
+        return kind.name().to_string();
+    }
+    let strength = match intensity {
+        i if i < 0.35 => "light",
+        i if i < 0.7 => "moderate",
+        _ => "heavy",
+    };
+    format!("{} {}", strength, kind.name())
+}
@@ -0,0 +1,214 @@
+use std::io::{self, Write, stdout};
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{self, disable_raw_mode, enable_raw_mode},
+};
+
+use crate::systems::config::Config;
+use crate::ui::capabilities::TerminalCapabilities;
+use crate::ui::graphics::{Colour, GraphicsProtocol, Picture};
+use crate::ui::screen::ScreenBuffer;
+use crate::ui::sprites::SpriteSet;
+
+/// Where finished frames go. The app builds each frame as text and hands it
+/// over; the backend owns whatever it is drawn on.
+pub trait RenderBackend {
+    /// Gets the output ready before the first frame.
+    fn init(&mut self) -> io::Result<()>;
+
+    /// Shows a finished frame: text with colour escapes and cursor moves,
+    /// and optionally a picture of the road on top.
+    fn present(&mut self, frame: &str, picture: Option<Picture>);
+
+    /// The screen is now `columns` by `rows`.
+    fn resize(&mut self, columns: u16, rows: u16);
+
+    /// Puts the output back the way `init` found it.
+    fn restore(&mut self) -> io::Result<()>;
+
+    /// How the road may be drawn as pixels, if at all.
+    fn graphics(&self) -> Option<GraphicsProtocol> {
+        None
+    }
+
+    /// What the last frame put on screen, for backends that keep track.
+    fn screen(&self) -> Option<&ScreenBuffer> {
+        None
+    }
+
+    /// Whether the output shows no colour, so frames should tell states
+    /// apart some other way.
+    fn monochrome(&self) -> bool {
+        false
+    }
+
+    /// Colours for plain text and the screen behind it, from the theme;
+    /// `None` keeps the terminal's own.
+    fn set_base_colours(&mut self, _foreground: Option<Colour>, _background: Option<Colour>) {}
+
+    /// What signals, vehicles and road markings are drawn as.
+    fn set_sprites(&mut self, _sprites: SpriteSet) {}
+}
+
+/// The terminal the program runs in, through crossterm: raw mode, mouse
+/// capture, and frames diffed against the last one.
+pub struct CrosstermBackend {
+    screen: ScreenBuffer,
+    /// Where frames are written: stdout, or a recorder passing them on to it.
+    output: Box<dyn Write>,
+}
+
+impl CrosstermBackend {
+    pub fn new(screen: ScreenBuffer, output: Box<dyn Write>) -> Self {
+        Self { screen, output }
+    }
+}
+
+impl RenderBackend for CrosstermBackend {
+    fn init(&mut self) -> io::Result<()> {
+        enable_raw_mode()?;
+        // A dumb terminal would print the escape sequences.
+        if !self.screen.capabilities().dumb {
+            execute!(stdout(), EnableMouseCapture)?;
+            self.screen.measure_glyph_widths()?;
+        }
+        let (columns, rows) = terminal::size()?;
+        self.screen.resize(columns, rows);
+        Ok(())
+    }
+
+    fn present(&mut self, frame: &str, picture: Option<Picture>) {
+        let out = self.screen.diff(frame, picture);
+        self.output.write_all(out.as_bytes()).unwrap();
+        self.output.flush().unwrap();
+    }
+
+    fn resize(&mut self, columns: u16, rows: u16) {
+        self.screen.resize(columns, rows);
+    }
+
+    fn restore(&mut self) -> io::Result<()> {
+        if !self.screen.capabilities().dumb {
+            execute!(stdout(), DisableMouseCapture)?;
+        }
+        disable_raw_mode()
+    }
+
+    fn graphics(&self) -> Option<GraphicsProtocol> {
+        self.screen.graphics()
+    }
+
+    fn screen(&self) -> Option<&ScreenBuffer> {
+        Some(&self.screen)
+    }
+
+    fn set_base_colours(&mut self, foreground: Option<Colour>, background: Option<Colour>) {
+        self.screen.set_base_colours(foreground, background);
+    }
+
+    fn set_sprites(&mut self, sprites: SpriteSet) {
+        self.screen.set_sprites(sprites);
+    }
+
+    fn monochrome(&self) -> bool {
+        self.screen.monochrome()
+    }
+}
+
+/// Draws nothing and leaves the terminal alone, for runs watched and
+/// steered from outside: over HTTP, MQTT, the control socket or a
+/// controller program. Lines typed on stdin still reach the key bindings.
+pub struct HeadlessBackend;
+
+impl RenderBackend for HeadlessBackend {
+    fn init(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn present(&mut self, _frame: &str, _picture: Option<Picture>) {}
+
+    fn resize(&mut self, _columns: u16, _rows: u16) {}
+
+    fn restore(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How the road is drawn in the terminal backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapStyle {
+    Text,
+    /// Kitty or Sixel images where the terminal supports them, text
+    /// elsewhere.
+    Graphics,
+    HalfBlocks,
+}
+
+/// The `[display]` section: which backend frames go to, how the road is
+/// drawn and with what sprites, and whether the widget interface replaces
+/// the text frame.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayConfig {
+    pub headless: bool,
+    pub map: MapStyle,
+    pub sprites: SpriteSet,
+    pub tui: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            map: MapStyle::Text,
+            sprites: SpriteSet::Emoji,
+            tui: false,
+        }
+    }
+}
+
+impl DisplayConfig {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut display = Self::default();
+        for (key, value) in config.section("display") {
+            match (key.as_str(), value.as_str()) {
+                ("backend", "terminal") => display.headless = false,
+                ("backend", "headless") => display.headless = true,
+                ("backend", _) => {
+                    return Err("[display] backend must be terminal or headless".to_string());
+                }
+                ("map", "text") => display.map = MapStyle::Text,
+                ("map", "graphics") => display.map = MapStyle::Graphics,
+                ("map", "half_blocks") => display.map = MapStyle::HalfBlocks,
+                ("map", _) => {
+                    return Err("[display] map must be text, graphics or half_blocks".to_string());
+                }
+                ("sprites", name) => {
+                    display.sprites = SpriteSet::from_name(name).ok_or_else(|| {
+                        "[display] sprites must be emoji, unicode or ascii".to_string()
+                    })?;
+                }
+                ("ui", "text") => display.tui = false,
+                ("ui", "tui") => display.tui = true,
+                ("ui", _) => return Err("[display] ui must be text or tui".to_string()),
+                (other, _) => return Err(format!("Unknown setting '{}' in [display]", other)),
+            }
+        }
+        Ok(display)
+    }
+
+    /// The backend this config asks for, writing frames to `output`.
+    pub fn backend(&self, output: Box<dyn Write>) -> Box<dyn RenderBackend> {
+        if self.headless {
+            return Box::new(HeadlessBackend);
+        }
+        let mut screen = ScreenBuffer::new(TerminalCapabilities::detect());
+        match self.map {
+            MapStyle::Text => {}
+            MapStyle::Graphics => screen.enable_graphics(),
+            MapStyle::HalfBlocks => screen.enable_half_blocks(),
+        }
+        Box::new(CrosstermBackend::new(screen, output))
+    }
+}
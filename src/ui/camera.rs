@@ -0,0 +1,68 @@
+/// Road cells folded into one glyph in the overview zoom level.
+pub const OVERVIEW_SCALE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zoom {
+    /// One road cell per glyph, with full vehicle sprites.
+    Detail,
+    /// Several cells per glyph, showing only signals and traffic density.
+    Overview,
+}
+
+/// Which stretch of the road is on screen.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub offset: usize,
+    pub zoom: Zoom,
+    /// Vehicle the camera is locked onto, if any.
+    pub following: Option<u32>,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            offset: 0,
+            zoom: Zoom::Detail,
+            following: None,
+        }
+    }
+
+    /// Road cells covered by one on-screen glyph.
+    pub fn scale(&self) -> usize {
+        match self.zoom {
+            Zoom::Detail => 1,
+            Zoom::Overview => OVERVIEW_SCALE,
+        }
+    }
+
+    /// Road cells that fit in `glyphs` on-screen glyphs.
+    pub fn visible_cells(&self, glyphs: usize) -> usize {
+        glyphs * self.scale()
+    }
+
+    pub fn pan(&mut self, cells: isize, road_length: usize, glyphs: usize) {
+        let max_offset = road_length.saturating_sub(self.visible_cells(glyphs));
+        self.offset = self
+            .offset
+            .saturating_add_signed(cells * self.scale() as isize)
+            .min(max_offset);
+    }
+
+    /// Scrolls so `cell` sits in the middle of the view.
+    pub fn center_on(&mut self, cell: usize, road_length: usize, glyphs: usize) {
+        self.offset = cell.saturating_sub(self.visible_cells(glyphs) / 2);
+        self.clamp(road_length, glyphs);
+    }
+
+    pub fn toggle_zoom(&mut self) {
+        self.zoom = match self.zoom {
+            Zoom::Detail => Zoom::Overview,
+            Zoom::Overview => Zoom::Detail,
+        };
+    }
+
+    /// Keeps the offset valid after the road or terminal changed size.
+    pub fn clamp(&mut self, road_length: usize, glyphs: usize) {
+        self.pan(0, road_length, glyphs);
+    }
+}
@@ -0,0 +1,248 @@
+use std::env;
+use std::io::{self, stdout};
+
+use crossterm::cursor::{self, MoveTo};
+use crossterm::execute;
+use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
+
+#[cfg(feature = "tui")]
+use crossterm::style::Color;
+
+use crate::ui::graphics::{Colour, GraphicsProtocol};
+
+/// Terminal programs known to support synchronized output (mode 2026),
+/// by the `TERM_PROGRAM` they set.
+const SYNC_PROGRAMS: [&str; 5] = ["WezTerm", "iTerm.app", "ghostty", "vscode", "contour"];
+/// The same by `TERM`, for terminals that don't set `TERM_PROGRAM`.
+const SYNC_TERMS: [&str; 4] = ["xterm-kitty", "foot", "alacritty", "xterm-ghostty"];
+/// Terminals that speak the Kitty graphics protocol, by `TERM_PROGRAM` and
+/// by `TERM`.
+const KITTY_PROGRAMS: [&str; 2] = ["WezTerm", "ghostty"];
+const KITTY_TERMS: [&str; 2] = ["xterm-kitty", "xterm-ghostty"];
+/// Terminals that draw Sixel images.
+const SIXEL_PROGRAMS: [&str; 1] = ["iTerm.app"];
+const SIXEL_TERMS: [&str; 3] = ["foot", "mlterm", "yaft"];
+/// Terminals that show any RGB colour without saying so in `COLORTERM`.
+const TRUECOLOUR_PROGRAMS: [&str; 4] = ["WezTerm", "iTerm.app", "ghostty", "vscode"];
+const TRUECOLOUR_TERMS: [&str; 4] = ["xterm-kitty", "xterm-ghostty", "alacritty", "foot"];
+
+/// The xterm palette for SGR colours 0-15.
+pub const ANSI_COLOURS: [Colour; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+/// The same colours by crossterm name.
+#[cfg(feature = "tui")]
+const ANSI_NAMES: [Color; 16] = [
+    Color::Black,
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Grey,
+    Color::DarkGrey,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+/// Channel values of the 6×6×6 colour cube in the 256-colour palette.
+pub const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colours the terminal can show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColourDepth {
+    /// None: `NO_COLOR` is set or the terminal is dumb. States are told
+    /// apart by glyph instead.
+    Monochrome,
+    /// The 16 named colours.
+    #[default]
+    Ansi16,
+    /// The xterm 256-colour palette.
+    Ansi256,
+    /// Any RGB colour.
+    TrueColour,
+}
+
+impl ColourDepth {
+    /// SGR parameters that set `colour` as the foreground (`code` 38) or
+    /// background (48), as near as this depth gets to it, if at all.
+    pub fn sgr(self, code: u16, colour: Colour) -> Option<String> {
+        Some(match self {
+            ColourDepth::Monochrome => return None,
+            ColourDepth::TrueColour => {
+                let [r, g, b] = colour;
+                format!("{};2;{};{};{}", code, r, g, b)
+            }
+            ColourDepth::Ansi256 => format!("{};5;{}", code, palette_index(colour)),
+            ColourDepth::Ansi16 => {
+                let index = nearest_ansi(colour) as u16;
+                // 30-37 and 90-97 for the foreground, 40-47 and 100-107
+                // for the background.
+                let bright = if index >= 8 { 60 } else { 0 };
+                (code - 8 + index % 8 + bright).to_string()
+            }
+        })
+    }
+
+    /// `colour` as near as this depth gets to it, for output that sets
+    /// crossterm colours directly.
+    #[cfg(feature = "tui")]
+    pub fn fit(self, colour: Color) -> Color {
+        match (self, colour) {
+            (ColourDepth::Monochrome, _) => Color::Reset,
+            (ColourDepth::Ansi256, Color::Rgb { r, g, b }) => {
+                Color::AnsiValue(palette_index([r, g, b]))
+            }
+            (ColourDepth::Ansi16, Color::Rgb { r, g, b }) => ANSI_NAMES[nearest_ansi([r, g, b])],
+            _ => colour,
+        }
+    }
+}
+
+/// Closest entry in the 256-colour palette: from the colour cube, or the
+/// grey ramp (232-255) when that is nearer.
+fn palette_index(colour: Colour) -> u8 {
+    let level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(channel))
+            .unwrap_or(0)
+    };
+    let [r, g, b] = colour.map(level);
+    let cube = [r, g, b].map(|i| CUBE_LEVELS[i]);
+    let average = colour.iter().map(|&c| c as i32).sum::<i32>() / 3;
+    let step = ((average - 3) / 10).clamp(0, 23);
+    let grey = (8 + 10 * step) as u8;
+    if distance(colour, [grey; 3]) < distance(colour, cube) {
+        232 + step as u8
+    } else {
+        (16 + 36 * r + 6 * g + b) as u8
+    }
+}
+
+/// Index of the closest of the 16 ANSI colours.
+fn nearest_ansi(colour: Colour) -> usize {
+    (0..ANSI_COLOURS.len())
+        .min_by_key(|&i| distance(colour, ANSI_COLOURS[i]))
+        .unwrap_or(0)
+}
+
+/// Pictographs, as opposed to wide letters like CJK that every terminal
+/// draws two columns wide.
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x2300..=0x2BFF | 0x1F000..=0x1FAFF)
+}
+
+fn distance(a: Colour, b: Colour) -> u32 {
+    (0..3).map(|i| (a[i].abs_diff(b[i]) as u32).pow(2)).sum()
+}
+
+/// What the terminal can do beyond plain text, worked out from the
+/// environment it started us with.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalCapabilities {
+    /// Holds back drawing between the begin and end synchronized update
+    /// sequences, so a frame shows up all at once.
+    pub synchronized_output: bool,
+    /// How to draw images, if the terminal can.
+    pub graphics: Option<GraphicsProtocol>,
+    /// RGB colours are brought down to this many before they are sent.
+    pub colours: ColourDepth,
+    /// Understands no escape sequences at all (`TERM=dumb`), so frames go
+    /// out as plain text.
+    pub dumb: bool,
+    /// Draws emoji like 🚗 one column wide, though Unicode makes them two.
+    pub narrow_emoji: bool,
+    /// Draws a text glyph turned emoji by a variation selector, like 🛣️,
+    /// one column wide.
+    pub narrow_variation: bool,
+}
+
+impl TerminalCapabilities {
+    pub fn detect() -> Self {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let (program, term) = (var("TERM_PROGRAM"), var("TERM"));
+        let known = |programs: &[&str], terms: &[&str]| {
+            programs.contains(&program.as_str()) || terms.iter().any(|t| term.starts_with(t))
+        };
+        let graphics = if known(&KITTY_PROGRAMS, &KITTY_TERMS) {
+            Some(GraphicsProtocol::Kitty)
+        } else if known(&SIXEL_PROGRAMS, &SIXEL_TERMS) {
+            Some(GraphicsProtocol::Sixel)
+        } else {
+            None
+        };
+        // WT_SESSION is set by Windows Terminal.
+        let windows_terminal = env::var_os("WT_SESSION").is_some();
+        // https://no-color.org: set to anything but empty turns colour off.
+        let dumb = term == "dumb";
+        let colours = if !var("NO_COLOR").is_empty() || dumb {
+            ColourDepth::Monochrome
+        } else if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit")
+            || known(&TRUECOLOUR_PROGRAMS, &TRUECOLOUR_TERMS)
+            || windows_terminal
+        {
+            ColourDepth::TrueColour
+        } else if term.contains("256color") {
+            ColourDepth::Ansi256
+        } else {
+            ColourDepth::Ansi16
+        };
+        Self {
+            synchronized_output: known(&SYNC_PROGRAMS, &SYNC_TERMS) || windows_terminal,
+            graphics,
+            colours,
+            dumb,
+            ..Self::default()
+        }
+    }
+
+    /// Prints a sample of each kind of wide glyph at the top left and asks
+    /// the terminal where the cursor ended up, to learn which ones it draws
+    /// a column short. Terminals that don't answer are taken to get them
+    /// all right. Needs raw mode.
+    pub fn measure_glyph_widths(&mut self) -> io::Result<()> {
+        let mut out = stdout();
+        for (sample, narrow) in [
+            ("🚗", &mut self.narrow_emoji),
+            ("🛣\u{FE0F}", &mut self.narrow_variation),
+        ] {
+            execute!(out, MoveTo(0, 0), Print(sample))?;
+            let Ok((column, _)) = cursor::position() else {
+                break;
+            };
+            *narrow = column < 2;
+        }
+        execute!(out, MoveTo(0, 0), Clear(ClearType::CurrentLine))
+    }
+
+    /// Whether `glyph`, two columns wide by Unicode's reckoning, comes out
+    /// one column wide on this terminal.
+    pub fn draws_narrow(&self, glyph: &str) -> bool {
+        if glyph.ends_with('\u{FE0F}') {
+            self.narrow_variation
+        } else {
+            self.narrow_emoji && glyph.chars().next().is_some_and(is_emoji)
+        }
+    }
+}
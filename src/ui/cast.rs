@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{self, Stdout, Write, stdout};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crossterm::terminal;
+
+use crate::remote::http::json_string;
+
+/// Passes terminal output through to stdout and records it, with the time
+/// each frame went out, as an asciinema v2 cast that `asciinema play` or
+/// the web player can replay.
+pub struct CastRecorder {
+    stdout: Stdout,
+    file: File,
+    started: Instant,
+    /// Output since the last flush; a frame becomes one event.
+    pending: Vec<u8>,
+}
+
+impl CastRecorder {
+    /// Creates (or truncates) `path` and writes the header for the
+    /// terminal's current size.
+    pub fn create(path: &str) -> Result<Self, String> {
+        let (columns, rows) = match terminal::size() {
+            Ok((columns, rows)) if columns > 0 && rows > 0 => (columns, rows),
+            _ => (80, 24),
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let term = std::env::var("TERM").unwrap_or_else(|_| "xterm-256color".to_string());
+        let mut file =
+            File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        writeln!(
+            file,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"title\": \"Traffic Light Simulator\", \"env\": {{\"TERM\": {}}}}}",
+            columns,
+            rows,
+            timestamp,
+            json_string(&term)
+        )
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        Ok(Self {
+            stdout: stdout(),
+            file,
+            started: Instant::now(),
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl Write for CastRecorder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.stdout.write(buf)?;
+        self.pending.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        // Anything after the last whole character waits for the next flush.
+        let whole = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        if whole == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.pending[..whole]);
+        writeln!(
+            self.file,
+            "[{:.6}, \"o\", {}]",
+            self.started.elapsed().as_secs_f64(),
+            json_string(&text)
+        )?;
+        self.pending.drain(..whole);
+        self.file.flush()
+    }
+}
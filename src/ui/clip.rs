@@ -0,0 +1,285 @@
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, stdout};
+use std::time::Instant;
+
+use crossterm::terminal;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::mono_font::ascii::{FONT_6X13, FONT_6X13_BOLD};
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+use gif::{Encoder, Frame, Repeat};
+
+use crate::ui::backend::RenderBackend;
+use crate::ui::capabilities::{ColourDepth, TerminalCapabilities};
+use crate::ui::graphics::{Colour, GraphicsProtocol, Image, Picture, glyph_colour};
+use crate::ui::screen::{CellView, ScreenBuffer};
+use crate::ui::sprites::SpriteSet;
+
+/// Pixels per character cell: the size of the font's glyphs.
+const CELL: (usize, usize) = (6, 13);
+/// Screen size clips are drawn at when there is no terminal to measure, as
+/// in a headless run started from a script.
+const CLIP_SIZE: (u16, u16) = (100, 40);
+/// Most colours a GIF frame's palette holds.
+const PALETTE_SIZE: usize = 256;
+/// How hard the encoder works at reducing frames with more colours than
+/// that, from 1 (best) to 30 (fastest).
+const QUANTIZE_SPEED: i32 = 10;
+
+/// Draws every frame into an animated GIF on its way to another backend,
+/// so a demo clip comes straight out of a run, headless or not. Each frame
+/// stays up for as long as it did in the run.
+pub struct ClipBackend {
+    inner: Box<dyn RenderBackend>,
+    /// The text frames as a terminal of the clip's size would show them.
+    screen: ScreenBuffer,
+    /// Taken when the clip is finished.
+    encoder: Option<Encoder<BufWriter<File>>>,
+    /// The last frame drawn and when it went up. It is written once the
+    /// next different one arrives and its delay is known.
+    pending: Option<(Image, Instant)>,
+}
+
+impl ClipBackend {
+    /// Creates (or truncates) `path` for a clip the size of the terminal.
+    pub fn create(path: &str, inner: Box<dyn RenderBackend>) -> Result<Self, String> {
+        // Without a terminal crossterm falls back on `tput`, which reports
+        // the terminfo default rather than anything on screen.
+        let measured = stdout().is_terminal().then(terminal::size);
+        let (columns, rows) = match measured {
+            Some(Ok((columns, rows))) if columns > 0 && rows > 0 => (columns, rows),
+            _ => CLIP_SIZE,
+        };
+        // Clips keep every colour, whatever the terminal shows.
+        let mut screen = ScreenBuffer::new(TerminalCapabilities {
+            colours: ColourDepth::TrueColour,
+            ..TerminalCapabilities::default()
+        });
+        screen.resize(columns, rows);
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        let (width, height) = (columns as usize * CELL.0, rows as usize * CELL.1);
+        let mut encoder = Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        Ok(Self {
+            inner,
+            screen,
+            encoder: Some(encoder),
+            pending: None,
+        })
+    }
+
+    /// Writes out the pending frame, shown until now.
+    fn write_pending(&mut self) -> io::Result<()> {
+        let (Some(encoder), Some((image, shown))) = (self.encoder.as_mut(), self.pending.take())
+        else {
+            return Ok(());
+        };
+        let mut frame = encode_frame(&image);
+        // GIF delays are in hundredths of a second.
+        frame.delay = (shown.elapsed().as_millis() / 10).clamp(1, u16::MAX as u128) as u16;
+        encoder.write_frame(&frame).map_err(io::Error::other)
+    }
+}
+
+impl RenderBackend for ClipBackend {
+    fn init(&mut self) -> io::Result<()> {
+        self.inner.init()
+    }
+
+    fn present(&mut self, frame: &str, picture: Option<Picture>) {
+        // The clip keeps the size it started with, so only the cells matter.
+        self.screen.diff(frame, None);
+        let image = rasterize(&self.screen);
+        // An unchanged frame just stays up longer.
+        if self.pending.as_ref().is_none_or(|(last, _)| *last != image) {
+            self.write_pending().unwrap();
+            self.pending = Some((image, Instant::now()));
+        }
+        self.inner.present(frame, picture);
+    }
+
+    fn resize(&mut self, columns: u16, rows: u16) {
+        self.inner.resize(columns, rows);
+    }
+
+    /// Finishes the clip as well.
+    fn restore(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        // Dropping the encoder writes the GIF trailer and flushes the file.
+        self.encoder = None;
+        self.inner.restore()
+    }
+
+    fn graphics(&self) -> Option<GraphicsProtocol> {
+        self.inner.graphics()
+    }
+
+    /// The terminal's screen, or in a headless run the clip's own.
+    fn screen(&self) -> Option<&ScreenBuffer> {
+        self.inner.screen().or(Some(&self.screen))
+    }
+
+    fn set_base_colours(&mut self, foreground: Option<Colour>, background: Option<Colour>) {
+        self.screen.set_base_colours(foreground, background);
+        self.inner.set_base_colours(foreground, background);
+    }
+
+    /// Only the terminal's: the clip's tiles are coloured by the emoji.
+    fn set_sprites(&mut self, sprites: SpriteSet) {
+        self.inner.set_sprites(sprites);
+    }
+
+    fn monochrome(&self) -> bool {
+        self.inner.monochrome()
+    }
+}
+
+/// One GIF frame for `image`, with its exact colours when there are few
+/// enough of them.
+fn encode_frame(image: &Image) -> Frame<'static> {
+    let (width, height) = (image.width as u16, image.height as u16);
+    let mut palette: Vec<Colour> = Vec::new();
+    let mut indices = Vec::with_capacity(image.pixels().len());
+    for pixel in image.pixels() {
+        let index = match palette.iter().position(|c| c == pixel) {
+            Some(index) => index,
+            None if palette.len() < PALETTE_SIZE => {
+                palette.push(*pixel);
+                palette.len() - 1
+            }
+            None => {
+                let rgb: Vec<u8> = image.pixels().iter().flatten().copied().collect();
+                return Frame::from_rgb_speed(width, height, &rgb, QUANTIZE_SPEED);
+            }
+        };
+        indices.push(index as u8);
+    }
+    let palette: Vec<u8> = palette.into_iter().flatten().collect();
+    Frame::from_palette_pixels(width, height, indices, palette, None)
+}
+
+/// The screen's cells as pixels: each cell's background, then its glyph in
+/// the bitmap font. Map glyphs the font lacks are drawn as tiles in the
+/// pixel map's colours, and box drawing as lines.
+fn rasterize(screen: &ScreenBuffer) -> Image {
+    let (columns, rows) = screen.size();
+    let mut image = Image::new(columns as usize * CELL.0, rows as usize * CELL.1, [0, 0, 0]);
+    for (row, cells) in screen.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            // The right half of a wide glyph is drawn with the left.
+            if cell.text.is_empty() {
+                continue;
+            }
+            let wide = cells.get(column + 1).is_some_and(|c| c.text.is_empty());
+            let (x, y) = (column * CELL.0, row * CELL.1);
+            let right = x + if wide { 2 * CELL.0 } else { CELL.0 };
+            image.fill(x, y, right, y + CELL.1, cell.background);
+            draw_glyph(&mut image, x, y, right, cell);
+        }
+    }
+    image
+}
+
+fn draw_glyph(image: &mut Image, x: usize, y: usize, right: usize, cell: &CellView) {
+    let bottom = y + CELL.1;
+    if let Some(colour) = glyph_colour(cell.text) {
+        image.fill(x + 1, y + 2, right - 1, bottom - 2, colour);
+        return;
+    }
+    let Some(c) = cell.text.chars().next() else {
+        return;
+    };
+    let (middle, centre) = (y + CELL.1 / 2, x + CELL.0 / 2);
+    let (foreground, background) = (cell.foreground, cell.background);
+    match c {
+        '─' | '━' | '╌' => image.fill(x, middle, right, middle + 1, foreground),
+        '═' => {
+            image.fill(x, middle - 1, right, middle, foreground);
+            image.fill(x, middle + 1, right, middle + 2, foreground);
+        }
+        '│' | '┃' | '║' => image.fill(centre, y, centre + 1, bottom, foreground),
+        // Double-line corners are drawn single.
+        '┌' | '┐' | '└' | '┘' | '╔' | '╗' | '╚' | '╝' => {
+            let (left, across) = if matches!(c, '┌' | '└' | '╔' | '╚') {
+                (centre, right)
+            } else {
+                (x, centre + 1)
+            };
+            let (top, down) = if matches!(c, '┌' | '┐' | '╔' | '╗') {
+                (middle, bottom)
+            } else {
+                (y, middle + 1)
+            };
+            image.fill(left, middle, across, middle + 1, foreground);
+            image.fill(centre, top, centre + 1, down, foreground);
+        }
+        '▀' => image.fill(x, y, right, middle, foreground),
+        '█' => image.fill(x, y, right, bottom, foreground),
+        '░' | '▒' | '▓' => {
+            let share = match c {
+                '░' => 1,
+                '▒' => 2,
+                _ => 3,
+            };
+            let blend = |f: u8, b: u8| ((f as u16 * share + b as u16 * (4 - share)) / 4) as u8;
+            let colour = [0, 1, 2].map(|i| blend(foreground[i], background[i]));
+            image.fill(x, y, right, bottom, colour);
+        }
+        _ => {
+            let c = match c {
+                '▲' | '↑' => '^',
+                '▼' | '↓' => 'v',
+                '◀' | '←' => '<',
+                '▶' | '→' => '>',
+                c if c.is_ascii() => c,
+                // Anything else shows up as a grey tile, or the font's
+                // replacement glyph when narrow.
+                _ if right - x > CELL.0 => {
+                    image.fill(x + 1, y + 2, right - 1, bottom - 2, [128, 128, 128]);
+                    return;
+                }
+                c => c,
+            };
+            let font = if cell.bold {
+                &FONT_6X13_BOLD
+            } else {
+                &FONT_6X13
+            };
+            let [r, g, b] = foreground;
+            let style = MonoTextStyle::new(font, Rgb888::new(r, g, b));
+            let mut text = [0; 4];
+            let position = Point::new(x as i32, y as i32);
+            Text::with_baseline(c.encode_utf8(&mut text), position, style, Baseline::Top)
+                .draw(image)
+                .expect("drawing on an Image cannot fail");
+        }
+    }
+}
+
+impl OriginDimensions for Image {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl DrawTarget for Image {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, colour) in pixels {
+            if let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) {
+                self.fill(x, y, x + 1, y + 1, [colour.r(), colour.g(), colour.b()]);
+            }
+        }
+        Ok(())
+    }
+}
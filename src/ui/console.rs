@@ -0,0 +1,253 @@
+use crossterm::event::KeyCode;
+
+use crate::systems::clock;
+use crate::systems::construction::WORKS_SECONDS;
+use crate::systems::vehicle::VehicleType;
+use crate::systems::weather::{self, WeatherType};
+use crate::ui::input::Action;
+
+/// A parsed console command. Any key-bindable action can be typed by its
+/// `[keybindings]` name, so the console and the keyboard drive the same
+/// actions.
+#[derive(Debug, Clone)]
+pub enum Command {
+    Action(Action),
+    Spawn(VehicleType, Option<usize>),
+    Set(Setting, f32),
+    Save(String),
+    Select(u32),
+    TeleportTo(usize),
+    Theme(String),
+    Weather(WeatherType, f32),
+    /// Minutes past midnight.
+    Time(f32),
+    Debris(usize),
+    /// First and last cell, and seconds.
+    RoadWorks(usize, usize, f32),
+    /// Light number, counting from 1.
+    Camera(usize),
+    /// Light number, counting from 1.
+    Malfunction(usize),
+    RightOnRed,
+}
+
+/// Simulation parameters that `:set` can change while running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    SpawnRate,
+    BreakdownRate,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 2] = [Setting::SpawnRate, Setting::BreakdownRate];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Setting::SpawnRate => "spawn_rate",
+            Setting::BreakdownRate => "breakdown_rate",
+        }
+    }
+}
+
+impl Command {
+    /// Usage lines for the commands that take arguments.
+    pub const USAGE: [(&str, &str); 14] = [
+        (
+            "spawn <type> [cell]",
+            "Add a car, motorcycle, bicycle or tow_truck",
+        ),
+        (
+            "set <setting> <value>",
+            "Change spawn_rate or breakdown_rate (0-1)",
+        ),
+        ("save <file.json>", "Write the current state as JSON"),
+        ("select <id>", "Select a vehicle"),
+        ("teleport <cell>", "Move the selected vehicle"),
+        ("theme <name>", "Switch to dark, light, solarized or neon"),
+        (
+            "weather <type> [0-1]",
+            "Bring in clear, rain, storm, snow or fog",
+        ),
+        ("time <HH:MM>", "Move the clock on to a time of day"),
+        ("debris <cell>", "Block the road with debris until cleared"),
+        (
+            "works <from> <to> [seconds]",
+            "Slow a stretch of road with road works (60s)",
+        ),
+        (
+            "camera <light>",
+            "Put up or take down an enforcement camera",
+        ),
+        (
+            "malfunction <light>",
+            "Break a light down to flashing red until repaired",
+        ),
+        ("right_on_red", "Allow or forbid right turns on red"),
+        ("<action>", "Run any action listed above by name"),
+    ];
+
+    /// Parses a command line such as `spawn car 3` or `set spawn_rate 0.5`.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or("Empty command")?;
+        let args: Vec<&str> = words.collect();
+
+        let command = match (name, args.as_slice()) {
+            ("spawn", [kind, rest @ ..]) => {
+                let kind = VehicleType::from_key(kind)
+                    .ok_or_else(|| format!("Unknown vehicle type '{}'", kind))?;
+                let cell = match rest {
+                    [] => None,
+                    [cell] => Some(number(cell)?),
+                    _ => return Err("Usage: spawn <type> [cell]".to_string()),
+                };
+                Command::Spawn(kind, cell)
+            }
+            ("spawn", []) => {
+                let kinds: Vec<&str> = VehicleType::ALL.iter().map(|k| k.key()).collect();
+                return Err(format!("Usage: spawn <{}> [cell]", kinds.join("|")));
+            }
+            ("set", [setting, value]) => {
+                let setting = Setting::ALL
+                    .into_iter()
+                    .find(|s| s.name() == *setting)
+                    .ok_or_else(|| format!("Unknown setting '{}'", setting))?;
+                let value: f32 = value
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a number", value))?;
+                Command::Set(setting, value)
+            }
+            ("set", _) => return Err("Usage: set <setting> <value>".to_string()),
+            ("save", [path]) => Command::Save(path.to_string()),
+            ("save", _) => return Err("Usage: save <file.json>".to_string()),
+            ("select", [id]) => Command::Select(number(id)? as u32),
+            ("teleport", [cell]) => Command::TeleportTo(number(cell)?),
+            ("theme", [name]) => Command::Theme(name.to_string()),
+            ("theme", _) => return Err("Usage: theme <name>".to_string()),
+            ("weather", [kind, rest @ ..]) => {
+                let kind = WeatherType::from_name(kind).ok_or_else(|| {
+                    format!("Unknown weather '{}' (try {})", kind, weather::names())
+                })?;
+                let intensity = match rest {
+                    [] => 0.5,
+                    [value] => value
+                        .parse()
+                        .ok()
+                        .filter(|n| (0.0..=1.0).contains(n))
+                        .ok_or_else(|| format!("'{}' is not a number from 0 to 1", value))?,
+                    _ => return Err("Usage: weather <type> [intensity]".to_string()),
+                };
+                Command::Weather(kind, intensity)
+            }
+            ("time", [time]) => Command::Time(
+                clock::parse_time(time)
+                    .ok_or_else(|| format!("'{}' is not a time like 21:30", time))?,
+            ),
+            ("time", _) => return Err("Usage: time <HH:MM>".to_string()),
+            ("debris", [cell]) => Command::Debris(number(cell)?),
+            ("debris", _) => return Err("Usage: debris <cell>".to_string()),
+            ("works", [from, to, rest @ ..]) => {
+                let seconds = match rest {
+                    [] => WORKS_SECONDS,
+                    [value] => value
+                        .parse()
+                        .ok()
+                        .filter(|n: &f32| *n > 0.0)
+                        .ok_or_else(|| format!("'{}' is not a positive number", value))?,
+                    _ => return Err("Usage: works <from> <to> [seconds]".to_string()),
+                };
+                Command::RoadWorks(number(from)?, number(to)?, seconds)
+            }
+            ("works", _) => return Err("Usage: works <from> <to> [seconds]".to_string()),
+            ("camera", [light]) => Command::Camera(number(light)?),
+            ("camera", _) => return Err("Usage: camera <light>".to_string()),
+            ("malfunction", [light]) => Command::Malfunction(number(light)?),
+            ("malfunction", _) => return Err("Usage: malfunction <light>".to_string()),
+            ("right_on_red", []) => Command::RightOnRed,
+            ("weather", []) => {
+                return Err(format!(
+                    "Usage: weather <{}> [intensity]",
+                    weather::names().replace(", ", "|")
+                ));
+            }
+            (name, []) => Command::Action(
+                Action::from_name(name).ok_or_else(|| format!("Unknown command '{}'", name))?,
+            ),
+            (name, _) => return Err(format!("Unknown command '{}'", name)),
+        };
+        Ok(command)
+    }
+}
+
+fn number(text: &str) -> Result<usize, String> {
+    text.parse()
+        .map_err(|_| format!("'{}' is not a whole number", text))
+}
+
+pub enum ConsoleResult {
+    Pending,
+    Closed,
+    Submitted(String),
+}
+
+/// The `:` command line, with a history of earlier commands and the outcome
+/// of the last one.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    /// Result of the last command, and whether it failed.
+    pub output: Option<(String, bool)>,
+    history: Vec<String>,
+    /// How far back the Up key has gone in `history`.
+    recall: usize,
+}
+
+impl Console {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.input.clear();
+        self.recall = 0;
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> ConsoleResult {
+        match code {
+            KeyCode::Char(ch) => self.input.push(ch),
+            KeyCode::Backspace if self.input.is_empty() => {
+                self.open = false;
+                return ConsoleResult::Closed;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Up if self.recall < self.history.len() => {
+                self.recall += 1;
+                self.input = self.history[self.history.len() - self.recall].clone();
+            }
+            KeyCode::Down if self.recall > 0 => {
+                self.recall -= 1;
+                self.input = match self.recall {
+                    0 => String::new(),
+                    back => self.history[self.history.len() - back].clone(),
+                };
+            }
+            KeyCode::Enter => {
+                self.open = false;
+                let line = std::mem::take(&mut self.input).trim().to_string();
+                if line.is_empty() {
+                    return ConsoleResult::Closed;
+                }
+                if self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                }
+                return ConsoleResult::Submitted(line);
+            }
+            KeyCode::Esc => {
+                self.open = false;
+                return ConsoleResult::Closed;
+            }
+            _ => {}
+        }
+        ConsoleResult::Pending
+    }
+}
@@ -0,0 +1,480 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+
+use crossterm::style::{Color, Stylize};
+use crossterm::terminal;
+
+use crate::remote::websocket::base64;
+use crate::systems::road::Facility;
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+use crate::systems::vehicle::{Lane, Vehicle, VehicleType};
+
+/// Image id the map is transmitted under, so each frame replaces the last.
+const KITTY_IMAGE: u32 = 1;
+/// Most base64 bytes the Kitty protocol takes in one escape sequence.
+const KITTY_CHUNK: usize = 4096;
+/// Pixels per terminal column and row the map is drawn at for Kitty, which
+/// scales the image to the cells it covers.
+const KITTY_CELL: (usize, usize) = (4, 8);
+/// Cell size for Sixel when the terminal doesn't report its pixel size.
+const SIXEL_CELL: (usize, usize) = (8, 16);
+
+pub type Colour = [u8; 3];
+
+const ASPHALT: Colour = [58, 58, 64];
+const MARKING: Colour = [190, 190, 180];
+const BIKE_PATH: Colour = [52, 92, 60];
+const VERGE: Colour = [36, 120, 48];
+const SPAWN: Colour = [70, 110, 210];
+const SPAWN_OFF: Colour = [150, 40, 40];
+const OUTLINE: Colour = [255, 255, 255];
+const BROKEN_DOWN: Colour = [235, 90, 30];
+const STREETLIGHT: Colour = [255, 210, 110];
+const DEBRIS: Colour = [120, 150, 60];
+const CONES: Colour = [250, 120, 20];
+const BARRICADE: Colour = [220, 220, 220];
+const SCHOOL: Colour = [250, 200, 40];
+const CAMERA: Colour = [30, 30, 36];
+const TOLL_BOOTH: Colour = [60, 170, 90];
+const CAR_PARK: Colour = [40, 90, 200];
+const DELIVERY: Colour = [200, 160, 90];
+const DECK: Colour = [120, 90, 60];
+const WATER: Colour = [40, 90, 180];
+const BOAT: Colour = [240, 240, 240];
+const VENUE: Colour = [150, 90, 200];
+const HAZARD: Colour = [255, 160, 0];
+const HOSPITAL: Colour = [240, 240, 240];
+const FIRE_STATION: Colour = [200, 30, 30];
+
+/// Ways the map can be drawn as pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Sixel,
+    /// `▀` characters with the top pixel as the foreground colour and the
+    /// bottom one as the background, so any true-colour terminal can show
+    /// two pixels per cell as plain text.
+    HalfBlocks,
+}
+
+impl GraphicsProtocol {
+    /// Pixels per terminal column and row to draw at.
+    pub fn cell_pixels(self) -> (usize, usize) {
+        match self {
+            GraphicsProtocol::Kitty => KITTY_CELL,
+            GraphicsProtocol::HalfBlocks => (1, 2),
+            GraphicsProtocol::Sixel => match terminal::window_size() {
+                Ok(size) if size.width > 0 && size.columns > 0 && size.rows > 0 => (
+                    (size.width / size.columns) as usize,
+                    (size.height / size.rows) as usize,
+                ),
+                _ => SIXEL_CELL,
+            },
+        }
+    }
+}
+
+/// An RGB picture, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, colour: Colour) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![colour; width * height],
+        }
+    }
+
+    /// Paints the rectangle from `(x, y)` to just short of `(right, bottom)`,
+    /// clipped to the image.
+    pub fn fill(&mut self, x: usize, y: usize, right: usize, bottom: usize, colour: Colour) {
+        for row in y..bottom.min(self.height) {
+            let line = row * self.width;
+            for pixel in &mut self.pixels[line + x.min(self.width)..line + right.min(self.width)] {
+                *pixel = colour;
+            }
+        }
+    }
+
+    #[cfg(feature = "gif")]
+    pub fn pixels(&self) -> &[Colour] {
+        &self.pixels
+    }
+
+    /// Turns the image upside down.
+    pub fn flip(&mut self) {
+        let width = self.width;
+        for top in 0..self.height / 2 {
+            let bottom = self.height - 1 - top;
+            let (upper, lower) = self.pixels.split_at_mut(bottom * width);
+            upper[top * width..(top + 1) * width].swap_with_slice(&mut lower[..width]);
+        }
+    }
+
+    /// Pixel rows `2 * line` and `2 * line + 1` as a row of half blocks.
+    pub fn half_block_line(&self, line: usize) -> String {
+        let colour = |y: usize, x: usize| {
+            let [r, g, b] = self.pixels[y.min(self.height - 1) * self.width + x];
+            Color::Rgb { r, g, b }
+        };
+        (0..self.width)
+            .map(|x| {
+                format!(
+                    "{}",
+                    "▀".with(colour(2 * line, x)).on(colour(2 * line + 1, x))
+                )
+            })
+            .collect()
+    }
+
+    /// A rectangle's one-pixel border.
+    fn outline(&mut self, x: usize, y: usize, right: usize, bottom: usize, colour: Colour) {
+        self.fill(x, y, right, y + 1, colour);
+        self.fill(x, bottom.saturating_sub(1), right, bottom, colour);
+        self.fill(x, y, x + 1, bottom, colour);
+        self.fill(right.saturating_sub(1), y, right, bottom, colour);
+    }
+}
+
+/// An image to show over the text frame, covering `columns` by `rows`
+/// cells from the zero-based `row` and `column`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picture {
+    pub row: u16,
+    pub column: u16,
+    pub columns: usize,
+    pub rows: usize,
+    pub image: Image,
+}
+
+impl Picture {
+    /// The escape sequences that draw it at the cursor.
+    pub fn encode(&self, protocol: GraphicsProtocol) -> String {
+        match protocol {
+            GraphicsProtocol::Kitty => kitty(&self.image, self.columns, self.rows),
+            GraphicsProtocol::Sixel => sixel(&self.image),
+            GraphicsProtocol::HalfBlocks => {
+                unreachable!("half blocks are drawn as part of the text frame")
+            }
+        }
+    }
+}
+
+/// Tells a Kitty terminal to forget the map image and take it off screen.
+pub fn kitty_delete() -> String {
+    format!("\x1B_Ga=d,d=I,i={},q=2\x1B\\", KITTY_IMAGE)
+}
+
+/// The lanes of road `cells` as pixel tiles: asphalt with its markings,
+/// spawn points, a stop line per signal in its colour, and each vehicle
+/// as a block as long as it is. Each road cell is two columns wide, the
+/// road two rows tall, and the bike lane one row plus its verge.
+pub fn road_image(
+    sim: &Simulation,
+    cells: Range<usize>,
+    inspected: Option<u32>,
+    (column, row): (usize, usize),
+) -> Image {
+    let road = &sim.road;
+    let cell = 2 * column;
+    let mark = (column / 2).max(1);
+    let rows = if road.bike_lane { 4 } else { 2 };
+    let mut image = Image::new(cells.len() * cell, rows * row, ASPHALT);
+    let x_of = |position: f32| ((position - cells.start as f32).max(0.0) * cell as f32) as usize;
+
+    for index in cells.clone().step_by(2) {
+        let x = x_of(index as f32);
+        image.fill(x, 2 * row - 2, x + column, 2 * row - 1, MARKING);
+    }
+    if road.bike_lane {
+        image.fill(0, 2 * row, image.width, 3 * row, BIKE_PATH);
+        for index in cells.clone() {
+            let x = x_of(index as f32);
+            image.fill(
+                x,
+                3 * row + row / 3,
+                x + column,
+                3 * row + row * 2 / 3,
+                VERGE,
+            );
+        }
+    }
+    for &spawn in road.spawn_points.iter().filter(|&&c| cells.contains(&c)) {
+        let colour = if sim.spawn_open(spawn) {
+            SPAWN
+        } else {
+            SPAWN_OFF
+        };
+        let x = x_of(spawn as f32);
+        image.fill(x, row + row / 4, x + mark, 2 * row - row / 4, colour);
+    }
+    for &(cell, facility) in road.facilities.iter().filter(|f| cells.contains(&f.0)) {
+        let x = x_of(cell as f32);
+        image.fill(
+            x,
+            row + row / 4,
+            x + mark,
+            2 * row - row / 4,
+            facility_colour(facility),
+        );
+    }
+    for light in sim.lights.iter().filter(|l| cells.contains(&l.position)) {
+        let x = x_of(light.position as f32);
+        image.fill(x, 0, x + mark, 2 * row, light_colour(light.state));
+        if light.camera.is_some() {
+            image.fill(x.saturating_sub(mark), 0, x, row / 2, CAMERA);
+        }
+        if road.bike_lane {
+            image.fill(
+                x,
+                2 * row,
+                x + mark,
+                3 * row,
+                light_colour(light.bike_phase()),
+            );
+        }
+    }
+
+    for lot in sim.parking_lots.iter().filter(|l| cells.contains(&l.cell)) {
+        let x = x_of(lot.cell as f32);
+        image.fill(x, row + row / 4, x + mark, 2 * row - row / 4, CAR_PARK);
+    }
+    for booth in sim.toll_booths.iter().filter(|b| cells.contains(&b.cell)) {
+        let x = x_of(booth.cell as f32);
+        image.fill(x, row / 4, x + mark, row - row / 4, TOLL_BOOTH);
+    }
+    if let Some(venue) = sim.special_event.venue.filter(|c| cells.contains(c)) {
+        let x = x_of(venue as f32);
+        image.fill(x, row + row / 4, x + mark, 2 * row - row / 4, VENUE);
+    }
+    if let Some(bridge) = &sim.bridge {
+        let middle = (bridge.from + bridge.to) as f32 / 2.0;
+        let open = bridge.lift() * (bridge.to - bridge.from + 1) as f32 / 2.0;
+        for cell in (bridge.from..=bridge.to).filter(|c| cells.contains(c)) {
+            let colour = if (cell as f32 - middle).abs() < open {
+                WATER
+            } else {
+                DECK
+            };
+            let x = x_of(cell as f32);
+            image.fill(x, row / 4, x + column, row + row * 3 / 4, colour);
+        }
+    }
+    for zone in sim.school_zones.iter().filter(|z| z.active) {
+        for cell in (zone.from..=zone.to).filter(|c| cells.contains(c)) {
+            let x = x_of(cell as f32);
+            image.fill(x, row + row * 3 / 4, x + column, 2 * row, SCHOOL);
+        }
+    }
+    for closure in sim.closures.iter().filter(|c| c.active) {
+        for cell in (closure.from..=closure.to).filter(|c| cells.contains(c)) {
+            let x = x_of(cell as f32);
+            image.fill(x, row / 2, x + mark, row + row / 2, BARRICADE);
+        }
+    }
+    for works in &sim.road_works {
+        for cone in (works.from..=works.to).filter(|c| cells.contains(c)) {
+            let x = x_of(cone as f32);
+            image.fill(x, row + row * 3 / 4, x + mark, 2 * row, CONES);
+        }
+    }
+    for debris in sim.debris.iter().filter(|d| cells.contains(&d.cell)) {
+        let x = x_of(debris.cell as f32);
+        image.fill(x, row / 2, x + cell, row + row / 2, DEBRIS);
+    }
+    for delivery in sim.deliveries.iter().filter(|d| cells.contains(&d.cell)) {
+        let x = x_of(delivery.cell as f32);
+        image.fill(x, row / 2, x + cell, row + row / 2, DELIVERY);
+    }
+
+    let visible = cells.start as f32..cells.end as f32;
+    for vehicle in sim
+        .vehicles
+        .iter()
+        .filter(|v| visible.contains(&v.position) || visible.contains(&v.rear()))
+    {
+        let (top, bottom) = match vehicle.lane {
+            Lane::Road => (row / 2, row + row / 2),
+            Lane::Bike => (2 * row + row / 4, 3 * row - row / 4),
+        };
+        let (left, right) = (x_of(vehicle.rear()), x_of(vehicle.position));
+        let right = right.max(left + 2).min(image.width);
+        image.fill(left, top, right, bottom, vehicle_colour(vehicle));
+        if inspected == Some(vehicle.id) {
+            image.outline(left, top, right, bottom, OUTLINE);
+        }
+    }
+    // Driving on the left, the curb and bike lane are on the other side.
+    if sim.drivers.drive_on_left {
+        image.flip();
+    }
+    image
+}
+
+fn light_colour(state: TrafficLightState) -> Colour {
+    match state {
+        TrafficLightState::Red => [220, 40, 40],
+        TrafficLightState::Yellow => [235, 200, 40],
+        TrafficLightState::Green => [40, 200, 70],
+    }
+}
+
+fn vehicle_colour(vehicle: &Vehicle) -> Colour {
+    if vehicle.broken_down {
+        return BROKEN_DOWN;
+    }
+    kind_colour(vehicle.kind)
+}
+
+fn facility_colour(facility: Facility) -> Colour {
+    match facility {
+        Facility::Hospital => HOSPITAL,
+        Facility::FireStation => FIRE_STATION,
+    }
+}
+
+fn kind_colour(kind: VehicleType) -> Colour {
+    match kind {
+        VehicleType::Car => [70, 130, 230],
+        VehicleType::Motorcycle => [200, 110, 220],
+        VehicleType::Bicycle => [140, 230, 140],
+        VehicleType::TowTruck => [240, 210, 60],
+        VehicleType::Ambulance => [245, 245, 245],
+        VehicleType::FireTruck => [220, 50, 40],
+        VehicleType::Police => [40, 70, 200],
+    }
+}
+
+/// The tile colour for a glyph of the text map: a signal, vehicle, spawn
+/// point or stretch of road. Anything else has none.
+pub fn glyph_colour(glyph: &str) -> Option<Colour> {
+    // Some glyphs carry a variation selector asking for emoji presentation.
+    let glyph = glyph.trim_end_matches('\u{FE0F}');
+    let states = [
+        TrafficLightState::Red,
+        TrafficLightState::Yellow,
+        TrafficLightState::Green,
+    ];
+    if let Some(&state) = states.iter().find(|s| s.symbol() == glyph) {
+        return Some(light_colour(state));
+    }
+    if let Some(&kind) = VehicleType::ALL.iter().find(|k| k.sprite() == glyph) {
+        return Some(kind_colour(kind));
+    }
+    match glyph {
+        "🛣" => Some(ASPHALT),
+        "⏬" => Some(SPAWN),
+        "⛔" => Some(SPAWN_OFF),
+        "💥" => Some(BROKEN_DOWN),
+        "🅿" => Some(kind_colour(VehicleType::Car)),
+        "💡" => Some(STREETLIGHT),
+        "🌿" => Some(DEBRIS),
+        "⚠" => Some(HAZARD),
+        "🚧" => Some(CONES),
+        "🏟" => Some(VENUE),
+        "🚫" => Some(BARRICADE),
+        "🌉" => Some(DECK),
+        "🌊" => Some(WATER),
+        "🚨" => Some(light_colour(TrafficLightState::Red)),
+        "⛵" => Some(BOAT),
+        "🚸" => Some(SCHOOL),
+        "📷" => Some(CAMERA),
+        "🎫" => Some(TOLL_BOOTH),
+        "🏬" => Some(CAR_PARK),
+        "🚚" => Some(DELIVERY),
+        "🟠" => Some(HAZARD),
+        "🏥" => Some(facility_colour(Facility::Hospital)),
+        "🧯" => Some(facility_colour(Facility::FireStation)),
+        _ => None,
+    }
+}
+
+/// Kitty graphics protocol: raw RGB, base64 encoded and sent in chunks,
+/// scaled by the terminal to `columns` by `rows` cells. The cursor stays
+/// where it was.
+fn kitty(image: &Image, columns: usize, rows: usize) -> String {
+    let data: Vec<u8> = image.pixels.iter().flatten().copied().collect();
+    let encoded = base64(&data);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+        if index == 0 {
+            write!(
+                out,
+                "\x1B_Ga=T,f=24,s={},v={},c={},r={},i={},C=1,q=2,m={};{}\x1B\\",
+                image.width, image.height, columns, rows, KITTY_IMAGE, more, chunk
+            )
+        } else {
+            write!(out, "\x1B_Gm={};{}\x1B\\", more, chunk)
+        }
+        .expect("writing to a String cannot fail");
+    }
+    out
+}
+
+/// Sixel: the image in bands six pixels tall, one pass per colour, with
+/// runs of the same column pattern compressed.
+fn sixel(image: &Image) -> String {
+    let mut palette: Vec<Colour> = Vec::new();
+    for pixel in &image.pixels {
+        if !palette.contains(pixel) {
+            palette.push(*pixel);
+        }
+    }
+
+    let mut out = format!("\x1BP0;1q\"1;1;{};{}", image.width, image.height);
+    for (index, [r, g, b]) in palette.iter().enumerate() {
+        let percent = |c: &u8| *c as u32 * 100 / 255;
+        write!(
+            out,
+            "#{};2;{};{};{}",
+            index,
+            percent(r),
+            percent(g),
+            percent(b)
+        )
+        .expect("writing to a String cannot fail");
+    }
+    for band in (0..image.height).step_by(6) {
+        for (index, colour) in palette.iter().enumerate() {
+            let sixels: Vec<u8> = (0..image.width)
+                .map(|x| {
+                    (0..6)
+                        .filter(|dy| {
+                            let y = band + dy;
+                            y < image.height && image.pixels[y * image.width + x] == *colour
+                        })
+                        .fold(0, |bits, dy| bits | 1 << dy)
+                })
+                .collect();
+            if sixels.iter().all(|&bits| bits == 0) {
+                continue;
+            }
+            write!(out, "#{}", index).expect("writing to a String cannot fail");
+            let mut x = 0;
+            while x < sixels.len() {
+                let run = sixels[x..].iter().take_while(|&&s| s == sixels[x]).count();
+                let glyph = (63 + sixels[x]) as char;
+                if run > 3 {
+                    write!(out, "!{}{}", run, glyph).expect("writing to a String cannot fail");
+                } else {
+                    out.extend(std::iter::repeat_n(glyph, run));
+                }
+                x += run;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1B\\");
+    out
+}
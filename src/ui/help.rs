@@ -0,0 +1,77 @@
+use std::fmt::{self, Write};
+
+use crossterm::style::Stylize;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::console::Command;
+use crate::ui::input::{Action, InputHandler, key_name};
+
+/// Screen position of the help box's top-left corner (1-based).
+const TOP: usize = 2;
+const LEFT: usize = 4;
+
+/// Lines of the help overlay, built from the key bindings actually in effect
+/// so remapped keys show up correctly.
+pub fn help_lines(input: &InputHandler) -> Vec<String> {
+    let mut lines = vec!["KEYS".to_string()];
+    for action in Action::ALL {
+        let keys: Vec<String> = input.keys_for(action).into_iter().map(key_name).collect();
+        let keys = if keys.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            keys.join(" ")
+        };
+        lines.push(format!(
+            "  {:<12} {:<16} {}",
+            keys,
+            action.name(),
+            action.description()
+        ));
+    }
+    lines.push(String::new());
+    lines.push("MOUSE".to_string());
+    lines.push("  Click a signal, spawn point or vehicle to inspect or toggle it".to_string());
+    lines.push(String::new());
+    lines.push("CONSOLE COMMANDS".to_string());
+    for (usage, description) in Command::USAGE {
+        lines.push(format!("  :{:<22} {}", usage, description));
+    }
+    lines
+}
+
+/// Dims the frame already in `out` and draws a boxed overlay on top of it.
+pub fn draw_overlay(out: &mut String, title: &str, lines: &[String]) -> fmt::Result {
+    let dimmed: String = out
+        .lines()
+        .map(|line| format!("{}\n", line.dim()))
+        .collect();
+    *out = dimmed;
+
+    let footer = "any key closes";
+    let width = lines
+        .iter()
+        .map(|line| line.width())
+        .chain([title.width() + 4, footer.width() + 3])
+        .max()
+        .unwrap_or(0)
+        + 2;
+
+    write!(out, "\x1B[{};{}H", TOP, LEFT)?;
+    write!(
+        out,
+        "╔═ {} {}╗",
+        title.bold(),
+        "═".repeat(width - title.width() - 3)
+    )?;
+    for (index, line) in lines.iter().enumerate() {
+        write!(out, "\x1B[{};{}H", TOP + 1 + index, LEFT)?;
+        write!(out, "║ {}{} ║", line, " ".repeat(width - 2 - line.width()))?;
+    }
+    write!(out, "\x1B[{};{}H", TOP + 1 + lines.len(), LEFT)?;
+    write!(
+        out,
+        "╚{} {} ═╝",
+        "═".repeat(width - footer.width() - 3),
+        footer
+    )
+}
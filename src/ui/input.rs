@@ -0,0 +1,352 @@
+use crossterm::event::KeyCode;
+
+use crate::systems::config::Config;
+
+/// Everything a key can be bound to while the simulation is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    OpenEditor,
+    OpenSandbox,
+    OpenConsole,
+    ToggleHelp,
+    TogglePause,
+    Step,
+    RewindBack,
+    RewindForward,
+    Speed1,
+    Speed2,
+    Speed3,
+    Speed4,
+    Speed5,
+    PanLeft,
+    PanRight,
+    ToggleZoom,
+    FollowNext,
+    StopFollowing,
+    SelectVehicle,
+    Teleport,
+    Despawn,
+    ClearSelection,
+    Screenshot,
+    CycleTheme,
+    CycleSprites,
+    ZoomJunction,
+    OpenTimingEditor,
+    ToggleLoops,
+    CycleWeather,
+    DispatchEmergency,
+}
+
+impl Action {
+    pub const ALL: [Action; 31] = [
+        Action::Quit,
+        Action::OpenEditor,
+        Action::OpenSandbox,
+        Action::OpenConsole,
+        Action::ToggleHelp,
+        Action::TogglePause,
+        Action::Step,
+        Action::RewindBack,
+        Action::RewindForward,
+        Action::Speed1,
+        Action::Speed2,
+        Action::Speed3,
+        Action::Speed4,
+        Action::Speed5,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::ToggleZoom,
+        Action::FollowNext,
+        Action::StopFollowing,
+        Action::SelectVehicle,
+        Action::Teleport,
+        Action::Despawn,
+        Action::ClearSelection,
+        Action::Screenshot,
+        Action::CycleTheme,
+        Action::CycleSprites,
+        Action::ZoomJunction,
+        Action::OpenTimingEditor,
+        Action::ToggleLoops,
+        Action::CycleWeather,
+        Action::DispatchEmergency,
+    ];
+
+    /// Name used for the action in the `[keybindings]` config section.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::OpenEditor => "open_editor",
+            Action::OpenSandbox => "open_sandbox",
+            Action::OpenConsole => "open_console",
+            Action::ToggleHelp => "toggle_help",
+            Action::TogglePause => "toggle_pause",
+            Action::Step => "step",
+            Action::RewindBack => "rewind_back",
+            Action::RewindForward => "rewind_forward",
+            Action::Speed1 => "speed_1",
+            Action::Speed2 => "speed_2",
+            Action::Speed3 => "speed_3",
+            Action::Speed4 => "speed_4",
+            Action::Speed5 => "speed_5",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::ToggleZoom => "toggle_zoom",
+            Action::FollowNext => "follow_next",
+            Action::StopFollowing => "stop_following",
+            Action::SelectVehicle => "select_vehicle",
+            Action::Teleport => "teleport",
+            Action::Despawn => "despawn",
+            Action::ClearSelection => "clear_selection",
+            Action::Screenshot => "screenshot",
+            Action::CycleTheme => "cycle_theme",
+            Action::CycleSprites => "cycle_sprites",
+            Action::ZoomJunction => "zoom_junction",
+            Action::OpenTimingEditor => "open_timing_editor",
+            Action::ToggleLoops => "toggle_loops",
+            Action::CycleWeather => "cycle_weather",
+            Action::DispatchEmergency => "dispatch_emergency",
+        }
+    }
+
+    /// One-line explanation shown in the help overlay.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::OpenEditor => "Open the map editor",
+            Action::OpenSandbox => "Sandbox: place vehicles, incidents and light states",
+            Action::OpenConsole => "Open the command console",
+            Action::ToggleHelp => "Show or hide this help",
+            Action::TogglePause => "Pause or resume",
+            Action::Step => "Advance one frame while paused",
+            Action::RewindBack => "Rewind",
+            Action::RewindForward => "Scrub forward after rewinding",
+            Action::Speed1 => "Play at 0.25x",
+            Action::Speed2 => "Play at 0.5x",
+            Action::Speed3 => "Play at normal speed",
+            Action::Speed4 => "Play at 5x",
+            Action::Speed5 => "Play at 10x",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::ToggleZoom => "Switch between detail and overview",
+            Action::FollowNext => "Follow the next vehicle",
+            Action::StopFollowing => "Stop following",
+            Action::SelectVehicle => "Select a vehicle by ID",
+            Action::Teleport => "Teleport the selected vehicle",
+            Action::Despawn => "Remove the selected vehicle",
+            Action::ClearSelection => "Clear the selection",
+            Action::Screenshot => "Save the screen as .ans and .txt files",
+            Action::CycleTheme => "Switch to the next colour theme",
+            Action::CycleSprites => "Draw the road with emoji, unicode or ascii sprites",
+            Action::ZoomJunction => "Zoom into the clicked signal's intersection",
+            Action::OpenTimingEditor => "Retime the clicked signal live",
+            Action::ToggleLoops => "Show or hide the induction loop debug overlay",
+            Action::CycleWeather => "Turn the weather to the next kind",
+            Action::DispatchEmergency => "Send an ambulance in from the first spawn point",
+        }
+    }
+
+    /// Playback speed a `speed_N` action switches to.
+    pub fn time_scale(self) -> Option<f32> {
+        match self {
+            Action::Speed1 => Some(0.25),
+            Action::Speed2 => Some(0.5),
+            Action::Speed3 => Some(1.0),
+            Action::Speed4 => Some(5.0),
+            Action::Speed5 => Some(10.0),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Action::Quit => &[KeyCode::Char('q')],
+            Action::OpenEditor => &[KeyCode::Char('e')],
+            Action::OpenSandbox => &[KeyCode::Char('g')],
+            Action::OpenConsole => &[KeyCode::Char(':')],
+            Action::ToggleHelp => &[KeyCode::Char('?'), KeyCode::F(1)],
+            Action::TogglePause => &[KeyCode::Char('p'), KeyCode::Char(' ')],
+            Action::Step => &[KeyCode::Char('.')],
+            Action::RewindBack => &[KeyCode::Char('[')],
+            Action::RewindForward => &[KeyCode::Char(']')],
+            Action::Speed1 => &[KeyCode::Char('1')],
+            Action::Speed2 => &[KeyCode::Char('2')],
+            Action::Speed3 => &[KeyCode::Char('3')],
+            Action::Speed4 => &[KeyCode::Char('4')],
+            Action::Speed5 => &[KeyCode::Char('5')],
+            Action::PanLeft => &[KeyCode::Left],
+            Action::PanRight => &[KeyCode::Right],
+            Action::ToggleZoom => &[KeyCode::Char('z')],
+            Action::FollowNext => &[KeyCode::Char('f')],
+            Action::StopFollowing => &[KeyCode::Char('F')],
+            Action::SelectVehicle => &[KeyCode::Char('i'), KeyCode::Char('#')],
+            Action::Teleport => &[KeyCode::Char('t')],
+            Action::Despawn => &[KeyCode::Char('x')],
+            Action::ClearSelection => &[KeyCode::Esc],
+            Action::Screenshot => &[KeyCode::Char('S')],
+            Action::CycleTheme => &[KeyCode::Char('T')],
+            Action::CycleSprites => &[KeyCode::Char('A')],
+            Action::ZoomJunction => &[KeyCode::Enter],
+            Action::OpenTimingEditor => &[KeyCode::Char('r')],
+            Action::ToggleLoops => &[KeyCode::Char('L')],
+            Action::CycleWeather => &[KeyCode::Char('w')],
+            Action::DispatchEmergency => &[KeyCode::Char('E')],
+        }
+    }
+}
+
+/// Maps key presses to actions, starting from the defaults and applying any
+/// overrides from the `[keybindings]` config section.
+pub struct InputHandler {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl InputHandler {
+    pub fn new() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .flat_map(|action| action.default_keys().iter().map(move |&key| (key, action)))
+            .collect();
+        Self { bindings }
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut handler = Self::new();
+        for (name, value) in config.section("keybindings") {
+            let action = Action::from_name(name)
+                .ok_or_else(|| format!("Unknown action '{}' in [keybindings]", name))?;
+            handler.bindings.retain(|&(_, bound)| bound != action);
+            for key_name in value.split(',').map(str::trim) {
+                let key = parse_key(key_name)
+                    .ok_or_else(|| format!("Unknown key '{}' for action '{}'", key_name, name))?;
+                handler.bindings.push((key, action));
+            }
+        }
+
+        for (index, &(key, action)) in handler.bindings.iter().enumerate() {
+            if let Some(&(_, other)) = handler.bindings[index + 1..]
+                .iter()
+                .find(|&&(k, a)| k == key && a != action)
+            {
+                return Err(format!(
+                    "Key {} is bound to both '{}' and '{}'",
+                    key_name(key),
+                    action.name(),
+                    other.name()
+                ));
+            }
+        }
+        Ok(handler)
+    }
+
+    pub fn keys_for(&self, action: Action) -> Vec<KeyCode> {
+        self.bindings
+            .iter()
+            .filter(|&&(_, bound)| bound == action)
+            .map(|&(key, _)| key)
+            .collect()
+    }
+
+    pub fn action_for(&self, key: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+}
+
+/// Parses a key name as written in the config: a single character, or one of
+/// `Left`, `Right`, `Up`, `Down`, `Esc`, `Enter`, `Tab`, `Backspace`,
+/// `Space`, or `F1`-`F12`.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(ch));
+    }
+    let key = match name.to_ascii_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        other => {
+            let number = other.strip_prefix('f')?.parse().ok()?;
+            if !(1..=12).contains(&number) {
+                return None;
+            }
+            KeyCode::F(number)
+        }
+    };
+    Some(key)
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(ch) => ch.to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weather_and_emergency_keys_can_be_remapped() {
+        let defaults = InputHandler::new();
+        assert_eq!(
+            defaults.action_for(KeyCode::Char('w')),
+            Some(Action::CycleWeather)
+        );
+        assert_eq!(
+            defaults.action_for(KeyCode::Char('E')),
+            Some(Action::DispatchEmergency)
+        );
+
+        let config =
+            Config::parse("[keybindings]\ncycle_weather = F5\ndispatch_emergency = !\n").unwrap();
+        let handler = InputHandler::from_config(&config).unwrap();
+        assert_eq!(
+            handler.action_for(KeyCode::F(5)),
+            Some(Action::CycleWeather)
+        );
+        assert_eq!(
+            handler.action_for(KeyCode::Char('!')),
+            Some(Action::DispatchEmergency)
+        );
+        assert_eq!(handler.action_for(KeyCode::Char('w')), None);
+    }
+
+    #[test]
+    fn the_example_config_binds_every_action() {
+        let config = Config::parse(include_str!("../../config.example")).unwrap();
+        let bound: Vec<&str> = config
+            .section("keybindings")
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        for action in Action::ALL {
+            assert!(bound.contains(&action.name()), "{} missing", action.name());
+        }
+        InputHandler::from_config(&config).unwrap();
+    }
+}
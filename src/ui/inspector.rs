@@ -0,0 +1,107 @@
+use std::fmt::{self, Write};
+
+use crate::systems::simulation::Simulation;
+use crate::systems::vehicle::{Lane, Vehicle};
+use crate::ui::phase_diagram::render_phase_diagram;
+
+/// Panel describing a single vehicle, shown while it is selected or the
+/// camera follows it.
+pub fn render_vehicle_panel(
+    out: &mut String,
+    sim: &Simulation,
+    vehicle: &Vehicle,
+    title: &str,
+) -> fmt::Result {
+    writeln!(out, "┌─ {} #{} ─────────────", title, vehicle.id)?;
+    writeln!(
+        out,
+        "│ Type:   {} {}",
+        vehicle.kind.sprite(),
+        vehicle.kind.name()
+    )?;
+    writeln!(out, "│ State:  {}", vehicle.state_label())?;
+    writeln!(out, "│ Speed:  {:.1} cells/s", vehicle.speed)?;
+    writeln!(out, "│ Limit:  {}", limit_text(sim, vehicle.cell()))?;
+    writeln!(out, "│ Waited: {:.1}s", vehicle.waited_time)?;
+    match vehicle.turns_at {
+        Some(index) => writeln!(
+            out,
+            "│ Route:  from cell {}, now at {:.1}, turning {} at light {}",
+            vehicle.origin,
+            vehicle.position,
+            sim.drivers.curb_side(),
+            index + 1
+        )?,
+        None => writeln!(
+            out,
+            "│ Route:  from cell {}, now at {:.1}, heading for the exit at {}",
+            vehicle.origin, vehicle.position, sim.road.length
+        )?,
+    }
+    match sim.road.next_signal(vehicle.position) {
+        Some(index) => {
+            let light = &sim.lights[index];
+            writeln!(
+                out,
+                "│ Ahead:  light {} at {} {} ({})",
+                index + 1,
+                light.position,
+                light.state.symbol(),
+                light.countdown()
+            )?;
+        }
+        None => writeln!(out, "│ Ahead:  no more signals")?,
+    }
+    writeln!(out, "└────────────────────────────")?;
+    writeln!(out, "  x despawn   t teleport   Esc deselect")?;
+    Ok(())
+}
+
+/// Panel for a signal picked by clicking it.
+pub fn render_light_panel(out: &mut String, sim: &Simulation, index: usize) -> fmt::Result {
+    let light = &sim.lights[index];
+    writeln!(
+        out,
+        "┌─ Light {} at cell {} ─────────────",
+        index + 1,
+        light.position
+    )?;
+    writeln!(
+        out,
+        "│ State:  {} ({})",
+        light.state.symbol(),
+        light.countdown()
+    )?;
+    writeln!(out, "│ Mode:   {}", light.controller.mode().name())?;
+    writeln!(out, "│ Limit:  {}", limit_text(sim, light.position))?;
+    writeln!(out, "│ Ran red: {} vehicles", light.violations)?;
+    match &light.camera {
+        Some(camera) => writeln!(out, "│ Camera: {}", camera.status())?,
+        None => writeln!(out, "│ Camera: none")?,
+    }
+    writeln!(
+        out,
+        "│ Queue:  {} vehicles",
+        sim.queue_length(index, Lane::Road)
+    )?;
+    if sim.road.bike_lane {
+        writeln!(
+            out,
+            "│ Bikes:  {} ({} queued)",
+            light.bike_phase().symbol(),
+            sim.queue_length(index, Lane::Bike)
+        )?;
+    }
+    render_phase_diagram(out, light, sim.road.bike_lane)?;
+    writeln!(out, "└────────────────────────────")?;
+    writeln!(out, "  Esc close")?;
+    Ok(())
+}
+
+/// The map's speed limit on the road at `cell`.
+fn limit_text(sim: &Simulation, cell: usize) -> String {
+    match sim.road.speed_limit_at(cell) {
+        Some(limit) => format!("{:.2} cells/s", limit),
+        None => "none".to_string(),
+    }
+}
@@ -0,0 +1,112 @@
+use crate::systems::simulation::Simulation;
+use crate::systems::vehicle::Lane;
+use crate::ui::phase_diagram::cross_street;
+use crate::ui::render::loop_marks;
+
+/// Road cells drawn before and after the intersection.
+const CELLS_BEFORE: usize = 8;
+const CELLS_AFTER: usize = 3;
+/// Columns each road cell is drawn across.
+const CELL_WIDTH: usize = 4;
+/// Lines of the event log mentioning the intersection.
+const EVENT_LINES: usize = 5;
+
+/// Lines of the magnified view of light `index`'s intersection: the road
+/// either side of it with the cross street running through, then its
+/// approaches, signals and recent events.
+pub fn junction_lines(sim: &Simulation, index: usize) -> Vec<String> {
+    let light = &sim.lights[index];
+    let from = light.position.saturating_sub(CELLS_BEFORE);
+    let to = (light.position + CELLS_AFTER).min(sim.road.length.saturating_sub(1));
+    let before = (light.position - from) * CELL_WIDTH + 3;
+    let after = (to - light.position) * CELL_WIDTH;
+    let cross = cross_street(light);
+
+    let lane_row = |lane: Lane, signal: &str| {
+        let sprite = |cell: usize| {
+            sim.vehicles
+                .iter()
+                .find(|v| v.lane == lane && v.cell() == cell)
+                .map_or("  ", |v| v.kind.sprite())
+        };
+        let mut row: String = (from..light.position)
+            .map(|cell| format!("{}  ", sprite(cell)))
+            .collect();
+        row.push_str(&format!("{} ┆ {} ┆", signal, sprite(light.position)));
+        for cell in light.position + 1..=to {
+            row.push_str(&format!("{}  ", sprite(cell)));
+        }
+        row
+    };
+    let mut lanes = vec![lane_row(Lane::Road, light.state.symbol())];
+    if sim.road.bike_lane {
+        lanes.push(format!("{}┆    ┆{}", "╌".repeat(before), "╌".repeat(after)));
+        lanes.push(lane_row(Lane::Bike, light.bike_phase().symbol()));
+    }
+    // The curb, and the bike lane along it, are on the driver's side.
+    if sim.drivers.drive_on_left {
+        lanes.reverse();
+    }
+
+    let street = format!("{}┃ {} ┃", " ".repeat(before), cross.symbol());
+    let plain_street = format!("{}┃    ┃", " ".repeat(before));
+    let mut lines = vec![
+        plain_street.clone(),
+        street.clone(),
+        format!("{}┛    ┗{}", "━".repeat(before), "━".repeat(after)),
+    ];
+    lines.extend(lanes);
+    lines.push(format!("{}┓    ┏{}", "━".repeat(before), "━".repeat(after)));
+    lines.push(street);
+    lines.push(plain_street);
+    lines.push(String::new());
+
+    let detectors = sim.detectors(index);
+    lines.push(format!(
+        "Signal:       {} {} ({})",
+        light.state.symbol(),
+        light.state.name(),
+        light.countdown()
+    ));
+    lines.push(format!("Cross street: {} {}", cross.symbol(), cross.name()));
+    lines.push(format!(
+        "Road:         {} queued, {} approaching, loops {}{}",
+        detectors.queue,
+        detectors.approaching,
+        loop_marks(sim, index),
+        if detectors.calls { " (calling)" } else { "" }
+    ));
+    if sim.road.bike_lane {
+        lines.push(format!(
+            "Bike lane:    {} {}, {} queued",
+            light.bike_phase().symbol(),
+            light.bike_phase().name(),
+            detectors.bike_queue
+        ));
+    }
+    lines.push(String::new());
+
+    let events: Vec<&(f32, String)> = sim
+        .events
+        .recent(usize::MAX)
+        .filter(|(_, text)| mentions(text, index + 1))
+        .collect();
+    lines.push("Recent events:".to_string());
+    if events.is_empty() {
+        lines.push("  none yet".to_string());
+    }
+    for (time, text) in &events[events.len().saturating_sub(EVENT_LINES)..] {
+        lines.push(format!("  [{:6.1}s] {}", time, text));
+    }
+    lines
+}
+
+/// Whether an event log line is about light `number`.
+fn mentions(text: &str, number: usize) -> bool {
+    let text = text.to_lowercase();
+    ["light ", "intersection "].iter().any(|word| {
+        let needle = format!("{}{}", word, number);
+        text.match_indices(&needle)
+            .any(|(at, _)| !text[at + needle.len()..].starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
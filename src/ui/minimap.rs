@@ -0,0 +1,79 @@
+use std::fmt::{self, Write};
+
+use crossterm::style::Stylize;
+
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+use crate::systems::vehicle::Lane;
+use crate::ui::camera::Camera;
+use crate::ui::render::Hud;
+
+/// Widest the minimap gets, in terminal columns.
+pub const MINIMAP_WIDTH: usize = 40;
+
+/// Draws the whole road squeezed into one line, colored (or, without
+/// colour, marked) by how congested each stretch is, with the camera's
+/// viewport marked underneath.
+pub fn render_minimap(
+    out: &mut String,
+    sim: &Simulation,
+    camera: &Camera,
+    glyphs: usize,
+    hud: &Hud,
+) -> fmt::Result {
+    let theme = hud.theme;
+    let length = sim.road.length;
+    let width = length.clamp(1, MINIMAP_WIDTH);
+    let span = |column: usize| (column * length / width)..((column + 1) * length / width);
+
+    write!(out, "Map  ")?;
+    for column in 0..width {
+        let cells = span(column);
+        if let Some(light) = sim.lights.iter().find(|l| cells.contains(&l.position)) {
+            let mark = match (light.state, hud.monochrome) {
+                (TrafficLightState::Red, false) => "┃".with(theme.stop),
+                (TrafficLightState::Yellow, false) => "┃".with(theme.caution),
+                (TrafficLightState::Green, false) => "┃".with(theme.go),
+                (TrafficLightState::Red, true) => "R".stylize(),
+                (TrafficLightState::Yellow, true) => "Y".stylize(),
+                (TrafficLightState::Green, true) => "G".stylize(),
+            };
+            write!(out, "{}", mark)?;
+            continue;
+        }
+        let queued = sim
+            .vehicles
+            .iter()
+            .filter(|v| v.lane == Lane::Road && v.stopped && cells.contains(&v.cell()))
+            .count();
+        let moving = sim
+            .vehicles
+            .iter()
+            .filter(|v| v.lane == Lane::Road && !v.stopped && cells.contains(&v.cell()))
+            .count();
+        let glyph = match (queued, moving) {
+            (0, 0) => "─".with(theme.muted),
+            // Heavier marks for heavier traffic where colour can't show it.
+            (0, _) if hud.monochrome => "=".stylize(),
+            (1, _) if hud.monochrome => "+".stylize(),
+            _ if hud.monochrome => "#".stylize(),
+            (0, _) => "━".with(theme.go),
+            (1, _) => "━".with(theme.caution),
+            _ => "━".with(theme.stop),
+        };
+        write!(out, "{}", glyph)?;
+    }
+    writeln!(out)?;
+
+    let view_start = camera.offset.min(length);
+    let view_end = (view_start + camera.visible_cells(glyphs)).min(length);
+    let first = view_start * width / length.max(1);
+    let last = (view_end * width).div_ceil(length.max(1)).max(first + 1);
+    write!(out, "     {}", " ".repeat(first))?;
+    if last - first == 1 {
+        writeln!(out, "▲")?;
+    } else {
+        writeln!(out, "└{}┘", "─".repeat(last - first - 2))?;
+    }
+    Ok(())
+}
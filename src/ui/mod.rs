@@ -0,0 +1,26 @@
+pub mod backend;
+pub mod camera;
+pub mod capabilities;
+pub mod cast;
+#[cfg(feature = "gif")]
+pub mod clip;
+pub mod console;
+pub mod graphics;
+pub mod help;
+pub mod input;
+pub mod inspector;
+pub mod junction;
+pub mod minimap;
+pub mod phase_diagram;
+pub mod render;
+pub mod sandbox;
+pub mod screen;
+pub mod screenshot;
+pub mod selection;
+pub mod sky;
+pub mod sprites;
+pub mod theme;
+pub mod timing_editor;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod tutorial;
@@ -0,0 +1,141 @@
+use std::fmt::{self, Write};
+
+use crate::systems::traffic_light::{LEADING_BIKE_INTERVAL, TrafficLight, TrafficLightState};
+
+/// Columns the whole cycle is drawn across.
+const WIDTH: u32 = 32;
+
+/// Planned phase lengths of a light, in whole seconds. The cycle runs
+/// green, yellow, then red, whose first and last `all_red` seconds are the
+/// clearance intervals. With clearances the bicycles' leading green comes
+/// after the last one, at the very end of the red.
+struct Cycle {
+    green: u32,
+    yellow: u32,
+    red: u32,
+    all_red: u32,
+}
+
+impl Cycle {
+    /// The cycle `light` is running, or `None` while it has none planned
+    /// (held, flashing or manual).
+    fn of(light: &TrafficLight) -> Option<Self> {
+        if light.held || light.flashing.is_some() {
+            return None;
+        }
+        let controller = &light.controller;
+        Some(Self {
+            green: controller.duration(TrafficLightState::Green)?,
+            yellow: controller.duration(TrafficLightState::Yellow)?,
+            red: controller.duration(TrafficLightState::Red)?,
+            all_red: controller.all_red(),
+        })
+    }
+
+    fn total(&self) -> u32 {
+        self.green + self.yellow + self.red
+    }
+
+    /// The road's own phase `second` into the cycle.
+    fn road(&self, second: u32) -> TrafficLightState {
+        if second < self.green {
+            TrafficLightState::Green
+        } else if second < self.green + self.yellow {
+            TrafficLightState::Yellow
+        } else {
+            TrafficLightState::Red
+        }
+    }
+
+    /// The bicycle signal, which turns green a little before the road.
+    fn bikes(&self, second: u32) -> TrafficLightState {
+        self.road(second).bike_phase(self.total() - second)
+    }
+
+    /// The cross street, green between the all-red intervals of the road's
+    /// red, less its own yellow.
+    fn cross(&self, second: u32) -> TrafficLightState {
+        let lead = if self.all_red > 0 {
+            LEADING_BIKE_INTERVAL
+        } else {
+            0
+        };
+        let starts = self.green + self.yellow + self.all_red;
+        let ends = self.total().saturating_sub(self.all_red + lead).max(starts);
+        let yellow_from = ends.saturating_sub(self.yellow).max(starts);
+        if second < starts || second >= ends {
+            TrafficLightState::Red
+        } else if second < yellow_from {
+            TrafficLightState::Green
+        } else {
+            TrafficLightState::Yellow
+        }
+    }
+
+    /// Seconds into the cycle `light` is now.
+    fn now(&self, light: &TrafficLight) -> u32 {
+        let start = match light.state {
+            TrafficLightState::Green => 0,
+            TrafficLightState::Yellow => self.green,
+            TrafficLightState::Red => self.green + self.yellow,
+        };
+        (start + light.elapsed() as u32).min(self.total().saturating_sub(1))
+    }
+}
+
+/// One row of bars: the phase of a movement at each column of the cycle.
+fn bars(cycle: &Cycle, phase: impl Fn(&Cycle, u32) -> TrafficLightState) -> String {
+    (0..WIDTH)
+        .map(
+            |column| match phase(cycle, column * cycle.total() / WIDTH) {
+                TrafficLightState::Green => '█',
+                TrafficLightState::Yellow => '▒',
+                TrafficLightState::Red => '░',
+            },
+        )
+        .collect()
+}
+
+/// Draws the cycle of `light` inside its panel: a bar per movement, the
+/// road, the cross street and, if there is one, the bike lane, with a
+/// marker under the moment the light is at now.
+pub fn render_phase_diagram(
+    out: &mut String,
+    light: &TrafficLight,
+    bike_lane: bool,
+) -> fmt::Result {
+    let Some(cycle) = Cycle::of(light) else {
+        return writeln!(out, "│ Cycle:  none planned ({})", light.countdown());
+    };
+    if cycle.total() == 0 {
+        return Ok(());
+    }
+    let now = cycle.now(light);
+    writeln!(
+        out,
+        "│ Cycle:  {}s, {}s in   █ green ▒ yellow ░ red",
+        cycle.total(),
+        now
+    )?;
+    writeln!(out, "│  Road   {}", bars(&cycle, Cycle::road))?;
+    writeln!(out, "│  Cross  {}", bars(&cycle, Cycle::cross))?;
+    if bike_lane {
+        writeln!(out, "│  Bikes  {}", bars(&cycle, Cycle::bikes))?;
+    }
+    let column = (now * WIDTH / cycle.total()) as usize;
+    writeln!(out, "│         {}▲ now", " ".repeat(column))
+}
+
+/// What the cross street is showing at `light` now: from the cycle when
+/// one is planned, the same flash when flashing, and otherwise the
+/// opposite of the road.
+pub fn cross_street(light: &TrafficLight) -> TrafficLightState {
+    if let Some(flashing) = light.flashing {
+        return flashing.state();
+    }
+    match Cycle::of(light) {
+        Some(cycle) if cycle.total() > 0 => cycle.cross(cycle.now(light)),
+        _ if light.state == TrafficLightState::Red => TrafficLightState::Green,
+        _ => TrafficLightState::Red,
+    }
+}
@@ -0,0 +1,897 @@
+use std::fmt::{self, Write as _};
+use std::ops::Range;
+
+use crossterm::style::{Color, Stylize};
+use crossterm::terminal;
+use unicode_width::UnicodeWidthStr;
+
+use crate::systems::bridge::BridgeState;
+use crate::systems::demand::DayType;
+use crate::systems::editor::MapEditor;
+use crate::systems::simulation::Simulation;
+use crate::systems::special_event::EventPhase;
+use crate::systems::traffic_light::TrafficLight;
+use crate::systems::vehicle::{Lane, VehicleType};
+use crate::ui::backend::RenderBackend;
+use crate::ui::camera::{Camera, Zoom};
+use crate::ui::console::Console;
+use crate::ui::graphics::{GraphicsProtocol, Picture, road_image};
+use crate::ui::help::draw_overlay;
+use crate::ui::inspector::{render_light_panel, render_vehicle_panel};
+use crate::ui::minimap::render_minimap;
+use crate::ui::selection::Selection;
+use crate::ui::sky::{road_tint, sky_row};
+use crate::ui::theme::Theme;
+
+/// Road glyphs that fit across the terminal next to the left margin.
+pub fn road_glyphs() -> usize {
+    terminal_columns().saturating_sub(8) / 2
+}
+
+/// Width of the terminal as it is now, so frames follow it when resized.
+fn terminal_columns() -> usize {
+    match terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => 80,
+    }
+}
+
+/// Terminal column where the first road glyph is drawn, after the light
+/// symbol, spacing, and scroll marker.
+pub const ROAD_COLUMN: u16 = 5;
+
+/// Smallest terminal the frame is drawn in, in columns and rows. Anything
+/// smaller gets a note asking for more room instead.
+pub const MIN_SIZE: (u16, u16) = (60, 24);
+
+/// Most recent event log entries listed under the inspector.
+const EVENT_LINES: usize = 3;
+
+/// Where the road ended up on screen in the last frame, so mouse clicks can
+/// be mapped back to road cells.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenLayout {
+    /// Terminal rows showing a lane, with the lane each one shows.
+    pub lane_rows: Vec<(u16, Lane)>,
+    pub first_cell: usize,
+    pub last_cell: usize,
+    pub cell_scale: usize,
+}
+
+impl ScreenLayout {
+    /// Lane and road cells under a terminal position, if it is on the road.
+    pub fn cells_at(&self, column: u16, row: u16) -> Option<(Lane, Range<usize>)> {
+        let &(_, lane) = self.lane_rows.iter().find(|(r, _)| *r == row)?;
+        let glyph = column.checked_sub(ROAD_COLUMN)? as usize / 2;
+        let first = self.first_cell + glyph * self.cell_scale;
+        if first >= self.last_cell {
+            return None;
+        }
+        Some((lane, first..(first + self.cell_scale).min(self.last_cell)))
+    }
+}
+
+/// Terminal row the next line written to `out` will land on. Lines wider
+/// than a narrow terminal wrap onto more than one row.
+fn current_row(out: &str) -> u16 {
+    let columns = terminal_columns();
+    // Every finished line; the unfinished last one is where the next starts.
+    out.split('\n')
+        .rev()
+        .skip(1)
+        .map(|line| visible_width(line).div_ceil(columns).max(1))
+        .sum::<usize>() as u16
+}
+
+/// Columns `line` takes on screen, leaving out its colour escapes.
+fn visible_width(line: &str) -> usize {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            // Up to and including the final byte of the sequence.
+            chars
+                .by_ref()
+                .find(|c| ('@'..='~').contains(c) && *c != '[');
+        } else {
+            text.push(c);
+        }
+    }
+    text.width()
+}
+
+/// Interface state drawn around the road: status text, the console line,
+/// tutorial or challenge banners, and any boxed overlay such as the help.
+pub struct Hud<'a> {
+    /// Pause, rewind or speed note shown next to the tick counter.
+    pub status: String,
+    pub console: &'a Console,
+    /// Highlighted lines under the title.
+    pub banners: Vec<String>,
+    /// Road cell the sandbox cursor is on, marked under the road.
+    pub cursor: Option<usize>,
+    /// Last frame's timing breakdown, when profiling.
+    pub profile: Option<String>,
+    /// Induction loop states, while the debug overlay is on.
+    pub loops: Option<String>,
+    /// Title and lines of a box drawn over the dimmed frame.
+    pub overlay: Option<(&'a str, &'a [String])>,
+    pub theme: &'a Theme,
+    /// The terminal shows no colour: marks that differ only by colour get
+    /// their own glyphs.
+    pub monochrome: bool,
+}
+
+pub fn render(
+    backend: &mut dyn RenderBackend,
+    sim: &Simulation,
+    camera: &Camera,
+    selection: &Selection,
+    hud: &Hud,
+) -> ScreenLayout {
+    let mut out = String::new();
+    let mut layout = ScreenLayout::default();
+    // Boxed overlays are drawn as text over the whole frame, map included.
+    let graphics = backend.graphics().filter(|_| hud.overlay.is_none());
+    let picture = draw(&mut out, &mut layout, sim, camera, selection, hud, graphics)
+        .expect("writing to a String cannot fail");
+    if let Some((title, lines)) = hud.overlay {
+        draw_overlay(&mut out, title, lines).expect("writing to a String cannot fail");
+    }
+    backend.present(&out, picture);
+    layout
+}
+
+fn draw(
+    out: &mut String,
+    layout: &mut ScreenLayout,
+    sim: &Simulation,
+    camera: &Camera,
+    selection: &Selection,
+    hud: &Hud,
+    graphics: Option<GraphicsProtocol>,
+) -> Result<Option<Picture>, fmt::Error> {
+    let road = &sim.road;
+    let mut picture = None;
+
+    writeln!(out)?;
+    writeln!(out, "===============================")?;
+    writeln!(out, "     TRAFFIC LIGHT SIMULATOR")?;
+    writeln!(out, "===============================")?;
+    writeln!(out)?;
+    for banner in &hud.banners {
+        writeln!(out, "{}", banner.as_str().with(hud.theme.banner).bold())?;
+    }
+    if !hud.banners.is_empty() {
+        writeln!(out)?;
+    }
+
+    write!(
+        out,
+        "Tick: {}   {} {} ({})   Weather: {}{}",
+        sim.tick,
+        sim.clock.date(),
+        sim.clock.format(),
+        sim.clock.time_of_day().name(),
+        sim.weather.describe(),
+        traffic_note(sim)
+    )?;
+    if !hud.status.is_empty() {
+        write!(out, "   {}", hud.status.as_str().with(hud.theme.status))?;
+    }
+    writeln!(out)?;
+    writeln!(out)?;
+
+    for (index, light) in sim.lights.iter().enumerate() {
+        write!(
+            out,
+            "Light {} at {}: {} ({})",
+            index + 1,
+            light.position,
+            light.state.symbol(),
+            light.countdown()
+        )?;
+        write!(out, "   violations: {}", light.violations)?;
+        if let Some(camera) = &light.camera {
+            write!(out, "   {} {}", CAMERA, camera.status())?;
+        }
+        if road.bike_lane {
+            let bike_queue = sim.queue_length(index, Lane::Bike);
+            write!(
+                out,
+                "   bike {} (queue: {})",
+                light.bike_phase().symbol(),
+                bike_queue
+            )?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "Vehicle count: {}", sim.spawned)?;
+    for kind in VehicleType::ALL.into_iter().filter(|k| !k.is_emergency()) {
+        let on_road = sim.vehicles.iter().filter(|v| v.kind == kind).count();
+        writeln!(out, "  {} {}: {}", kind.sprite(), kind.name(), on_road)?;
+    }
+    writeln!(
+        out,
+        "  {} Emergency: {}",
+        VehicleType::Ambulance.sprite(),
+        sim.vehicles
+            .iter()
+            .filter(|v| v.kind.is_emergency())
+            .count()
+    )?;
+    writeln!(
+        out,
+        "Trips completed: {}   Avg wait: {:.1}s   Throughput: {:.1}/min   Crashes: {}",
+        sim.stats.completed_trips,
+        sim.stats.average_wait(),
+        sim.stats.throughput(),
+        sim.stats.crash_summary()
+    )?;
+    if !sim.toll_booths.is_empty() {
+        writeln!(out, "{}", toll_summary(sim))?;
+    }
+    if !sim.parking_lots.is_empty() {
+        writeln!(out, "{}", parking_summary(sim))?;
+    }
+    if sim.turns.right_on_red || sim.stats.turns_on_red > 0 {
+        writeln!(out, "{}", turn_summary(sim))?;
+    }
+    if sim.stats.manual_periods > 0 || sim.lights.iter().any(|l| l.manual_since.is_some()) {
+        writeln!(out, "{}", manual_summary(sim))?;
+    }
+    for incident in &sim.incidents {
+        writeln!(out, "  💥 Incident: {}", incident.status())?;
+    }
+    for debris in &sim.debris {
+        writeln!(out, "  {} Incident: {}", DEBRIS, debris.status())?;
+    }
+    for delivery in &sim.deliveries {
+        writeln!(out, "  {} {}", DELIVERY, delivery.status())?;
+    }
+    for (index, light) in sim.lights.iter().enumerate() {
+        if light.repair_left.is_some() {
+            writeln!(
+                out,
+                "  {} {}",
+                light.state.symbol(),
+                fault_status(index, light)
+            )?;
+        }
+    }
+    for works in &sim.road_works {
+        writeln!(out, "  {} {}", CONES, works.status())?;
+    }
+    for zone in sim.school_zones.iter().filter(|z| z.active) {
+        writeln!(out, "  {} {}", SCHOOL, zone.status())?;
+    }
+    for closure in sim.closures.iter().filter(|c| c.active) {
+        writeln!(out, "  {} {}", BARRICADE, closure.status())?;
+    }
+    if let Some(bridge) = &sim.bridge {
+        writeln!(out, "  {} {}", BRIDGE, bridge.status())?;
+    }
+    for lot in &sim.parking_lots {
+        writeln!(out, "  {} {}", CAR_PARK, lot.status(sim.parking.capacity))?;
+    }
+
+    let glyphs = road_glyphs();
+    let start = camera.offset.min(road.length);
+    let end = (start + camera.visible_cells(glyphs)).min(road.length);
+    let sky = sky_row(sim, start..end, camera.scale());
+    writeln!(
+        out,
+        "{}",
+        format!("     {}", sky).trim_end().with(hud.theme.muted)
+    )?;
+    let left_marker = if start > 0 { "◀" } else { " " };
+    let right_marker = if end < road.length { "▶" } else { " " };
+
+    let light_symbol = sim
+        .lights
+        .first()
+        .map_or("  ", |light| light.state.symbol());
+
+    let inspected = camera.following.or(selection.selected);
+    let highlight = inspected
+        .and_then(|id| sim.vehicles.get(id))
+        .map(|v| (v.lane, v.cell()));
+    // A background colour would turn into reverse video without colour.
+    let tint = road_tint(sim.clock.time_of_day(), hud.theme).filter(|_| !hud.monochrome);
+
+    // Driving on the left puts the curb, and the bike lane along it, above
+    // the road instead of below.
+    let left_hand = sim.drivers.drive_on_left;
+    match camera.zoom {
+        Zoom::Detail if graphics == Some(GraphicsProtocol::HalfBlocks) => {
+            // The image comes mirrored already, bike lane first.
+            let image = road_image(sim, start..end, inspected, (1, 2));
+            let first = if road.bike_lane && left_hand { 2 } else { 0 };
+            if first > 0 {
+                writeln!(out, "     {}", image.half_block_line(0))?;
+                layout.lane_rows.push((current_row(out), Lane::Bike));
+                writeln!(out, "     {}", image.half_block_line(1))?;
+            }
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            writeln!(
+                out,
+                "{}  {}{}{}",
+                light_symbol,
+                left_marker,
+                image.half_block_line(first),
+                right_marker
+            )?;
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            writeln!(
+                out,
+                "    {}{}{}",
+                left_marker,
+                image.half_block_line(first + 1),
+                right_marker
+            )?;
+            if road.bike_lane && !left_hand {
+                layout.lane_rows.push((current_row(out), Lane::Bike));
+                writeln!(out, "     {}", image.half_block_line(2))?;
+                writeln!(out, "     {}", image.half_block_line(3))?;
+            }
+        }
+        Zoom::Detail if let Some(protocol) = graphics => {
+            // Text only around the map; the picture covers the lane rows.
+            let blank = " ".repeat((end - start) * 2);
+            picture = Some(Picture {
+                row: current_row(out),
+                column: ROAD_COLUMN,
+                columns: blank.len(),
+                rows: if road.bike_lane { 4 } else { 2 },
+                image: road_image(sim, start..end, inspected, protocol.cell_pixels()),
+            });
+            if road.bike_lane && left_hand {
+                writeln!(out)?;
+                layout.lane_rows.push((current_row(out), Lane::Bike));
+                writeln!(out)?;
+            }
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            writeln!(
+                out,
+                "{}  {}{}{}",
+                light_symbol, left_marker, blank, right_marker
+            )?;
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            writeln!(out, "    {}{}{}", left_marker, blank, right_marker)?;
+            if road.bike_lane && !left_hand {
+                layout.lane_rows.push((current_row(out), Lane::Bike));
+                writeln!(out)?;
+                writeln!(out)?;
+            }
+        }
+        Zoom::Detail => {
+            let glyphs = LaneGlyphs::new(sim);
+            let curb = "╌".repeat((end - start) * 2).with(hud.theme.bike_lane);
+
+            if road.bike_lane && left_hand {
+                writeln!(out, "     {}", curb)?;
+                layout.lane_rows.push((current_row(out), Lane::Bike));
+                write!(out, "     ")?;
+                write_row(
+                    out,
+                    &glyphs.bike,
+                    start..end,
+                    highlight,
+                    Lane::Bike,
+                    tint,
+                    hud,
+                )?;
+                writeln!(out)?;
+            }
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            write!(out, "{}  {}", light_symbol, left_marker)?;
+            write_row(
+                out,
+                &glyphs.road,
+                start..end,
+                highlight,
+                Lane::Road,
+                tint,
+                hud,
+            )?;
+            writeln!(out, "{}", right_marker)?;
+
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            write!(out, "    {}", left_marker)?;
+            for symbol in &glyphs.markings[start..end] {
+                write_tinted(out, symbol, tint)?;
+            }
+            writeln!(out, "{}", right_marker)?;
+
+            if road.bike_lane && !left_hand {
+                layout.lane_rows.push((current_row(out), Lane::Bike));
+                write!(out, "     ")?;
+                write_row(
+                    out,
+                    &glyphs.bike,
+                    start..end,
+                    highlight,
+                    Lane::Bike,
+                    tint,
+                    hud,
+                )?;
+                writeln!(out)?;
+                writeln!(out, "     {}", curb)?;
+            }
+        }
+        Zoom::Overview => {
+            let scale = camera.scale();
+            layout.lane_rows.push((current_row(out), Lane::Road));
+            write!(out, "{}  {}", light_symbol, left_marker)?;
+            for chunk in (start..end).step_by(scale) {
+                let cells = chunk..(chunk + scale).min(end);
+                if let Some(light) = sim.lights.iter().find(|l| cells.contains(&l.position)) {
+                    write_tinted(out, light.state.symbol(), tint)?;
+                    continue;
+                }
+                let count = sim
+                    .vehicles
+                    .iter()
+                    .filter(|v| v.lane == Lane::Road && cells.contains(&v.cell()))
+                    .count();
+                write_tinted(out, density_glyph(count), tint)?;
+            }
+            writeln!(out, "{}", right_marker)?;
+        }
+    }
+
+    if let Some(cursor) = hud.cursor.filter(|cell| (start..end).contains(cell)) {
+        let glyph = (cursor - start) / camera.scale();
+        writeln!(
+            out,
+            "     {}{} cell {}",
+            "  ".repeat(glyph),
+            "▲".with(hud.theme.cursor).bold(),
+            cursor
+        )?;
+    }
+
+    layout.first_cell = start;
+    layout.last_cell = end;
+    layout.cell_scale = camera.scale();
+
+    writeln!(out)?;
+    render_minimap(out, sim, camera, glyphs, hud)?;
+    writeln!(
+        out,
+        "View: cells {}-{} of {} ({})",
+        start,
+        end,
+        road.length,
+        match camera.zoom {
+            Zoom::Detail => "detail",
+            Zoom::Overview => "overview",
+        }
+    )?;
+    writeln!(out)?;
+    match inspected.and_then(|id| sim.vehicles.get(id)) {
+        Some(vehicle) => {
+            let title = if camera.following.is_some() {
+                "Following"
+            } else {
+                "Selected"
+            };
+            render_vehicle_panel(out, sim, vehicle, title)?;
+        }
+        None => match selection.selected_light {
+            Some(index) if index < sim.lights.len() => render_light_panel(out, sim, index)?,
+            _ => {
+                for vehicle in sim.vehicles.iter().take(5) {
+                    writeln!(out, "{}", vehicle.render())?;
+                }
+            }
+        },
+    }
+    writeln!(out)?;
+    let mut events = sim.events.recent(EVENT_LINES).peekable();
+    if events.peek().is_some() {
+        writeln!(out, "Events:")?;
+        for (time, text) in events {
+            writeln!(out, "  [{:6.1}s] {}", time, text)?;
+        }
+        writeln!(out)?;
+    }
+    if let Some(profile) = &hud.profile {
+        writeln!(out, "{}", profile.as_str().with(hud.theme.muted))?;
+        writeln!(out)?;
+    }
+    if let Some(loops) = &hud.loops {
+        writeln!(out, "{}", loops.as_str().with(hud.theme.muted))?;
+        writeln!(out)?;
+    }
+    if let Some(prompt) = &selection.prompt {
+        writeln!(out, "{}: {}_", prompt.kind.label(), prompt.input)?;
+    }
+    let console = hud.console;
+    if console.open {
+        writeln!(out, ":{}_", console.input)?;
+    } else if let Some((message, failed)) = &console.output {
+        if *failed {
+            writeln!(out, "{}", message.as_str().with(hud.theme.error))?;
+        } else {
+            writeln!(out, "{}", message)?;
+        }
+    }
+    Ok(picture)
+}
+
+/// Beam in front of a vehicle driving with its lights on.
+const HEADLIGHTS: &str = "››";
+/// Storm debris lying in the road lane.
+pub const DEBRIS: &str = "🌿";
+/// A delivery truck double-parked in the road lane.
+pub const DELIVERY: &str = "🚚";
+/// The venue of a special event.
+pub const VENUE: &str = "🏟️";
+/// Drawbridge deck while it is down.
+pub const BRIDGE: &str = "🌉";
+/// Water showing where the deck has lifted.
+const WATER: &str = "🌊";
+/// Warning lights flashing before the bridge lifts.
+const BRIDGE_WARNING: &str = "🚨";
+/// Boat passing under the lifted bridge.
+const BOAT: &str = "⛵";
+/// Barricades along a closed stretch of road.
+pub const BARRICADE: &str = "🚫";
+/// A school zone: its signs, and the whole stretch while it is active.
+pub const SCHOOL: &str = "🚸";
+/// Beacons flashing at the ends of an active school zone.
+const BEACON: &str = "🟠";
+/// A car park.
+pub const CAR_PARK: &str = "🏬";
+/// A toll booth.
+const TOLL_BOOTH: &str = "🎫";
+/// Enforcement camera watching the stop line just ahead.
+pub const CAMERA: &str = "📷";
+/// Cones along a stretch of road works.
+pub const CONES: &str = "🚧";
+/// Hazard lights flashing on a vehicle pulled over for a siren.
+const HAZARD: &str = "⚠️";
+
+/// What each road cell shows in the detail view, row by row.
+pub struct LaneGlyphs<'a> {
+    /// Vehicles on the road.
+    pub road: Vec<&'a str>,
+    /// The road surface under them, with spawn points, facilities, road
+    /// works, the drawbridge and signals.
+    pub markings: Vec<&'a str>,
+    pub bike: Vec<&'a str>,
+}
+
+impl<'a> LaneGlyphs<'a> {
+    pub fn new(sim: &'a Simulation) -> Self {
+        let length = sim.road.length;
+        let mut road = vec!["  "; length];
+        let mut bike = vec!["  "; length];
+        for vehicle in sim.vehicles.iter().filter(|v| v.lane == Lane::Bike) {
+            bike[vehicle.cell()] = vehicle.lane_symbol();
+        }
+        for vehicle in sim.vehicles.iter().filter(|v| v.lane == Lane::Road) {
+            // Cars win the cell when a motorcycle is filtering past them,
+            // and sirens when they pass a car pulled over for them.
+            if road[vehicle.cell()] == "  "
+                || vehicle.kind == VehicleType::Car
+                || vehicle.kind.has_siren()
+            {
+                road[vehicle.cell()] = vehicle.lane_symbol();
+            }
+        }
+        // Drivers pulled over for a siren or at the curb for a ride-share
+        // passenger flash their hazard lights.
+        if ((sim.stats.elapsed * 2.0) as u32).is_multiple_of(2) {
+            for vehicle in sim.vehicles.iter().filter(|v| v.yielding || v.at_curb()) {
+                if road[vehicle.cell()] == vehicle.lane_symbol() {
+                    road[vehicle.cell()] = HAZARD;
+                }
+            }
+        }
+        // Headlights light up the empty cell in front of moving vehicles.
+        if sim.clock.lights_on() {
+            for vehicle in sim.vehicles.iter().filter(|v| v.lane == Lane::Road) {
+                let ahead = vehicle.cell() + 1;
+                if !vehicle.stopped && !vehicle.broken_down && road.get(ahead) == Some(&"  ") {
+                    road[ahead] = HEADLIGHTS;
+                }
+            }
+        }
+
+        for debris in &sim.debris {
+            road[debris.cell] = DEBRIS;
+        }
+        for delivery in &sim.deliveries {
+            road[delivery.cell] = DELIVERY;
+        }
+
+        let mut markings = vec!["🛣️"; length];
+        for &(cell, facility) in &sim.road.facilities {
+            markings[cell] = facility.symbol();
+        }
+        if let Some(venue) = sim.special_event.venue.filter(|&cell| cell < length) {
+            markings[venue] = VENUE;
+        }
+        for works in &sim.road_works {
+            markings[works.from..=works.to].fill(CONES);
+        }
+        let flash = ((sim.stats.elapsed * 2.0) as u32).is_multiple_of(2);
+        for zone in &sim.school_zones {
+            if zone.active {
+                markings[zone.from..=zone.to].fill(SCHOOL);
+            }
+            let sign = if zone.active && flash { BEACON } else { SCHOOL };
+            markings[zone.from] = sign;
+            markings[zone.to] = sign;
+        }
+        for closure in sim.closures.iter().filter(|c| c.active) {
+            markings[closure.from..=closure.to].fill(BARRICADE);
+        }
+        if let Some(bridge) = &sim.bridge {
+            // The two leaves lift from the middle of the deck outwards.
+            let middle = (bridge.from + bridge.to) as f32 / 2.0;
+            let half = (bridge.to - bridge.from + 1) as f32 / 2.0;
+            let open = bridge.lift() * half;
+            for (cell, marking) in markings
+                .iter_mut()
+                .enumerate()
+                .take(bridge.to + 1)
+                .skip(bridge.from)
+            {
+                *marking = if (cell as f32 - middle).abs() < open {
+                    WATER
+                } else if bridge.state == BridgeState::Warning && flash {
+                    BRIDGE_WARNING
+                } else {
+                    BRIDGE
+                };
+            }
+            let boat = middle as usize;
+            if bridge.state == BridgeState::Up && road[boat] == "  " {
+                road[boat] = BOAT;
+            }
+        }
+        for booth in &sim.toll_booths {
+            markings[booth.cell] = TOLL_BOOTH;
+        }
+        for lot in &sim.parking_lots {
+            markings[lot.cell] = CAR_PARK;
+        }
+        for light in sim.lights.iter().filter(|l| l.camera.is_some()) {
+            markings[light.position.saturating_sub(1)] = CAMERA;
+        }
+        for &cell in &sim.road.spawn_points {
+            markings[cell] = if sim.spawn_open(cell) { "⏬" } else { "⛔" };
+        }
+        // Lights flashing for the night blink on and off.
+        for light in sim.lights.iter().filter(|l| l.flashing.is_none() || flash) {
+            markings[light.position] = light.state.symbol();
+        }
+        Self {
+            road,
+            markings,
+            bike,
+        }
+    }
+}
+
+/// Writes the visible part of a lane row, highlighting the inspected vehicle.
+fn write_row(
+    out: &mut String,
+    row: &[&str],
+    cells: Range<usize>,
+    highlight: Option<(Lane, usize)>,
+    lane: Lane,
+    tint: Option<Color>,
+    hud: &Hud,
+) -> fmt::Result {
+    for cell in cells {
+        if highlight == Some((lane, cell)) {
+            // Without a highlight colour, as on a monochrome screen.
+            if hud.monochrome || hud.theme.highlight == Color::Reset {
+                write!(out, "{}", row[cell].reverse())?;
+            } else {
+                write!(out, "{}", row[cell].on(hud.theme.highlight))?;
+            }
+        } else {
+            write_tinted(out, row[cell], tint)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `text` on the road's time-of-day colour, if it has one.
+fn write_tinted(out: &mut String, text: &str, tint: Option<Color>) -> fmt::Result {
+    match tint {
+        Some(colour) => write!(out, "{}", text.on(colour)),
+        None => write!(out, "{}", text),
+    }
+}
+
+/// A broken signal, for the incident list.
+pub fn fault_status(index: usize, light: &TrafficLight) -> String {
+    format!("light {} {}", index + 1, light.countdown())
+}
+
+/// Takings and waiting at the toll booths, for the statistics.
+pub fn toll_summary(sim: &Simulation) -> String {
+    format!(
+        "Tolls: ${:.2} from {} drivers   Avg toll delay: {:.1}s",
+        sim.stats.toll_revenue,
+        sim.stats.tolls_paid,
+        sim.stats.average_toll_delay()
+    )
+}
+
+/// How much turning on red has saved drivers turning off at the
+/// signals, for the statistics.
+pub fn turn_summary(sim: &Simulation) -> String {
+    let rule = if sim.turns.right_on_red {
+        "allowed"
+    } else {
+        "off"
+    };
+    format!(
+        "Turning {} on red: {}   Turns on red: {}   Red skipped: {:.0}s",
+        sim.drivers.curb_side(),
+        rule,
+        sim.stats.turns_on_red,
+        sim.stats.red_saved
+    )
+}
+
+/// How long operators have run lights by hand, for the statistics.
+pub fn manual_summary(sim: &Simulation) -> String {
+    let now = sim.stats.elapsed;
+    let current: Vec<f32> = sim.lights.iter().filter_map(|l| l.manual_since).collect();
+    format!(
+        "Manual control: {} periods, {:.0}s   Lights under an operator: {}",
+        sim.stats.manual_periods + current.len() as u32,
+        sim.stats.manual_seconds + current.iter().map(|since| now - since).sum::<f32>(),
+        current.len()
+    )
+}
+
+/// The induction loops at light `index`, nearest the stop line first: `▣`
+/// with a vehicle over it, `□` empty, each followed by its setback.
+pub fn loop_marks(sim: &Simulation, index: usize) -> String {
+    let marks: Vec<String> = sim
+        .loop_states(index)
+        .into_iter()
+        .map(|(setback, occupied)| format!("{}{}", if occupied { '▣' } else { '□' }, setback))
+        .collect();
+    if marks.is_empty() {
+        "none".to_string()
+    } else {
+        marks.join(" ")
+    }
+}
+
+/// Debug line with every light's induction loops, and which are calling
+/// for green.
+pub fn loops_panel(sim: &Simulation) -> String {
+    let lights: Vec<String> = (0..sim.lights.len())
+        .map(|index| {
+            let call = if sim.detectors(index).calls {
+                " (call)"
+            } else {
+                ""
+            };
+            format!("light {} {}{}", index + 1, loop_marks(sim, index), call)
+        })
+        .collect();
+    format!("🔲 Loops: {}", lights.join("   "))
+}
+
+/// How the car parks are being used, for the statistics.
+pub fn parking_summary(sim: &Simulation) -> String {
+    format!(
+        "Parked: {}   Found full: {}   Circling: {}",
+        sim.stats.parked,
+        sim.stats.parking_full,
+        sim.circling.len()
+    )
+}
+
+/// Anything unusual about today's traffic, for the status line.
+pub fn traffic_note(sim: &Simulation) -> &'static str {
+    let event = sim.special_event.phase(&sim.clock);
+    if event == Some(EventPhase::Arriving) {
+        "   Crowd arriving"
+    } else if event == Some(EventPhase::Leaving) {
+        "   Crowd leaving"
+    } else if sim.rush_hour_active() {
+        "   Rush hour"
+    } else if sim.demand.day_type(&sim.clock) == DayType::Holiday {
+        "   Holiday"
+    } else {
+        ""
+    }
+}
+
+/// Two-column shading for how many vehicles share an overview glyph.
+pub fn density_glyph(count: usize) -> &'static str {
+    match count {
+        0 => "══",
+        1 => "░░",
+        2..=3 => "▒▒",
+        _ => "▓▓",
+    }
+}
+
+/// The note shown instead of the frame in a terminal smaller than
+/// [`MIN_SIZE`], now `size`.
+pub fn too_small_lines((columns, rows): (u16, u16)) -> [String; 3] {
+    [
+        "Terminal too small".to_string(),
+        format!("Please enlarge it to {}x{}", MIN_SIZE.0, MIN_SIZE.1),
+        format!("(now {}x{})", columns, rows),
+    ]
+}
+
+/// Draws the too-small note in the middle of the screen.
+pub fn render_too_small(backend: &mut dyn RenderBackend, size: (u16, u16)) {
+    let mut out = String::new();
+    let lines = too_small_lines(size);
+    let top = (size.1 as usize).saturating_sub(lines.len()) / 2;
+    for (index, line) in lines.iter().enumerate() {
+        let left = (size.0 as usize).saturating_sub(line.len()) / 2;
+        write!(out, "\x1B[{};{}H{}", top + index + 1, left + 1, line)
+            .expect("writing to a String cannot fail");
+    }
+    backend.present(&out, None);
+}
+
+pub fn render_editor(backend: &mut dyn RenderBackend, editor: &MapEditor, theme: &Theme) {
+    let mut out = String::new();
+    draw_editor(&mut out, editor, theme).expect("writing to a String cannot fail");
+    backend.present(&out, None);
+}
+
+fn draw_editor(out: &mut String, editor: &MapEditor, theme: &Theme) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(out, "===============================")?;
+    writeln!(out, "          MAP EDITOR")?;
+    writeln!(out, "===============================")?;
+    writeln!(out)?;
+
+    let road: String = editor.cells.iter().collect();
+    writeln!(out, "    {}", road)?;
+    writeln!(out, "    {}^", " ".repeat(editor.cursor))?;
+    if editor.bike_lane {
+        writeln!(
+            out,
+            "    {}",
+            "b".repeat(editor.cells.len()).with(theme.bike_lane)
+        )?;
+    }
+    if editor.has_limits() {
+        writeln!(out, "    {}", editor.limits.iter().collect::<String>())?;
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Cursor: {} / {}",
+        editor.cursor + 1,
+        editor.cells.len()
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "←/→ move (→ past the end extends the road)   = road   | signal   s spawn   h hospital   f fire station   d bridge   t toll booth   p car park"
+    )?;
+    writeln!(
+        out,
+        "b toggle bike lane   1-9 speed limit (quarter cells/s)   - no limit   Backspace delete cell   w save to {} and apply   e/Esc cancel",
+        editor.path
+    )?;
+    if !editor.message.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", editor.message.as_str().with(theme.error))?;
+    }
+    Ok(())
+}
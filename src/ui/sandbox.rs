@@ -0,0 +1,76 @@
+use crossterm::event::KeyCode;
+
+use crate::systems::events::SimEvent;
+use crate::systems::simulation::Simulation;
+use crate::systems::traffic_light::TrafficLightState;
+use crate::systems::vehicle::VehicleType;
+
+pub enum SandboxAction {
+    None,
+    Close,
+    Apply(SimEvent),
+}
+
+/// God-mode cursor over the road. Everything it does goes through
+/// `Simulation::apply`, so it shows up in the event log.
+pub struct Sandbox {
+    pub cursor: usize,
+}
+
+impl Sandbox {
+    pub const HELP: &str = "←/→ move  c/m/b/t/a/f/p spawn car/motorcycle/bicycle/tow truck/ambulance/fire truck/police  i incident  s spawn point  R/Y/G force light  o operate light  n next phase  C camera  x remove  g/Esc exit";
+
+    pub fn new(cursor: usize) -> Self {
+        Self { cursor }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, sim: &Simulation) -> SandboxAction {
+        let cell = self.cursor;
+        let spawn = |kind| SandboxAction::Apply(SimEvent::Spawn(kind, Some(cell)));
+        // Light keys act on the signal at or just ahead of the cursor.
+        let light = |state| match sim.road.next_signal(cell as f32) {
+            Some(index) => SandboxAction::Apply(SimEvent::ForceLight(index, state)),
+            None => SandboxAction::None,
+        };
+        match code {
+            KeyCode::Left => {
+                self.cursor = self.cursor.saturating_sub(1);
+                SandboxAction::None
+            }
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(sim.road.length.saturating_sub(1));
+                SandboxAction::None
+            }
+            KeyCode::Char('c') => spawn(VehicleType::Car),
+            KeyCode::Char('m') => spawn(VehicleType::Motorcycle),
+            KeyCode::Char('b') => spawn(VehicleType::Bicycle),
+            KeyCode::Char('t') => spawn(VehicleType::TowTruck),
+            KeyCode::Char('a') => spawn(VehicleType::Ambulance),
+            KeyCode::Char('f') => spawn(VehicleType::FireTruck),
+            KeyCode::Char('p') => spawn(VehicleType::Police),
+            KeyCode::Char('i') => SandboxAction::Apply(SimEvent::Breakdown(cell)),
+            KeyCode::Char('s') => SandboxAction::Apply(SimEvent::ToggleSpawn(cell)),
+            KeyCode::Char('R') => light(TrafficLightState::Red),
+            KeyCode::Char('Y') => light(TrafficLightState::Yellow),
+            KeyCode::Char('G') => light(TrafficLightState::Green),
+            KeyCode::Char('o') => match sim.road.next_signal(cell as f32) {
+                Some(index) => SandboxAction::Apply(SimEvent::Operate(index)),
+                None => SandboxAction::None,
+            },
+            KeyCode::Char('n') => match sim.road.next_signal(cell as f32) {
+                Some(index) => SandboxAction::Apply(SimEvent::AdvanceLight(index)),
+                None => SandboxAction::None,
+            },
+            KeyCode::Char('C') => match sim.road.next_signal(cell as f32) {
+                Some(index) => SandboxAction::Apply(SimEvent::ToggleCamera(index)),
+                None => SandboxAction::None,
+            },
+            KeyCode::Char('x') => match sim.vehicles.iter().find(|v| v.cell() == cell) {
+                Some(vehicle) => SandboxAction::Apply(SimEvent::Despawn(vehicle.id)),
+                None => SandboxAction::None,
+            },
+            KeyCode::Char('g') | KeyCode::Esc => SandboxAction::Close,
+            _ => SandboxAction::None,
+        }
+    }
+}
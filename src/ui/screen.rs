@@ -0,0 +1,647 @@
+use std::fmt::Write as _;
+use std::io;
+use std::rc::Rc;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[cfg(feature = "gif")]
+use crate::ui::capabilities::{ANSI_COLOURS, CUBE_LEVELS};
+use crate::ui::capabilities::{ColourDepth, TerminalCapabilities};
+use crate::ui::graphics::{self, Colour, GraphicsProtocol, Picture};
+use crate::ui::sprites::SpriteSet;
+
+/// Begin and end synchronized update: the terminal shows nothing written in
+/// between until the end arrives.
+const BEGIN_SYNC: &str = "\x1B[?2026h";
+const END_SYNC: &str = "\x1B[?2026l";
+
+/// Screen size assumed until the backend reports the real one.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// Unchanged cells a write may run over rather than jumping the cursor past
+/// them; a cursor move costs about as much as a few cells.
+const MERGE_GAP: usize = 4;
+
+/// One terminal column as the last frame left it.
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    /// Glyph drawn here, with any combining marks or variation selector.
+    /// Empty for the right half of a double-width glyph.
+    text: String,
+    /// SGR sequence that sets this cell's colours and attributes, or empty
+    /// for the terminal's defaults.
+    style: Rc<str>,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Self {
+            text: " ".to_string(),
+            style: Rc::from(""),
+        }
+    }
+}
+
+/// A cell as the last frame left it, with its colours worked out the way
+/// a terminal with the usual xterm palette would show them.
+#[cfg(feature = "gif")]
+#[derive(Debug, Clone, Copy)]
+pub struct CellView<'a> {
+    /// Glyph drawn here, or empty for the right half of a double-width one.
+    pub text: &'a str,
+    pub foreground: Colour,
+    pub background: Colour,
+    pub bold: bool,
+}
+
+/// Colours and attributes as SGR parameters, e.g. `38;5;8` for the
+/// foreground.
+#[derive(Debug, Clone, Default)]
+struct Style {
+    foreground: Option<String>,
+    background: Option<String>,
+    /// Bit `n` for SGR attribute `n`: 1 bold, 2 dim, 4 underline and so on.
+    attributes: u16,
+}
+
+impl Style {
+    /// Applies the parameters of one `ESC [ ... m` sequence.
+    fn apply(&mut self, params: &str) {
+        let mut params = params.split(';').map(|p| p.parse::<u16>().unwrap_or(0));
+        while let Some(code) = params.next() {
+            match code {
+                0 => *self = Self::default(),
+                1..=9 => self.attributes |= 1 << code,
+                22 => self.attributes &= !(1 << 1 | 1 << 2),
+                23..=29 => self.attributes &= !(1 << (code - 20)),
+                30..=37 | 90..=97 => self.foreground = Some(code.to_string()),
+                40..=47 | 100..=107 => self.background = Some(code.to_string()),
+                39 => self.foreground = None,
+                49 => self.background = None,
+                38 | 48 => {
+                    let colour = match params.next() {
+                        Some(5) => params.next().map(|n| format!("{};5;{}", code, n)),
+                        Some(2) => {
+                            let rgb: Vec<String> =
+                                params.by_ref().take(3).map(|c| c.to_string()).collect();
+                            Some(format!("{};2;{}", code, rgb.join(";")))
+                        }
+                        _ => None,
+                    };
+                    if code == 38 {
+                        self.foreground = colour;
+                    } else {
+                        self.background = colour;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Foreground and background as RGB, with reverse video and dimming
+    /// applied.
+    #[cfg(feature = "gif")]
+    fn colours(&self) -> (Colour, Colour) {
+        let mut foreground = self
+            .foreground
+            .as_deref()
+            .map_or(DEFAULT_FOREGROUND, sgr_colour);
+        let mut background = self
+            .background
+            .as_deref()
+            .map_or(DEFAULT_BACKGROUND, sgr_colour);
+        if self.attributes & (1 << 7) != 0 {
+            (foreground, background) = (background, foreground);
+        }
+        if self.attributes & (1 << 2) != 0 {
+            foreground = foreground.map(|c| c / 2);
+        }
+        (foreground, background)
+    }
+
+    /// Brings RGB colours down to what a terminal of `depth` shows.
+    fn fit(&mut self, depth: ColourDepth) {
+        if depth == ColourDepth::Monochrome {
+            // A background marks something out, like the inspected
+            // vehicle; reverse video still does.
+            if self.background.take().is_some() {
+                self.attributes |= 1 << 7;
+            }
+            self.foreground = None;
+            return;
+        }
+        for (code, colour) in [(38, &mut self.foreground), (48, &mut self.background)] {
+            let rgb: Vec<u8> = match colour
+                .as_deref()
+                .and_then(|c| c.strip_prefix(&format!("{};2;", code)))
+            {
+                Some(rgb) => rgb.split(';').map(|c| c.parse().unwrap_or(0)).collect(),
+                None => continue,
+            };
+            if let [r, g, b] = *rgb.as_slice() {
+                *colour = depth.sgr(code, [r, g, b]);
+            }
+        }
+    }
+
+    /// The one sequence that sets this style from the defaults.
+    fn sequence(&self) -> String {
+        let params: Vec<String> = (1..10)
+            .filter(|bit| self.attributes & (1 << bit) != 0)
+            .map(|bit| bit.to_string())
+            .chain(self.foreground.clone())
+            .chain(self.background.clone())
+            .collect();
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("\x1B[{}m", params.join(";"))
+        }
+    }
+}
+
+/// Colours of plain text, for frames drawn somewhere other than the
+/// terminal.
+#[cfg(feature = "gif")]
+const DEFAULT_FOREGROUND: Colour = [204, 204, 204];
+#[cfg(feature = "gif")]
+const DEFAULT_BACKGROUND: Colour = [0, 0, 0];
+
+/// RGB for a colour as `Style` keeps it: `31`, `96`, `38;5;208` or
+/// `48;2;10;20;30`.
+#[cfg(feature = "gif")]
+fn sgr_colour(colour: &str) -> Colour {
+    let params: Vec<u16> = colour.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+    match *params.as_slice() {
+        [code] if (30..=37).contains(&code) || (40..=47).contains(&code) => {
+            ANSI_COLOURS[(code % 10) as usize]
+        }
+        [code] if (90..=97).contains(&code) || (100..=107).contains(&code) => {
+            ANSI_COLOURS[(code % 10) as usize + 8]
+        }
+        [_, 5, index] => match index {
+            0..=15 => ANSI_COLOURS[index as usize],
+            16..=231 => {
+                let index = index - 16;
+                [index / 36, index / 6 % 6, index % 6].map(|n| CUBE_LEVELS[n as usize])
+            }
+            _ => [(8 + 10 * (index.min(255) - 232)) as u8; 3],
+        },
+        [_, 2, r, g, b] => [r as u8, g as u8, b as u8],
+        _ => DEFAULT_FOREGROUND,
+    }
+}
+
+/// What is on the terminal, so each frame only sends the cells that
+/// changed since the one before.
+#[derive(Debug)]
+pub struct ScreenBuffer {
+    previous: Vec<Vec<Cell>>,
+    /// Columns and rows.
+    size: (u16, u16),
+    /// Whether the next frame has to clear the screen and draw everything.
+    stale: bool,
+    capabilities: TerminalCapabilities,
+    /// Protocol pictures are drawn with, once turned on and supported.
+    graphics: Option<GraphicsProtocol>,
+    /// Picture the last frame left on screen.
+    picture: Option<Picture>,
+    /// Colours of cells that set none, filling the whole screen when any
+    /// are given.
+    base: Style,
+    /// What signals, vehicles and road markings are drawn as.
+    sprites: SpriteSet,
+}
+
+impl ScreenBuffer {
+    pub fn new(capabilities: TerminalCapabilities) -> Self {
+        Self {
+            previous: Vec::new(),
+            size: DEFAULT_SIZE,
+            stale: true,
+            capabilities,
+            graphics: None,
+            picture: None,
+            base: Style::default(),
+            sprites: SpriteSet::default(),
+        }
+    }
+
+    /// Takes a new screen size; the next frame is drawn from scratch.
+    pub fn resize(&mut self, columns: u16, rows: u16) {
+        self.size = if columns > 0 && rows > 0 {
+            (columns, rows)
+        } else {
+            DEFAULT_SIZE
+        };
+        self.stale = true;
+    }
+
+    /// Draws pictures from now on if the terminal can show them; other
+    /// terminals keep the text map.
+    pub fn enable_graphics(&mut self) {
+        self.graphics = self.capabilities.graphics;
+    }
+
+    /// Draws the map in half blocks from now on, in any terminal.
+    pub fn enable_half_blocks(&mut self) {
+        self.graphics = Some(GraphicsProtocol::HalfBlocks);
+    }
+
+    pub fn graphics(&self) -> Option<GraphicsProtocol> {
+        self.graphics
+    }
+
+    pub fn capabilities(&self) -> TerminalCapabilities {
+        self.capabilities
+    }
+
+    /// Finds out which wide glyphs the terminal draws narrow, so they can
+    /// be padded out to the two columns they are laid out in.
+    pub fn measure_glyph_widths(&mut self) -> io::Result<()> {
+        self.capabilities.measure_glyph_widths()
+    }
+
+    /// Whether the terminal shows no colour, so frames should tell states
+    /// apart some other way.
+    pub fn monochrome(&self) -> bool {
+        self.capabilities.colours == ColourDepth::Monochrome
+    }
+
+    /// Paints text and the space around it in these colours from the next
+    /// frame on, wherever the frame leaves the terminal's defaults. `None`
+    /// keeps the terminal's own.
+    pub fn set_base_colours(&mut self, foreground: Option<Colour>, background: Option<Colour>) {
+        let depth = self.capabilities.colours;
+        self.base = Style {
+            foreground: foreground.and_then(|colour| depth.sgr(38, colour)),
+            background: background.and_then(|colour| depth.sgr(48, colour)),
+            attributes: 0,
+        };
+        self.stale = true;
+    }
+
+    /// Draws signals, vehicles and road markings as `sprites` from the
+    /// next frame on.
+    pub fn set_sprites(&mut self, sprites: SpriteSet) {
+        self.sprites = sprites;
+    }
+
+    /// Columns and rows.
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    /// The screen as the last frame left it, row by row. Rows end after
+    /// their last drawn cell.
+    #[cfg(feature = "gif")]
+    pub fn rows(&self) -> impl Iterator<Item = Vec<CellView<'_>>> {
+        self.previous.iter().map(|line| {
+            line.iter()
+                .map(|cell| {
+                    let mut style = Style::default();
+                    if let Some(params) = cell
+                        .style
+                        .strip_prefix("\x1B[")
+                        .and_then(|s| s.strip_suffix('m'))
+                    {
+                        style.apply(params);
+                    }
+                    let (foreground, background) = style.colours();
+                    CellView {
+                        text: &cell.text,
+                        foreground,
+                        background,
+                        bold: style.attributes & (1 << 1) != 0,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// The screen as the last frame left it, in colour: each row's cells
+    /// with SGR sequences wherever the style changes, ready to `cat` back
+    /// to a terminal.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for line in self.drawn_rows() {
+            let mut style: Option<&str> = None;
+            for cell in line {
+                if style != Some(&cell.style) {
+                    out.push_str("\x1B[0m");
+                    out.push_str(&cell.style);
+                    style = Some(&cell.style);
+                }
+                out.push_str(&cell.text);
+            }
+            out.push_str("\x1B[0m\n");
+        }
+        out
+    }
+
+    /// The screen as the last frame left it, as plain text.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for line in self.drawn_rows() {
+            let text: String = line.iter().map(|cell| cell.text.as_str()).collect();
+            out.push_str(text.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Rows down to the last one with anything on it.
+    fn drawn_rows(&self) -> &[Vec<Cell>] {
+        let blank = Cell::blank();
+        let used = self
+            .previous
+            .iter()
+            .rposition(|line| line.iter().any(|cell| *cell != blank))
+            .map_or(0, |last| last + 1);
+        &self.previous[..used]
+    }
+
+    /// What to write to turn the screen into `frame`, given as text with
+    /// colour escapes and cursor moves as if the screen had been cleared
+    /// first, with `picture` on top. The picture is only sent again when
+    /// it changed.
+    pub fn diff(&mut self, frame: &str, picture: Option<Picture>) -> String {
+        let size = self.size;
+        if self.capabilities.dumb {
+            // No cursor moves: each new frame is printed below the last.
+            let (next, _) = parse(frame, size, &self.base, &self.capabilities, self.sprites);
+            if next == self.previous {
+                return String::new();
+            }
+            self.previous = next;
+            return self.to_text().replace('\n', "\r\n") + "\r\n";
+        }
+        let mut out = String::new();
+        if self.capabilities.synchronized_output {
+            out.push_str(BEGIN_SYNC);
+        }
+        let picture_changed = picture != self.picture;
+        // Sixel pixels replace the text under them, so whatever the old
+        // picture covered has to be drawn again.
+        let sixel_moved = self.graphics == Some(GraphicsProtocol::Sixel)
+            && self.picture.as_ref().is_some_and(|old| {
+                picture.as_ref().is_none_or(|new| {
+                    (new.row, new.column, new.columns, new.rows)
+                        != (old.row, old.column, old.columns, old.rows)
+                })
+            });
+        let redraw = self.stale || sixel_moved;
+        if redraw {
+            out.push_str("\x1B[0m\x1B[2J");
+            self.previous.clear();
+            self.stale = false;
+        }
+        let (next, (row, column)) =
+            parse(frame, size, &self.base, &self.capabilities, self.sprites);
+        for (index, cells) in next.iter().enumerate() {
+            let old = self.previous.get(index).map_or(&[][..], Vec::as_slice);
+            write_changes(&mut out, index, old, cells);
+        }
+        if picture_changed || redraw {
+            if self.graphics == Some(GraphicsProtocol::Kitty) && self.picture.is_some() {
+                out.push_str(&graphics::kitty_delete());
+            }
+            if let (Some(protocol), Some(picture)) = (self.graphics, &picture) {
+                write!(out, "\x1B[{};{}H", picture.row + 1, picture.column + 1)
+                    .expect("writing to a String cannot fail");
+                out.push_str(&picture.encode(protocol));
+            }
+        }
+        self.picture = picture;
+        // Leave the cursor where a plain write of the frame would have.
+        write!(out, "\x1B[{};{}H", row + 1, column + 1).expect("writing to a String cannot fail");
+        self.previous = next;
+        if self.capabilities.synchronized_output {
+            out.push_str(END_SYNC);
+        }
+        out
+    }
+}
+
+/// Plays `frame` onto an empty screen of `size`, returning the cells and
+/// where the cursor ended up. Lines wrap at the right edge like they would
+/// on the terminal; anything below the bottom row is dropped. Colours the
+/// frame leaves at the defaults come from `base`, and RGB colours are
+/// brought down to what the terminal shows. Emoji are swapped for
+/// `sprites`.
+fn parse(
+    frame: &str,
+    (columns, rows): (u16, u16),
+    base: &Style,
+    capabilities: &TerminalCapabilities,
+    sprites: SpriteSet,
+) -> (Vec<Vec<Cell>>, (usize, usize)) {
+    let depth = capabilities.colours;
+    let (columns, rows) = (columns.max(1) as usize, rows as usize);
+    let mut grid: Vec<Vec<Cell>> = vec![Vec::new(); rows];
+    let mut sgr = base.clone();
+    let base_style: Rc<str> = Rc::from(base.sequence());
+    let mut style = Rc::clone(&base_style);
+    let (mut row, mut column) = (0, 0);
+    // Cell that zero-width characters attach to.
+    let mut last: Option<(usize, usize)> = None;
+
+    let mut chars = frame.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1B' if chars.peek() == Some(&'[') => {
+                chars.next();
+                let mut params = String::new();
+                let mut command = None;
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        command = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                match command {
+                    Some('m') => {
+                        sgr.apply(&params);
+                        if sgr.foreground.is_none() {
+                            sgr.foreground = base.foreground.clone();
+                        }
+                        if sgr.background.is_none() {
+                            sgr.background = base.background.clone();
+                        }
+                        sgr.fit(depth);
+                        style = Rc::from(sgr.sequence());
+                    }
+                    Some('H') => {
+                        let mut position = params.split(';').map(|p| p.parse().unwrap_or(1));
+                        let r: usize = position.next().unwrap_or(1);
+                        let c: usize = position.next().unwrap_or(1);
+                        (row, column) = (r.saturating_sub(1), c.saturating_sub(1));
+                        last = None;
+                    }
+                    _ => {}
+                }
+            }
+            '\n' => {
+                (row, column) = (row + 1, 0);
+                last = None;
+            }
+            '\r' => column = 0,
+            c => {
+                let width = c.width().unwrap_or(0);
+                if width == 0 {
+                    if let Some((r, c_at)) = last {
+                        let cell = &mut grid[r][c_at];
+                        let before = cell.text.width();
+                        cell.text.push(c);
+                        // A variation selector can turn a narrow glyph wide.
+                        if cell.text.width() > before && column < columns {
+                            put(&mut grid, r, column, String::new(), &style);
+                            column += 1;
+                        }
+                    }
+                    continue;
+                }
+                if column + width > columns {
+                    (row, column) = (row + 1, 0);
+                }
+                if row >= rows {
+                    last = None;
+                    continue;
+                }
+                put(&mut grid, row, column, c.to_string(), &style);
+                if width == 2 {
+                    put(&mut grid, row, column + 1, String::new(), &style);
+                }
+                last = Some((row, column));
+                column += width;
+            }
+        }
+    }
+    // A wide glyph the terminal draws narrow is followed by a space, so
+    // everything after it still lines up.
+    if capabilities.narrow_emoji || capabilities.narrow_variation {
+        for line in &mut grid {
+            for column in 0..line.len().saturating_sub(1) {
+                if line[column + 1].text.is_empty() && capabilities.draws_narrow(&line[column].text)
+                {
+                    line[column + 1].text = " ".to_string();
+                }
+            }
+        }
+    }
+    // Monochrome screens always get letters, which need no colour.
+    let sprites = if depth == ColourDepth::Monochrome {
+        SpriteSet::Ascii
+    } else {
+        sprites
+    };
+    if sprites != SpriteSet::Emoji {
+        for line in &mut grid {
+            for column in 0..line.len() {
+                let Some(sprite) = sprites.sprite(&line[column].text) else {
+                    continue;
+                };
+                let colour = graphics::glyph_colour(&line[column].text)
+                    .and_then(|colour| depth.sgr(38, colour));
+                let cell = &mut line[column];
+                // A glyph drawn narrow only has room for the sprite's
+                // first column.
+                cell.text = if cell.text.width() == 2 {
+                    sprite.to_string()
+                } else {
+                    sprite.chars().take(1).collect()
+                };
+                if let Some(sgr) = colour {
+                    cell.style = Rc::from(format!("{}\x1B[{}m", cell.style, sgr));
+                }
+                if cell.text.width() == 2
+                    && let Some(right) = line.get_mut(column + 1)
+                {
+                    right.text = String::new();
+                }
+            }
+        }
+    }
+    // Base colours cover the whole screen, not just where text went.
+    if !base_style.is_empty() {
+        for line in &mut grid {
+            line.resize_with(columns, || Cell {
+                text: " ".to_string(),
+                style: Rc::clone(&base_style),
+            });
+        }
+    }
+    (grid, (row.min(rows.saturating_sub(1)), column))
+}
+
+/// Sets one cell, blanking the other half of any double-width glyph it
+/// lands on.
+fn put(grid: &mut [Vec<Cell>], row: usize, column: usize, text: String, style: &Rc<str>) {
+    let line = &mut grid[row];
+    if line.len() <= column {
+        line.resize_with(column + 1, Cell::blank);
+    }
+    if line[column].text.is_empty() && column > 0 && !text.is_empty() {
+        line[column - 1] = Cell::blank();
+    }
+    if line[column].text.width() == 2
+        && let Some(right) = line.get_mut(column + 1)
+    {
+        *right = Cell::blank();
+    }
+    line[column] = Cell {
+        text,
+        style: Rc::clone(style),
+    };
+}
+
+/// Appends what it takes to turn row `row` from `old` into `new`: each
+/// stretch of changed cells as one write, and an erase for a row that got
+/// shorter.
+fn write_changes(out: &mut String, row: usize, old: &[Cell], new: &[Cell]) {
+    let blank = Cell::blank();
+    let mut changed = new
+        .iter()
+        .enumerate()
+        .filter(|&(column, cell)| old.get(column).unwrap_or(&blank) != cell)
+        .map(|(column, _)| column);
+
+    if let Some(first) = changed.next() {
+        let (mut start, mut end) = (first, first);
+        for column in changed {
+            if column - end > MERGE_GAP {
+                write_run(out, row, new, start, end);
+                start = column;
+            }
+            end = column;
+        }
+        write_run(out, row, new, start, end);
+    }
+    if old.iter().skip(new.len()).any(|cell| *cell != blank) {
+        write!(out, "\x1B[{};{}H\x1B[K", row + 1, new.len() + 1)
+            .expect("writing to a String cannot fail");
+    }
+}
+
+/// Writes the cells of `line` from `start` to `end`, starting on the left
+/// half of a double-width glyph and restating the style wherever it changes.
+fn write_run(out: &mut String, row: usize, line: &[Cell], start: usize, end: usize) {
+    let start = if line[start].text.is_empty() {
+        start.saturating_sub(1)
+    } else {
+        start
+    };
+    write!(out, "\x1B[{};{}H", row + 1, start + 1).expect("writing to a String cannot fail");
+    let mut style: Option<&str> = None;
+    for cell in &line[start..=end] {
+        if style != Some(&cell.style) {
+            out.push_str("\x1B[0m");
+            out.push_str(&cell.style);
+            style = Some(&cell.style);
+        }
+        out.push_str(&cell.text);
+    }
+    out.push_str("\x1B[0m");
+}
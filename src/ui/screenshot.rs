@@ -0,0 +1,49 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ui::screen::ScreenBuffer;
+
+/// Writes what is on screen to `screenshot-<UTC time>.ans`, colours and
+/// all, and to a `.txt` of the same name as plain text. Returns a line
+/// naming the files.
+pub fn save(screen: &ScreenBuffer) -> Result<String, String> {
+    let name = format!("screenshot-{}", utc_timestamp());
+    for (extension, contents) in [("ans", screen.to_ansi()), ("txt", screen.to_text())] {
+        let path = format!("{}.{}", name, extension);
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(format!("Screenshot saved to {0}.ans and {0}.txt", name))
+}
+
+/// The current time as `YYYYMMDD-HHMMSS` in UTC.
+fn utc_timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Days since 1970-01-01 to a civil date, counting years from March so
+    // the leap day falls at the end.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
@@ -0,0 +1,63 @@
+use crossterm::event::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    SelectVehicle,
+    TeleportTo,
+}
+
+impl PromptKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptKind::SelectVehicle => "Select vehicle #",
+            PromptKind::TeleportTo => "Teleport to cell",
+        }
+    }
+}
+
+pub enum PromptResult {
+    Pending,
+    Cancelled,
+    Submitted(PromptKind, usize),
+}
+
+/// A one-line numeric entry shown at the bottom of the screen.
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            input: String::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> PromptResult {
+        match code {
+            KeyCode::Char(ch) if ch.is_ascii_digit() => self.input.push(ch),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => {
+                return match self.input.parse() {
+                    Ok(value) => PromptResult::Submitted(self.kind, value),
+                    Err(_) => PromptResult::Cancelled,
+                };
+            }
+            KeyCode::Esc => return PromptResult::Cancelled,
+            _ => {}
+        }
+        PromptResult::Pending
+    }
+}
+
+/// The vehicle or light picked for inspection, plus any open ID-entry prompt.
+#[derive(Default)]
+pub struct Selection {
+    pub selected: Option<u32>,
+    pub selected_light: Option<usize>,
+    pub prompt: Option<Prompt>,
+}
@@ -0,0 +1,124 @@
+use std::ops::Range;
+
+use crossterm::style::Color;
+
+use crate::systems::clock::TimeOfDay;
+use crate::systems::simulation::Simulation;
+use crate::systems::weather::WeatherType;
+use crate::ui::theme::Theme;
+
+/// Weathers that fall or drift past, drawn in this order so the wilder
+/// one wins a column they both want.
+const PARTICLES: [WeatherType; 4] = [
+    WeatherType::Storm,
+    WeatherType::Rain,
+    WeatherType::Snow,
+    WeatherType::Fog,
+];
+
+/// Share of columns holding a particle at full strength.
+fn density(kind: WeatherType) -> f32 {
+    match kind {
+        WeatherType::Clear => 0.0,
+        WeatherType::Rain => 0.35,
+        WeatherType::Storm => 0.6,
+        WeatherType::Snow => 0.3,
+        WeatherType::Fog => 0.8,
+    }
+}
+
+/// Animation steps per simulated second at full strength.
+fn speed(kind: WeatherType) -> f32 {
+    match kind {
+        WeatherType::Clear => 0.0,
+        WeatherType::Rain => 10.0,
+        WeatherType::Storm => 16.0,
+        WeatherType::Snow => 3.0,
+        WeatherType::Fog => 0.5,
+    }
+}
+
+/// Columns the particles are blown sideways per step. Rain without wind
+/// falls straight, so its drops come and go in place instead.
+fn drift(kind: WeatherType) -> i64 {
+    match kind {
+        WeatherType::Clear | WeatherType::Rain => 0,
+        WeatherType::Storm | WeatherType::Snow | WeatherType::Fog => 1,
+    }
+}
+
+fn glyphs(kind: WeatherType) -> &'static [char] {
+    match kind {
+        WeatherType::Clear => &[],
+        WeatherType::Rain => &['\'', '|', ','],
+        WeatherType::Storm => &['/', '/', '\''],
+        WeatherType::Snow => &['*', '·', '+'],
+        WeatherType::Fog => &['░', '▒'],
+    }
+}
+
+/// Road glyphs between streetlights.
+const STREETLIGHT_SPACING: usize = 6;
+const STREETLIGHT: &str = "💡";
+
+/// Colour behind the road at this time of day, if the theme gives one.
+/// Full daylight leaves the theme's background.
+pub fn road_tint(time: TimeOfDay, theme: &Theme) -> Option<Color> {
+    let colour = match time {
+        TimeOfDay::Night => theme.night,
+        TimeOfDay::Dawn => theme.dawn,
+        TimeOfDay::Dusk => theme.dusk,
+        TimeOfDay::Day => return None,
+    };
+    (colour != Color::Reset).then_some(colour)
+}
+
+/// The row drawn above the road over `cells`, one glyph per `scale` cells:
+/// streetlights while they are lit, and between them rain, snow or fog as
+/// thick as the weather is. Heavier weather also moves faster.
+pub fn sky_row(sim: &Simulation, cells: Range<usize>, scale: usize) -> String {
+    let time = sim.stats.elapsed;
+    let lamps = sim.clock.lights_on();
+    let mut row = String::new();
+    for (glyph, cell) in cells.step_by(scale).enumerate() {
+        // Streetlights stand at fixed points along the road, so they
+        // scroll with it.
+        if lamps && (cell / scale).is_multiple_of(STREETLIGHT_SPACING) {
+            row.push_str(STREETLIGHT);
+            continue;
+        }
+        for column in [glyph * 2, glyph * 2 + 1] {
+            let particle = PARTICLES
+                .into_iter()
+                .find_map(|kind| particle(kind, sim.weather.strength(kind), column as i64, time));
+            row.push(particle.unwrap_or(' '));
+        }
+    }
+    row
+}
+
+fn particle(kind: WeatherType, strength: f32, column: i64, time: f32) -> Option<char> {
+    if strength <= 0.0 {
+        return None;
+    }
+    let step = (time * speed(kind) * (0.5 + strength)) as i64;
+    // Drifting particles keep their place in the field for a few steps
+    // while it slides along; falling ones are rolled again every step.
+    let (x, generation) = match drift(kind) {
+        0 => (column, step),
+        drift => (column - step * drift, step / 4),
+    };
+    let roll = hash(x, generation + kind as i64 * 7919);
+    let glyphs = glyphs(kind);
+    ((roll % 1000) as f32 / 1000.0 < density(kind) * strength)
+        .then(|| glyphs[(roll / 1000) as usize % glyphs.len()])
+}
+
+/// Well-mixed bits from two numbers, so particles look scattered.
+fn hash(a: i64, b: i64) -> u64 {
+    let mut x = (a as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (b as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    x ^= x >> 29;
+    x = x.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x ^ (x >> 32)
+}
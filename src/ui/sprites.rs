@@ -0,0 +1,68 @@
+/// Glyphs the frame is drawn with, in the order each set lists what it
+/// draws instead.
+const GLYPHS: [&str; 33] = [
+    "🟥", "🟨", "🟩", "🚗", "🛵", "🚲", "🛻", "🚑", "🚒", "🚓", "💥", "🅿", "🛣", "⏬", "⛔", "💡",
+    "🌿", "🏥", "🧯", "⚠", "🚧", "🏟", "🚫", "🌉", "🌊", "🚨", "⛵", "🚸", "🟠", "📷", "🎫", "🏬",
+    "🚚",
+];
+/// Single-width symbols, coloured like the emoji they replace.
+const UNICODE: [&str; 33] = [
+    "██", "██", "██", "◖◗", "◆ ", "◇ ", "▣▣", "✚ ", "▰▰", "◉ ", "✖ ", "[]", "··", "▼ ", "✕ ", "☼ ",
+    "≋≋", "⊕ ", "△ ", "◈ ", "▴▴", "◎ ", "▬▬", "╤╤", "≈≈", "✱ ", "◮ ", "⚑ ", "● ", "◙ ", "╪╪", "⊞ ",
+    "▤▤",
+];
+/// Letters, which also stand in on a monochrome screen, where signal
+/// states can't be told apart by colour.
+const ASCII: [&str; 33] = [
+    "R ", "Y ", "G ", "C ", "M ", "B ", "T ", "A ", "F ", "U ", "X ", "P ", ". ", "v ", "x ", "o ",
+    "# ", "h ", "f ", "! ", "^^", "@ ", "--", "==", "~~", "* ", "d ", "s ", "+ ", "c ", "$ ", "p ",
+    "D ",
+];
+
+/// What signals, vehicles and road markings are drawn as. Every sprite is
+/// two columns wide, like the emoji, so the layout doesn't move when the
+/// set is switched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpriteSet {
+    #[default]
+    Emoji,
+    Unicode,
+    Ascii,
+}
+
+impl SpriteSet {
+    pub const ALL: [SpriteSet; 3] = [SpriteSet::Emoji, SpriteSet::Unicode, SpriteSet::Ascii];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SpriteSet::Emoji => "emoji",
+            SpriteSet::Unicode => "unicode",
+            SpriteSet::Ascii => "ascii",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|set| set.name() == name)
+    }
+
+    /// The set after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&set| set == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// What this set draws in place of `glyph`, or `None` to keep it.
+    pub fn sprite(self, glyph: &str) -> Option<&'static str> {
+        let table = match self {
+            SpriteSet::Emoji => return None,
+            SpriteSet::Unicode => &UNICODE,
+            SpriteSet::Ascii => &ASCII,
+        };
+        // Some glyphs carry a variation selector asking for emoji presentation.
+        let glyph = glyph.trim_end_matches('\u{FE0F}');
+        GLYPHS
+            .iter()
+            .position(|&emoji| emoji == glyph)
+            .map(|index| table[index])
+    }
+}
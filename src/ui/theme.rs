@@ -0,0 +1,307 @@
+use crossterm::style::Color;
+
+use crate::systems::config::Config;
+#[cfg(feature = "tui")]
+use crate::ui::capabilities::ColourDepth;
+use crate::ui::graphics::Colour;
+
+/// Built-in themes, as the `[theme]` settings that would give them. Every
+/// theme sets every colour; `default` leaves the terminal's own.
+const BUILT_IN: [(&str, [(&str, &str); 15]); 4] = [
+    (
+        "dark",
+        [
+            ("foreground", "default"),
+            ("background", "default"),
+            ("banner", "#00cdcd"),
+            ("status", "#cdcd00"),
+            ("error", "#cd0000"),
+            ("highlight", "#00008b"),
+            ("cursor", "#cd00cd"),
+            ("bike_lane", "#00cd00"),
+            ("muted", "#7f7f7f"),
+            ("go", "#00cd00"),
+            ("caution", "#cdcd00"),
+            ("stop", "#cd0000"),
+            ("night", "#10142c"),
+            ("dawn", "#48343a"),
+            ("dusk", "#3a2648"),
+        ],
+    ),
+    (
+        "light",
+        [
+            ("foreground", "#1e1e1e"),
+            ("background", "#f5f5f0"),
+            ("banner", "#005f87"),
+            ("status", "#875f00"),
+            ("error", "#b00000"),
+            ("highlight", "#add8e6"),
+            ("cursor", "#8700af"),
+            ("bike_lane", "#2e7d32"),
+            ("muted", "#8a8a8a"),
+            ("go", "#2e7d32"),
+            ("caution", "#b58900"),
+            ("stop", "#c62828"),
+            ("night", "#c8cce0"),
+            ("dawn", "#f5dcc8"),
+            ("dusk", "#e0d0ec"),
+        ],
+    ),
+    (
+        "solarized",
+        [
+            ("foreground", "#839496"),
+            ("background", "#002b36"),
+            ("banner", "#2aa198"),
+            ("status", "#b58900"),
+            ("error", "#dc322f"),
+            ("highlight", "#073642"),
+            ("cursor", "#d33682"),
+            ("bike_lane", "#859900"),
+            ("muted", "#586e75"),
+            ("go", "#859900"),
+            ("caution", "#b58900"),
+            ("stop", "#dc322f"),
+            ("night", "#001a22"),
+            ("dawn", "#2b3036"),
+            ("dusk", "#1f2a44"),
+        ],
+    ),
+    (
+        "neon",
+        [
+            ("foreground", "#e0e0ff"),
+            ("background", "#0d0221"),
+            ("banner", "#00f0ff"),
+            ("status", "#fff200"),
+            ("error", "#ff2079"),
+            ("highlight", "#3d0066"),
+            ("cursor", "#ff00ff"),
+            ("bike_lane", "#39ff14"),
+            ("muted", "#6b5b95"),
+            ("go", "#39ff14"),
+            ("caution", "#fff200"),
+            ("stop", "#ff2079"),
+            ("night", "#05010f"),
+            ("dawn", "#2a0a2e"),
+            ("dusk", "#1a0640"),
+        ],
+    ),
+];
+
+/// Colours the text frame is drawn in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Plain text and the screen behind it.
+    pub foreground: Color,
+    pub background: Color,
+    /// Tutorial and challenge banners.
+    pub banner: Color,
+    /// Pause, rewind or speed note next to the tick counter.
+    pub status: Color,
+    /// Failed console commands and editor messages.
+    pub error: Color,
+    /// Behind the inspected vehicle.
+    pub highlight: Color,
+    /// The sandbox cursor.
+    pub cursor: Color,
+    pub bike_lane: Color,
+    /// Profiler timings and empty stretches of the minimap.
+    pub muted: Color,
+    /// Signal states on the minimap, and how congested each stretch is.
+    pub go: Color,
+    pub caution: Color,
+    pub stop: Color,
+    /// Behind the road at night, dawn and dusk.
+    pub night: Color,
+    pub dawn: Color,
+    pub dusk: Color,
+}
+
+impl Theme {
+    /// A built-in theme by name.
+    pub fn built_in(name: &str) -> Option<Self> {
+        let (name, settings) = BUILT_IN.iter().find(|(theme, _)| *theme == name)?;
+        let mut theme = Self {
+            name: name.to_string(),
+            foreground: Color::Reset,
+            background: Color::Reset,
+            banner: Color::Reset,
+            status: Color::Reset,
+            error: Color::Reset,
+            highlight: Color::Reset,
+            cursor: Color::Reset,
+            bike_lane: Color::Reset,
+            muted: Color::Reset,
+            go: Color::Reset,
+            caution: Color::Reset,
+            stop: Color::Reset,
+            night: Color::Reset,
+            dawn: Color::Reset,
+            dusk: Color::Reset,
+        };
+        for (key, value) in settings {
+            theme.set(key, value).expect("built-in themes are valid");
+        }
+        Some(theme)
+    }
+
+    /// Changes one colour, given as `#rrggbb` or `default`.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let colour = parse_colour(value).ok_or_else(|| {
+            format!(
+                "[theme] {} must be a #rrggbb colour or default, not '{}'",
+                key, value
+            )
+        })?;
+        let field = match key {
+            "foreground" => &mut self.foreground,
+            "background" => &mut self.background,
+            "banner" => &mut self.banner,
+            "status" => &mut self.status,
+            "error" => &mut self.error,
+            "highlight" => &mut self.highlight,
+            "cursor" => &mut self.cursor,
+            "bike_lane" => &mut self.bike_lane,
+            "muted" => &mut self.muted,
+            "go" => &mut self.go,
+            "caution" => &mut self.caution,
+            "stop" => &mut self.stop,
+            "night" => &mut self.night,
+            "dawn" => &mut self.dawn,
+            "dusk" => &mut self.dusk,
+            _ => return Err(format!("Unknown setting '{}' in [theme]", key)),
+        };
+        *field = colour;
+        Ok(())
+    }
+
+    /// The theme with every colour brought down to what a terminal of
+    /// `depth` shows.
+    #[cfg(feature = "tui")]
+    pub fn fitted(&self, depth: ColourDepth) -> Self {
+        Self {
+            name: self.name.clone(),
+            foreground: depth.fit(self.foreground),
+            background: depth.fit(self.background),
+            banner: depth.fit(self.banner),
+            status: depth.fit(self.status),
+            error: depth.fit(self.error),
+            highlight: depth.fit(self.highlight),
+            cursor: depth.fit(self.cursor),
+            bike_lane: depth.fit(self.bike_lane),
+            muted: depth.fit(self.muted),
+            go: depth.fit(self.go),
+            caution: depth.fit(self.caution),
+            stop: depth.fit(self.stop),
+            night: depth.fit(self.night),
+            dawn: depth.fit(self.dawn),
+            dusk: depth.fit(self.dusk),
+        }
+    }
+
+    /// Foreground and background the whole screen is painted in, or `None`
+    /// for the terminal's own.
+    pub fn base_colours(&self) -> (Option<Colour>, Option<Colour>) {
+        (rgb(self.foreground), rgb(self.background))
+    }
+}
+
+/// `#rrggbb` (the `#` is optional) as an RGB colour, or `default` for the
+/// terminal's.
+pub fn parse_colour(text: &str) -> Option<Color> {
+    if text == "default" {
+        return Some(Color::Reset);
+    }
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+    Some(Color::Rgb {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+    })
+}
+
+fn rgb(colour: Color) -> Option<Colour> {
+    match colour {
+        Color::Rgb { r, g, b } => Some([r, g, b]),
+        _ => None,
+    }
+}
+
+/// The themes a run can switch between, and the one in use. The `[theme]`
+/// section picks the starting theme with `name` and overrides any of its
+/// colours:
+///
+/// ```text
+/// [theme]
+/// name = solarized
+/// highlight = #264f78
+/// ```
+#[derive(Debug, Clone)]
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self {
+            themes: BUILT_IN
+                .iter()
+                .filter_map(|(name, _)| Theme::built_in(name))
+                .collect(),
+            current: 0,
+        }
+    }
+}
+
+impl Themes {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut themes = Self::default();
+        let section = config.section("theme");
+        if let Some((_, name)) = section.iter().find(|(key, _)| key == "name") {
+            themes.select(name)?;
+        }
+        let theme = &mut themes.themes[themes.current];
+        for (key, value) in section.iter().filter(|(key, _)| key != "name") {
+            theme.set(key, value)?;
+        }
+        Ok(themes)
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// Switches to the theme called `name`.
+    pub fn select(&mut self, name: &str) -> Result<&Theme, String> {
+        self.current = self
+            .themes
+            .iter()
+            .position(|theme| theme.name == name)
+            .ok_or_else(|| format!("Unknown theme '{}' (try {})", name, self.names()))?;
+        Ok(self.current())
+    }
+
+    /// Switches to the next theme, wrapping around.
+    pub fn cycle(&mut self) -> &Theme {
+        self.current = (self.current + 1) % self.themes.len();
+        self.current()
+    }
+
+    /// Every theme's name, comma-separated.
+    pub fn names(&self) -> String {
+        let names: Vec<&str> = self
+            .themes
+            .iter()
+            .map(|theme| theme.name.as_str())
+            .collect();
+        names.join(", ")
+    }
+}
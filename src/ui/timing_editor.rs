@@ -0,0 +1,231 @@
+use crossterm::event::KeyCode;
+
+use crate::systems::events::SimEvent;
+use crate::systems::signal_control::{ControlMode, MAX_TIMING_SECONDS, Timing};
+use crate::systems::simulation::Simulation;
+use crate::systems::stats::SimulationStats;
+
+pub enum TimingEditorAction {
+    None,
+    Close,
+    Apply(SimEvent),
+    /// Write every light's timing into the config file.
+    Save,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Green,
+    Yellow,
+    Red,
+    Offset,
+    Cycle,
+}
+
+impl Field {
+    const ALL: [Field; 5] = [
+        Field::Green,
+        Field::Yellow,
+        Field::Red,
+        Field::Offset,
+        Field::Cycle,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Green => "green",
+            Field::Yellow => "yellow",
+            Field::Red => "red",
+            Field::Offset => "offset",
+            Field::Cycle => "cycle",
+        }
+    }
+}
+
+/// Trips completed and their total wait at some moment, to compare the
+/// traffic before and after a change.
+#[derive(Debug, Clone, Copy)]
+struct Mark {
+    elapsed: f32,
+    trips: u32,
+    total_wait: f32,
+}
+
+impl Mark {
+    fn of(stats: &SimulationStats) -> Self {
+        Self {
+            elapsed: stats.elapsed,
+            trips: stats.completed_trips,
+            total_wait: stats.total_wait,
+        }
+    }
+
+    /// Trips completed since the mark, and their average wait.
+    fn since(&self, stats: &SimulationStats) -> (u32, Option<f32>) {
+        // Rewinding can take the counts back past the mark.
+        let trips = stats.completed_trips.saturating_sub(self.trips);
+        let wait = (trips > 0).then(|| (stats.total_wait - self.total_wait) / trips as f32);
+        (trips, wait)
+    }
+}
+
+/// Live retiming of one signal: +/- change the picked part of its timing,
+/// which goes through `Simulation::apply` at once, and the panel compares
+/// the wait of trips finished since the last change with the one before.
+pub struct TimingEditor {
+    pub light: usize,
+    field: Field,
+    /// When the timing last changed, or the editor opened.
+    changed: Mark,
+    /// Whether the timing has changed since the editor opened.
+    retimed: bool,
+    /// Average wait of the trips finished between the change before last
+    /// and the last one.
+    before: Option<f32>,
+}
+
+impl TimingEditor {
+    pub const HELP: &str = "↑/↓ pick  +/- adjust  w write to config  r/Esc close";
+
+    pub fn new(light: usize, sim: &Simulation) -> Self {
+        Self {
+            light,
+            field: Field::Green,
+            changed: Mark::of(&sim.stats),
+            retimed: false,
+            before: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, sim: &Simulation) -> TimingEditorAction {
+        let step = match code {
+            KeyCode::Up | KeyCode::Down => {
+                let count = Field::ALL.len();
+                let at = Field::ALL
+                    .iter()
+                    .position(|&f| f == self.field)
+                    .unwrap_or(0);
+                let at = if code == KeyCode::Up {
+                    (at + count - 1) % count
+                } else {
+                    (at + 1) % count
+                };
+                self.field = Field::ALL[at];
+                return TimingEditorAction::None;
+            }
+            KeyCode::Char('+' | '=') => 1,
+            KeyCode::Char('-' | '_') => -1,
+            KeyCode::Char('w') => return TimingEditorAction::Save,
+            KeyCode::Char('r') | KeyCode::Esc => return TimingEditorAction::Close,
+            _ => return TimingEditorAction::None,
+        };
+        let old = sim.timing(self.light);
+        let timing = self.adjust(old, step);
+        if timing == old {
+            return TimingEditorAction::None;
+        }
+        TimingEditorAction::Apply(SimEvent::SetTiming(self.light, timing))
+    }
+
+    /// `timing` with the picked part moved by `step` seconds, kept within
+    /// what a `[timing]` line allows. The cycle grows or shrinks by moving
+    /// the green and red together, keeping their split.
+    fn adjust(&self, mut timing: Timing, step: i32) -> Timing {
+        let nudge = |seconds: u32| {
+            seconds
+                .saturating_add_signed(step)
+                .clamp(1, MAX_TIMING_SECONDS)
+        };
+        match self.field {
+            Field::Green => timing.green = nudge(timing.green),
+            Field::Yellow => timing.yellow = nudge(timing.yellow),
+            Field::Red => timing.red = nudge(timing.red),
+            Field::Offset => {
+                timing.offset =
+                    (timing.offset + timing.cycle()).saturating_add_signed(step) % timing.cycle()
+            }
+            Field::Cycle => {
+                let split = timing.green + timing.red;
+                let wanted = split
+                    .saturating_add_signed(step)
+                    .clamp(2, 2 * MAX_TIMING_SECONDS);
+                let green = (timing.green as f32 * wanted as f32 / split as f32).round() as u32;
+                timing.green = green.clamp(1, wanted - 1).min(MAX_TIMING_SECONDS);
+                timing.red = (wanted - timing.green).min(MAX_TIMING_SECONDS);
+            }
+        }
+        timing.offset %= timing.cycle();
+        timing
+    }
+
+    /// Starts measuring afresh after a change went through.
+    pub fn changed(&mut self, sim: &Simulation) {
+        let (trips, wait) = self.changed.since(&sim.stats);
+        if trips > 0 {
+            self.before = wait;
+        }
+        self.changed = Mark::of(&sim.stats);
+        self.retimed = true;
+    }
+
+    /// Panel lines: the timing with the picked part marked, then how the
+    /// traffic has done since the last change.
+    pub fn lines(&self, sim: &Simulation) -> Vec<String> {
+        let Some(light) = sim.lights.get(self.light) else {
+            return Vec::new();
+        };
+        let timing = sim.timing(self.light);
+        let parts: Vec<String> = Field::ALL
+            .iter()
+            .map(|&field| {
+                let seconds = match field {
+                    Field::Green => timing.green,
+                    Field::Yellow => timing.yellow,
+                    Field::Red => timing.red,
+                    Field::Offset => timing.offset,
+                    Field::Cycle => timing.cycle(),
+                };
+                if field == self.field {
+                    format!("[{} {}s]", field.name(), seconds)
+                } else {
+                    format!(" {} {}s ", field.name(), seconds)
+                }
+            })
+            .collect();
+        let mode = light.controller.mode();
+        let note = if mode == ControlMode::FixedTime {
+            String::new()
+        } else {
+            format!("   ({} light: only the yellow applies)", mode.name())
+        };
+        let mut lines = vec![format!(
+            "⏱ TIMING light {}:{}{}",
+            self.light + 1,
+            parts.join(""),
+            note
+        )];
+        let seconds = (sim.stats.elapsed - self.changed.elapsed).max(0.0);
+        let period = if self.retimed {
+            format!("the {:.0}s since the last change", seconds)
+        } else {
+            format!("the {:.0}s since opening", seconds)
+        };
+        let efficiency = match (self.changed.since(&sim.stats), self.before) {
+            ((trips, Some(wait)), Some(before)) => format!(
+                "{} trips in {}, average wait {:.1}s vs {:.1}s before ({:+.1}s)",
+                trips,
+                period,
+                wait,
+                before,
+                wait - before
+            ),
+            ((trips, Some(wait)), None) => {
+                format!("{} trips in {}, average wait {:.1}s", trips, period, wait)
+            }
+            (_, _) => format!("no trips finished in {}", period),
+        };
+        lines.push(format!("   {}", efficiency));
+        lines.push(format!("   {}", Self::HELP));
+        lines
+    }
+}
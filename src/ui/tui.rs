@@ -0,0 +1,709 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crossterm::style::Color;
+use ratatui::Frame;
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use unicode_width::UnicodeWidthStr;
+
+use crate::systems::editor::MapEditor;
+use crate::systems::simulation::Simulation;
+use crate::systems::vehicle::{Lane, VehicleType};
+use crate::ui::camera::{Camera, Zoom};
+use crate::ui::capabilities::{ColourDepth, TerminalCapabilities};
+use crate::ui::graphics::glyph_colour;
+use crate::ui::input::{Action, InputHandler, key_name};
+use crate::ui::inspector::{render_light_panel, render_vehicle_panel};
+use crate::ui::render::{
+    BARRICADE, BRIDGE, CAMERA, CAR_PARK, CONES, DEBRIS, DELIVERY, Hud, LaneGlyphs, SCHOOL,
+    ScreenLayout, density_glyph, fault_status, manual_summary, parking_summary, road_glyphs,
+    toll_summary, too_small_lines, traffic_note, turn_summary,
+};
+use crate::ui::selection::Selection;
+use crate::ui::sky::{road_tint, sky_row};
+use crate::ui::sprites::SpriteSet;
+use crate::ui::theme::Theme;
+
+/// Actions listed in the controls bar, in this order.
+const CONTROLS: [Action; 7] = [
+    Action::ToggleHelp,
+    Action::TogglePause,
+    Action::Step,
+    Action::ToggleZoom,
+    Action::FollowNext,
+    Action::OpenConsole,
+    Action::Quit,
+];
+/// Rows the statistics panel needs inside its border: vehicles spawned, one
+/// per type with emergency vehicles together, then trips, wait, throughput
+/// and crashes.
+const STATS_ROWS: usize = VehicleType::ALL.len() - 2 + 5;
+/// Narrowest terminal that keeps the statistics and alerts panels beside
+/// the signals and the inspector. Below it the statistics fold into one
+/// header line and the alerts move under the inspector.
+const SIDE_PANELS_WIDTH: u16 = 80;
+
+/// The interface as ratatui widgets: status, signals, statistics, the road,
+/// the inspector and alerts each get a panel, and the panels share out the
+/// terminal again whenever it is resized.
+pub struct TuiFrontend {
+    terminal: Terminal<CrosstermBackend<Box<dyn Write>>>,
+    /// Key hints for the controls bar, from the bindings in effect.
+    controls: String,
+    /// Theme colours are brought down to this many.
+    colours: ColourDepth,
+    /// What signals, vehicles and road markings are drawn as.
+    pub sprites: SpriteSet,
+}
+
+impl TuiFrontend {
+    /// Draws to `output`: stdout, or a recorder passing frames on to it.
+    pub fn new(input: &InputHandler, output: Box<dyn Write>) -> io::Result<Self> {
+        let controls: Vec<String> = CONTROLS
+            .iter()
+            .filter_map(|&action| {
+                let key = *input.keys_for(action).first()?;
+                Some(format!("{} {}", key_name(key), action.name()))
+            })
+            .collect();
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(output))?,
+            controls: controls.join("  "),
+            colours: TerminalCapabilities::detect().colours,
+            sprites: SpriteSet::default(),
+        })
+    }
+
+    pub fn render(
+        &mut self,
+        sim: &Simulation,
+        camera: &Camera,
+        selection: &Selection,
+        hud: &Hud,
+    ) -> ScreenLayout {
+        let mut layout = ScreenLayout::default();
+        let controls = self.controls.as_str();
+        let theme = hud.theme.fitted(self.colours);
+        let (sprites, colours) = (self.sprites, self.colours);
+        self.terminal
+            .draw(|frame| {
+                layout = draw(frame, sim, camera, selection, hud, &theme, controls);
+                swap_sprites(frame, sprites, colours);
+            })
+            .unwrap();
+        layout
+    }
+
+    pub fn render_editor(&mut self, editor: &MapEditor, theme: &Theme) {
+        let theme = &theme.fitted(self.colours);
+        let (sprites, colours) = (self.sprites, self.colours);
+        self.terminal
+            .draw(|frame| {
+                draw_editor(frame, editor, theme);
+                swap_sprites(frame, sprites, colours);
+            })
+            .unwrap();
+    }
+
+    /// The too-small note, centred, as the text frame draws it.
+    pub fn render_too_small(&mut self, size: (u16, u16)) {
+        let lines: Vec<Line> = too_small_lines(size).into_iter().map(Line::from).collect();
+        self.terminal
+            .draw(|frame| {
+                let screen = frame.size();
+                let height = (lines.len() as u16).min(screen.height);
+                let area = Rect {
+                    y: screen.y + (screen.height - height) / 2,
+                    height,
+                    ..screen
+                };
+                frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), area);
+            })
+            .unwrap();
+    }
+}
+
+fn draw(
+    frame: &mut Frame,
+    sim: &Simulation,
+    camera: &Camera,
+    selection: &Selection,
+    hud: &Hud,
+    theme: &Theme,
+    controls: &str,
+) -> ScreenLayout {
+    let road = &sim.road;
+    let lane_lines = match camera.zoom {
+        Zoom::Detail if road.bike_lane => 4,
+        Zoom::Detail => 2,
+        Zoom::Overview => 1,
+    } + u16::from(hud.cursor.is_some());
+    let wide = frame.size().width >= SIDE_PANELS_WIDTH;
+    let panel_rows = if wide {
+        STATS_ROWS
+    } else {
+        sim.lights.len().max(1)
+    };
+    let [header, panels, map, lower, profile, loops, footer, console] = Layout::vertical([
+        Constraint::Length(hud.banners.len() as u16 + 3 + u16::from(!wide)),
+        Constraint::Length(panel_rows as u16 + 2),
+        // The road's top rule, the sky above it and the view line.
+        Constraint::Length(lane_lines + 3),
+        Constraint::Min(4),
+        Constraint::Length(u16::from(hud.profile.is_some())),
+        Constraint::Length(u16::from(hud.loops.is_some())),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.size());
+    paint_background(frame, theme);
+
+    let mut status: Vec<Line> = hud
+        .banners
+        .iter()
+        .map(|banner| Line::from(banner.as_str().fg(theme.banner).bold()))
+        .collect();
+    status.push(Line::from(vec![
+        Span::raw(format!(
+            "Tick: {}   {} {} ({})   Weather: {}{}   ",
+            sim.tick,
+            sim.clock.date(),
+            sim.clock.format(),
+            sim.clock.time_of_day().name(),
+            sim.weather.describe(),
+            traffic_note(sim)
+        )),
+        hud.status.as_str().fg(theme.status),
+    ]));
+    if !wide {
+        status.push(Line::from(format!(
+            "Vehicles: {}   Trips: {}   Avg wait: {:.1}s",
+            sim.vehicles.len(),
+            sim.stats.completed_trips,
+            sim.stats.average_wait()
+        )));
+    }
+    frame.render_widget(
+        Paragraph::new(status).block(panel(" TRAFFIC LIGHT SIMULATOR ")),
+        header,
+    );
+
+    let lights = if wide {
+        let [lights, stats] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(panels);
+        frame.render_widget(
+            Paragraph::new(stat_lines(sim)).block(panel(" Statistics ")),
+            stats,
+        );
+        lights
+    } else {
+        panels
+    };
+    frame.render_widget(
+        Paragraph::new(light_lines(sim)).block(panel(" Signals ")),
+        lights,
+    );
+
+    let layout = draw_road(frame, map, sim, camera, selection, hud, theme);
+
+    let [inspector, alerts] = if wide {
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(lower)
+    } else {
+        Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(lower)
+    };
+    frame.render_widget(
+        Paragraph::new(inspector_text(sim, camera, selection))
+            .block(Block::default().borders(Borders::TOP).title(" Inspector ")),
+        inspector,
+    );
+    // As many of the latest events as fit under the incidents.
+    let mut lines: Vec<Line> =
+        sim.incidents
+            .iter()
+            .map(|incident| Line::from(format!("💥 {}", incident.status()).fg(theme.error)))
+            .chain(sim.debris.iter().map(|debris| {
+                Line::from(format!("{} {}", DEBRIS, debris.status()).fg(theme.error))
+            }))
+            .chain(sim.deliveries.iter().map(|delivery| {
+                Line::from(format!("{} {}", DELIVERY, delivery.status()).fg(theme.error))
+            }))
+            .chain(
+                sim.lights
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, light)| light.repair_left.is_some())
+                    .map(|(index, light)| {
+                        let text =
+                            format!("{} {}", light.state.symbol(), fault_status(index, light));
+                        Line::from(text.fg(theme.error))
+                    }),
+            )
+            .chain(
+                sim.road_works.iter().map(|works| {
+                    Line::from(format!("{} {}", CONES, works.status()).fg(theme.error))
+                }),
+            )
+            .chain(
+                sim.school_zones.iter().filter(|z| z.active).map(|zone| {
+                    Line::from(format!("{} {}", SCHOOL, zone.status()).fg(theme.status))
+                }),
+            )
+            .chain(sim.closures.iter().filter(|c| c.active).map(|closure| {
+                Line::from(format!("{} {}", BARRICADE, closure.status()).fg(theme.error))
+            }))
+            .chain(
+                sim.bridge
+                    .iter()
+                    .map(|bridge| Line::from(format!("{} {}", BRIDGE, bridge.status()))),
+            )
+            .chain(sim.parking_lots.iter().map(|lot| {
+                Line::from(format!("{} {}", CAR_PARK, lot.status(sim.parking.capacity)))
+            }))
+            .collect();
+    let room = (alerts.height as usize).saturating_sub(2 + lines.len());
+    lines.extend(
+        sim.events
+            .recent(room)
+            .map(|(time, text)| Line::from(format!("[{:6.1}s] {}", time, text))),
+    );
+    frame.render_widget(Paragraph::new(lines).block(panel(" Alerts ")), alerts);
+
+    if let Some(text) = &hud.profile {
+        frame.render_widget(Paragraph::new(text.as_str().fg(theme.muted)), profile);
+    }
+    if let Some(text) = &hud.loops {
+        frame.render_widget(Paragraph::new(text.as_str().fg(theme.muted)), loops);
+    }
+    let controls = if hud.monochrome {
+        controls.reversed()
+    } else {
+        controls.black().on_gray()
+    };
+    frame.render_widget(Paragraph::new(controls), footer);
+
+    let prompt = match &selection.prompt {
+        Some(prompt) => Some(format!("{}: {}", prompt.kind.label(), prompt.input)),
+        None if hud.console.open => Some(format!(":{}", hud.console.input)),
+        None => None,
+    };
+    match (prompt, &hud.console.output) {
+        (Some(prompt), _) => {
+            let column = console.x + Span::raw(prompt.as_str()).width() as u16;
+            frame.render_widget(Paragraph::new(prompt), console);
+            frame.set_cursor(column.min(console.right().saturating_sub(1)), console.y);
+        }
+        (None, Some((message, true))) => {
+            frame.render_widget(Paragraph::new(message.as_str().fg(theme.error)), console)
+        }
+        (None, Some((message, false))) => {
+            frame.render_widget(Paragraph::new(message.as_str()), console)
+        }
+        (None, None) => {}
+    }
+
+    if let Some((title, lines)) = hud.overlay {
+        draw_overlay(frame, title, lines, theme);
+    }
+    layout
+}
+
+/// Fills the screen with the theme's text and background colours, for the
+/// panels to be drawn over.
+fn paint_background(frame: &mut Frame, theme: &Theme) {
+    let style = Style::new()
+        .fg(theme.foreground.into())
+        .bg(theme.background.into());
+    frame.render_widget(Block::default().style(style), frame.size());
+}
+
+/// Swaps emoji for `sprites`, as the text frame does, coloured like the
+/// emoji. A monochrome screen always gets letters.
+fn swap_sprites(frame: &mut Frame, sprites: SpriteSet, colours: ColourDepth) {
+    let sprites = if colours == ColourDepth::Monochrome {
+        SpriteSet::Ascii
+    } else {
+        sprites
+    };
+    for cell in &mut frame.buffer_mut().content {
+        let Some(sprite) = sprites.sprite(cell.symbol()) else {
+            continue;
+        };
+        if let Some([r, g, b]) = glyph_colour(cell.symbol()) {
+            cell.set_fg(colours.fit(Color::Rgb { r, g, b }).into());
+        }
+        if cell.symbol().width() == 2 {
+            cell.set_symbol(sprite);
+        } else {
+            let first = &sprite[..sprite.chars().next().map_or(0, char::len_utf8)];
+            cell.set_symbol(first);
+        }
+    }
+}
+
+/// A bordered panel with a title.
+fn panel(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+fn light_lines(sim: &Simulation) -> Vec<Line<'_>> {
+    sim.lights
+        .iter()
+        .enumerate()
+        .map(|(index, light)| {
+            let mut text = format!(
+                "{} {} at {} ({})   violations: {}",
+                index + 1,
+                light.state.symbol(),
+                light.position,
+                light.countdown(),
+                light.violations
+            );
+            if let Some(camera) = &light.camera {
+                write!(text, "   {} {}", CAMERA, camera.status())
+                    .expect("writing to a String cannot fail");
+            }
+            if sim.road.bike_lane {
+                write!(
+                    text,
+                    "   bike {} (queue: {})",
+                    light.bike_phase().symbol(),
+                    sim.queue_length(index, Lane::Bike)
+                )
+                .expect("writing to a String cannot fail");
+            }
+            Line::from(text)
+        })
+        .collect()
+}
+
+fn stat_lines(sim: &Simulation) -> Vec<Line<'_>> {
+    let mut lines = vec![Line::from(format!("Vehicles spawned: {}", sim.spawned))];
+    for kind in VehicleType::ALL.into_iter().filter(|k| !k.is_emergency()) {
+        let on_road = sim.vehicles.iter().filter(|v| v.kind == kind).count();
+        lines.push(Line::from(format!(
+            "  {} {}: {}",
+            kind.sprite(),
+            kind.name(),
+            on_road
+        )));
+    }
+    lines.push(Line::from(format!(
+        "  {} Emergency: {}",
+        VehicleType::Ambulance.sprite(),
+        sim.vehicles
+            .iter()
+            .filter(|v| v.kind.is_emergency())
+            .count()
+    )));
+    lines.push(Line::from(format!(
+        "Trips completed: {}",
+        sim.stats.completed_trips
+    )));
+    lines.push(Line::from(format!(
+        "Avg wait: {:.1}s",
+        sim.stats.average_wait()
+    )));
+    lines.push(Line::from(format!(
+        "Throughput: {:.1}/min",
+        sim.stats.throughput()
+    )));
+    lines.push(Line::from(format!(
+        "Crashes: {}",
+        sim.stats.crash_summary()
+    )));
+    if !sim.toll_booths.is_empty() {
+        lines.push(Line::from(toll_summary(sim)));
+    }
+    if !sim.parking_lots.is_empty() {
+        lines.push(Line::from(parking_summary(sim)));
+    }
+    if sim.turns.right_on_red || sim.stats.turns_on_red > 0 {
+        lines.push(Line::from(turn_summary(sim)));
+    }
+    if sim.stats.manual_periods > 0 || sim.lights.iter().any(|l| l.manual_since.is_some()) {
+        lines.push(Line::from(manual_summary(sim)));
+    }
+    lines
+}
+
+/// The road under a top rule, starting in the same column as in the text
+/// frame so [`ScreenLayout::cells_at`] maps clicks the same way.
+fn draw_road(
+    frame: &mut Frame,
+    area: Rect,
+    sim: &Simulation,
+    camera: &Camera,
+    selection: &Selection,
+    hud: &Hud,
+    theme: &Theme,
+) -> ScreenLayout {
+    let road = &sim.road;
+    let glyph_count = road_glyphs();
+    let start = camera.offset.min(road.length);
+    let end = (start + camera.visible_cells(glyph_count)).min(road.length);
+    let left_marker = if start > 0 { "◀" } else { " " };
+    let right_marker = if end < road.length { "▶" } else { " " };
+    let light_symbol = sim
+        .lights
+        .first()
+        .map_or("  ", |light| light.state.symbol());
+    let highlight = camera
+        .following
+        .or(selection.selected)
+        .and_then(|id| sim.vehicles.get(id))
+        .map(|v| (v.lane, v.cell()));
+    // The theme is already fitted, so without colour this is None.
+    let tint = road_tint(sim.clock.time_of_day(), theme);
+
+    let mut lanes = Vec::new();
+    let sky = sky_row(sim, start..end, camera.scale());
+    let mut lines = vec![Line::from(format!("     {}", sky).fg(theme.muted))];
+    match camera.zoom {
+        Zoom::Detail => {
+            let glyphs = LaneGlyphs::new(sim);
+            // Driving on the left puts the curb, and the bike lane along it,
+            // above the road instead of below.
+            let left_hand = sim.drivers.drive_on_left;
+            let bike_row = Line::from(
+                [
+                    vec![Span::raw("     ")],
+                    lane_spans(&glyphs.bike, start..end, highlight, Lane::Bike, tint, theme),
+                ]
+                .concat(),
+            );
+            let curb = Line::from(vec![
+                Span::raw("     "),
+                "╌".repeat((end - start) * 2).fg(theme.bike_lane),
+            ]);
+            if road.bike_lane && left_hand {
+                lines.push(curb.clone());
+                lanes.push((lines.len(), Lane::Bike));
+                lines.push(bike_row.clone());
+            }
+            lanes.push((lines.len(), Lane::Road));
+            lines.push(Line::from(
+                [
+                    vec![Span::raw(format!("{}  {}", light_symbol, left_marker))],
+                    lane_spans(&glyphs.road, start..end, highlight, Lane::Road, tint, theme),
+                    vec![Span::raw(right_marker)],
+                ]
+                .concat(),
+            ));
+            lanes.push((lines.len(), Lane::Road));
+            lines.push(Line::from(
+                [
+                    vec![Span::raw(format!("    {}", left_marker))],
+                    lane_spans(&glyphs.markings, start..end, None, Lane::Road, tint, theme),
+                    vec![Span::raw(right_marker)],
+                ]
+                .concat(),
+            ));
+            if road.bike_lane && !left_hand {
+                lanes.push((lines.len(), Lane::Bike));
+                lines.push(bike_row);
+                lines.push(curb);
+            }
+        }
+        Zoom::Overview => {
+            let scale = camera.scale();
+            let spans: Vec<Span> = (start..end)
+                .step_by(scale)
+                .map(|chunk| {
+                    let cells = chunk..(chunk + scale).min(end);
+                    let span = match sim.lights.iter().find(|l| cells.contains(&l.position)) {
+                        Some(light) => Span::raw(light.state.symbol()),
+                        None => {
+                            let count = sim
+                                .vehicles
+                                .iter()
+                                .filter(|v| v.lane == Lane::Road && cells.contains(&v.cell()))
+                                .count();
+                            Span::raw(density_glyph(count))
+                        }
+                    };
+                    tinted(span, tint)
+                })
+                .collect();
+            lanes.push((lines.len(), Lane::Road));
+            lines.push(Line::from(
+                [
+                    vec![Span::raw(format!("{}  {}", light_symbol, left_marker))],
+                    spans,
+                    vec![Span::raw(right_marker)],
+                ]
+                .concat(),
+            ));
+        }
+    }
+    if let Some(cursor) = hud.cursor.filter(|cell| (start..end).contains(cell)) {
+        let glyph = (cursor - start) / camera.scale();
+        lines.push(Line::from(vec![
+            Span::raw(format!("     {}", "  ".repeat(glyph))),
+            "▲".fg(theme.cursor).bold(),
+            Span::raw(format!(" cell {}", cursor)),
+        ]));
+    }
+    lines.push(Line::from(format!(
+        "View: cells {}-{} of {} ({})",
+        start,
+        end,
+        road.length,
+        match camera.zoom {
+            Zoom::Detail => "detail",
+            Zoom::Overview => "overview",
+        }
+    )));
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::TOP).title(" Road ")),
+        area,
+    );
+
+    ScreenLayout {
+        lane_rows: lanes
+            .into_iter()
+            .map(|(line, lane)| (area.y + 1 + line as u16, lane))
+            .collect(),
+        first_cell: start,
+        last_cell: end,
+        cell_scale: camera.scale(),
+    }
+}
+
+/// `span` on the road's time-of-day colour, if it has one.
+fn tinted(span: Span<'_>, tint: Option<Color>) -> Span<'_> {
+    match tint {
+        Some(colour) => span.bg(colour.into()),
+        None => span,
+    }
+}
+
+/// The visible part of a lane row, with the inspected vehicle highlighted.
+fn lane_spans<'a>(
+    row: &[&'a str],
+    cells: Range<usize>,
+    highlight: Option<(Lane, usize)>,
+    lane: Lane,
+    tint: Option<Color>,
+    theme: &Theme,
+) -> Vec<Span<'a>> {
+    cells
+        .map(|cell| {
+            let span = Span::raw(row[cell]);
+            if highlight == Some((lane, cell)) {
+                // Without a highlight colour, as on a monochrome screen.
+                if theme.highlight == Color::Reset {
+                    span.reversed()
+                } else {
+                    span.bg(theme.highlight.into())
+                }
+            } else {
+                tinted(span, tint)
+            }
+        })
+        .collect()
+}
+
+/// The same panels the text frame shows under the road: the inspected
+/// vehicle or light, or else the first few vehicles.
+fn inspector_text(sim: &Simulation, camera: &Camera, selection: &Selection) -> String {
+    let mut out = String::new();
+    match camera
+        .following
+        .or(selection.selected)
+        .and_then(|id| sim.vehicles.get(id))
+    {
+        Some(vehicle) => {
+            let title = if camera.following.is_some() {
+                "Following"
+            } else {
+                "Selected"
+            };
+            render_vehicle_panel(&mut out, sim, vehicle, title)
+        }
+        None => match selection.selected_light {
+            Some(index) if index < sim.lights.len() => render_light_panel(&mut out, sim, index),
+            _ => sim
+                .vehicles
+                .iter()
+                .take(5)
+                .try_for_each(|vehicle| writeln!(out, "{}", vehicle.render())),
+        },
+    }
+    .expect("writing to a String cannot fail");
+    out
+}
+
+/// A box in the middle of the screen over everything else, like the help.
+fn draw_overlay(frame: &mut Frame, title: &str, lines: &[String], theme: &Theme) {
+    let screen = frame.size();
+    let width = lines
+        .iter()
+        .map(|line| line.width())
+        .chain([title.width()])
+        .max()
+        .unwrap_or(0) as u16
+        + 4;
+    let height = lines.len() as u16 + 2;
+    let area = Rect {
+        x: screen.width.saturating_sub(width) / 2,
+        y: screen.height.saturating_sub(height) / 2,
+        width: width.min(screen.width),
+        height: height.min(screen.height),
+    };
+    let text: Vec<Line> = lines.iter().map(|line| Line::from(line.as_str())).collect();
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text).block(
+            panel(title)
+                .title_bottom(" any key closes ")
+                .border_style(Style::new().fg(theme.banner.into())),
+        ),
+        area,
+    );
+}
+
+fn draw_editor(frame: &mut Frame, editor: &MapEditor, theme: &Theme) {
+    paint_background(frame, theme);
+    let road: String = editor.cells.iter().collect();
+    let mut lines = vec![
+        Line::from(road),
+        Line::from(format!("{}^", " ".repeat(editor.cursor))),
+    ];
+    if editor.bike_lane {
+        lines.push(Line::from(
+            "b".repeat(editor.cells.len()).fg(theme.bike_lane),
+        ));
+    }
+    if editor.has_limits() {
+        lines.push(Line::from(editor.limits.iter().collect::<String>()));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(format!(
+        "Cursor: {} / {}",
+        editor.cursor + 1,
+        editor.cells.len()
+    )));
+    lines.push(Line::default());
+    lines.push(Line::from(
+        "←/→ move (→ past the end extends the road)   = road   | signal   s spawn   h hospital   f fire station   d bridge   t toll booth   p car park",
+    ));
+    lines.push(Line::from(format!(
+        "b toggle bike lane   1-9 speed limit (quarter cells/s)   - no limit   Backspace delete cell   w save to {} and apply   e/Esc cancel",
+        editor.path
+    )));
+    if !editor.message.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::from(editor.message.as_str().fg(theme.error)));
+    }
+    frame.render_widget(
+        Paragraph::new(lines).block(panel(" MAP EDITOR ")),
+        frame.size(),
+    );
+}
@@ -0,0 +1,195 @@
+use crate::systems::simulation::Simulation;
+use crate::systems::vehicle::VehicleType;
+use crate::ui::input::{Action, InputHandler, key_name};
+
+/// Something the user did that a tutorial step may be waiting for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TutorialEvent {
+    Performed(Action),
+    Spawned(VehicleType),
+}
+
+/// What finishes a tutorial step.
+#[derive(Debug, Clone, Copy)]
+enum Goal {
+    /// The first signal changes state this many times.
+    LightChanges(u32),
+    /// This many more trips finish.
+    TripsCompleted(u32),
+    Event(TutorialEvent),
+}
+
+struct Step {
+    /// Instruction text; `{key}` is replaced with the keys bound to `action`.
+    text: &'static str,
+    action: Option<Action>,
+    goal: Goal,
+}
+
+const STEPS: [Step; 12] = [
+    Step {
+        text: "Watch the signal go red → green → yellow. Wait for one full cycle.",
+        action: None,
+        goal: Goal::LightChanges(3),
+    },
+    Step {
+        text: "Press {key} to pause the simulation.",
+        action: Some(Action::TogglePause),
+        goal: Goal::Event(TutorialEvent::Performed(Action::TogglePause)),
+    },
+    Step {
+        text: "While paused, press {key} to advance a single frame.",
+        action: Some(Action::Step),
+        goal: Goal::Event(TutorialEvent::Performed(Action::Step)),
+    },
+    Step {
+        text: "Press {key} to rewind a little.",
+        action: Some(Action::RewindBack),
+        goal: Goal::Event(TutorialEvent::Performed(Action::RewindBack)),
+    },
+    Step {
+        text: "Press {key} to resume from this moment.",
+        action: Some(Action::TogglePause),
+        goal: Goal::Event(TutorialEvent::Performed(Action::TogglePause)),
+    },
+    Step {
+        text: "Press {key} to follow a vehicle. Its panel shows speed, state and time spent waiting.",
+        action: Some(Action::FollowNext),
+        goal: Goal::Event(TutorialEvent::Performed(Action::FollowNext)),
+    },
+    Step {
+        text: "Press {key} to open the console, type `spawn motorcycle 5` and press Enter.",
+        action: Some(Action::OpenConsole),
+        goal: Goal::Event(TutorialEvent::Spawned(VehicleType::Motorcycle)),
+    },
+    Step {
+        text: "Press {key} to zoom out to the overview of the whole road.",
+        action: Some(Action::ToggleZoom),
+        goal: Goal::Event(TutorialEvent::Performed(Action::ToggleZoom)),
+    },
+    Step {
+        text: "Press {key} to change the weather. Rain, snow and fog cost grip and visibility, so drivers slow down and brake sooner.",
+        action: Some(Action::CycleWeather),
+        goal: Goal::Event(TutorialEvent::Performed(Action::CycleWeather)),
+    },
+    Step {
+        text: "Press {key} to send an ambulance. Cars ahead of its siren pull over until it has passed.",
+        action: Some(Action::DispatchEmergency),
+        goal: Goal::Event(TutorialEvent::Performed(Action::DispatchEmergency)),
+    },
+    Step {
+        text: "The statistics count trips completed, the average wait and throughput. Watch two more trips finish.",
+        action: None,
+        goal: Goal::TripsCompleted(2),
+    },
+    Step {
+        text: "Press {key} to see every control and command.",
+        action: Some(Action::ToggleHelp),
+        goal: Goal::Event(TutorialEvent::Performed(Action::ToggleHelp)),
+    },
+];
+
+/// A step-by-step walkthrough started with `--tutorial`. Each step only
+/// advances once the user has done what it asks.
+pub struct Tutorial {
+    step: usize,
+    /// Light changes seen when the current step began.
+    start_tick: u32,
+    /// Trips completed when the current step began.
+    start_trips: u32,
+}
+
+impl Tutorial {
+    pub fn new(sim: &Simulation) -> Self {
+        Self {
+            step: 0,
+            start_tick: sim.tick,
+            start_trips: sim.stats.completed_trips,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.step >= STEPS.len()
+    }
+
+    /// Checks goals that depend on the simulation itself.
+    pub fn update(&mut self, sim: &Simulation) {
+        let done = match STEPS.get(self.step).map(|s| s.goal) {
+            Some(Goal::LightChanges(changes)) => sim.tick >= self.start_tick + changes,
+            Some(Goal::TripsCompleted(trips)) => {
+                sim.stats.completed_trips >= self.start_trips + trips
+            }
+            _ => false,
+        };
+        if done {
+            self.advance(sim);
+        }
+    }
+
+    pub fn notify(&mut self, event: TutorialEvent, sim: &Simulation) {
+        if let Some(Goal::Event(expected)) = STEPS.get(self.step).map(|s| s.goal)
+            && expected == event
+        {
+            self.advance(sim);
+        }
+    }
+
+    fn advance(&mut self, sim: &Simulation) {
+        self.step += 1;
+        self.start_tick = sim.tick;
+        self.start_trips = sim.stats.completed_trips;
+    }
+
+    /// The current instruction, using whatever keys are bound right now.
+    pub fn instruction(&self, input: &InputHandler) -> Option<String> {
+        let step = STEPS.get(self.step)?;
+        let keys = step.action.map_or(String::new(), |action| {
+            let names: Vec<String> = input.keys_for(action).into_iter().map(key_name).collect();
+            if names.is_empty() {
+                format!("(unbound: {})", action.name())
+            } else {
+                names.join(" or ")
+            }
+        });
+        Some(format!(
+            "🎓 Tutorial {}/{}: {}",
+            self.step + 1,
+            STEPS.len(),
+            step.text.replace("{key}", &keys)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::rng::Rng;
+    use crate::systems::road::Road;
+
+    #[test]
+    fn weather_and_emergency_steps_wait_for_their_actions() {
+        let mut sim = Simulation::new(Road::default(), Rng::seeded(1));
+        let mut tutorial = Tutorial::new(&sim);
+        tutorial.step = STEPS.len() - 4;
+        let input = InputHandler::new();
+        assert!(
+            tutorial
+                .instruction(&input)
+                .unwrap()
+                .contains("Press w to change the weather")
+        );
+
+        tutorial.notify(TutorialEvent::Performed(Action::DispatchEmergency), &sim);
+        assert_eq!(tutorial.step, STEPS.len() - 4);
+        tutorial.notify(TutorialEvent::Performed(Action::CycleWeather), &sim);
+        tutorial.notify(TutorialEvent::Performed(Action::DispatchEmergency), &sim);
+
+        // The statistics step counts trips from when it began.
+        sim.stats.completed_trips += 1;
+        tutorial.update(&sim);
+        assert_eq!(tutorial.step, STEPS.len() - 2);
+        sim.stats.completed_trips += 1;
+        tutorial.update(&sim);
+        assert_eq!(tutorial.step, STEPS.len() - 1);
+    }
+}